@@ -0,0 +1,175 @@
+# Messages of the soar command line interface.
+#
+# Translations live in locales/<language>-<REGION>/soar.ftl and only need to
+# contain the messages they translate; missing ones fall back to en-US.
+
+## Shared
+
+invalid-selection = Invalid selection, please try again.
+package-not-found = Package { $package } not found
+package-did-you-mean = Did you mean: { $suggestions }?
+
+## Selection
+
+select-no-match = No packages match { $pattern }
+select-remove-header = Packages to remove:
+
+## Install
+
+install-already-installed-reinstall = { $package } is already installed - reinstalling
+install-already-installed-skip = { $package } is already installed - skipping
+install-fetching-github = Fetching releases from GitHub: { $project }
+install-bin-conflict = { $path } for { $package } already exists ({ $owner })
+install-bin-owner = owned by { $owner }
+install-bin-unmanaged = not managed by soar
+install-bin-conflict-prompt = [o]verwrite, [s]kip linking, [r]ename, [a]bort:{ " " }
+install-rename-prompt = New name [{ $name }]:{ " " }
+install-resolved-name = No package is named { $query }, using { $name }
+install-multiple-found = Multiple packages found for { $package }
+install-select-prompt = Select a package:{ " " }
+install-preview-package = Package
+install-preview-version = Version
+install-preview-download = Download
+install-preview-installed = Installed
+install-preview-total = Packages: { $count }, to download: { $download }, installed: { $installed }
+install-confirm = Proceed with installation? [Y/n]:{ " " }
+install-nothing = No packages to install
+install-summary = Installed { $installed }/{ $total } packages
+install-failures = Failed to install { $count } packages:
+install-failure-reason = Reason
+install-retry-hint = Run `soar install --retry-failed` to retry them
+install-no-failures = No packages failed in the last transaction
+install-history-failed = Failed to record the transaction: { $error }
+recovery-interrupted = A previous transaction was interrupted before { $count } packages finished:
+recovery-prompt = [r]esume, roll [b]ack, or decide [l]ater:{ " " }
+recovery-interrupted-reason = Interrupted
+recovery-rollback-failed = Failed to roll back { $package }: { $error }
+recovery-rolled-back = Rolled back { $count } packages
+recovery-later = Leaving the interrupted transaction for later
+reinstall-failed = Can't reinstall { $package }: { $error }
+reinstall-failed-summary = { $count } of { $total } packages could not be reinstalled
+
+## Dry run
+
+dry-run-install = Would install { $package } { $version }
+dry-run-replace = Would replace { $package } { $old } with { $version }
+dry-run-download = download { $url }
+dry-run-link = link { $path }
+dry-run-link-skip = leave the binary unlinked
+dry-run-conflict = fail: { $error }
+dry-run-remove = Would remove { $package } { $version }
+dry-run-delete = delete { $path }
+dry-run-unlink = unlink { $path }
+dry-run-done = Dry run, nothing was changed
+
+## Hooks
+
+hook-failed = { $hook } hook `{ $command }` failed: { $error }
+path-hint = { $path } is not in your PATH, add it with: export PATH="{ $path }:$PATH"
+
+## Notes
+
+notes-header = Notes for { $package }:
+notes-none = { $package } has no notes
+
+## Remove
+
+remove-not-installed = Package { $package } is not installed.
+installed-ambiguous = { $package } matches several installs, pick one of { $candidates }
+remove-done = Removed { $package }
+remove-wipe = wipe { $path }
+remove-failed = Failed to remove { $package }: { $error }
+remove-task-failed = A removal was interrupted: { $error }
+remove-failed-summary = Failed to remove { $count } of { $total } packages
+remove-confirm = Remove { $count } packages, freeing { $size }? [Y/n]:{ " " }
+remove-not-installed-summary = { $count } of { $total } packages are not installed
+remove-other-versions = { $package } is still installed at { $versions }, link one of them as { $package } with `soar use { $package }@<version>`
+gc-done = Removed { $count } unreferenced store entries, freeing { $size }
+
+## Use
+
+use-linked = { $package } is now linked as { $path }
+use-displaced = { $package } is now linked as { $path }
+
+## Update
+
+update-nothing = No packages to update.
+update-excluded = Skipping excluded package { $package }
+update-pinned = Skipping pinned package { $package }
+update-deferred = Deferring update of { $package }, it is running (pid { $pids })
+update-check-failed = Failed to check { $project } for updates: { $error }
+update-ambiguous-asset = Multiple release assets of { $project } match, reinstall it with --asset to update
+
+## Sync
+
+sync-done = Synced { $repo }
+sync-failed = Failed to sync { $repo }: { $error }
+sync-never = never
+
+## Repositories
+
+repo-added = Added repository { $repo }
+repo-removed = Removed repository { $repo }
+repo-enabled = Enabled repository { $repo }
+repo-disabled = Disabled repository { $repo }
+repo-status-disabled = disabled
+repo-installed-kept = { $count } installed packages from { $repo } are kept but won't receive updates
+
+## Trust
+
+trust-new-repo = Repository { $repo } was added recently and isn't trusted yet
+trust-url = URL: { $url }
+trust-key = Key: { $fingerprint }
+trust-unsigned = Key: none, the repository is unsigned
+trust-confirm = Trust { $repo }? [y/N]:
+
+## Search
+
+search-showing = Showing { $shown } of { $total }
+search-showing-range = Showing { $from }-{ $to } of { $total }
+search-no-provider = No package provides { $name }
+categories-none = No package has a category
+categories-no-tags = No package has a tag
+discover-none = No package to discover, all matching packages are installed
+discover-prompt = Packages to install, e.g. 1 3, or nothing to skip:{ " " }
+popular-no-stats = No repository publishes install counts
+whatsnew-none = No package was added or updated since { $since }
+
+## Pick
+
+pick-prompt = Install:
+pick-header = { $shown }/{ $total }, { $selected } selected (Tab to select, Enter to confirm, Esc to cancel)
+pick-none = Nothing selected
+pick-no-terminal = Picking packages needs a terminal, see soar pick --help for using an external picker with soar search --porcelain
+
+## Init
+
+init-config-exists = Keeping the existing config at { $path }
+init-repos-ignored = Repositories are only chosen when the config is first written
+init-config-written = Wrote the config to { $path }
+init-repo-prompt = Enable the { $name } repository? [Y/n]:{ " " }
+init-directories = Packages go to { $packages }, their binaries are linked in { $bin }
+init-path-ok = { $path } is in your PATH
+init-path-prompt = Add { $path } to your PATH in { $file }? [Y/n]:{ " " }
+init-done = soar is ready, install packages with: soar install <package>
+
+## Env
+
+env-unknown-shell = Can't tell which shell you use, pass it with --shell
+env-path-added = Added to PATH in { $file }, it takes effect in new shells
+env-path-configured = PATH is already set up in { $file }
+
+## Health
+
+health-not-on-path = { $count } installed binaries can't be run by name, { $path } is not in your PATH
+health-path-fix = Add it with: soar env install-path
+health-broken-link = { $path } of { $package } points to a missing file, reinstall it with: soar reinstall { $package }
+health-shadowed = { $name } of { $package } is shadowed by { $path }, which comes first in PATH
+health-problems = Found { $count } problems
+health-ok = No problems found
+
+## Help
+
+help-topics = Topics:
+help-unknown-topic = No command or topic named { $topic }, see soar help
+man-written = Wrote { $count } man pages to { $dir }
@@ -0,0 +1,30 @@
+use soar_core::{
+    package::adopt::{adopt_package, AdoptOptions},
+    SoarResult,
+};
+use tracing::info;
+
+use crate::{
+    color::{Color, ColorExt},
+    state::AppState,
+};
+
+pub async fn adopt_packages(targets: &[String], options: AdoptOptions) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+
+    for target in targets {
+        let adopted = adopt_package(target, &options, state.core_db().clone())?;
+        info!(
+            pkg_name = %adopted.pkg_name,
+            installed_path = %adopted.installed_path.display(),
+            bin_path = %adopted.bin_path.display(),
+            checksum = %adopted.checksum,
+            "Adopted {} as {} ({})",
+            target,
+            adopted.pkg_name.color(Color::Accent),
+            adopted.bin_path.display().color(Color::Accent)
+        );
+    }
+
+    Ok(())
+}
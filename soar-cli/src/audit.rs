@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use futures::{stream, StreamExt};
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use soar_core::{
+    database::{
+        models::InstalledPackage,
+        packages::{get_installed_packages, get_packages, FilterOp, QueryOptions},
+    },
+    error::SoarError,
+    http::{client, send_with_retry},
+    SoarResult,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    color::{Color, ColorExt},
+    state::AppState,
+};
+
+const OSV_QUERY_BATCH_URL: &str = "https://api.osv.dev/v1/querybatch";
+const OSV_VULN_URL: &str = "https://api.osv.dev/v1/vulns";
+/// Most queries OSV.dev accepts in one batch.
+const OSV_BATCH_LIMIT: usize = 1000;
+/// Vulnerability details fetched at once.
+const DETAIL_JOBS: usize = 8;
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    results: Vec<BatchResult>,
+}
+
+#[derive(Deserialize, Default)]
+struct BatchResult {
+    #[serde(default)]
+    vulns: Vec<VulnRef>,
+    /// Set when there are more vulnerabilities than fit in the result,
+    /// which the query has to be sent again with to get.
+    #[serde(default)]
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VulnRef {
+    id: String,
+}
+
+#[derive(Deserialize, Default, Clone)]
+struct Vulnerability {
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+/// Package as OSV.dev knows it, in one of its ecosystems.
+#[derive(Debug, PartialEq)]
+struct OsvPackage {
+    ecosystem: &'static str,
+    name: String,
+}
+
+/// The OSV.dev package a package is built from, going by the registry one
+/// of its source URLs points to.
+///
+/// A GitHub project alone doesn't tell which ecosystem, if any, it's
+/// published in, so packages only known by their repository are skipped
+/// rather than looked up in the wrong one and reported clean.
+fn osv_package<'a>(urls: impl IntoIterator<Item = &'a str>) -> Option<OsvPackage> {
+    urls.into_iter().find_map(|url| {
+        let url = Url::parse(url.trim()).ok()?;
+        let host = url.host_str().unwrap_or_default();
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+
+        let (ecosystem, name) = match (host, segments.as_slice()) {
+            ("crates.io", ["crates", name, ..]) => ("crates.io", name.to_string()),
+            ("npmjs.com", ["package", scope, name, ..]) if scope.starts_with('@') => {
+                ("npm", format!("{}/{}", scope, name))
+            }
+            ("npmjs.com", ["package", name, ..]) => ("npm", name.to_string()),
+            ("pypi.org", ["project", name, ..]) => ("PyPI", name.to_string()),
+            _ => return None,
+        };
+        Some(OsvPackage { ecosystem, name })
+    })
+}
+
+/// Source URLs of `package` from its repository metadata.
+fn source_urls(state: &AppState, package: &InstalledPackage) -> SoarResult<Vec<String>> {
+    let mut filters = HashMap::new();
+    filters.insert(
+        "r.name".to_string(),
+        (FilterOp::Eq, package.repo_name.clone().into()).into(),
+    );
+    filters.insert(
+        "pkg_name".to_string(),
+        (FilterOp::Eq, package.pkg_name.clone().into()).into(),
+    );
+    filters.insert(
+        "pkg_id".to_string(),
+        (FilterOp::Eq, package.pkg_id.clone().into()).into(),
+    );
+    let metadata = get_packages(
+        state.repo_db().clone(),
+        QueryOptions {
+            limit: 1,
+            filters,
+            ..Default::default()
+        },
+    )?
+    .items
+    .into_iter()
+    .next();
+
+    Ok(metadata
+        .map(|metadata| {
+            let mut urls = metadata.source_urls.unwrap_or_default();
+            urls.extend(metadata.homepages.unwrap_or_default());
+            urls
+        })
+        .unwrap_or_default())
+}
+
+/// Sends `queries` in batches OSV.dev accepts, following the pages of
+/// results, returning the IDs of the vulnerabilities each query matched.
+async fn query_batches(queries: &[Value]) -> SoarResult<Vec<Vec<String>>> {
+    let client = client();
+    let mut vulns = vec![Vec::new(); queries.len()];
+    // queries left to send, with the page of results to get
+    let mut pending: Vec<(usize, Option<String>)> = (0..queries.len()).map(|i| (i, None)).collect();
+
+    while !pending.is_empty() {
+        let mut next = Vec::new();
+        for batch in pending.chunks(OSV_BATCH_LIMIT) {
+            let batch_queries: Vec<Value> = batch
+                .iter()
+                .map(|(idx, page_token)| {
+                    let mut query = queries[*idx].clone();
+                    if let Some(page_token) = page_token {
+                        query["page_token"] = json!(page_token);
+                    }
+                    query
+                })
+                .collect();
+            let body = json!({ "queries": batch_queries });
+            let resp = send_with_retry(|| client.post(OSV_QUERY_BATCH_URL).json(&body)).await?;
+            if !resp.status().is_success() {
+                return Err(SoarError::FailedToFetchRemote(
+                    OSV_QUERY_BATCH_URL.to_string(),
+                ));
+            }
+            let response: BatchResponse = resp.json().await?;
+
+            for ((idx, _), result) in batch.iter().zip(response.results) {
+                vulns[*idx].extend(result.vulns.into_iter().map(|vuln| vuln.id));
+                if let Some(page_token) = result.next_page_token {
+                    next.push((*idx, Some(page_token)));
+                }
+            }
+        }
+        pending = next;
+    }
+
+    Ok(vulns)
+}
+
+/// Checks installed packages against the OSV.dev vulnerability database,
+/// looked up as the package their sources point to. Packages without such
+/// a source are skipped.
+///
+/// Fails with [`SoarError::VulnerabilitiesFound`] if any installed package is
+/// affected, so it can be used to gate CI.
+pub async fn audit_packages(repo_name: Option<String>) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let core_db = state.core_db().clone();
+
+    let mut filters = HashMap::new();
+    filters.insert(
+        "is_installed".to_string(),
+        (FilterOp::Eq, "1".to_string().into()).into(),
+    );
+    if let Some(repo_name) = repo_name {
+        filters.insert(
+            "repo_name".to_string(),
+            (FilterOp::Eq, repo_name.into()).into(),
+        );
+    }
+    let packages = get_installed_packages(
+        core_db,
+        QueryOptions {
+            filters,
+            limit: u32::MAX,
+            ..Default::default()
+        },
+    )?
+    .items;
+
+    if packages.is_empty() {
+        info!("No installed packages to audit");
+        return Ok(());
+    }
+
+    let mut audited = Vec::new();
+    let mut queries = Vec::new();
+    let mut unknown = 0;
+    for package in &packages {
+        let Some(osv) = osv_package(source_urls(&state, package)?.iter().map(String::as_str))
+        else {
+            debug!(
+                "{}#{} has no source known to OSV.dev, skipping it",
+                package.pkg_name, package.pkg_id
+            );
+            unknown += 1;
+            continue;
+        };
+        queries.push(json!({
+            "package": { "ecosystem": osv.ecosystem, "name": osv.name },
+            "version": package.version.trim_start_matches('v'),
+        }));
+        audited.push(package);
+    }
+
+    let results = query_batches(&queries).await?;
+
+    let mut ids: Vec<&String> = results.iter().flatten().collect();
+    ids.sort();
+    ids.dedup();
+    let details: HashMap<&String, Vulnerability> = stream::iter(ids)
+        .map(|id| async move { (id, fetch_vulnerability(id).await.unwrap_or_default()) })
+        .buffer_unordered(DETAIL_JOBS)
+        .collect()
+        .await;
+
+    let mut affected = 0;
+    for (package, vulns) in audited.iter().zip(&results) {
+        if vulns.is_empty() {
+            continue;
+        }
+        affected += 1;
+
+        warn!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            version = %package.version,
+            repo_name = %package.repo_name,
+            count = vulns.len(),
+            "{}#{}-{}:{} has {} known {}",
+            package.pkg_name.color(Color::Accent),
+            package.pkg_id.color(Color::Info),
+            package.version.color(Color::Value),
+            package.repo_name,
+            vulns.len(),
+            if vulns.len() == 1 {
+                "vulnerability"
+            } else {
+                "vulnerabilities"
+            }
+        );
+
+        for id in vulns {
+            let detail = &details[id];
+            info!(
+                pkg_name = %package.pkg_name,
+                vuln_id = %id,
+                aliases = ?detail.aliases,
+                summary = detail.summary.as_deref().unwrap_or_default(),
+                "  {} {}{}",
+                id.color(Color::Warn),
+                detail.summary.as_deref().unwrap_or_default(),
+                if detail.aliases.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", detail.aliases.join(", "))
+                }
+            );
+        }
+    }
+
+    if unknown > 0 {
+        info!(
+            skipped = unknown,
+            "Skipped {} packages without a source known to OSV.dev", unknown
+        );
+    }
+    info!(
+        audited = audited.len(),
+        affected = affected,
+        "Audited {} packages, {} affected",
+        audited.len(),
+        affected
+    );
+
+    if affected > 0 {
+        return Err(SoarError::VulnerabilitiesFound(affected));
+    }
+
+    Ok(())
+}
+
+async fn fetch_vulnerability(id: &str) -> SoarResult<Vulnerability> {
+    let client = client();
+    let url = format!("{OSV_VULN_URL}/{id}");
+    let resp = send_with_retry(|| client.get(&url)).await?;
+    if !resp.status().is_success() {
+        return Err(SoarError::FailedToFetchRemote(url));
+    }
+    Ok(resp.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn osv(ecosystem: &'static str, name: &str) -> Option<OsvPackage> {
+        Some(OsvPackage {
+            ecosystem,
+            name: name.to_string(),
+        })
+    }
+
+    #[test]
+    fn maps_registry_urls_to_their_ecosystem() {
+        assert_eq!(
+            osv_package(["https://crates.io/crates/ripgrep"]),
+            osv("crates.io", "ripgrep")
+        );
+        assert_eq!(
+            osv_package(["https://www.npmjs.com/package/@scope/tool/v/1.0.0"]),
+            osv("npm", "@scope/tool")
+        );
+        assert_eq!(
+            osv_package(["https://www.npmjs.com/package/tool"]),
+            osv("npm", "tool")
+        );
+        assert_eq!(
+            osv_package(["https://pypi.org/project/yt-dlp/"]),
+            osv("PyPI", "yt-dlp")
+        );
+    }
+
+    #[test]
+    fn prefers_registries_over_repositories() {
+        assert_eq!(
+            osv_package([
+                "https://github.com/BurntSushi/ripgrep",
+                "https://crates.io/crates/ripgrep",
+            ]),
+            osv("crates.io", "ripgrep")
+        );
+    }
+
+    #[test]
+    fn skips_unknown_sources() {
+        assert_eq!(osv_package(["https://example.com/tool", "not a url"]), None);
+        // the ecosystem of a GitHub project is unknown
+        assert_eq!(osv_package(["https://github.com/junegunn/fzf.git"]), None);
+        assert_eq!(osv_package(["https://github.com/owner"]), None);
+        assert_eq!(osv_package([]), None);
+    }
+}
@@ -1,4 +1,7 @@
-use clap::{ArgAction, Parser, Subcommand, ValueHint};
+use std::path::PathBuf;
+
+use clap::{ArgAction, Parser, Subcommand, ValueEnum, ValueHint};
+use soar_core::package::channel::Channel;
 
 #[derive(Parser)]
 #[command(
@@ -10,25 +13,245 @@ use clap::{ArgAction, Parser, Subcommand, ValueHint};
 {usage-heading} {usage}
 
 {all-args}{after-help}",
-    arg_required_else_help = true
+    arg_required_else_help = true,
+    disable_help_subcommand = true
 )]
 pub struct Args {
+    /// Increase verbosity, -vv for trace output
     #[arg(short = 'v', long, action = ArgAction::Count)]
     pub verbose: u8,
 
+    /// Only print errors, without progress unless --progress is given
     #[arg(short, long)]
     pub quiet: bool,
 
+    /// Print output as JSON
     #[arg(short, long)]
     pub json: bool,
 
+    /// When to color the output
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
     #[arg(short, long)]
     pub profile: Option<String>,
 
+    /// Operate on the system-wide install under the system prefix, gaining
+    /// root privileges through sudo or pkexec if needed
+    #[arg(long, global = true)]
+    pub system: bool,
+
+    /// Install into the root filesystem at DIR, e.g. of a container image,
+    /// laid out like the system-wide install inside it, with relative
+    /// symlinks and nothing recorded outside it. Defaults to `DESTDIR`
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "system", value_hint = ValueHint::DirPath)]
+    pub root: Option<PathBuf>,
+
+    /// Wait for other soar processes to finish instead of failing
+    #[arg(long, global = true)]
+    pub wait: bool,
+
+    /// Fetch repository metadata even if the cached copy is still fresh
+    #[arg(long, global = true)]
+    pub refresh: bool,
+
+    /// Seconds to wait for a connection, 0 for no limit
+    #[arg(long, global = true, value_name = "SECS")]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds to wait for a server to send more data, 0 for no limit
+    #[arg(long, global = true, value_name = "SECS")]
+    pub read_timeout: Option<u64>,
+
+    /// Seconds a single request or download may take, 0 for no limit
+    #[arg(long, global = true, value_name = "SECS")]
+    pub download_timeout: Option<u64>,
+
+    /// How to show download progress. Defaults to bars, or JSON lines on
+    /// stdout when stdout isn't a terminal. Bars are only drawn on a
+    /// terminal
+    #[arg(long, global = true, value_enum, value_name = "MODE")]
+    pub progress: Option<ProgressMode>,
+
     #[clap(subcommand)]
     pub command: Commands,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressMode {
+    Bar,
+    Json,
+    None,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ListSort {
+    Name,
+    Size,
+    Date,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SearchSort {
+    /// Best matches of the query first
+    Relevance,
+    Name,
+    Size,
+    Date,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReleaseChannel {
+    Stable,
+    Prerelease,
+    Nightly,
+}
+
+impl From<ReleaseChannel> for Channel {
+    fn from(value: ReleaseChannel) -> Self {
+        match value {
+            ReleaseChannel::Stable => Channel::Stable,
+            ReleaseChannel::Prerelease => Channel::Prerelease,
+            ReleaseChannel::Nightly => Channel::Nightly,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum HookShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// What to do when a download would replace an existing file.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum OutputConflict {
+    #[default]
+    Overwrite,
+    /// Keep the existing file and don't download
+    Skip,
+    /// Append `.1`, `.2`, ... to the new file's name
+    Rename,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SbomFormat {
+    Spdx,
+    Cyclonedx,
+}
+
+impl Commands {
+    /// Whether the command modifies the installation and has to hold the
+    /// instance lock.
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            Commands::Install { .. }
+            | Commands::Reinstall { .. }
+            | Commands::Remove { .. }
+            | Commands::Sync { .. }
+            | Commands::Init { .. }
+            | Commands::Update { .. }
+            | Commands::Adopt { .. }
+            | Commands::Import { .. }
+            | Commands::Ensure { .. }
+            | Commands::Use { .. }
+            | Commands::Discover { .. }
+            | Commands::Clean { .. }
+            | Commands::Gc
+            | Commands::SelfCmd { .. } => true,
+            Commands::Search { install, .. } => *install,
+            Commands::Pick { .. } => true,
+            Commands::Verify { repair, .. } => *repair,
+            Commands::Repo { action } => !matches!(action, RepoAction::List),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum RepoAction {
+    /// Add a repository and fetch its metadata
+    #[command(arg_required_else_help = true)]
+    Add {
+        /// Name of the repository
+        name: String,
+
+        /// Metadata URL
+        url: String,
+
+        /// Base64 encoded ed25519 public key allowed to sign the metadata
+        #[arg(required = false, long = "pubkey")]
+        pubkeys: Vec<String>,
+
+        /// Collection of the packages whose metadata doesn't name one
+        #[arg(required = false, long)]
+        collection: Option<String>,
+    },
+    /// Remove a repository and its metadata
+    #[command(arg_required_else_help = true)]
+    #[clap(visible_alias = "rm")]
+    Remove {
+        /// Name of the repository
+        name: String,
+    },
+    /// Enable a disabled repository
+    #[command(arg_required_else_help = true)]
+    Enable {
+        /// Name of the repository
+        name: String,
+    },
+    /// Disable a repository without removing it
+    #[command(arg_required_else_help = true)]
+    Disable {
+        /// Name of the repository
+        name: String,
+    },
+    /// List the configured repositories
+    #[clap(visible_alias = "ls")]
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum EnvAction {
+    /// Print the line adding the bin directory to PATH
+    Path {
+        /// Shell to print it for, detected from $SHELL unless given
+        #[arg(required = false, long, value_enum)]
+        shell: Option<HookShell>,
+    },
+    /// Add the bin directory to PATH in the startup file of the shell
+    #[clap(name = "install-path")]
+    InstallPath {
+        /// Shell to set it up for, detected from $SHELL unless given
+        #[arg(required = false, long, value_enum)]
+        shell: Option<HookShell>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// List the cached downloads and packages
+    #[clap(visible_alias = "ls")]
+    List,
+    /// Remove all cached downloads and packages
+    #[clap(visible_alias = "clean")]
+    Clear,
+    /// Remove the cached downloads not used for a while
+    Prune {
+        /// Days a download has to go unused to be removed
+        #[arg(required = false, long, value_name = "DAYS", default_value_t = 30)]
+        older_than: u64,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum SelfAction {
     /// Update soar
@@ -43,10 +266,32 @@ pub enum Commands {
     #[command(arg_required_else_help = true)]
     #[clap(name = "install", visible_alias = "i", visible_alias = "add")]
     Install {
-        /// Packages to install
-        #[arg(required = true)]
+        /// Packages to install. Names containing * or ? select all matching
+        /// packages
+        #[arg(required_unless_present_any = ["from", "retry_failed"])]
         packages: Vec<String>,
 
+        /// Treat the packages as regular expressions matching package names
+        #[arg(required = false, long, conflicts_with = "from")]
+        regex: bool,
+
+        /// Retry the packages that failed in the last install or update
+        #[arg(required = false, long, conflicts_with_all = ["packages", "from", "regex"])]
+        retry_failed: bool,
+
+        /// Install from a GitHub release instead, e.g. github:owner/repo[@tag]
+        #[arg(
+            required = false,
+            long,
+            value_name = "SOURCE",
+            conflicts_with = "packages"
+        )]
+        from: Option<String>,
+
+        /// Regex selecting the release asset to install with --from
+        #[arg(required = false, long, requires = "from")]
+        asset: Option<String>,
+
         /// Whether to force install the package
         #[arg(required = false, short, long)]
         force: bool,
@@ -55,6 +300,26 @@ pub enum Commands {
         #[arg(required = false, short, long)]
         yes: bool,
 
+        /// Number of parallel installs. Without it, up to the configured limit run, as many as throughput allows
+        #[arg(required = false, short, long)]
+        jobs: Option<usize>,
+
+        /// Follow prereleases when updating, same as --channel prerelease
+        #[arg(required = false, long, conflicts_with = "channel")]
+        pre: bool,
+
+        /// Release channel the packages follow when updating
+        #[arg(required = false, long, value_enum)]
+        channel: Option<ReleaseChannel>,
+
+        /// Overwrite existing files in the bin directory
+        #[arg(required = false, long, conflicts_with = "rename_on_conflict")]
+        force_overwrite: bool,
+
+        /// Link under another name if the bin name is taken. Supports {pkg_name}, {pkg_id}, {repo_name} and {version}
+        #[arg(required = false, long, num_args = 0..=1, value_name = "TEMPLATE")]
+        rename_on_conflict: Option<Option<String>>,
+
         /// Set portable dir for home & config
         #[arg(required = false, short, long, num_args = 0..=1, value_hint = ValueHint::AnyPath)]
         portable: Option<Option<String>>,
@@ -66,15 +331,48 @@ pub enum Commands {
         /// Set portable config
         #[arg(required = false, long, num_args = 0..=1, value_hint = ValueHint::AnyPath)]
         portable_config: Option<Option<String>>,
+
+        /// Unpack AppImages and link the binary their AppRun points to, so
+        /// they run without FUSE
+        #[arg(required = false, long)]
+        extract_appimage: bool,
+
+        /// Show what would be installed without changing anything
+        #[arg(required = false, long)]
+        dry_run: bool,
+    },
+
+    /// Install packages again at their installed version, e.g. to repair
+    /// them, from the cache if their files were kept
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "reinstall")]
+    Reinstall {
+        /// Packages to reinstall
+        #[arg(required = true)]
+        packages: Vec<String>,
+
+        /// Skip all prompts and use first
+        #[arg(required = false, short, long)]
+        yes: bool,
     },
 
     /// Search package
     #[command(arg_required_else_help = true)]
     #[clap(name = "search", visible_alias = "s", visible_alias = "find")]
     Search {
-        /// Query to search
-        #[arg(required = true)]
-        query: String,
+        /// Words to look for in package names, descriptions and the commands
+        /// they provide, or a filter expression such as
+        /// 'name~^rip && size<10MB', see `soar help filters`
+        #[arg(required_unless_present_any = ["tag", "category"])]
+        query: Option<String>,
+
+        /// Only show packages with the given tag
+        #[arg(required = false, long)]
+        tag: Option<String>,
+
+        /// Only show packages in the given category
+        #[arg(required = false, long)]
+        category: Option<String>,
 
         /// Case sensitive search
         #[arg(required = false, long, alias = "exact")]
@@ -83,6 +381,56 @@ pub enum Commands {
         /// Limit number of result
         #[arg(required = false, long)]
         limit: Option<usize>,
+
+        /// Skip this many results, to page through them with --limit
+        #[arg(required = false, long, default_value_t = 0)]
+        offset: u32,
+
+        /// Sort results by the given field
+        #[arg(required = false, long, value_enum, default_value_t = SearchSort::Relevance)]
+        sort: SearchSort,
+
+        /// Pick packages to install from the results
+        #[arg(required = false, long, conflicts_with_all = ["porcelain", "names_only"])]
+        install: bool,
+
+        /// Print the results as tab separated fields for scripts: the package
+        /// as name#pkg_id:repo, version, size in bytes, install status
+        /// (+ installed, ? partially, - not) and description, all of them
+        /// unless --limit is given
+        #[arg(required = false, long, conflicts_with = "names_only")]
+        porcelain: bool,
+
+        /// Print only the names of the matching packages, all of them unless
+        /// --limit is given
+        #[arg(required = false, long)]
+        names_only: bool,
+    },
+
+    /// Pick packages to install with a fuzzy finder
+    ///
+    /// Lists the packages matching the query, or all of them, narrowing them
+    /// down as you type. Tab selects a package, Enter installs the selected
+    /// ones, or the one under the cursor if none are, and Esc cancels.
+    ///
+    /// This needs a terminal. To use an external picker such as fzf instead,
+    /// feed it the output of `soar search --porcelain` and install the first
+    /// field of the picked lines, e.g.
+    /// `soar search --porcelain QUERY | fzf -m | cut -f1 | xargs soar install`.
+    #[clap(name = "pick")]
+    Pick {
+        /// Words to look for in the packages, or a filter expression, see
+        /// `soar help filters`
+        #[arg(required = false)]
+        query: Option<String>,
+
+        /// Only offer packages with the given tag
+        #[arg(required = false, long)]
+        tag: Option<String>,
+
+        /// Only offer packages in the given category
+        #[arg(required = false, long)]
+        category: Option<String>,
     },
 
     /// Query package info
@@ -94,18 +442,111 @@ pub enum Commands {
         query: String,
     },
 
+    /// Find packages that provide a command
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "provides", visible_alias = "whatprovides")]
+    Provides {
+        /// Command to look up
+        #[arg(required = true)]
+        name: String,
+    },
+
+    /// List the most installed packages, from the install counts the
+    /// repositories publish
+    #[clap(name = "popular")]
+    Popular {
+        /// Only show packages from the given repository
+        #[arg(required = false, long)]
+        repo: Option<String>,
+
+        /// Number of packages to show
+        #[arg(required = false, long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Show a few random packages that aren't installed, and offer to
+    /// install them
+    #[clap(name = "discover")]
+    Discover {
+        /// Number of packages to show
+        #[arg(short = 'n', long, default_value_t = 5)]
+        count: usize,
+
+        /// Only show packages from the given repository
+        #[arg(required = false, long)]
+        repo: Option<String>,
+
+        /// Only show packages in the given category
+        #[arg(required = false, long)]
+        category: Option<String>,
+    },
+
+    /// List the packages added or updated in the repositories recently
+    #[clap(name = "whatsnew")]
+    WhatsNew {
+        /// How many days back to look
+        #[arg(required = false, long, default_value_t = 7)]
+        days: u32,
+
+        /// Only show packages from the given repository
+        #[arg(required = false, long)]
+        repo: Option<String>,
+    },
+
+    /// Print a command-not-found handler for the given shell
+    ///
+    /// Add `eval "$(soar hook bash)"` to your shell config, or
+    /// `soar hook fish | source` for fish.
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "hook")]
+    Hook {
+        /// Shell to generate the handler for
+        #[arg(required = true, value_enum)]
+        shell: HookShell,
+    },
+
+    /// Handle a missing command, used by the shell hook
+    #[clap(name = "command-not-found", hide = true)]
+    CommandNotFound {
+        /// Missing command and its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+
     /// Remove packages
     #[command(arg_required_else_help = true)]
     #[clap(name = "remove", visible_alias = "r", visible_alias = "del")]
     Remove {
-        /// Packages to remove
+        /// Packages to remove. Names containing * or ? select all matching
+        /// packages
         #[arg(required = true)]
         packages: Vec<String>,
+
+        /// Treat the packages as regular expressions matching package names
+        #[arg(required = false, long)]
+        regex: bool,
+
+        /// Remove the packages without asking
+        #[arg(required = false, short, long)]
+        yes: bool,
+
+        /// Show what would be removed without changing anything
+        #[arg(required = false, long)]
+        dry_run: bool,
+
+        /// Also wipe the data the packages leave behind: their portable home
+        /// and config directories and their `soar run` cache
+        #[arg(required = false, long)]
+        purge: bool,
     },
 
     /// Sync with remote metadata
     #[clap(name = "sync", visible_alias = "S", visible_alias = "fetch")]
-    Sync,
+    Sync {
+        /// Repository to sync, all of them if omitted
+        #[arg(required = false)]
+        repo_name: Option<String>,
+    },
 
     /// Update packages
     #[clap(name = "update", visible_alias = "u", visible_alias = "upgrade")]
@@ -113,6 +554,38 @@ pub enum Commands {
         /// Packages to update
         #[arg(required = false)]
         packages: Option<Vec<String>>,
+
+        /// Number of parallel updates. Without it, up to the configured limit run, as many as throughput allows
+        #[arg(required = false, short, long)]
+        jobs: Option<usize>,
+
+        /// Skip packages matching the glob, can be repeated
+        #[arg(required = false, short, long)]
+        exclude: Vec<String>,
+
+        /// Only update packages installed from the given repository
+        #[arg(required = false, long, conflicts_with = "packages")]
+        repo: Option<String>,
+
+        /// Only update packages of the given collection, e.g. bin or appimage
+        #[arg(required = false, long, conflicts_with = "packages")]
+        collection: Option<String>,
+
+        /// Leave packages whose binary is running for a later update
+        #[arg(required = false, long)]
+        defer: bool,
+
+        /// Show what would be updated without changing anything
+        #[arg(required = false, long)]
+        dry_run: bool,
+    },
+
+    /// Show the notes of a package again, e.g. setup steps after installing it
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "notes")]
+    Notes {
+        /// Package to show the notes of
+        package: String,
     },
 
     /// Show info about installed packages
@@ -133,6 +606,184 @@ pub enum Commands {
         /// Which repository to get the packages from
         #[arg(required = false)]
         repo_name: Option<String>,
+
+        /// Which repository to get the packages from
+        #[arg(required = false, long = "repo", conflicts_with = "repo_name")]
+        repo: Option<String>,
+
+        /// Only show installed packages
+        #[arg(required = false, long, conflicts_with = "updatable")]
+        installed: bool,
+
+        /// Only show packages of the given collection, e.g. bin or appimage
+        #[arg(required = false, long, conflicts_with_all = ["installed", "updatable"])]
+        collection: Option<String>,
+
+        /// Only show packages in the given category
+        #[arg(required = false, long, conflicts_with_all = ["installed", "updatable"])]
+        category: Option<String>,
+
+        /// Only show packages with the given tag
+        #[arg(required = false, long, conflicts_with_all = ["installed", "updatable"])]
+        tag: Option<String>,
+
+        /// Only show packages matching a filter expression, see `soar help filters`
+        #[arg(
+            required = false,
+            long,
+            value_name = "EXPR",
+            conflicts_with_all = ["installed", "updatable"]
+        )]
+        filter: Option<String>,
+
+        /// List the repositories and when they were last synced instead
+        #[arg(
+            required = false,
+            long,
+            conflicts_with_all = [
+                "repo_name", "repo", "installed", "updatable", "collection", "category", "tag",
+                "filter"
+            ]
+        )]
+        repos: bool,
+
+        /// Only show installed packages with a newer version available
+        #[arg(required = false, long)]
+        updatable: bool,
+
+        /// Sort packages by the given field
+        #[arg(required = false, long, value_enum, default_value_t = ListSort::Name)]
+        sort: ListSort,
+    },
+
+    /// List the package categories, or tags, with how many packages each has
+    #[clap(name = "categories")]
+    Categories {
+        /// List the tags instead
+        #[arg(required = false, long)]
+        tags: bool,
+    },
+
+    /// Bring binaries installed outside soar under soar management
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "adopt")]
+    Adopt {
+        /// Paths to the binaries or AppImages, or command names in PATH
+        #[arg(required = true, value_hint = ValueHint::AnyPath)]
+        targets: Vec<String>,
+
+        /// Name to register the package under, defaults to the file name
+        #[arg(required = false, long, short)]
+        name: Option<String>,
+
+        /// Version of the binary
+        #[arg(required = false, long = "pkg-version")]
+        version: Option<String>,
+
+        /// Upstream GitHub project (owner/repo) to check for updates
+        #[arg(required = false, long, value_name = "OWNER/REPO")]
+        github: Option<String>,
+
+        /// Copy the files instead of moving them
+        #[arg(required = false, long)]
+        copy: bool,
+    },
+
+    /// Compare an installed package with the version an update would install
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "diff")]
+    Diff {
+        /// Installed package to compare
+        #[arg(required = true)]
+        package: String,
+
+        /// Compare against this version instead of the latest
+        #[arg(required = false, long)]
+        against: Option<String>,
+    },
+
+    /// Print the installed packages as a list that can be imported elsewhere
+    #[clap(name = "export")]
+    Export {
+        /// Include the installed versions
+        #[arg(required = false, long)]
+        with_versions: bool,
+    },
+
+    /// Converge the installed packages to the desired state in a spec file
+    ///
+    /// Prints a JSON summary whose status is either changed or unchanged.
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "ensure")]
+    Ensure {
+        /// TOML file listing the packages with optional versions and pins
+        #[arg(required = true, long)]
+        spec: String,
+
+        /// Remove installed packages the spec doesn't list
+        #[arg(required = false, long)]
+        prune: bool,
+
+        /// Number of parallel installs. Without it, up to the configured limit run, as many as throughput allows
+        #[arg(required = false, short, long)]
+        jobs: Option<usize>,
+    },
+
+    /// Install the packages listed in a file created by export
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "import")]
+    Import {
+        /// File listing the packages, one per line
+        #[arg(required = true)]
+        file: String,
+
+        /// Skip all prompts and use first
+        #[arg(required = false, short, long)]
+        yes: bool,
+    },
+
+    /// Show statistics about installed packages and repositories
+    #[clap(name = "stats")]
+    Stats,
+
+    /// Show disk usage of installed packages
+    #[clap(name = "du")]
+    Du {
+        /// Packages to show, all installed packages if omitted
+        #[arg(required = false)]
+        packages: Option<Vec<String>>,
+    },
+
+    /// Verify installed packages against their recorded checksums
+    #[clap(name = "verify")]
+    Verify {
+        /// Packages to verify, all installed packages if omitted
+        #[arg(required = false)]
+        packages: Option<Vec<String>>,
+
+        /// Reinstall packages that fail verification
+        #[arg(required = false, long)]
+        repair: bool,
+    },
+
+    /// Check installed packages for known vulnerabilities
+    #[clap(name = "audit")]
+    Audit {
+        /// Only audit packages from this repository
+        #[arg(required = false, long, short)]
+        repo_name: Option<String>,
+    },
+
+    /// Generate a software bill of materials for installed packages
+    #[clap(name = "sbom")]
+    Sbom {
+        /// Document format
+        #[arg(required = false, short, long, value_enum, default_value_t = SbomFormat::Spdx)]
+        format: SbomFormat,
+
+        /// Write the document to a file instead of stdout
+        #[arg(required = false, short, long, value_hint = ValueHint::FilePath)]
+        output: Option<String>,
     },
 
     /// Inspect package build log
@@ -142,6 +793,18 @@ pub enum Commands {
         /// Package to view log for
         #[arg(required = true)]
         package: String,
+
+        /// Write the log to stdout unmodified instead of using a pager
+        #[arg(required = false, long, conflicts_with = "save")]
+        raw: bool,
+
+        /// Save the log to a file
+        #[arg(required = false, long)]
+        save: Option<String>,
+
+        /// Show what changed since this version of the package as a unified diff
+        #[arg(required = false, long, value_name = "OLD_VERSION")]
+        compare: Option<String>,
     },
 
     /// Inspect package build script
@@ -151,6 +814,49 @@ pub enum Commands {
         /// Package to view build script for
         #[arg(required = true)]
         package: String,
+
+        /// Write the build script to stdout unmodified instead of using a pager
+        #[arg(required = false, long, conflicts_with = "save")]
+        raw: bool,
+
+        /// Save the build script to a file
+        #[arg(required = false, long)]
+        save: Option<String>,
+
+        /// Show what changed since this version of the package as a unified diff
+        #[arg(required = false, long, value_name = "OLD_VERSION")]
+        compare: Option<String>,
+    },
+
+    /// Show the README of a package's upstream project
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "readme")]
+    Readme {
+        /// Package to view the README of
+        #[arg(required = true)]
+        package: String,
+
+        /// Write the README to stdout as Markdown instead of rendering it
+        /// in a pager
+        #[arg(required = false, long)]
+        raw: bool,
+    },
+
+    /// Open the homepage of a package in the browser
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "open")]
+    Open {
+        /// Package to open the homepage of
+        #[arg(required = true)]
+        package: String,
+
+        /// Open the source URL instead
+        #[arg(required = false, long, conflicts_with = "build_log")]
+        source: bool,
+
+        /// Open the build log instead
+        #[arg(required = false, long)]
+        build_log: bool,
     },
 
     /// Run packages without installing to PATH
@@ -166,11 +872,12 @@ pub enum Commands {
         command: Vec<String>,
     },
 
-    /// Use package from different family
+    /// Link an installed version or variant of a package under its plain
+    /// name, moving the one linked there before to a suffixed name
     #[command(arg_required_else_help = true)]
     #[clap(name = "use")]
     Use {
-        /// The package to use
+        /// The installed package to link, e.g. node@20.11.0 or node#nodejs
         #[arg(required = true)]
         package: String,
     },
@@ -187,10 +894,25 @@ pub enum Commands {
         #[arg(required = false, short, long)]
         yes: bool,
 
+        /// Take the release asset that suits the host best without asking,
+        /// even if others rank equally
+        #[arg(required = false, long)]
+        auto: bool,
+
         /// Output file path
         #[arg(required = false, short, long, value_hint = ValueHint::AnyPath)]
         output: Option<String>,
 
+        /// Path of each download under the output directory, e.g.
+        /// "{repo}/{tag}/{name}". `{repo}` is the project, OCI package or
+        /// host, `{tag}` the release or OCI tag and `{name}` the file name
+        #[arg(required = false, long, value_name = "TEMPLATE")]
+        output_template: Option<String>,
+
+        /// What to do if a templated output path already exists
+        #[arg(long, value_enum, default_value_t, requires = "output_template")]
+        on_conflict: OutputConflict,
+
         /// Regex to select the asset. Only works for github downloads
         #[arg(required = false, short = 'r', long = "regex")]
         regex_patterns: Option<Vec<String>>,
@@ -207,16 +929,73 @@ pub enum Commands {
         #[arg(required = false, long)]
         github: Vec<String>,
 
+        /// Allow picking prereleases for GitHub and GitLab projects
+        #[arg(required = false, long)]
+        pre: bool,
+
         /// Gitlab project
         #[arg(required = false, long)]
         gitlab: Vec<String>,
 
+        /// Self-hosted GitLab instance the GitLab projects are on, e.g.
+        /// https://gitlab.example.com
+        #[arg(required = false, long, value_name = "URL")]
+        gitlab_instance: Option<String>,
+
         /// OCI reference
         #[arg(required = false, long)]
         ghcr: Vec<String>,
+
+        /// Download only the files of OCI artifacts matching the glob, e.g.
+        /// "*.AppImage", can be repeated
+        #[arg(required = false, long = "oci-file", value_name = "GLOB")]
+        oci_files: Vec<String>,
+
+        /// Maximum number of parallel downloads
+        #[arg(required = false, short, long, value_name = "N")]
+        jobs: Option<usize>,
+
+        /// Keep downloading the rest when an item fails, the default,
+        /// overrides an earlier --fail-fast
+        #[arg(required = false, long, overrides_with = "fail_fast")]
+        keep_going: bool,
+
+        /// Stop starting downloads once one fails, the rest stay queued for
+        /// --resume-all
+        #[arg(required = false, long, overrides_with = "keep_going")]
+        fail_fast: bool,
+
+        /// Resume the downloads left unfinished by interrupted sessions
+        #[arg(required = false, long)]
+        resume_all: bool,
+
+        /// Download even if the file is cached, and don't cache it
+        #[arg(required = false, long)]
+        no_cache: bool,
+
+        /// Fail unless each download is proven to come from its project,
+        /// by a GitHub artifact attestation, checked with the gh CLI, or a
+        /// minisign signature checked with --minisign-key
+        #[arg(required = false, long)]
+        require_attestation: bool,
+
+        /// Minisign public key, or the file containing it, to check the
+        /// `.minisig` signatures published with release assets against
+        #[arg(required = false, long, value_name = "KEY")]
+        minisign_key: Option<String>,
+
+        /// List the releases of a GitHub or GitLab project, e.g.
+        /// github:owner/repo, or the files of an OCI artifact, e.g.
+        /// ghcr.io/owner/package:tag, instead of downloading
+        #[arg(required = false, long, value_name = "PROJECT")]
+        list: Option<String>,
+
+        /// List the assets of this release of the listed project
+        #[arg(required = false, long, value_name = "TAG", requires = "list")]
+        list_assets: Option<String>,
     },
 
-    /// Health check
+    /// Check that the installed binaries can be run
     #[clap(name = "health")]
     Health,
 
@@ -224,9 +1003,66 @@ pub enum Commands {
     #[clap(name = "defconfig")]
     DefConfig,
 
+    /// Show the help of a command, or a help topic
+    #[clap(name = "help")]
+    Help {
+        /// Command, e.g. `repo add`, or topic. Lists the topics if omitted
+        topic: Vec<String>,
+    },
+
+    /// Write man pages for soar and each of its commands
+    #[clap(name = "generate-man")]
+    GenerateMan {
+        /// Directory to write the pages to
+        #[arg(required = false, short, long, default_value = "man", value_hint = ValueHint::DirPath)]
+        out_dir: PathBuf,
+    },
+
+    /// Set up soar for first use: write the config, create the directories,
+    /// add the bin directory to PATH and sync the repositories
+    #[clap(name = "init")]
+    Init {
+        /// Default repositories to enable, all of them unless given
+        #[arg(
+            required = false,
+            long = "repo",
+            value_name = "NAME",
+            value_delimiter = ','
+        )]
+        repos: Vec<String>,
+
+        /// Shell to set up PATH for, detected from $SHELL unless given
+        #[arg(required = false, long, value_enum)]
+        shell: Option<HookShell>,
+
+        /// Leave PATH as it is
+        #[arg(required = false, long, conflicts_with = "shell")]
+        no_path: bool,
+
+        /// Don't sync the repositories
+        #[arg(required = false, long)]
+        no_sync: bool,
+
+        /// Take the defaults without asking
+        #[arg(required = false, short, long)]
+        yes: bool,
+    },
+
     /// View env
     #[clap(name = "env")]
-    Env,
+    Env {
+        #[command(subcommand)]
+        action: Option<EnvAction>,
+    },
+
+    /// Manage the cache of files downloaded with `soar dl`, and of packages
+    /// with `keep_downloads` enabled
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "cache")]
+    Cache {
+        #[clap(subcommand)]
+        action: CacheAction,
+    },
 
     /// Garbage collection
     #[clap(name = "clean")]
@@ -238,6 +1074,39 @@ pub enum Commands {
         /// Clean broken symlinks
         #[arg(required = false, short, long)]
         broken_symlinks: bool,
+
+        /// Drop the unfinished downloads kept for `soar dl --resume-all`
+        #[arg(required = false, long)]
+        downloads: bool,
+    },
+
+    /// Remove stored package files no installed package refers to
+    #[clap(name = "gc")]
+    Gc,
+
+    /// Serve soar operations over JSON-RPC on a unix socket
+    #[clap(name = "daemon")]
+    Daemon {
+        /// Path to the unix socket to listen on
+        #[arg(required = false, short, long, value_hint = ValueHint::FilePath)]
+        socket: Option<String>,
+
+        /// Serve Prometheus metrics over HTTP at /metrics on this address (e.g. 127.0.0.1:9184)
+        #[arg(required = false, long, value_name = "ADDR")]
+        metrics: Option<String>,
+    },
+
+    /// Expose soar as a D-Bus service on the session bus
+    #[cfg(feature = "dbus")]
+    #[clap(name = "dbus")]
+    Dbus,
+
+    /// Manage repositories
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "repo")]
+    Repo {
+        #[clap(subcommand)]
+        action: RepoAction,
     },
 
     /// Modify the soar installation
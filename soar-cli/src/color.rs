@@ -0,0 +1,170 @@
+use std::{
+    env,
+    fmt::Display,
+    io::{stdout, IsTerminal},
+    sync::OnceLock,
+};
+
+use nu_ansi_term::Color as AnsiColor;
+use soar_core::config::ThemeConfig;
+use tracing::warn;
+
+use crate::cli::ColorChoice;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Role of a piece of output, mapped to an actual color by the theme.
+#[derive(Clone, Copy)]
+pub enum Color {
+    Success,
+    Warn,
+    Error,
+    Accent,
+    Info,
+    Value,
+    Label,
+}
+
+struct Theme {
+    enabled: bool,
+    success: AnsiColor,
+    warn: AnsiColor,
+    error: AnsiColor,
+    accent: AnsiColor,
+    info: AnsiColor,
+    value: AnsiColor,
+    label: AnsiColor,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            success: AnsiColor::Green,
+            warn: AnsiColor::Yellow,
+            error: AnsiColor::Red,
+            accent: AnsiColor::Blue,
+            info: AnsiColor::Cyan,
+            value: AnsiColor::Magenta,
+            label: AnsiColor::Red,
+        }
+    }
+}
+
+impl Theme {
+    fn get(&self, color: Color) -> AnsiColor {
+        match color {
+            Color::Success => self.success,
+            Color::Warn => self.warn,
+            Color::Error => self.error,
+            Color::Accent => self.accent,
+            Color::Info => self.info,
+            Color::Value => self.value,
+            Color::Label => self.label,
+        }
+    }
+}
+
+fn theme() -> &'static Theme {
+    static DEFAULT: OnceLock<Theme> = OnceLock::new();
+    THEME
+        .get()
+        .unwrap_or_else(|| DEFAULT.get_or_init(Theme::default))
+}
+
+/// Parses a color name, 256-color index or `#rrggbb` hex code.
+fn parse_color(value: &str) -> Option<AnsiColor> {
+    let value = value.trim().to_lowercase().replace('-', "_");
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(AnsiColor::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(index) = value.parse::<u8>() {
+        return Some(AnsiColor::Fixed(index));
+    }
+
+    let color = match value.as_str() {
+        "black" => AnsiColor::Black,
+        "red" => AnsiColor::Red,
+        "green" => AnsiColor::Green,
+        "yellow" => AnsiColor::Yellow,
+        "blue" => AnsiColor::Blue,
+        "purple" | "magenta" => AnsiColor::Purple,
+        "cyan" => AnsiColor::Cyan,
+        "white" => AnsiColor::White,
+        "dark_gray" | "dark_grey" => AnsiColor::DarkGray,
+        "light_red" => AnsiColor::LightRed,
+        "light_green" => AnsiColor::LightGreen,
+        "light_yellow" => AnsiColor::LightYellow,
+        "light_blue" => AnsiColor::LightBlue,
+        "light_purple" | "light_magenta" => AnsiColor::LightPurple,
+        "light_cyan" => AnsiColor::LightCyan,
+        "light_gray" | "light_grey" => AnsiColor::LightGray,
+        "default" => AnsiColor::Default,
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// Decides whether output is colored and loads the configured theme.
+///
+/// With [`ColorChoice::Auto`], colors are used only when stdout is a
+/// terminal, `NO_COLOR` is unset and the output isn't JSON.
+pub fn init_colors(choice: ColorChoice, json: bool, config: &ThemeConfig) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            !json
+                && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && stdout().is_terminal()
+        }
+    };
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+
+    let mut theme = Theme {
+        enabled,
+        ..Default::default()
+    };
+    let entries = [
+        ("success", &config.success, &mut theme.success),
+        ("warn", &config.warn, &mut theme.warn),
+        ("error", &config.error, &mut theme.error),
+        ("accent", &config.accent, &mut theme.accent),
+        ("info", &config.info, &mut theme.info),
+        ("value", &config.value, &mut theme.value),
+        ("label", &config.label, &mut theme.label),
+    ];
+    for (name, value, color) in entries {
+        let Some(value) = value else {
+            continue;
+        };
+        match parse_color(value) {
+            Some(parsed) => *color = parsed,
+            None => warn!("Invalid color '{}' for theme.{}", value, name),
+        }
+    }
+
+    let _ = THEME.set(theme);
+}
+
+pub trait ColorExt {
+    /// Renders `self` in the theme color of `color`, or as plain text if
+    /// colors are disabled.
+    fn color(&self, color: Color) -> String;
+}
+
+impl<T: Display + ?Sized> ColorExt for T {
+    fn color(&self, color: Color) -> String {
+        let theme = theme();
+        if theme.enabled {
+            theme.get(color).paint(self.to_string()).to_string()
+        } else {
+            self.to_string()
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, Permissions},
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    sync::{atomic::AtomicU64, Arc},
+};
+
+use indicatif::ProgressDrawTarget;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use soar_core::{
+    config::get_config,
+    database::{
+        models::{InstalledPackage, Package},
+        packages::{get_installed_packages, get_packages, FilterOp, QueryOptions},
+    },
+    error::SoarError,
+    events::{EventSink, InstallEvent},
+    lock::InstanceLock,
+    package::install::InstallTarget,
+    SoarResult,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::mpsc::{self, UnboundedSender},
+    task::block_in_place,
+};
+use tracing::{error, info};
+
+use crate::{
+    install::{create_install_context, install_single_package, resolve_packages},
+    metrics::{serve_metrics, Metrics},
+    state::AppState,
+    update::resolve_update_targets,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<SoarError> for RpcError {
+    fn from(value: SoarError) -> Self {
+        RpcError {
+            code: SERVER_ERROR,
+            message: value.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(value: serde_json::Error) -> Self {
+        RpcError {
+            code: INVALID_PARAMS,
+            message: value.to_string(),
+        }
+    }
+}
+
+type RpcResult = Result<Value, RpcError>;
+
+#[derive(Deserialize)]
+struct SearchParams {
+    query: String,
+    #[serde(default)]
+    case_sensitive: bool,
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+struct ListParams {
+    repo_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct InstallParams {
+    packages: Vec<String>,
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct UpdateParams {
+    packages: Option<Vec<String>>,
+}
+
+/// Default location of the daemon socket.
+///
+/// Uses `$XDG_RUNTIME_DIR/soar.sock` when available, falling back to the
+/// soar database directory.
+pub fn default_socket_path() -> SoarResult<PathBuf> {
+    match env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => Ok(PathBuf::from(dir).join("soar.sock")),
+        _ => Ok(get_config().get_db_path()?.join("soar.sock")),
+    }
+}
+
+pub async fn run_daemon(socket: Option<String>, metrics_addr: Option<String>) -> SoarResult<()> {
+    let socket_path = match socket {
+        Some(path) => PathBuf::from(path),
+        None => default_socket_path()?,
+    };
+
+    if socket_path.exists() {
+        if UnixStream::connect(&socket_path).await.is_ok() {
+            return Err(SoarError::Custom(format!(
+                "Another soar daemon is already listening on {}",
+                socket_path.display()
+            )));
+        }
+        fs::remove_file(&socket_path)?;
+    }
+
+    let state = AppState::new().await?;
+
+    let metrics = Metrics::new();
+    if let Some(addr) = metrics_addr {
+        serve_metrics(&addr, metrics.clone(), state.clone()).await?;
+    }
+
+    let listener = UnixListener::bind(&socket_path)?;
+    fs::set_permissions(&socket_path, Permissions::from_mode(0o600))?;
+    info!("Listening on {}", socket_path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = state.clone();
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle_connection(stream, state, metrics).await {
+                error!("Daemon connection error: {}", err);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    state: AppState,
+    metrics: Arc<Metrics>,
+) -> SoarResult<()> {
+    let (reader, mut writer) = stream.into_split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
+    let writer_task = tokio::spawn(async move {
+        while let Some(mut line) = rx.recv().await {
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let tx = tx.clone();
+        let state = state.clone();
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Some(response) = handle_message(&line, state, &metrics, tx.clone()).await {
+                let _ = tx.send(response.to_string());
+            }
+        });
+    }
+
+    drop(tx);
+    let _ = writer_task.await;
+
+    Ok(())
+}
+
+async fn handle_message(
+    line: &str,
+    state: AppState,
+    metrics: &Arc<Metrics>,
+    notifier: UnboundedSender<String>,
+) -> Option<Value> {
+    let request: RpcRequest = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError {
+                    code: PARSE_ERROR,
+                    message: err.to_string(),
+                },
+            ))
+        }
+    };
+
+    let id = request.id.clone();
+    if request.jsonrpc != "2.0" {
+        return Some(error_response(
+            id.unwrap_or(Value::Null),
+            RpcError {
+                code: INVALID_REQUEST,
+                message: "Only JSON-RPC 2.0 is supported".into(),
+            },
+        ));
+    }
+
+    let result = dispatch(&request, state, metrics, notifier).await;
+
+    // requests without id are notifications and expect no response
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => error_response(id, err),
+    })
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": err.code, "message": err.message },
+    })
+}
+
+fn parse_params<T: for<'de> Deserialize<'de> + Default>(params: &Value) -> Result<T, RpcError> {
+    if params.is_null() {
+        Ok(T::default())
+    } else {
+        Ok(serde_json::from_value(params.clone())?)
+    }
+}
+
+async fn dispatch(
+    request: &RpcRequest,
+    state: AppState,
+    metrics: &Arc<Metrics>,
+    notifier: UnboundedSender<String>,
+) -> RpcResult {
+    match request.method.as_str() {
+        "search" => {
+            let params: SearchParams = serde_json::from_value(request.params.clone())?;
+            rpc_search(&state, params)
+        }
+        "list" => rpc_list(&state, parse_params(&request.params)?),
+        "install" => {
+            let params: InstallParams = serde_json::from_value(request.params.clone())?;
+            rpc_install(&state, params, metrics, notifier).await
+        }
+        "update" => rpc_update(&state, parse_params(&request.params)?, metrics, notifier).await,
+        method => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("Method {} not found", method),
+        }),
+    }
+}
+
+fn package_to_json(package: &Package) -> Value {
+    json!({
+        "pkg": package.pkg,
+        "pkg_id": package.pkg_id,
+        "pkg_name": package.pkg_name,
+        "repo_name": package.repo_name,
+        "version": package.version,
+        "description": package.description,
+        "size": package.size,
+    })
+}
+
+fn installed_to_json(package: &InstalledPackage) -> Value {
+    json!({
+        "pkg": package.pkg,
+        "pkg_id": package.pkg_id,
+        "pkg_name": package.pkg_name,
+        "repo_name": package.repo_name,
+        "version": package.version,
+        "size": package.size,
+        "installed_date": package.installed_date,
+        "installed_path": package.installed_path,
+        "pinned": package.pinned,
+        "is_installed": package.is_installed,
+    })
+}
+
+fn rpc_search(state: &AppState, params: SearchParams) -> RpcResult {
+    let mut filters = HashMap::new();
+    let op = if params.case_sensitive {
+        FilterOp::Like
+    } else {
+        FilterOp::ILike
+    };
+    filters.insert("pkg_name".to_string(), (op, params.query.into()).into());
+
+    let limit = params
+        .limit
+        .or(get_config().search_limit.map(|l| l as u32))
+        .unwrap_or(20);
+
+    let packages = get_packages(
+        state.repo_db().clone(),
+        QueryOptions {
+            limit,
+            filters,
+            ..Default::default()
+        },
+    )?;
+
+    Ok(json!({
+        "total": packages.total,
+        "items": packages.items.iter().map(package_to_json).collect::<Vec<_>>(),
+    }))
+}
+
+fn rpc_list(state: &AppState, params: ListParams) -> RpcResult {
+    let mut filters = HashMap::new();
+    if let Some(repo_name) = params.repo_name {
+        filters.insert(
+            "repo_name".to_string(),
+            (FilterOp::Eq, repo_name.into()).into(),
+        );
+    }
+
+    let packages = get_installed_packages(
+        state.core_db().clone(),
+        QueryOptions {
+            filters,
+            ..Default::default()
+        },
+    )?;
+
+    Ok(json!({
+        "total": packages.total,
+        "items": packages.items.iter().map(installed_to_json).collect::<Vec<_>>(),
+    }))
+}
+
+async fn rpc_install(
+    state: &AppState,
+    params: InstallParams,
+    metrics: &Arc<Metrics>,
+    notifier: UnboundedSender<String>,
+) -> RpcResult {
+    let targets = resolve_packages(
+        state.repo_db().clone(),
+        state.core_db().clone(),
+        &params.packages,
+        true,
+        params.force,
+    )?;
+
+    run_targets(state, targets, metrics, notifier).await
+}
+
+async fn rpc_update(
+    state: &AppState,
+    params: UpdateParams,
+    metrics: &Arc<Metrics>,
+    notifier: UnboundedSender<String>,
+) -> RpcResult {
+    let targets = resolve_update_targets(state, params.packages, Vec::new(), None, None).await?;
+
+    run_targets(state, targets, metrics, notifier).await
+}
+
+async fn run_targets(
+    state: &AppState,
+    targets: Vec<InstallTarget>,
+    metrics: &Arc<Metrics>,
+    notifier: UnboundedSender<String>,
+) -> RpcResult {
+    let ctx = create_install_context(targets.len(), Some(1), None, None, None);
+    ctx.multi_progress
+        .set_draw_target(ProgressDrawTarget::hidden());
+
+    let _lock = block_in_place(InstanceLock::acquire)?;
+
+    let mut installed = Vec::new();
+    let mut failed = Vec::new();
+
+    for target in targets {
+        let notifier = notifier.clone();
+
+        let metrics_sink = metrics.clone();
+        let position = AtomicU64::new(0);
+        let events: EventSink = Arc::new(move |event: InstallEvent| {
+            metrics_sink.record_event(&event, &position);
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "progress",
+                "params": event,
+            });
+            let _ = notifier.send(notification.to_string());
+        });
+
+        let package = package_to_json(&target.package);
+        let result = install_single_package(&ctx, target, events, state.core_db().clone()).await;
+        metrics.record_install(result.is_ok());
+        match result {
+            Ok(()) => installed.push(package),
+            Err(err) => failed.push(json!({ "package": package, "error": err.to_string() })),
+        }
+    }
+
+    Ok(json!({ "installed": installed, "failed": failed }))
+}
@@ -0,0 +1,221 @@
+//! D-Bus service exposing soar as `dev.soar.PackageManager` on the session
+//! bus.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use indicatif::ProgressDrawTarget;
+use soar_core::{
+    config::get_config,
+    database::packages::{get_packages, FilterOp, QueryOptions},
+    error::SoarError,
+    events::{EventSink, InstallEvent},
+    lock::InstanceLock,
+    SoarResult,
+};
+use tokio::{sync::mpsc, task::block_in_place};
+use tracing::{error, info};
+use zbus::{connection, interface, object_server::SignalEmitter, DBusError};
+
+use crate::{
+    install::{create_install_context, install_single_package, resolve_packages},
+    remove::remove_packages,
+    state::AppState,
+};
+
+pub const SERVICE_NAME: &str = "dev.soar.PackageManager";
+pub const OBJECT_PATH: &str = "/dev/soar/PackageManager";
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "dev.soar.PackageManager.Error")]
+enum Error {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Failed(String),
+}
+
+impl From<SoarError> for Error {
+    fn from(err: SoarError) -> Self {
+        error!("D-Bus call failed: {}", err);
+        Error::Failed(err.to_string())
+    }
+}
+
+struct PackageManager {
+    state: AppState,
+}
+
+#[interface(name = "dev.soar.PackageManager")]
+impl PackageManager {
+    #[zbus(out_args("packages"))]
+    async fn search(&self, query: String) -> Result<Vec<(String, String, String, String)>, Error> {
+        Ok(search(&self.state, query)?)
+    }
+
+    #[zbus(out_args("installed", "failed"))]
+    async fn install(
+        &self,
+        packages: Vec<String>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> Result<(Vec<String>, Vec<String>), Error> {
+        Ok(install(&self.state, emitter.to_owned(), packages).await?)
+    }
+
+    #[zbus(out_args("removed", "failed"))]
+    async fn remove(&self, packages: Vec<String>) -> Result<(Vec<String>, Vec<String>), Error> {
+        Ok(remove(packages).await?)
+    }
+
+    #[zbus(signal)]
+    async fn progress(
+        emitter: &SignalEmitter<'_>,
+        package: &str,
+        state: &str,
+        current: u64,
+        total: u64,
+    ) -> zbus::Result<()>;
+}
+
+fn bus_error(err: zbus::Error) -> SoarError {
+    SoarError::Custom(format!("D-Bus error: {}", err))
+}
+
+pub async fn run_dbus_service() -> SoarResult<()> {
+    let state = AppState::new().await?;
+
+    let _conn = connection::Builder::session()
+        .and_then(|builder| builder.name(SERVICE_NAME))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, PackageManager { state }))
+        .map_err(bus_error)?
+        .build()
+        .await
+        .map_err(|err| {
+            SoarError::Custom(format!(
+                "Failed to register {} on the session bus: {}",
+                SERVICE_NAME, err
+            ))
+        })?;
+    info!("Registered {} on the session bus", SERVICE_NAME);
+
+    std::future::pending::<()>().await;
+    Ok(())
+}
+
+fn search(state: &AppState, query: String) -> SoarResult<Vec<(String, String, String, String)>> {
+    let mut filters = HashMap::new();
+    filters.insert(
+        "pkg_name".to_string(),
+        (FilterOp::ILike, query.into()).into(),
+    );
+
+    let packages = get_packages(
+        state.repo_db().clone(),
+        QueryOptions {
+            limit: get_config().search_limit.unwrap_or(20) as u32,
+            filters,
+            ..Default::default()
+        },
+    )?;
+
+    Ok(packages
+        .items
+        .into_iter()
+        .map(|p| (p.pkg_name, p.pkg_id, p.version, p.repo_name))
+        .collect())
+}
+
+async fn install(
+    state: &AppState,
+    emitter: SignalEmitter<'static>,
+    packages: Vec<String>,
+) -> SoarResult<(Vec<String>, Vec<String>)> {
+    let targets = resolve_packages(
+        state.repo_db().clone(),
+        state.core_db().clone(),
+        &packages,
+        true,
+        false,
+    )?;
+
+    let ctx = create_install_context(targets.len(), Some(1), None, None, None);
+    ctx.multi_progress
+        .set_draw_target(ProgressDrawTarget::hidden());
+
+    let _lock = block_in_place(InstanceLock::acquire)?;
+
+    // events are emitted synchronously, the signals are sent in order from here
+    let (tx, mut rx) = mpsc::unbounded_channel::<(String, &'static str, u64, u64)>();
+    let signals = tokio::spawn(async move {
+        while let Some((package, state, current, total)) = rx.recv().await {
+            if let Err(err) =
+                PackageManager::progress(&emitter, &package, state, current, total).await
+            {
+                error!("Failed to emit D-Bus progress signal: {}", err);
+            }
+        }
+    });
+
+    let mut installed = Vec::new();
+    let mut failed = Vec::new();
+    for target in targets {
+        let pkg_name = target.package.pkg_name.clone();
+        let tx = tx.clone();
+        let total = Arc::new(AtomicU64::new(0));
+        let events: EventSink = Arc::new(move |event: InstallEvent| {
+            let signal = match event {
+                InstallEvent::DownloadStarted {
+                    pkg_name,
+                    total: len,
+                    ..
+                } => {
+                    total.store(len, Ordering::Relaxed);
+                    (pkg_name, "preparing", 0, len)
+                }
+                InstallEvent::DownloadProgress {
+                    pkg_name,
+                    downloaded,
+                    ..
+                } => (
+                    pkg_name,
+                    "progress",
+                    downloaded,
+                    total.load(Ordering::Relaxed),
+                ),
+                InstallEvent::DownloadComplete { pkg_name, .. } => (pkg_name, "complete", 0, 0),
+                _ => return,
+            };
+            let _ = tx.send(signal);
+        });
+
+        match install_single_package(&ctx, target, events, state.core_db().clone()).await {
+            Ok(()) => installed.push(pkg_name),
+            Err(err) => failed.push(format!("{}: {}", pkg_name, err)),
+        }
+    }
+    drop(tx);
+    let _ = signals.await;
+
+    Ok((installed, failed))
+}
+
+/// Removes `packages` one at a time through the same path as `soar remove`,
+/// so hooks and history apply, reporting each as removed or failed.
+async fn remove(packages: Vec<String>) -> SoarResult<(Vec<String>, Vec<String>)> {
+    let _lock = block_in_place(InstanceLock::acquire)?;
+
+    let mut removed = Vec::new();
+    let mut failed = Vec::new();
+    for package in packages {
+        match remove_packages(std::slice::from_ref(&package), false, true, false, false).await {
+            Ok(()) => removed.push(package),
+            Err(err) => failed.push(format!("{}: {}", package, err)),
+        }
+    }
+
+    Ok((removed, failed))
+}
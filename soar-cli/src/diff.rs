@@ -0,0 +1,223 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+
+use indicatif::HumanBytes;
+use soar_core::{
+    database::{
+        models::{InstalledPackage, Package},
+        packages::{
+            get_installed_packages, get_package_provides, get_packages, Filter, FilterOp,
+            QueryOptions,
+        },
+    },
+    error::SoarError,
+    package::{
+        github::{fetch_github_assets, github_package},
+        query::PackageQuery,
+    },
+    platform::is_executable,
+    SoarResult,
+};
+use tracing::info;
+
+use crate::{
+    color::{Color, ColorExt},
+    state::AppState,
+    update::find_update,
+};
+
+/// Compares an installed package against the version an update would
+/// install, or against `against` if given.
+pub async fn diff_package(package: &str, against: Option<&str>) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let core_db = state.core_db().clone();
+    let repo_db = state.repo_db().clone();
+
+    let query = PackageQuery::try_from(package)?;
+    let mut filters = query.create_filter();
+    filters.insert(
+        "is_installed".to_string(),
+        (FilterOp::Eq, "1".to_string().into()).into(),
+    );
+    let options = QueryOptions {
+        filters,
+        limit: 1,
+        ..Default::default()
+    };
+    let installed = get_installed_packages(core_db, options)?
+        .items
+        .into_iter()
+        .next()
+        .ok_or_else(|| SoarError::PackageNotFound(package.to_string()))?;
+
+    let candidate = if let Some(project) = installed.upstream.as_deref() {
+        let release = fetch_github_assets(
+            project,
+            against,
+            installed.asset_pattern.as_deref(),
+            installed.channel,
+        )
+        .await?;
+        release
+            .assets
+            .first()
+            .map(|asset| github_package(project, &release.tag, asset))
+    } else {
+        let mut filters: HashMap<String, Filter> = [
+            ("r.name", installed.repo_name.clone()),
+            ("pkg_name", installed.pkg_name.clone()),
+            ("pkg_id", installed.pkg_id.clone()),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), (FilterOp::Eq, value.into()).into()))
+        .collect();
+
+        match against {
+            Some(version) => {
+                filters.insert(
+                    "version".to_string(),
+                    (FilterOp::Eq, version.to_string().into()).into(),
+                );
+                let options = QueryOptions {
+                    filters,
+                    limit: 1,
+                    ..Default::default()
+                };
+                get_packages(repo_db.clone(), options)?
+                    .items
+                    .into_iter()
+                    .next()
+            }
+            None => find_update(repo_db.clone(), filters, &installed)?,
+        }
+    };
+
+    let Some(candidate) = candidate else {
+        if let Some(version) = against {
+            return Err(SoarError::PackageNotFound(format!(
+                "{}#{}@{}",
+                installed.pkg_name, installed.pkg_id, version
+            )));
+        }
+        info!("{}#{} is up to date", installed.pkg_name, installed.pkg_id);
+        return Ok(());
+    };
+
+    let mut old_bins: BTreeSet<String> = get_package_provides(
+        repo_db.clone(),
+        &installed.repo_name,
+        &installed.pkg_id,
+        &installed.version,
+    )?
+    .into_iter()
+    .collect();
+    if old_bins.is_empty() {
+        old_bins = installed_executables(&installed);
+    }
+    let mut new_bins: BTreeSet<String> = get_package_provides(
+        repo_db,
+        &installed.repo_name,
+        &installed.pkg_id,
+        &candidate.version,
+    )?
+    .into_iter()
+    .collect();
+    if new_bins.is_empty() {
+        new_bins.insert(candidate.pkg_name.clone());
+    }
+
+    print_diff(&installed, &candidate, &old_bins, &new_bins);
+
+    Ok(())
+}
+
+/// Executable files in the install directory of `package`.
+fn installed_executables(package: &InstalledPackage) -> BTreeSet<String> {
+    let Ok(entries) = fs::read_dir(Path::new(&package.installed_path)) else {
+        return BTreeSet::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|meta| is_executable(&entry.path(), &meta))
+        })
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect()
+}
+
+fn size_delta(old: u64, new: u64) -> String {
+    if new >= old {
+        format!("+{}", HumanBytes(new - old))
+    } else {
+        format!("-{}", HumanBytes(old - new))
+    }
+}
+
+fn print_diff(
+    installed: &InstalledPackage,
+    candidate: &Package,
+    old_bins: &BTreeSet<String>,
+    new_bins: &BTreeSet<String>,
+) {
+    info!(
+        "{}: {}#{}:{}",
+        "Package".color(Color::Label),
+        installed.pkg_name.color(Color::Accent),
+        installed.pkg_id.color(Color::Info),
+        installed.repo_name.color(Color::Info)
+    );
+    info!(
+        "{}: {} -> {}",
+        "Version".color(Color::Label),
+        installed.version.color(Color::Value),
+        candidate.version.color(Color::Value)
+    );
+    info!(
+        "{}: {} -> {} ({})",
+        "Size".color(Color::Label),
+        HumanBytes(installed.size),
+        HumanBytes(candidate.size),
+        size_delta(installed.size, candidate.size).color(Color::Warn)
+    );
+
+    // stripped or compressed binaries are compared as they were downloaded
+    let installed_checksum = installed
+        .original_checksum
+        .as_ref()
+        .unwrap_or(&installed.checksum);
+    if candidate.checksum.is_empty() {
+        info!(
+            "{}: unknown for {}",
+            "Checksum".color(Color::Label),
+            candidate.version
+        );
+    } else if candidate.checksum == *installed_checksum {
+        info!("{}: unchanged", "Checksum".color(Color::Label));
+    } else {
+        info!(
+            "{}: {} -> {}",
+            "Checksum".color(Color::Label),
+            installed_checksum,
+            candidate.checksum
+        );
+    }
+
+    let added: Vec<_> = new_bins.difference(old_bins).collect();
+    let removed: Vec<_> = old_bins.difference(new_bins).collect();
+    if added.is_empty() && removed.is_empty() {
+        info!("{}: unchanged", "Binaries".color(Color::Label));
+        return;
+    }
+    info!("{}:", "Binaries".color(Color::Label));
+    for bin in added {
+        info!("  {} {}", "+".color(Color::Success), bin);
+    }
+    for bin in removed {
+        info!("  {} {}", "-".color(Color::Error), bin);
+    }
+}
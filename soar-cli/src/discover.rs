@@ -0,0 +1,127 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{stdin, stdout, IsTerminal},
+};
+
+use soar_core::{
+    database::packages::{get_installed_packages, get_packages, FilterOp, QueryOptions, SortOrder},
+    SoarResult,
+};
+use tracing::{error, info};
+
+use crate::{
+    color::{Color, ColorExt},
+    i18n::t,
+    install::{install_packages, InstallArgs},
+    state::AppState,
+    utils::interactive_ask,
+};
+
+/// Shows `count` random packages that aren't installed, optionally from
+/// `repo_name` or in `category`, and offers to install some of them.
+pub async fn discover_packages(
+    count: usize,
+    repo_name: Option<String>,
+    category: Option<String>,
+) -> SoarResult<()> {
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+    let core_db = state.core_db().clone();
+
+    let installed: HashSet<(String, String, String)> =
+        get_installed_packages(core_db, QueryOptions::default())?
+            .items
+            .into_iter()
+            .map(|p| (p.repo_name, p.pkg_id, p.pkg_name))
+            .collect();
+
+    let mut filters = HashMap::new();
+    filters.insert(
+        "disabled".to_string(),
+        (FilterOp::Eq, "0".to_string().into()).into(),
+    );
+    if let Some(repo_name) = repo_name {
+        filters.insert(
+            "r.name".to_string(),
+            (FilterOp::Eq, repo_name.into()).into(),
+        );
+    }
+    if let Some(category) = category {
+        filters.insert(
+            "category".to_string(),
+            (FilterOp::Eq, category.into()).into(),
+        );
+    }
+    // enough to still have `count` left if all installed ones are drawn
+    let options = QueryOptions {
+        limit: (count + installed.len()) as u32,
+        filters,
+        sort_by: vec![("RANDOM()".to_string(), SortOrder::Asc)],
+        ..Default::default()
+    };
+    let packages: Vec<_> = get_packages(repo_db, options)?
+        .items
+        .into_iter()
+        .filter(|p| {
+            !installed.contains(&(p.repo_name.clone(), p.pkg_id.clone(), p.pkg_name.clone()))
+        })
+        .take(count)
+        .collect();
+    if packages.is_empty() {
+        info!("{}", t!("discover-none"));
+        return Ok(());
+    }
+
+    for (idx, package) in packages.iter().enumerate() {
+        info!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            version = %package.version,
+            repo_name = %package.repo_name,
+            description = %package.description,
+            "[{}] {}#{}-{}:{} - {}",
+            idx + 1,
+            package.pkg_name.color(Color::Accent),
+            package.pkg_id.color(Color::Info),
+            package.version.color(Color::Value),
+            package.repo_name.color(Color::Info),
+            package.description
+        );
+    }
+
+    if !stdin().is_terminal() || !stdout().is_terminal() {
+        return Ok(());
+    }
+    let response = interactive_ask(&t!("discover-prompt"))?;
+    let mut selected = Vec::new();
+    for choice in response
+        .split([' ', ','])
+        .filter(|choice| !choice.is_empty())
+    {
+        match choice.parse::<usize>() {
+            Ok(n) if n > 0 && n <= packages.len() => {
+                let package = &packages[n - 1];
+                selected.push(format!(
+                    "{}#{}:{}",
+                    package.pkg_name, package.pkg_id, package.repo_name
+                ));
+            }
+            _ => {
+                error!("{}", t!("invalid-selection"));
+                return Ok(());
+            }
+        }
+    }
+    if selected.is_empty() {
+        return Ok(());
+    }
+
+    install_packages(
+        &selected,
+        InstallArgs {
+            yes: true,
+            ..Default::default()
+        },
+    )
+    .await
+}
@@ -1,34 +1,144 @@
-use std::sync::Arc;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{self, Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
-use indicatif::HumanBytes;
-use regex::Regex;
+use futures::{stream, StreamExt};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar};
+use reqwest::Url;
+use rusqlite::Connection;
 use serde::Deserialize;
-use soar_core::SoarResult;
+use soar_core::{
+    attestation::{verify_asset, AssetSources, Check},
+    config::get_config,
+    database::download_queue::{
+        finish_download, get_queued_downloads, queue_download, record_download_progress,
+        QueuedDownload,
+    },
+    download_cache::{self, fetch_validators},
+    error::SoarError,
+    http::{with_failover, with_retry},
+    package_cache,
+    platform::asset_score,
+    utils::glob_match,
+    SoarResult,
+};
 use soar_dl::{
-    downloader::{DownloadOptions, DownloadState, Downloader},
+    cancel,
+    downloader::{DownloadOptions, DownloadState, Downloader, FileFilter},
     github::{Github, GithubAsset, GithubRelease},
     gitlab::{Gitlab, GitlabAsset, GitlabRelease},
+    oci::{OciClient, Reference},
     platform::{
-        PlatformDownloadOptions, PlatformUrl, Release, ReleaseAsset, ReleaseHandler,
+        Instance, PlatformDownloadOptions, PlatformUrl, Release, ReleaseAsset, ReleaseHandler,
         ReleasePlatform,
     },
+    utils::extract_filename,
 };
-use tracing::{error, info};
+
+use tracing::{error, info, warn};
 
 use crate::{
+    cli::{CacheAction, OutputConflict},
+    color::{Color, ColorExt},
+    interrupt::CancelOnInterrupt,
     progress::{self, create_progress_bar},
+    state::AppState,
     utils::interactive_ask,
 };
 
+/// Bytes downloaded between progress updates of the download queue.
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
 pub struct DownloadContext {
     regex_patterns: Option<Vec<String>>,
     match_keywords: Option<Vec<String>>,
     exclude_keywords: Option<Vec<String>>,
     output: Option<String>,
+    output_template: Option<String>,
+    on_conflict: OutputConflict,
+    gitlab_instance: Option<String>,
     yes: bool,
-    progress_callback: Arc<dyn Fn(DownloadState) + Send + Sync>,
+    auto: bool,
+    prerelease: bool,
+    no_cache: bool,
+    require_attestation: bool,
+    minisign_key: Option<String>,
+    /// Globs selecting the files of OCI artifacts to download.
+    oci_files: Vec<String>,
+    /// Shows a progress bar for each running download.
+    multi_progress: MultiProgress,
+    core_db: Arc<Mutex<Connection>>,
+    /// Output paths of the downloads queued so far, so they don't collide
+    /// with each other either.
+    claimed: Mutex<HashSet<PathBuf>>,
+    /// Name and verification sources of the queued release assets, by
+    /// queued download.
+    sources: Mutex<HashMap<i64, (String, AssetSources)>>,
+}
+
+/// Values of the placeholders in the output template for a download.
+struct OutputFields {
+    repo: String,
+    tag: String,
+    name: String,
 }
 
+impl OutputFields {
+    fn direct(url: &str) -> Self {
+        Self {
+            repo: Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(String::from))
+                .unwrap_or_default(),
+            tag: String::new(),
+            name: extract_filename(url),
+        }
+    }
+
+    fn oci(reference: &str) -> Self {
+        let reference = Reference::from(reference);
+        let name = reference
+            .package
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        Self {
+            repo: reference.package,
+            tag: reference.tag,
+            name,
+        }
+    }
+
+    /// Renders `template`, dropping the path segments that end up empty.
+    fn render(&self, template: &str) -> PathBuf {
+        template
+            .replace("{repo}", &self.repo)
+            .replace("{tag}", &self.tag)
+            .replace("{name}", &self.name)
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != "..")
+            .collect()
+    }
+}
+
+/// Downloads the given links, release assets and OCI references.
+///
+/// Everything is resolved first and added to the download queue as a batch,
+/// which the downloads leave one by one as they complete. With `resume_all`,
+/// the downloads still queued from an interrupted session go first.
+///
+/// Fails if any item failed, once the others are done, or with `fail_fast`
+/// without starting the rest.
+#[allow(clippy::too_many_arguments)]
 pub async fn download(
     links: Vec<String>,
     github: Vec<String>,
@@ -38,155 +148,768 @@ pub async fn download(
     match_keywords: Option<Vec<String>>,
     exclude_keywords: Option<Vec<String>>,
     output: Option<String>,
+    output_template: Option<String>,
+    on_conflict: OutputConflict,
+    gitlab_instance: Option<String>,
     yes: bool,
+    auto: bool,
+    prerelease: bool,
+    resume_all: bool,
+    no_cache: bool,
+    require_attestation: bool,
+    minisign_key: Option<String>,
+    oci_files: Vec<String>,
+    jobs: usize,
+    fail_fast: bool,
 ) -> SoarResult<()> {
-    let progress_bar = create_progress_bar();
-    let progress_callback = Arc::new(move |state| progress::handle_progress(state, &progress_bar));
+    // the key may be given as the path of its file
+    let minisign_key = match minisign_key {
+        Some(key) if Path::new(&key).is_file() => Some(fs::read_to_string(key)?),
+        key => key,
+    };
+    let core_db = AppState::create_core_db(&get_config())?.conn;
 
     let ctx = DownloadContext {
         regex_patterns: regex_patterns.clone(),
         match_keywords: match_keywords.clone(),
         exclude_keywords: exclude_keywords.clone(),
         output: output.clone(),
+        output_template,
+        on_conflict,
+        gitlab_instance,
         yes,
-        progress_callback: progress_callback.clone(),
+        auto,
+        prerelease,
+        no_cache,
+        require_attestation,
+        minisign_key,
+        oci_files,
+        multi_progress: progress::create_multi_progress(),
+        core_db: core_db.clone(),
+        claimed: Mutex::new(HashSet::new()),
+        sources: Mutex::new(HashMap::new()),
     };
 
-    handle_direct_downloads(&ctx, links, output.clone(), progress_callback.clone()).await?;
-
-    if !github.is_empty() {
-        handle_github_downloads(&ctx, github).await?;
+    let mut queue = Vec::new();
+    if resume_all {
+        let interrupted = get_queued_downloads(core_db.clone())?;
+        if interrupted.is_empty() {
+            info!("No downloads to resume");
+        }
+        queue.extend(interrupted);
     }
 
-    if !gitlab.is_empty() {
-        handle_gitlab_downloads(&ctx, gitlab).await?;
+    let mut reports = Vec::new();
+    queue.extend(queue_links(&ctx, &links, fail_fast, &mut reports).await?);
+    for project in &github {
+        info!("Fetching releases from GitHub: {}", project);
+        match queue_release::<Github, GithubRelease, GithubAsset>(&ctx, project, None).await {
+            Ok(download) => queue.extend(download),
+            Err(e) if fail_fast => return Err(e),
+            Err(e) => {
+                eprintln!("{}", e);
+                reports.push(Report::new(project, Outcome::Failed(e.to_string())));
+            }
+        }
+    }
+    for project in &gitlab {
+        info!("Fetching releases from GitLab: {}", project);
+        let instance = ctx.gitlab_instance.as_deref().map(gitlab_instance_at);
+        match queue_release::<Gitlab, GitlabRelease, GitlabAsset>(&ctx, project, instance).await {
+            Ok(download) => queue.extend(download),
+            Err(e) if fail_fast => return Err(e),
+            Err(e) => {
+                eprintln!("{}", e);
+                reports.push(Report::new(project, Outcome::Failed(e.to_string())));
+            }
+        }
     }
+    for reference in &ghcr {
+        queue.extend(enqueue(
+            &ctx,
+            reference,
+            true,
+            OutputFields::oci(reference),
+        )?);
+    }
+
+    // interrupted downloads stay queued for `--resume-all`
+    let _interrupt = CancelOnInterrupt::install()?;
+    let fixed_width = 30;
+    let failed = AtomicBool::new(false);
+    let outcomes: Vec<Report> = stream::iter(&queue)
+        .map(|download| async {
+            let name = download_name(download);
+            // the rest stay queued for `--resume-all`
+            if fail_fast && failed.load(Ordering::Relaxed) {
+                return Report::new(&name, Outcome::Skipped);
+            }
+            let progress_bar = ctx.multi_progress.add(create_progress_bar());
+            progress_bar.set_message(if name.len() > fixed_width {
+                format!("{:.width$}", name, width = fixed_width)
+            } else {
+                format!("{:<width$}", name, width = fixed_width)
+            });
 
-    if !ghcr.is_empty() {
-        handle_oci_downloads(ghcr, output.clone(), progress_callback.clone()).await?;
+            let outcome = match run_download(&ctx, download, &progress_bar).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    failed.store(true, Ordering::Relaxed);
+                    progress::fail(&progress_bar, &e.to_string());
+                    if !cancel::is_cancelled() {
+                        ctx.multi_progress.suspend(|| eprintln!("{}", e));
+                    }
+                    Outcome::Failed(e.to_string())
+                }
+            };
+            Report::new(&name, outcome)
+        })
+        .buffer_unordered(jobs.max(1))
+        .collect()
+        .await;
+    reports.extend(outcomes);
+
+    if cancel::is_cancelled() {
+        return Err(SoarError::Cancelled);
+    }
+    if reports.len() > 1 {
+        print_summary(&reports);
     }
 
+    let failed = reports
+        .iter()
+        .filter(|report| matches!(report.outcome, Outcome::Failed(_)))
+        .count();
+    if failed > 0 {
+        return Err(SoarError::Custom(format!(
+            "{} of {} downloads failed",
+            failed,
+            reports.len()
+        )));
+    }
     Ok(())
 }
 
-pub async fn handle_direct_downloads(
-    ctx: &DownloadContext,
-    links: Vec<String>,
-    output: Option<String>,
-    progress_callback: Arc<dyn Fn(DownloadState) + Send + Sync>,
+/// How one of the items given to `soar dl` turned out.
+enum Outcome {
+    Downloaded(PathBuf),
+    Cached(PathBuf),
+    Failed(String),
+    /// Not started after an earlier failure with `--fail-fast`.
+    Skipped,
+}
+
+struct Report {
+    name: String,
+    outcome: Outcome,
+}
+
+impl Report {
+    fn new(name: &str, outcome: Outcome) -> Self {
+        Self {
+            name: name.to_string(),
+            outcome,
+        }
+    }
+}
+
+/// Prints a line per item with how it turned out, failures last.
+fn print_summary(reports: &[Report]) {
+    let mut reports: Vec<&Report> = reports.iter().collect();
+    reports.sort_by_key(|report| match report.outcome {
+        Outcome::Downloaded(_) | Outcome::Cached(_) => 0,
+        Outcome::Skipped => 1,
+        Outcome::Failed(_) => 2,
+    });
+
+    info!("{}", "Summary".color(Color::Label));
+    for report in &reports {
+        let (status, detail) = match &report.outcome {
+            Outcome::Downloaded(path) => ("done".color(Color::Success), path.display().to_string()),
+            Outcome::Cached(path) => ("cached".color(Color::Success), path.display().to_string()),
+            Outcome::Skipped => ("skipped".color(Color::Warn), String::new()),
+            Outcome::Failed(err) => ("failed".color(Color::Error), err.clone()),
+        };
+        let size = match &report.outcome {
+            Outcome::Downloaded(path) | Outcome::Cached(path) => fs::metadata(path)
+                .ok()
+                .filter(|meta| meta.is_file())
+                .map(|meta| HumanBytes(meta.len()).to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        let line = format!(
+            "  {:<30} {:<8} {:>10}  {}",
+            report.name, status, size, detail
+        );
+        info!(name = report.name, status = %status, "{}", line.trim_end());
+    }
+
+    let succeeded = reports
+        .iter()
+        .filter(|report| matches!(report.outcome, Outcome::Downloaded(_) | Outcome::Cached(_)))
+        .count();
+    info!(
+        succeeded,
+        total = reports.len(),
+        "{} of {} downloads succeeded",
+        succeeded,
+        reports.len()
+    );
+}
+
+/// Name shown next to the progress bar of `download`: the file it's saved
+/// to, or the URL's file name or OCI reference when saved to a directory.
+fn download_name(download: &QueuedDownload) -> String {
+    match Path::new(&download.output_path).file_name() {
+        Some(name) if !download.output_path.ends_with('/') => name.to_string_lossy().into_owned(),
+        _ if download.oci => download.url.clone(),
+        _ => extract_filename(&download.url),
+    }
+}
+
+/// Prints the releases of a GitHub or GitLab project, or with `tag` the
+/// assets of that release. GitLab projects are looked up on
+/// `gitlab_instance` if given. For an OCI reference, the files of the
+/// artifact matching `oci_files` are printed instead.
+pub async fn list_releases(
+    project: &str,
+    tag: Option<&str>,
+    gitlab_instance: Option<&str>,
+    oci_files: &[String],
 ) -> SoarResult<()> {
-    let downloader = Downloader::default();
+    if let Some((instance, project)) = configured_gitlab_project(project) {
+        return print_releases::<Gitlab, GitlabRelease, GitlabAsset>(&project, tag, Some(instance))
+            .await;
+    }
+    match PlatformUrl::parse(project)? {
+        PlatformUrl::Github(project) => {
+            print_releases::<Github, GithubRelease, GithubAsset>(&project, tag, None).await
+        }
+        PlatformUrl::Gitlab(project) => {
+            let instance = gitlab_instance.map(gitlab_instance_at);
+            print_releases::<Gitlab, GitlabRelease, GitlabAsset>(&project, tag, instance).await
+        }
+        PlatformUrl::Oci(reference) => print_oci_files(&reference, oci_files).await,
+        _ => Err(SoarError::InvalidPackageQuery(format!(
+            "{} is not a GitHub or GitLab project or an OCI reference",
+            project
+        ))),
+    }
+}
 
-    for link in &links {
-        match PlatformUrl::parse(link) {
-            Ok(PlatformUrl::DirectUrl(url)) => {
-                info!("Downloading using direct link: {}", url);
-
-                let options = DownloadOptions {
-                    url: link.clone(),
-                    output_path: output.clone(),
-                    progress_callback: Some(progress_callback.clone()),
-                };
-                let _ = downloader
-                    .download(options)
+async fn print_releases<P: ReleasePlatform, R, A>(
+    project: &str,
+    tag: Option<&str>,
+    instance: Option<Instance>,
+) -> SoarResult<()>
+where
+    R: Release<A> + for<'de> Deserialize<'de>,
+    A: ReleaseAsset,
+{
+    // a tag in the project, e.g. `owner/repo@v1.0`, selects the release too
+    let (project, tag) = match project.split_once('@') {
+        Some((project, project_tag)) => (project, tag.or(Some(project_tag))),
+        None => (project, tag),
+    };
+
+    let handler = release_handler::<P>(instance);
+    let releases = with_retry(|| async {
+        handler
+            .fetch_releases::<R>(project)
+            .await
+            .map_err(SoarError::from)
+    })
+    .await?;
+
+    let Some(tag) = tag else {
+        for release in &releases {
+            let date = release.published_at().get(..10).unwrap_or_default();
+            let assets = release.assets().len();
+            info!(
+                tag = release.tag_name(),
+                name = release.name(),
+                published_at = release.published_at(),
+                prerelease = release.is_prerelease(),
+                assets,
+                "{} {} ({} assets){}",
+                release.tag_name().color(Color::Accent),
+                date.color(Color::Value),
+                assets,
+                if release.is_prerelease() {
+                    " [prerelease]".color(Color::Warn)
+                } else {
+                    String::new()
+                }
+            );
+        }
+        return Ok(());
+    };
+
+    let release = releases
+        .iter()
+        .find(|release| release.tag_name() == tag)
+        .ok_or_else(|| SoarError::PackageNotFound(format!("{}@{}", project, tag)))?;
+    for asset in release.assets() {
+        let size = asset
+            .size()
+            .map(|size| format!(" ({})", HumanBytes(size)))
+            .unwrap_or_default();
+        info!(
+            name = asset.name(),
+            size = asset.size(),
+            url = asset.download_url(),
+            "{}{}",
+            asset.name().color(Color::Accent),
+            size
+        );
+    }
+    Ok(())
+}
+
+/// Prints the files of the OCI artifact `reference`, those matching any of
+/// `globs` if there are some.
+async fn print_oci_files(reference: &str, globs: &[String]) -> SoarResult<()> {
+    let client = OciClient::new(&Reference::from(reference));
+    let manifest =
+        with_retry(|| async { client.manifest().await.map_err(SoarError::from) }).await?;
+
+    for layer in &manifest.layers {
+        let Some(name) = layer.get_title() else {
+            continue;
+        };
+        if !globs.is_empty() && !globs.iter().any(|glob| glob_match(glob, &name)) {
+            continue;
+        }
+        info!(
+            name,
+            size = layer.size,
+            digest = layer.digest,
+            "{} ({})",
+            name.color(Color::Accent),
+            HumanBytes(layer.size)
+        );
+    }
+    Ok(())
+}
+
+fn release_handler<P: ReleasePlatform>(instance: Option<Instance>) -> ReleaseHandler<P> {
+    match instance {
+        Some(instance) => ReleaseHandler::with_instance(instance),
+        None => ReleaseHandler::new(),
+    }
+}
+
+/// GitLab instance at `url`, with the token configured for it if any.
+fn gitlab_instance_at(url: &str) -> Instance {
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+    let host = Url::parse(&url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from));
+    let token = get_config()
+        .gitlab_instances
+        .iter()
+        .find(|instance| {
+            Url::parse(&instance.url)
+                .is_ok_and(|configured| configured.host_str() == host.as_deref())
+        })
+        .and_then(|instance| instance.token.clone());
+    Instance { url, token }
+}
+
+/// Splits a link to a project on a configured GitLab instance, e.g.
+/// `https://gitlab.example.com/group/project/-/releases/v1.0`, into the
+/// instance and the project path with the tag, `group/project@v1.0`.
+fn configured_gitlab_project(link: &str) -> Option<(Instance, String)> {
+    let url = Url::parse(link).ok()?;
+    let (instance, base) = get_config().gitlab_instances.iter().find_map(|instance| {
+        let base = Url::parse(&instance.url).ok()?;
+        (base.host_str() == url.host_str() && url.path().starts_with(base.path()))
+            .then(|| (instance.clone(), base))
+    })?;
+
+    let path = url.path()[base.path().len()..].trim_matches('/');
+    let (project, tag) = match path.split_once("/-/") {
+        Some((project, rest)) => (project, rest.strip_prefix("releases/")),
+        None => (path, None),
+    };
+    if project.is_empty() {
+        return None;
+    }
+    let project = match tag {
+        Some(tag) => format!("{}@{}", project, tag),
+        None => project.to_string(),
+    };
+    Some((
+        Instance {
+            url: instance.url,
+            token: instance.token,
+        },
+        project,
+    ))
+}
+
+/// Resolves `links` into queued downloads, GitHub and GitLab project URLs
+/// to the release asset to download.
+///
+/// Links that can't be resolved are reported as failed, or fail the whole
+/// download with `fail_fast`.
+async fn queue_links(
+    ctx: &DownloadContext,
+    links: &[String],
+    fail_fast: bool,
+    reports: &mut Vec<Report>,
+) -> SoarResult<Vec<QueuedDownload>> {
+    let mut queue = Vec::new();
+    for link in links {
+        let queued = match PlatformUrl::parse(link) {
+            Ok(PlatformUrl::DirectUrl(_)) => match configured_gitlab_project(link) {
+                Some((instance, project)) => {
+                    info!(
+                        "Detected {} project, processing as GitLab release",
+                        instance.url
+                    );
+                    queue_release::<Gitlab, GitlabRelease, GitlabAsset>(
+                        ctx,
+                        &project,
+                        Some(instance),
+                    )
                     .await
-                    .map_err(|e| eprintln!("{}", e));
-            }
+                }
+                None => enqueue(ctx, link, false, OutputFields::direct(link)),
+            },
             Ok(PlatformUrl::Github(project)) => {
                 info!("Detected GitHub URL, processing as GitHub release");
-                let handler = ReleaseHandler::<Github>::new();
-                if let Err(e) = handle_platform_download::<Github, GithubRelease, GithubAsset>(
-                    ctx, &handler, &project,
-                )
-                .await
-                {
-                    eprintln!("{}", e);
-                }
+                queue_release::<Github, GithubRelease, GithubAsset>(ctx, &project, None).await
             }
             Ok(PlatformUrl::Gitlab(project)) => {
                 info!("Detected GitLab URL, processing as GitLab release");
-                let handler = ReleaseHandler::<Gitlab>::new();
-                if let Err(e) = handle_platform_download::<Gitlab, GitlabRelease, GitlabAsset>(
-                    ctx, &handler, &project,
-                )
-                .await
-                {
-                    eprintln!("{}", e);
-                }
-            }
-            Ok(PlatformUrl::Oci(url)) => {
-                info!("Downloading using OCI reference: {}", url);
-
-                let options = DownloadOptions {
-                    url: link.clone(),
-                    output_path: output.clone(),
-                    progress_callback: Some(progress_callback.clone()),
-                };
-                let _ = downloader
-                    .download_oci(options)
-                    .await
-                    .map_err(|e| eprintln!("{}", e));
+                let instance = ctx.gitlab_instance.as_deref().map(gitlab_instance_at);
+                queue_release::<Gitlab, GitlabRelease, GitlabAsset>(ctx, &project, instance).await
             }
-            Err(err) => eprintln!("Error parsing URL '{}' : {}", link, err),
+            Ok(PlatformUrl::Oci(_)) => enqueue(ctx, link, true, OutputFields::oci(link)),
+            Err(err) => Err(SoarError::Custom(format!(
+                "Error parsing URL '{}' : {}",
+                link, err
+            ))),
         };
+        match queued {
+            Ok(download) => queue.extend(download),
+            Err(e) if fail_fast => return Err(e),
+            Err(e) => {
+                eprintln!("{}", e);
+                reports.push(Report::new(link, Outcome::Failed(e.to_string())));
+            }
+        }
     }
+    Ok(queue)
+}
 
-    Ok(())
+/// Adds a download of `url` to the queue, saving it to the output path of
+/// `ctx` made absolute, so it resumes to the same place from anywhere.
+///
+/// With an output template, `fields` fill in its placeholders and the
+/// conflict policy of `ctx` applies; a skipped download isn't queued.
+fn enqueue(
+    ctx: &DownloadContext,
+    url: &str,
+    oci: bool,
+    fields: OutputFields,
+) -> SoarResult<Option<QueuedDownload>> {
+    let output_path = match (&ctx.output_template, &ctx.output) {
+        (Some(template), output) => {
+            let base = match output {
+                Some(output) => path::absolute(output)?,
+                None => env::current_dir()?,
+            };
+            let Some(path) = resolve_conflict(ctx, base.join(fields.render(template))) else {
+                return Ok(None);
+            };
+            let mut path = path.to_string_lossy().into_owned();
+            // OCI downloads are directories of layers
+            if oci {
+                path.push('/');
+            }
+            path
+        }
+        (None, Some(output)) => {
+            let mut path = path::absolute(output)?.to_string_lossy().into_owned();
+            if output.ends_with('/') && !path.ends_with('/') {
+                path.push('/');
+            }
+            path
+        }
+        (None, None) => format!("{}/", env::current_dir()?.display()),
+    };
+    let oci_files = if oci {
+        ctx.oci_files.clone()
+    } else {
+        Vec::new()
+    };
+    let id = queue_download(ctx.core_db.clone(), url, &output_path, oci, &oci_files)?;
+    Ok(Some(QueuedDownload {
+        id,
+        url: url.to_string(),
+        output_path,
+        oci,
+        oci_files,
+        downloaded: 0,
+        total: 0,
+    }))
 }
 
-pub async fn handle_oci_downloads(
-    references: Vec<String>,
-    output: Option<String>,
-    progress_callback: Arc<dyn Fn(DownloadState) + Send + Sync>,
-) -> SoarResult<()> {
+/// Applies the conflict policy of `ctx` to `path`, returning where to save
+/// the download or `None` to skip it.
+fn resolve_conflict(ctx: &DownloadContext, path: PathBuf) -> Option<PathBuf> {
+    let mut claimed = ctx.claimed.lock().unwrap();
+    let taken = |path: &Path| path.exists() || claimed.contains(path);
+
+    let path = match ctx.on_conflict {
+        OutputConflict::Overwrite => path,
+        OutputConflict::Skip if taken(&path) => {
+            info!("Skipping {}, it already exists", path.display());
+            return None;
+        }
+        OutputConflict::Skip => path,
+        OutputConflict::Rename => {
+            let mut candidate = path.clone();
+            let mut n = 1;
+            while taken(&candidate) {
+                let mut name = path.file_name().unwrap_or_default().to_os_string();
+                name.push(format!(".{}", n));
+                candidate = path.with_file_name(name);
+                n += 1;
+            }
+            candidate
+        }
+    };
+    claimed.insert(path.clone());
+    Some(path)
+}
+
+/// Runs a queued download, showing its progress on `progress_bar`, and
+/// removes it from the queue once it completes.
+///
+/// Returns where the download was saved, a directory for OCI artifacts.
+async fn run_download(
+    ctx: &DownloadContext,
+    download: &QueuedDownload,
+    progress_bar: &ProgressBar,
+) -> SoarResult<Outcome> {
+    let sources = ctx.sources.lock().unwrap().get(&download.id).cloned();
+    if ctx.require_attestation {
+        let authenticable = sources.as_ref().is_some_and(|(_, sources)| {
+            sources.github_project.is_some()
+                || (sources.minisig.is_some() && ctx.minisign_key.is_some())
+        });
+        if !authenticable {
+            finish_download(ctx.core_db.clone(), download.id)?;
+            return Err(SoarError::SignatureVerificationFailed(format!(
+                "{}: nothing to verify it with",
+                download.url
+            )));
+        }
+    }
+
     let downloader = Downloader::default();
+    let progress_callback = track_progress(ctx, download.id, progress_bar.clone());
+
+    if download.downloaded > 0 {
+        info!(
+            "Resuming {} ({} of {})",
+            download.url,
+            HumanBytes(download.downloaded),
+            HumanBytes(download.total)
+        );
+    }
+
+    let outcome = if download.oci {
+        info!("Downloading using OCI reference: {}", download.url);
+        let file_filter = (!download.oci_files.is_empty()).then(|| {
+            let globs = download.oci_files.clone();
+            Arc::new(move |name: &str| globs.iter().any(|glob| glob_match(glob, name)))
+                as FileFilter
+        });
+        with_retry(|| async {
+            let options = DownloadOptions::builder(&download.url)
+                .output_path(&download.output_path)
+                .progress_callback(progress_callback.clone())
+                .file_filter(file_filter.clone())
+                .build()?;
+            downloader
+                .download_oci(options)
+                .await
+                .map_err(SoarError::from)
+        })
+        .await?;
+        Outcome::Downloaded(PathBuf::from(&download.output_path))
+    } else {
+        let validators = if ctx.no_cache {
+            None
+        } else {
+            fetch_validators(&download.url).await
+        };
+        let cached = match &validators {
+            Some(validators) => {
+                download_cache::restore(&download.url, validators, &download.output_path)?
+            }
+            None => None,
+        };
 
-    for reference in &references {
-        let options = DownloadOptions {
-            url: reference.clone(),
-            output_path: output.clone(),
-            progress_callback: Some(progress_callback.clone()),
+        let fresh = cached.is_none();
+        let path = match cached {
+            Some(path) => {
+                info!("Using cached download of {}", download.url);
+                path
+            }
+            None => {
+                info!("Downloading using direct link: {}", download.url);
+                with_failover(&download.url, |url| async {
+                    let options = DownloadOptions::builder(url)
+                        .output_path(&download.output_path)
+                        .progress_callback(progress_callback.clone())
+                        .build()?;
+                    downloader.download(options).await.map_err(SoarError::from)
+                })
+                .await?
+            }
         };
 
-        info!("Downloading using OCI reference: {}", reference);
-        let _ = downloader
-            .download_oci(options)
-            .await
-            .map_err(|e| eprintln!("{}", e));
+        if let Some((name, sources)) = sources {
+            if let Err(err) = verify_download(ctx, &name, &sources, &path).await {
+                // downloading it again won't help
+                let _ = fs::remove_file(&path);
+                finish_download(ctx.core_db.clone(), download.id)?;
+                return Err(err);
+            }
+        }
+        if let Some(validators) = validators.as_ref().filter(|_| fresh) {
+            if let Err(err) = download_cache::insert(&download.url, validators, &path) {
+                warn!("Failed to cache {}: {}", path.display(), err);
+            }
+        }
+        if fresh {
+            Outcome::Downloaded(path)
+        } else {
+            Outcome::Cached(path)
+        }
+    };
+
+    finish_download(ctx.core_db.clone(), download.id)?;
+    Ok(outcome)
+}
+
+/// Verifies the release asset `name` downloaded to `path` with what its
+/// release publishes, failing if it's required and nothing proves where
+/// the asset comes from.
+async fn verify_download(
+    ctx: &DownloadContext,
+    name: &str,
+    sources: &AssetSources,
+    path: &Path,
+) -> SoarResult<()> {
+    let verification = verify_asset(path, name, sources, ctx.minisign_key.as_deref()).await?;
+
+    if verification.signature == Check::Unchecked {
+        warn!(
+            "{} is signed, pass --minisign-key to check its signature",
+            name
+        );
+    }
+    if verification.attestation == Check::Unchecked {
+        warn!(
+            "Couldn't check the attestations of {}, is the gh CLI installed?",
+            name
+        );
+    }
+    if ctx.require_attestation && !verification.is_authenticated() {
+        return Err(SoarError::SignatureVerificationFailed(format!(
+            "{}: no attestation or signature",
+            name
+        )));
+    }
+
+    let passed: Vec<&str> = [
+        (verification.checksum, "checksum"),
+        (verification.signature, "signature"),
+        (verification.attestation, "attestation"),
+    ]
+    .into_iter()
+    .filter(|(check, _)| *check == Check::Passed)
+    .map(|(_, what)| what)
+    .collect();
+    if !passed.is_empty() {
+        info!(
+            "Verified {} ({})",
+            name.color(Color::Value),
+            passed.join(", ").color(Color::Success)
+        );
     }
     Ok(())
 }
 
-fn create_platform_options(ctx: &DownloadContext, tag: Option<String>) -> PlatformDownloadOptions {
-    let asset_regexes = ctx
-        .regex_patterns
-        .clone()
-        .map(|patterns| {
-            patterns
-                .iter()
-                .map(|pattern| Regex::new(pattern))
-                .collect::<Result<Vec<Regex>, regex::Error>>()
-        })
-        .transpose()
-        .unwrap()
-        .unwrap_or_default();
-
-    PlatformDownloadOptions {
-        output_path: ctx.output.clone(),
-        progress_callback: Some(ctx.progress_callback.clone()),
-        tag,
-        regex_patterns: asset_regexes,
-        match_keywords: ctx.match_keywords.clone().unwrap_or_default(),
-        exclude_keywords: ctx.exclude_keywords.clone().unwrap_or_default(),
-        exact_case: false,
+/// Progress callback showing the progress of the queued download `id` on
+/// `progress_bar`, and recording it every [`PROGRESS_INTERVAL`] bytes.
+fn track_progress(
+    ctx: &DownloadContext,
+    id: i64,
+    progress_bar: ProgressBar,
+) -> Arc<dyn Fn(DownloadState) + Send + Sync> {
+    let core_db = ctx.core_db.clone();
+    // total size and bytes downloaded when progress was last recorded
+    let recorded = Mutex::new((0, 0));
+
+    Arc::new(move |state: DownloadState| {
+        if let Ok(mut recorded) = recorded.lock() {
+            match state {
+                DownloadState::Preparing(total) => recorded.0 = total,
+                DownloadState::Progress(downloaded)
+                    if downloaded >= recorded.1 + PROGRESS_INTERVAL =>
+                {
+                    recorded.1 = downloaded;
+                    let _ = record_download_progress(core_db.clone(), id, downloaded, recorded.0);
+                }
+                _ => {}
+            }
+        }
+        progress::handle_progress(state, &progress_bar);
+    })
+}
+
+fn create_platform_options(
+    ctx: &DownloadContext,
+    tag: Option<&str>,
+) -> SoarResult<PlatformDownloadOptions> {
+    let mut builder = PlatformDownloadOptions::builder().prerelease(ctx.prerelease);
+    if let Some(output) = &ctx.output {
+        builder = builder.output_path(output);
+    }
+    if let Some(tag) = tag {
+        builder = builder.tag(tag);
     }
+    for pattern in ctx.regex_patterns.iter().flatten() {
+        builder = builder.regex(pattern);
+    }
+    for keyword in ctx.match_keywords.iter().flatten() {
+        builder = builder.match_keyword(keyword);
+    }
+    for keyword in ctx.exclude_keywords.iter().flatten() {
+        builder = builder.exclude_keyword(keyword);
+    }
+    Ok(builder.build()?)
 }
 
-async fn handle_platform_download<P: ReleasePlatform, R, A>(
+/// Picks the release asset of `project`, on `instance` if it's self-hosted,
+/// to download and adds it to the queue.
+async fn queue_release<P: ReleasePlatform, R, A>(
     ctx: &DownloadContext,
-    handler: &ReleaseHandler<P>,
     project: &str,
-) -> SoarResult<()>
+    instance: Option<Instance>,
+) -> SoarResult<Option<QueuedDownload>>
 where
     R: Release<A> + for<'de> Deserialize<'de>,
     A: ReleaseAsset + Clone,
@@ -196,52 +919,76 @@ where
         _ => (project.trim_end_matches('@'), None),
     };
 
-    let options = create_platform_options(&ctx, tag.map(String::from));
-    let releases = handler.fetch_releases::<R>(project).await?;
+    let handler = release_handler::<P>(instance);
+    let options = create_platform_options(ctx, tag)?;
+    let releases = with_retry(|| async {
+        handler
+            .fetch_releases::<R>(project)
+            .await
+            .map_err(SoarError::from)
+    })
+    .await?;
     let assets = handler.filter_releases(&releases, &options).await?;
 
-    let selected_asset = if assets.len() == 1 || ctx.yes {
-        assets[0].clone()
-    } else {
-        select_asset(&assets)?
+    let selected_asset = choose_asset(&assets, ctx.yes || ctx.auto)?;
+    let url = selected_asset.download_url();
+    let name = selected_asset.name();
+    let release = releases.iter().find(|release| {
+        release
+            .assets()
+            .iter()
+            .any(|asset| asset.download_url() == url)
+    });
+    let release_assets = release.map(|release| release.assets()).unwrap_or_default();
+    let sources = AssetSources::discover(
+        name,
+        release_assets
+            .iter()
+            .map(|asset| (asset.name(), asset.download_url())),
+        P::ATTESTATIONS.then_some(project),
+    );
+    let fields = OutputFields {
+        repo: project.to_string(),
+        tag: release
+            .map(|release| release.tag_name().to_string())
+            .unwrap_or_default(),
+        name: name.to_string(),
     };
-    handler.download(&selected_asset, options.clone()).await?;
-    Ok(())
-}
 
-pub async fn handle_github_downloads(
-    ctx: &DownloadContext,
-    projects: Vec<String>,
-) -> SoarResult<()> {
-    let handler = ReleaseHandler::<Github>::new();
-    for project in &projects {
-        info!("Fetching releases from GitHub: {}", project);
-        if let Err(e) =
-            handle_platform_download::<_, GithubRelease, _>(ctx, &handler, project).await
-        {
-            eprintln!("{}", e);
-        }
+    let download = enqueue(ctx, url, false, fields)?;
+    if let Some(download) = &download {
+        ctx.sources
+            .lock()
+            .unwrap()
+            .insert(download.id, (name.to_string(), sources));
     }
-    Ok(())
+    Ok(download)
 }
 
-pub async fn handle_gitlab_downloads(
-    ctx: &DownloadContext,
-    projects: Vec<String>,
-) -> SoarResult<()> {
-    let handler = ReleaseHandler::<Gitlab>::new();
-    for project in &projects {
-        info!("Fetching releases from GitLab: {}", project);
-        if let Err(e) =
-            handle_platform_download::<_, GitlabRelease, _>(ctx, &handler, project).await
-        {
-            eprintln!("{}", e);
+/// Picks the asset that suits the host best, only asking if several rank
+/// equally and `auto` isn't set.
+pub fn choose_asset<A>(assets: &[A], auto: bool) -> SoarResult<A>
+where
+    A: Clone,
+    A: ReleaseAsset,
+{
+    let mut ranked = assets.to_vec();
+    ranked.sort_by_key(|asset| Reverse(asset_score(asset.name())));
+    let best = asset_score(ranked[0].name());
+    let ambiguous = ranked
+        .get(1)
+        .is_some_and(|asset| asset_score(asset.name()) == best);
+
+    if auto || !ambiguous {
+        if ranked.len() > 1 {
+            info!("Selected {}", ranked[0].name());
         }
+        return Ok(ranked[0].clone());
     }
-    Ok(())
+    select_asset(&ranked)
 }
 
-fn select_asset<A>(assets: &[A]) -> SoarResult<A>
+pub fn select_asset<A>(assets: &[A]) -> SoarResult<A>
 where
     A: Clone,
     A: ReleaseAsset,
@@ -264,3 +1011,63 @@ where
         }
     }
 }
+
+/// Lists, clears or prunes the cache of downloaded files.
+pub fn process_cache_action(action: CacheAction) -> SoarResult<()> {
+    let (removed, freed) = match action {
+        CacheAction::List => {
+            let entries = download_cache::entries()?;
+            let packages = package_cache::entries()?;
+            if entries.is_empty() && packages.is_empty() {
+                info!("No cached downloads");
+            }
+            for entry in &entries {
+                info!(
+                    url = entry.url,
+                    name = entry.name,
+                    size = entry.size,
+                    "{} ({}) {}",
+                    entry.name.color(Color::Value),
+                    HumanBytes(entry.size),
+                    entry.url.color(Color::Info)
+                );
+            }
+            for package in &packages {
+                info!(
+                    pkg_name = package.pkg_name,
+                    pkg_id = package.pkg_id,
+                    version = package.version,
+                    repo_name = package.repo_name,
+                    checksum = package.checksum,
+                    size = package.size,
+                    "{}#{}-{}:{} ({})",
+                    package.pkg_name.color(Color::Accent),
+                    package.pkg_id.color(Color::Info),
+                    package.version.color(Color::Value),
+                    package.repo_name.color(Color::Info),
+                    HumanBytes(package.size)
+                );
+            }
+            return Ok(());
+        }
+        CacheAction::Clear => {
+            let (removed, freed) = download_cache::prune(None)?;
+            let (packages, package_bytes) = package_cache::prune(None)?;
+            (removed + packages, freed + package_bytes)
+        }
+        CacheAction::Prune { older_than } => {
+            let max_age = Duration::from_secs(older_than * 24 * 60 * 60);
+            let (removed, freed) = download_cache::prune(Some(max_age))?;
+            let (packages, package_bytes) = package_cache::prune(Some(max_age))?;
+            (removed + packages, freed + package_bytes)
+        }
+    };
+    info!(
+        removed = removed,
+        freed = freed,
+        "Removed {} cached downloads, freeing {}",
+        removed,
+        HumanBytes(freed)
+    );
+    Ok(())
+}
@@ -0,0 +1,373 @@
+use std::{
+    collections::HashMap,
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::Connection;
+use serde::Deserialize;
+use serde_json::json;
+use soar_core::{
+    database::{
+        models::{InstalledPackage, Package},
+        packages::{
+            get_installed_packages, get_package_names_matching, get_packages, FilterOp,
+            QueryOptions,
+        },
+    },
+    error::SoarError,
+    package::{
+        adopt::ADOPTED_REPO_NAME,
+        github::GITHUB_REPO_NAME,
+        install::{set_pinned, InstallTarget},
+        query::PackageQuery,
+    },
+    SoarResult,
+};
+use tracing::debug;
+
+use crate::{
+    install::{confirm_trust, create_install_context, perform_installation, resolve_packages},
+    remove::remove_packages,
+    state::AppState,
+};
+
+/// Desired state of the installation.
+///
+/// ```toml
+/// prune = false
+///
+/// [[packages]]
+/// name = "ripgrep"
+///
+/// [[packages]]
+/// name = "fzf#fzf:bincache"
+/// version = "0.56.3"
+/// pinned = true
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    /// Remove installed repository packages the spec doesn't list.
+    #[serde(default)]
+    prune: bool,
+    #[serde(default)]
+    packages: Vec<SpecPackage>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecPackage {
+    /// Package query, e.g. `ripgrep` or `ripgrep#ripgrep:bincache`.
+    name: String,
+    /// Exact version to have installed. Any installed version is accepted
+    /// if unset.
+    version: Option<String>,
+    /// Whether the package is pinned; left as it is if unset.
+    pinned: Option<bool>,
+}
+
+#[derive(Default)]
+struct Changes {
+    installed: Vec<String>,
+    updated: Vec<String>,
+    removed: Vec<String>,
+    pinned: Vec<String>,
+    unpinned: Vec<String>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.installed.is_empty()
+            && self.updated.is_empty()
+            && self.removed.is_empty()
+            && self.pinned.is_empty()
+            && self.unpinned.is_empty()
+    }
+}
+
+/// Converges the installation to the desired state described in the TOML
+/// file at `path`: installs missing packages, switches packages to the
+/// requested versions, applies pins, and with `prune` removes the
+/// repository packages the spec doesn't list.
+///
+/// Prints a JSON summary with `status` set to `changed` or `unchanged`.
+pub async fn ensure_state(path: &str, prune: bool, jobs: Option<usize>) -> SoarResult<()> {
+    let content = fs::read_to_string(path)?;
+    let spec: Spec = toml::from_str(&content)
+        .map_err(|err| SoarError::Custom(format!("Failed to parse {}: {}", path, err)))?;
+    let prune = prune || spec.prune;
+
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+    let core_db = state.core_db().clone();
+
+    let mut changes = Changes::default();
+    let mut targets = Vec::new();
+    let mut wanted = Vec::new();
+    let mut pins = Vec::new();
+
+    for entry in &spec.packages {
+        let query = PackageQuery::try_from(entry.name.as_str())?;
+        let version = entry.version.clone().or(query.version.clone());
+
+        let mut filters = query.create_filter();
+        filters.remove("version");
+        if let Some(ref name) = query.name {
+            // the package may be installed under the name an alias points to
+            let mut names = get_package_names_matching(repo_db.clone(), name)?;
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+            filters.insert("pkg_name".to_string(), (FilterOp::In, names.into()).into());
+        }
+        filters.insert(
+            "is_installed".to_string(),
+            (FilterOp::Eq, "1".to_string().into()).into(),
+        );
+        let installed = get_installed_packages(
+            core_db.clone(),
+            QueryOptions {
+                filters,
+                limit: 1,
+                ..Default::default()
+            },
+        )?
+        .items
+        .into_iter()
+        .next();
+
+        let target = match installed {
+            Some(installed) => {
+                wanted.push(package_key(&installed));
+                match version {
+                    Some(version) if installed.version != version => {
+                        let package = find_version(repo_db.clone(), &installed, &version)?;
+                        Some(InstallTarget {
+                            package,
+                            existing_install: Some(installed),
+                        })
+                    }
+                    _ => {
+                        if let Some(pinned) = entry.pinned {
+                            pins.push((package_key(&installed), installed.pinned, pinned));
+                        }
+                        None
+                    }
+                }
+            }
+            None => {
+                let query = match &version {
+                    Some(version) if query.version.is_none() => with_version(&entry.name, version),
+                    _ => entry.name.clone(),
+                };
+                let target = resolve_packages(
+                    repo_db.clone(),
+                    core_db.clone(),
+                    std::slice::from_ref(&query),
+                    true,
+                    false,
+                )?
+                .into_iter()
+                .next()
+                .ok_or_else(|| SoarError::Custom(format!("Failed to resolve {}", query)))?;
+                wanted.push(target_key(&target));
+                Some(target)
+            }
+        };
+
+        if let Some(target) = target {
+            if let Some(pinned) = entry.pinned {
+                let was_pinned = target
+                    .existing_install
+                    .as_ref()
+                    .is_some_and(|existing| existing.pinned);
+                pins.push((target_key(&target), was_pinned, pinned));
+            }
+            targets.push(target);
+        }
+    }
+
+    if !targets.is_empty() {
+        let total = targets.len();
+        let expected: Vec<(String, bool)> = targets
+            .iter()
+            .map(|target| {
+                let replaced = target
+                    .existing_install
+                    .as_ref()
+                    .is_some_and(|existing| existing.is_installed);
+                (target_name(target), replaced)
+            })
+            .collect();
+
+        let ctx = create_install_context(total, jobs, None, None, None);
+        // listing a package in the spec is consent to trust its repository
+        confirm_trust(&targets, true)?;
+        let installed = perform_installation(ctx, targets, core_db.clone()).await?;
+        for package in &installed {
+            let name = format!(
+                "{}#{}:{}",
+                package.pkg_name, package.pkg_id, package.repo_name
+            );
+            let replaced = expected
+                .iter()
+                .any(|(expected, replaced)| *expected == name && *replaced);
+            if replaced {
+                changes.updated.push(name);
+            } else {
+                changes.installed.push(name);
+            }
+        }
+    }
+
+    for ((repo_name, pkg_id, pkg_name), was_pinned, pinned) in pins {
+        if was_pinned == pinned {
+            continue;
+        }
+        set_pinned(core_db.clone(), &repo_name, &pkg_id, &pkg_name, pinned)?;
+        let name = format!("{}#{}:{}", pkg_name, pkg_id, repo_name);
+        if pinned {
+            changes.pinned.push(name);
+        } else {
+            changes.unpinned.push(name);
+        }
+    }
+
+    if prune {
+        let mut filters = HashMap::new();
+        filters.insert(
+            "is_installed".to_string(),
+            (FilterOp::Eq, "1".to_string().into()).into(),
+        );
+        let installed = get_installed_packages(
+            core_db.clone(),
+            QueryOptions {
+                filters,
+                limit: u32::MAX,
+                ..Default::default()
+            },
+        )?
+        .items;
+
+        let mut unlisted = Vec::new();
+        for package in installed {
+            // adopted and GitHub packages can't be listed in the spec
+            if package.repo_name == ADOPTED_REPO_NAME
+                || package.repo_name == GITHUB_REPO_NAME
+                || wanted.contains(&package_key(&package))
+            {
+                continue;
+            }
+            debug!(
+                pkg_name = %package.pkg_name,
+                pkg_id = %package.pkg_id,
+                "Removing unlisted package {}#{}",
+                package.pkg_name,
+                package.pkg_id
+            );
+            unlisted.push(format!(
+                "{}#{}@{}:{}",
+                package.pkg_name, package.pkg_id, package.version, package.repo_name
+            ));
+            changes.removed.push(format!(
+                "{}#{}:{}",
+                package.pkg_name, package.pkg_id, package.repo_name
+            ));
+        }
+        if !unlisted.is_empty() {
+            remove_packages(&unlisted, false, true, false, false).await?;
+        }
+    }
+
+    let status = if changes.is_empty() {
+        "unchanged"
+    } else {
+        "changed"
+    };
+    println!(
+        "{}",
+        json!({
+            "status": status,
+            "installed": changes.installed,
+            "updated": changes.updated,
+            "removed": changes.removed,
+            "pinned": changes.pinned,
+            "unpinned": changes.unpinned,
+        })
+    );
+
+    Ok(())
+}
+
+type PackageKey = (String, String, String);
+
+fn package_key(package: &InstalledPackage) -> PackageKey {
+    (
+        package.repo_name.clone(),
+        package.pkg_id.clone(),
+        package.pkg_name.clone(),
+    )
+}
+
+fn target_key(target: &InstallTarget) -> PackageKey {
+    (
+        target.package.repo_name.clone(),
+        target.package.pkg_id.clone(),
+        target.package.pkg_name.clone(),
+    )
+}
+
+fn target_name(target: &InstallTarget) -> String {
+    format!(
+        "{}#{}:{}",
+        target.package.pkg_name, target.package.pkg_id, target.package.repo_name
+    )
+}
+
+/// Adds `@version` to a package query that has none.
+fn with_version(query: &str, version: &str) -> String {
+    match query.rfind(':') {
+        Some(idx) => format!("{}@{}{}", &query[..idx], version, &query[idx..]),
+        None => format!("{}@{}", query, version),
+    }
+}
+
+/// Finds `version` of the installed package in the repository it came from.
+fn find_version(
+    repo_db: Arc<Mutex<Connection>>,
+    installed: &InstalledPackage,
+    version: &str,
+) -> SoarResult<Package> {
+    let filters = [
+        ("r.name", installed.repo_name.as_str()),
+        ("pkg_name", installed.pkg_name.as_str()),
+        ("pkg_id", installed.pkg_id.as_str()),
+        ("version", version),
+    ]
+    .into_iter()
+    .map(|(key, value)| {
+        (
+            key.to_string(),
+            (FilterOp::Eq, value.to_string().into()).into(),
+        )
+    })
+    .collect();
+    get_packages(
+        repo_db,
+        QueryOptions {
+            filters,
+            limit: 1,
+            ..Default::default()
+        },
+    )?
+    .items
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+        SoarError::PackageNotFound(format!(
+            "{}#{}@{}:{}",
+            installed.pkg_name, installed.pkg_id, version, installed.repo_name
+        ))
+    })
+}
@@ -0,0 +1,117 @@
+use std::{collections::HashMap, fs};
+
+use soar_core::{
+    database::packages::{get_installed_packages, FilterOp, QueryOptions},
+    package::adopt::ADOPTED_REPO_NAME,
+    SoarResult,
+};
+use tracing::{info, warn};
+
+use crate::{
+    install::{install_from_github, install_packages, InstallArgs},
+    state::AppState,
+};
+
+/// Prints the installed packages as a list of package queries, one per line,
+/// that `soar import` can install again.
+///
+/// Packages installed from GitHub are written as `github:owner/repo`.
+/// Adopted packages are skipped since soar can't reinstall them.
+pub async fn export_packages(with_versions: bool) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let core_db = state.core_db().clone();
+
+    let mut filters = HashMap::new();
+    filters.insert(
+        "is_installed".to_string(),
+        (FilterOp::Eq, "1".to_string().into()).into(),
+    );
+    let options = QueryOptions {
+        filters,
+        limit: u32::MAX,
+        ..Default::default()
+    };
+    let packages = get_installed_packages(core_db, options)?.items;
+
+    let mut lines = Vec::new();
+    for package in packages {
+        if package.repo_name == ADOPTED_REPO_NAME {
+            warn!(
+                "Skipping adopted package {}, it can't be reinstalled",
+                package.pkg_name
+            );
+            continue;
+        }
+
+        let line = match (&package.upstream, with_versions) {
+            (Some(project), false) => format!("github:{}", project),
+            (Some(project), true) => format!("github:{}@{}", project, package.version),
+            (None, false) => format!(
+                "{}#{}:{}",
+                package.pkg_name, package.pkg_id, package.repo_name
+            ),
+            (None, true) => format!(
+                "{}#{}@{}:{}",
+                package.pkg_name, package.pkg_id, package.version, package.repo_name
+            ),
+        };
+        lines.push(line);
+    }
+    lines.sort();
+    lines.dedup();
+
+    for line in lines {
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Installs the packages listed in the file at `path`.
+///
+/// Blank lines and lines starting with `# ` are ignored.
+pub async fn import_packages(path: &str, yes: bool) -> SoarResult<()> {
+    let content = fs::read_to_string(path)?;
+
+    let mut packages = Vec::new();
+    let mut github = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line == "#" || line.starts_with("# ") {
+            continue;
+        }
+        if line.starts_with("github:") {
+            github.push(line.to_string());
+        } else {
+            packages.push(line.to_string());
+        }
+    }
+
+    if packages.is_empty() && github.is_empty() {
+        info!("No packages to import");
+        return Ok(());
+    }
+
+    if !packages.is_empty() {
+        install_packages(
+            &packages,
+            InstallArgs {
+                yes,
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+    for source in github {
+        install_from_github(
+            &source,
+            None,
+            InstallArgs {
+                yes,
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use soar_core::{
+    config::get_config,
+    database::packages::{get_installed_packages, QueryOptions},
+    error::SoarError,
+    SoarResult,
+};
+use tracing::{info, warn};
+
+use crate::{
+    color::{Color, ColorExt},
+    i18n::t,
+    post_hooks::is_on_path,
+    state::AppState,
+};
+
+/// First file named `name` in the directories of `PATH`.
+fn which(name: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Checks that the binaries of the installed packages can be run by name:
+/// that the bin directory is in `PATH`, their links aren't broken and no
+/// file earlier in `PATH` shadows them.
+pub fn check_health() -> SoarResult<()> {
+    let bin_dir = get_config().get_bin_path()?;
+    let core_db = AppState::create_core_db(&get_config())?.conn;
+    let installed = get_installed_packages(core_db, QueryOptions::default())?.items;
+    let bins: Vec<(String, PathBuf)> = installed
+        .into_iter()
+        .filter(|package| package.is_installed)
+        .filter_map(|package| {
+            let bin_path = PathBuf::from(package.bin_path?);
+            Some((format!("{}#{}", package.pkg_name, package.pkg_id), bin_path))
+        })
+        .collect();
+
+    let mut problems = 0;
+    let on_path = is_on_path(&bin_dir);
+    if !on_path && !bins.is_empty() {
+        warn!(
+            "{}",
+            t!(
+                "health-not-on-path",
+                count = bins.len(),
+                path = bin_dir.display().color(Color::Accent)
+            )
+        );
+        info!("  {}", t!("health-path-fix"));
+        problems += 1;
+    }
+
+    for (package, bin_path) in &bins {
+        let name = bin_path.file_name().unwrap_or_default().to_string_lossy();
+        if !bin_path.exists() {
+            warn!(
+                "{}",
+                t!(
+                    "health-broken-link",
+                    package = package.color(Color::Accent),
+                    path = bin_path.display()
+                )
+            );
+            problems += 1;
+            continue;
+        }
+        // only links in the bin directory are looked up through PATH
+        if !on_path || bin_path.parent() != Some(Path::new(&bin_dir)) {
+            continue;
+        }
+        if let Some(found) = which(&name).filter(|found| found != bin_path) {
+            warn!(
+                "{}",
+                t!(
+                    "health-shadowed",
+                    package = package.color(Color::Accent),
+                    name = name,
+                    path = found.display()
+                )
+            );
+            problems += 1;
+        }
+    }
+
+    if problems > 0 {
+        return Err(SoarError::Custom(t!("health-problems", count = problems)));
+    }
+    info!("{}", t!("health-ok"));
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::{
+    env,
+    io::{stdin, IsTerminal},
+};
+
+use soar_core::{database::packages::get_packages_providing, SoarResult};
+use tracing::{error, info};
+
+use crate::{
+    cli::HookShell,
+    color::{Color, ColorExt},
+    install::{install_packages, InstallArgs},
+    recovery::recover_interrupted,
+    run::run_package,
+    state::AppState,
+    utils::{interactive_ask, lock_instance},
+};
+
+/// Exit status shells use for commands that can't be found.
+const NOT_FOUND_EXIT_CODE: i32 = 127;
+
+pub fn print_hook(shell: HookShell) -> SoarResult<()> {
+    let soar = env::current_exe()?;
+    let soar = soar.display();
+
+    let script = match shell {
+        HookShell::Bash => {
+            format!("command_not_found_handle() {{\n    \"{soar}\" command-not-found \"$@\"\n}}\n")
+        }
+        HookShell::Zsh => {
+            format!("command_not_found_handler() {{\n    \"{soar}\" command-not-found \"$@\"\n}}\n")
+        }
+        HookShell::Fish => format!(
+            "function fish_command_not_found\n    \"{soar}\" command-not-found $argv\nend\n"
+        ),
+    };
+    print!("{script}");
+
+    Ok(())
+}
+
+/// Looks up packages providing a missing command and offers to run or
+/// install one of them. Exits with 127 unless a package was run or installed.
+///
+/// The instance lock is only taken once installing is picked, waiting for
+/// it if `wait` is set, so typos don't have to wait on other soar processes.
+pub async fn command_not_found(command: &[String], wait: bool) -> SoarResult<()> {
+    let name = &command[0];
+    let state = AppState::new_cached().await?;
+    let providers = get_packages_providing(state.repo_db().clone(), name)?;
+
+    if providers.is_empty() {
+        eprintln!("{name}: command not found");
+        std::process::exit(NOT_FOUND_EXIT_CODE);
+    }
+
+    info!(
+        "{} is not installed, but is provided by:",
+        name.color(Color::Accent)
+    );
+    for (idx, package) in providers.iter().enumerate() {
+        info!(
+            "[{}] {}#{}-{}:{}",
+            idx + 1,
+            package.pkg_name.color(Color::Accent),
+            package.pkg_id.color(Color::Info),
+            package.version.color(Color::Value),
+            package.repo_name.color(Color::Info)
+        );
+    }
+
+    if !stdin().is_terminal() {
+        let package = &providers[0];
+        info!(
+            "Install it with: soar install {}#{}",
+            package.pkg_name, package.pkg_id
+        );
+        std::process::exit(NOT_FOUND_EXIT_CODE);
+    }
+
+    let package = if providers.len() == 1 {
+        &providers[0]
+    } else {
+        loop {
+            let response = interactive_ask("Select a package (empty to cancel): ")?;
+            if response.is_empty() {
+                std::process::exit(NOT_FOUND_EXIT_CODE);
+            }
+            match response.parse::<usize>() {
+                Ok(n) if n > 0 && n <= providers.len() => break &providers[n - 1],
+                _ => error!("Invalid selection, please try again."),
+            }
+        }
+    };
+    let query = format!("{}#{}", package.pkg_name, package.pkg_id);
+
+    loop {
+        let response = interactive_ask("[r]un once, [i]nstall, [c]ancel: ")?;
+        match response.to_lowercase().as_str() {
+            "r" | "run" => {
+                let mut run_command = vec![query];
+                run_command.extend_from_slice(&command[1..]);
+                return run_package(&run_command).await;
+            }
+            "i" | "install" => {
+                let _lock = lock_instance(wait)?;
+                recover_interrupted().await?;
+                return install_packages(
+                    &[query],
+                    InstallArgs {
+                        yes: true,
+                        ..Default::default()
+                    },
+                )
+                .await;
+            }
+            "" | "c" | "cancel" => std::process::exit(NOT_FOUND_EXIT_CODE),
+            _ => error!("Invalid selection, please try again."),
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Message catalog for user-facing strings.
+//!
+//! Catalogs use a subset of the [Fluent](https://projectfluent.org) syntax:
+//! comments, single and multiline messages, variable placeables
+//! (`{ $name }`) and string literal placeables (`{ " " }`). Selectors and
+//! terms are not supported.
+
+use std::{collections::HashMap, env, fmt::Display, sync::LazyLock};
+
+const FALLBACK_LOCALE: &str = "en-US";
+
+/// Catalogs embedded in the binary, by locale.
+const LOCALES: &[(&str, &str)] = &[("en-US", include_str!("../locales/en-US/soar.ftl"))];
+
+type Catalog = HashMap<String, Vec<Segment>>;
+
+enum Segment {
+    Text(String),
+    Variable(String),
+}
+
+struct Messages {
+    locale: Option<Catalog>,
+    fallback: Catalog,
+}
+
+static MESSAGES: LazyLock<Messages> = LazyLock::new(|| {
+    let fallback = catalog_for(FALLBACK_LOCALE).unwrap_or_default();
+    let locale = detect_locale()
+        .filter(|locale| locale != FALLBACK_LOCALE)
+        .and_then(|locale| {
+            catalog_for(&locale).or_else(|| {
+                // fall back from a regional variant to any catalog of the language
+                let language = locale.split('-').next()?;
+                LOCALES
+                    .iter()
+                    .find(|(name, _)| name.split('-').next() == Some(language))
+                    .map(|(_, source)| parse(source))
+            })
+        });
+    Messages { locale, fallback }
+});
+
+/// Locale from the first of `LC_ALL`, `LC_MESSAGES` and `LANG` that is set,
+/// normalized from `de_DE.UTF-8` to `de-DE`.
+fn detect_locale() -> Option<String> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    let locale = value.split(['.', '@']).next()?.replace('_', "-");
+    match locale.as_str() {
+        "" | "C" | "POSIX" => None,
+        _ => Some(locale),
+    }
+}
+
+fn catalog_for(locale: &str) -> Option<Catalog> {
+    LOCALES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(locale))
+        .map(|(_, source)| parse(source))
+}
+
+fn parse(source: &str) -> Catalog {
+    let mut catalog = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+
+    for line in source.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            if let Some((id, value)) = current.take() {
+                catalog.insert(id, parse_pattern(&value));
+            }
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            // continuation of a multiline message
+            if let Some((_, ref mut value)) = current {
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+
+        if let Some((id, value)) = current.take() {
+            catalog.insert(id, parse_pattern(&value));
+        }
+        if let Some((id, value)) = line.split_once('=') {
+            current = Some((id.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    if let Some((id, value)) = current {
+        catalog.insert(id, parse_pattern(&value));
+    }
+
+    catalog
+}
+
+fn parse_pattern(value: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let expr = rest[start + 1..].trim_start();
+        let placeable = match expr.strip_prefix('"') {
+            Some(literal) => {
+                parse_string_literal(literal).map(|(value, after)| (Segment::Text(value), after))
+            }
+            None => expr.strip_prefix('$').and_then(|name| {
+                let end = name.find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))?;
+                Some((Segment::Variable(name[..end].to_string()), &name[end..]))
+            }),
+        };
+        let Some((segment, after)) = placeable
+            .and_then(|(segment, after)| Some((segment, after.trim_start().strip_prefix('}')?)))
+        else {
+            // not a placeable this understands, kept as written
+            text.push('{');
+            rest = &rest[start + 1..];
+            continue;
+        };
+
+        match segment {
+            Segment::Text(value) => text.push_str(&value),
+            variable => {
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(variable);
+            }
+        }
+        rest = after;
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    segments
+}
+
+/// Value of a string literal placeable, given what follows its opening
+/// quote, and the rest after the closing one.
+///
+/// Supports the escapes of Fluent: `\"`, `\\`, `\uHHHH` and `\UHHHHHH`.
+fn parse_string_literal(literal: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = literal.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &literal[idx + 1..])),
+            '\\' => match chars.next()?.1 {
+                escaped @ ('"' | '\\') => value.push(escaped),
+                kind @ ('u' | 'U') => {
+                    let len = if kind == 'u' { 4 } else { 6 };
+                    let hex: String = chars.by_ref().take(len).map(|(_, c)| c).collect();
+                    if hex.len() != len {
+                        return None;
+                    }
+                    value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                _ => return None,
+            },
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+impl Messages {
+    fn translate(&self, id: &str, args: &[(&str, &dyn Display)]) -> String {
+        let Some(segments) = self
+            .locale
+            .as_ref()
+            .and_then(|catalog| catalog.get(id))
+            .or_else(|| self.fallback.get(id))
+        else {
+            return id.to_string();
+        };
+
+        let mut message = String::new();
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => message.push_str(text),
+                Segment::Variable(name) => match args.iter().find(|(arg, _)| arg == name) {
+                    Some((_, value)) => message.push_str(&value.to_string()),
+                    None => {
+                        message.push_str("{$");
+                        message.push_str(name);
+                        message.push('}');
+                    }
+                },
+            }
+        }
+        message
+    }
+}
+
+/// Looks up the message `id` in the catalog of the current locale and fills
+/// in its variables from `args`.
+///
+/// Falls back to the en-US message, and to `id` itself if that is missing
+/// too. Prefer the `t!` macro over calling this directly.
+pub fn translate(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    MESSAGES.translate(id, args)
+}
+
+/// Translates a message, e.g. `t!("remove-done", package = name)`.
+macro_rules! t {
+    ($id:literal) => {
+        $crate::i18n::translate($id, &[])
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::translate(
+            $id,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(locale: Option<&str>, fallback: &str) -> Messages {
+        Messages {
+            locale: locale.map(parse),
+            fallback: parse(fallback),
+        }
+    }
+
+    #[test]
+    fn interpolates_arguments() {
+        let messages = messages(None, "greet = Hello { $name }, { $count } new\n");
+        assert_eq!(
+            messages.translate("greet", &[("count", &3), ("name", &"soar")]),
+            "Hello soar, 3 new"
+        );
+        // a missing argument stays visible rather than vanishing
+        assert_eq!(
+            messages.translate("greet", &[("name", &"soar")]),
+            "Hello soar, {$count} new"
+        );
+    }
+
+    #[test]
+    fn unescapes_string_literals() {
+        let messages = messages(
+            None,
+            r#"prompt = Continue?{ " " }
+quote = { "\"" }quoted{ "\"" }
+slash = a{ "\\" }b
+brace = { "{" }{ $name }{ "}" }
+unicode = { "\u00e9" }{ "\U01F600" }
+"#,
+        );
+        assert_eq!(messages.translate("prompt", &[]), "Continue? ");
+        assert_eq!(messages.translate("quote", &[]), "\"quoted\"");
+        assert_eq!(messages.translate("slash", &[]), "a\\b");
+        assert_eq!(messages.translate("brace", &[("name", &"x")]), "{x}");
+        assert_eq!(messages.translate("unicode", &[]), "\u{e9}\u{1f600}");
+    }
+
+    #[test]
+    fn joins_multiline_messages() {
+        let messages = messages(
+            None,
+            "# comment\nhelp =\n    First line\n    second { $n }\n\nnext = Next\n",
+        );
+        assert_eq!(
+            messages.translate("help", &[("n", &2)]),
+            "First line\nsecond 2"
+        );
+        assert_eq!(messages.translate("next", &[]), "Next");
+    }
+
+    #[test]
+    fn falls_back_for_missing_messages() {
+        let messages = messages(Some("both = Beide\n"), "both = Both\nonly = Only\n");
+        assert_eq!(messages.translate("both", &[]), "Beide");
+        assert_eq!(messages.translate("only", &[]), "Only");
+        assert_eq!(messages.translate("missing", &[]), "missing");
+    }
+
+    #[test]
+    fn parses_the_embedded_catalogs() {
+        for (locale, source) in LOCALES {
+            let catalog = parse(source);
+            assert!(!catalog.is_empty(), "{} is empty", locale);
+        }
+    }
+}
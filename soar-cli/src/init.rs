@@ -0,0 +1,142 @@
+use std::{
+    fs,
+    io::{stdin, stdout, IsTerminal},
+};
+
+use soar_core::{
+    config::{config_path, generate_config, get_config, reload_config, Config},
+    utils::setup_required_paths,
+    SoarResult,
+};
+use tracing::{info, warn};
+
+use crate::{
+    cli::HookShell,
+    color::{Color, ColorExt},
+    i18n::t,
+    post_hooks::{is_on_path, print_path_hint},
+    shell::{detect_shell, path_line, rc_file, write_rc_block},
+    sync::sync_repositories,
+    utils::interactive_ask,
+};
+
+/// Sets soar up for first use: writes the configuration with the chosen
+/// default repositories, creates the directories, adds the bin directory to
+/// `PATH` in the startup file of the shell and syncs the repositories.
+///
+/// Asks what to do when attached to a terminal, unless `yes` is set, and
+/// takes the defaults otherwise. An existing configuration is kept as is.
+pub async fn init(
+    repos: Vec<String>,
+    shell: Option<HookShell>,
+    no_path: bool,
+    no_sync: bool,
+    yes: bool,
+) -> SoarResult<()> {
+    let interactive = !yes && stdin().is_terminal() && stdout().is_terminal();
+
+    let path = config_path();
+    if path.exists() {
+        info!(
+            "{}",
+            t!(
+                "init-config-exists",
+                path = path.display().color(Color::Accent)
+            )
+        );
+        if !repos.is_empty() {
+            warn!("{}", t!("init-repos-ignored"));
+        }
+    } else {
+        let repos = if !repos.is_empty() {
+            Some(repos)
+        } else if interactive {
+            Some(choose_repositories()?)
+        } else {
+            None
+        };
+        generate_config(repos.as_deref())?;
+        reload_config()?;
+        info!(
+            "{}",
+            t!(
+                "init-config-written",
+                path = path.display().color(Color::Accent)
+            )
+        );
+    }
+
+    setup_required_paths()?;
+    {
+        let config = get_config();
+        info!(
+            "{}",
+            t!(
+                "init-directories",
+                bin = config.get_bin_path()?.display(),
+                packages = config.get_packages_path()?.display()
+            )
+        );
+    }
+
+    if !no_path {
+        setup_path(shell, interactive, yes)?;
+    }
+
+    if !no_sync {
+        sync_repositories(None).await?;
+    }
+
+    info!("{}", t!("init-done"));
+    Ok(())
+}
+
+/// Asks which of the default repositories to enable.
+fn choose_repositories() -> SoarResult<Vec<String>> {
+    let mut chosen = Vec::new();
+    for repo in Config::default().repositories {
+        let response = interactive_ask(&t!("init-repo-prompt", name = repo.name))?;
+        if !response.to_lowercase().starts_with('n') {
+            chosen.push(repo.name);
+        }
+    }
+    Ok(chosen)
+}
+
+/// Adds the bin directory to `PATH` in the startup file of the shell if it
+/// isn't on it yet, asking first when `interactive`. Without a terminal it's
+/// only done with `yes`.
+fn setup_path(shell: Option<HookShell>, interactive: bool, yes: bool) -> SoarResult<()> {
+    let bin_path = get_config().get_bin_path()?;
+    if is_on_path(&bin_path) {
+        info!("{}", t!("init-path-ok", path = bin_path.display()));
+        return Ok(());
+    }
+    let Some(shell) = detect_shell(shell) else {
+        return print_path_hint();
+    };
+
+    let rc_file = rc_file(shell);
+    let rc = rc_file.display().color(Color::Accent);
+    let line = path_line(Some(shell), &bin_path);
+    if fs::read_to_string(&rc_file).is_ok_and(|content| content.contains(&line)) {
+        info!("{}", t!("env-path-configured", file = rc));
+        return Ok(());
+    }
+    if interactive {
+        let response = interactive_ask(&t!(
+            "init-path-prompt",
+            path = bin_path.display(),
+            file = rc
+        ))?;
+        if response.to_lowercase().starts_with('n') {
+            return print_path_hint();
+        }
+    } else if !yes {
+        return print_path_hint();
+    }
+
+    write_rc_block(&rc_file, &line)?;
+    info!("{}", t!("env-path-added", file = rc));
+    Ok(())
+}
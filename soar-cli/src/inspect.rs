@@ -1,15 +1,36 @@
-use std::fmt::Display;
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Display,
+    fs::File,
+    io::{stdout, ErrorKind, IsTerminal, StdoutLock, Write},
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use futures::StreamExt;
 use indicatif::HumanBytes;
+use rusqlite::Connection;
+use similar::{Algorithm, ChangeTag, DiffTag, TextDiff};
 use soar_core::{
-    database::packages::{get_packages, QueryOptions},
+    database::{
+        models::Package,
+        packages::{get_packages, Filter, FilterOp, QueryOptions, SortOrder},
+    },
+    error::SoarError,
+    http::{client, send_with_retry},
     package::query::PackageQuery,
     SoarResult,
 };
-use tracing::{error, info};
+use tracing::info;
 
-use crate::{state::AppState, utils::interactive_ask};
+use crate::{
+    color::{Color, ColorExt},
+    state::AppState,
+    utils::suggest_similar_packages,
+};
 
 pub enum InspectType {
     BuildLog,
@@ -25,79 +46,331 @@ impl Display for InspectType {
     }
 }
 
-pub async fn inspect_log(package: &str, inspect_type: InspectType) -> SoarResult<()> {
+pub enum Sink {
+    File(File),
+    Stdout(StdoutLock<'static>),
+    Pager(Child),
+}
+
+impl Sink {
+    pub fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        match self {
+            Sink::File(file) => file.write_all(buf),
+            Sink::Stdout(out) => out.write_all(buf),
+            Sink::Pager(child) => child.stdin.as_mut().unwrap().write_all(buf),
+        }
+    }
+
+    pub fn finish(self) -> std::io::Result<()> {
+        match self {
+            Sink::File(mut file) => file.flush(),
+            Sink::Stdout(mut out) => match out.flush() {
+                Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            },
+            Sink::Pager(mut child) => {
+                drop(child.stdin.take());
+                child.wait().map(|_| ())
+            }
+        }
+    }
+}
+
+/// Starts `$PAGER` (or `less`) reading from a pipe, if it can be spawned.
+pub fn spawn_pager() -> Option<Sink> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less -R".to_string());
+    let mut args = pager.split_whitespace();
+    let program = args.next()?;
+
+    let mut command = Command::new(program);
+    command.args(args).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    command.spawn().ok().map(Sink::Pager)
+}
+
+/// URL the raw content of `url` can be fetched from, for pages on GitHub.
+fn raw_url(url: &str) -> String {
+    if url.starts_with("https://github.com") {
+        url.replacen("/tree/", "/raw/refs/heads/", 1)
+            .replacen("/blob/", "/raw/refs/heads/", 1)
+    } else {
+        url.to_string()
+    }
+}
+
+fn build_url(package: &Package, inspect_type: &InspectType) -> Option<String> {
+    let url = match inspect_type {
+        InspectType::BuildLog => &package.build_log,
+        InspectType::BuildScript => &package.build_script,
+    };
+    url.as_deref().map(raw_url)
+}
+
+pub async fn inspect_log(
+    package: &str,
+    inspect_type: InspectType,
+    raw: bool,
+    save: Option<&Path>,
+    compare: Option<&str>,
+) -> SoarResult<()> {
     let state = AppState::new().await?;
     let repo_db = state.repo_db().clone();
 
     let query = PackageQuery::try_from(package)?;
-    let filters = query.create_filter();
+    let filters = query.create_repo_filter();
 
+    // the latest version, unless the query names one
     let options = QueryOptions {
         limit: 1,
         filters,
+        sort_by: vec![("version".into(), SortOrder::Desc)],
         ..Default::default()
     };
 
-    let packages = get_packages(repo_db, options)?;
-    if packages.items.is_empty() {
-        error!("Package {} not found", package);
-    } else {
-        let first_pkg = packages.items.first().unwrap();
-
-        let url = if matches!(inspect_type, InspectType::BuildLog) {
-            &first_pkg.build_log
-        } else {
-            &first_pkg.build_script
-        };
-
-        let Some(url) = url else {
-            error!("No build {} found for {}", inspect_type, first_pkg.pkg_name);
-            return Ok(());
-        };
-
-        let url = if url.starts_with("https://github.com") {
-            &url.replacen("/tree/", "/raw/refs/heads/", 1)
-                .replacen("/blob/", "/raw/refs/heads/", 1)
-        } else {
-            url
-        };
-
-        let resp = reqwest::get(url).await?;
-        if !resp.status().is_success() {
-            error!(
-                "Error fetching build {inspect_type} from {} [{}]",
-                url,
-                resp.status()
-            );
-            return Ok(());
-        }
+    let packages = get_packages(repo_db.clone(), options)?;
+    let Some(first_pkg) = packages.items.first() else {
+        suggest_similar_packages(repo_db, package)?;
+        return Err(SoarError::PackageNotFound(package.to_string()));
+    };
 
-        let content_length = resp.content_length().unwrap_or_default();
-        if content_length > 1_048_576 {
-            let response = interactive_ask(
-                "The {inspect_type} file is too large. Do you really want to view it (y/N)?",
-            )?;
-            if !response.starts_with('y') {
-                return Ok(());
-            }
+    if let Some(old_version) = compare {
+        return compare_builds(repo_db, first_pkg, old_version, inspect_type, raw, save).await;
+    }
+
+    let url = build_url(first_pkg, &inspect_type).ok_or_else(|| {
+        SoarError::Custom(format!(
+            "No build {} found for {}",
+            inspect_type, first_pkg.pkg_name
+        ))
+    })?;
+
+    let client = client();
+    let resp = send_with_retry(|| client.get(&url)).await?;
+    if !resp.status().is_success() {
+        return Err(SoarError::FailedToFetchRemote(format!(
+            "build {inspect_type} from {} [{}]",
+            url,
+            resp.status()
+        )));
+    }
+
+    let content_length = resp.content_length().unwrap_or_default();
+    info!(
+        "Fetching build {inspect_type} from {} [{}]",
+        url,
+        HumanBytes(content_length)
+    );
+
+    let mut sink = match save {
+        Some(path) => Sink::File(File::create(path)?),
+        None if raw || !stdout().is_terminal() => Sink::Stdout(stdout().lock()),
+        None => spawn_pager().unwrap_or_else(|| Sink::Stdout(stdout().lock())),
+    };
+    // keep carriage-return progress lines readable unless asked for the raw bytes
+    let translate = !raw && save.is_none();
+
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let mut chunk = chunk?.to_vec();
+        if translate {
+            chunk
+                .iter_mut()
+                .filter(|b| **b == b'\r')
+                .for_each(|b| *b = b'\n');
+        }
+        match sink.write_all(&chunk) {
+            Ok(()) => {}
+            // the pager was closed before reaching the end
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => break,
+            Err(err) => return Err(err.into()),
         }
+    }
+    sink.finish()?;
 
-        info!(
-            "Fetching build {inspect_type} from {} [{}]",
+    if let Some(path) = save {
+        info!("Saved build {inspect_type} to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Fetches the build log or script of `package` as text.
+async fn fetch_build_text(package: &Package, inspect_type: &InspectType) -> SoarResult<String> {
+    let url = build_url(package, inspect_type).ok_or_else(|| {
+        SoarError::Custom(format!(
+            "No build {} found for {} {}",
+            inspect_type, package.pkg_name, package.version
+        ))
+    })?;
+
+    let client = client();
+    let resp = send_with_retry(|| client.get(&url)).await?;
+    if !resp.status().is_success() {
+        return Err(SoarError::FailedToFetchRemote(format!(
+            "build {inspect_type} from {} [{}]",
             url,
-            HumanBytes(content_length)
+            resp.status()
+        )));
+    }
+    info!("Fetching build {inspect_type} from {}", url);
+
+    // progress lines are redrawn with carriage returns
+    Ok(resp.text().await?.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+/// Shows how the build log or script of `package` changed since
+/// `old_version`, as a unified diff.
+async fn compare_builds(
+    repo_db: Arc<Mutex<Connection>>,
+    package: &Package,
+    old_version: &str,
+    inspect_type: InspectType,
+    raw: bool,
+    save: Option<&Path>,
+) -> SoarResult<()> {
+    let filters: HashMap<String, Filter> = [
+        ("r.name", package.repo_name.clone()),
+        ("pkg_name", package.pkg_name.clone()),
+        ("pkg_id", package.pkg_id.clone()),
+        ("version", old_version.to_string()),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), (FilterOp::Eq, value.into()).into()))
+    .collect();
+    let options = QueryOptions {
+        limit: 1,
+        filters,
+        ..Default::default()
+    };
+    let old_pkg = get_packages(repo_db, options)?
+        .items
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            SoarError::PackageNotFound(format!(
+                "{}#{}@{}:{}",
+                package.pkg_name, package.pkg_id, old_version, package.repo_name
+            ))
+        })?;
+
+    let old = fetch_build_text(&old_pkg, &inspect_type).await?;
+    let new = fetch_build_text(package, &inspect_type).await?;
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    // patience diffing keeps logs aligned on their distinctive lines rather
+    // than on blank lines and repeated output
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Patience)
+        .timeout(DIFF_TIMEOUT)
+        .diff_slices(&old_lines, &new_lines);
+    if diff.ops().iter().all(|op| op.tag() == DiffTag::Equal) {
+        info!(
+            "Build {inspect_type}s of {} {} and {} are the same",
+            package.pkg_name, old_pkg.version, package.version
         );
+        return Ok(());
+    }
 
-        let mut stream = resp.bytes_stream();
-        let mut content = Vec::new();
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            content.extend_from_slice(&chunk);
+    let mut sink = match save {
+        Some(path) => Sink::File(File::create(path)?),
+        None if raw || !stdout().is_terminal() => Sink::Stdout(stdout().lock()),
+        None => spawn_pager().unwrap_or_else(|| Sink::Stdout(stdout().lock())),
+    };
+    let diff = unified_diff(
+        &format!("{} {}", package.pkg_name, old_pkg.version),
+        &format!("{} {}", package.pkg_name, package.version),
+        &diff,
+        !raw && save.is_none(),
+    );
+    match sink.write_all(diff.as_bytes()) {
+        // the pager was closed before reaching the end
+        Err(err) if err.kind() != ErrorKind::BrokenPipe => return Err(err.into()),
+        _ => {}
+    }
+    sink.finish()?;
+
+    if let Some(path) = save {
+        info!("Saved build {inspect_type} diff to {}", path.display());
+    }
+    Ok(())
+}
+
+/// How long diffing may take before settling for a rougher diff, which
+/// only matters for huge logs.
+const DIFF_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lines of context around each change.
+const CONTEXT: usize = 3;
+
+/// Formats `diff` as a unified diff between `old_name` and `new_name`.
+fn unified_diff(
+    old_name: &str,
+    new_name: &str,
+    diff: &TextDiff<'_, '_, str>,
+    color: bool,
+) -> String {
+    let paint = |text: String, role: Color| if color { text.color(role) } else { text };
+
+    let mut output = String::new();
+    output.push_str(&paint(format!("--- {}", old_name), Color::Accent));
+    output.push('\n');
+    output.push_str(&paint(format!("+++ {}", new_name), Color::Accent));
+    output.push('\n');
+
+    for hunk in diff.unified_diff().context_radius(CONTEXT).iter_hunks() {
+        output.push_str(&paint(hunk.header().to_string(), Color::Info));
+        output.push('\n');
+        for change in hunk.iter_changes() {
+            let line = match change.tag() {
+                ChangeTag::Equal => format!(" {}", change.value()),
+                ChangeTag::Delete => paint(format!("-{}", change.value()), Color::Error),
+                ChangeTag::Insert => paint(format!("+{}", change.value()), Color::Success),
+            };
+            output.push_str(&line);
+            output.push('\n');
         }
-        let output = String::from_utf8_lossy(&content).replace("\r", "\n");
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        info!("\n{}", output);
+    fn diff(old: &str, new: &str) -> String {
+        let old_lines: Vec<&str> = old.lines().collect();
+        let new_lines: Vec<&str> = new.lines().collect();
+        let diff = TextDiff::configure()
+            .algorithm(Algorithm::Patience)
+            .diff_slices(&old_lines, &new_lines);
+        unified_diff("old", "new", &diff, false)
     }
 
-    Ok(())
+    #[test]
+    fn formats_insertions_and_deletions() {
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nc\nd\n"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n c\n+d\n"
+        );
+    }
+
+    #[test]
+    fn shows_moved_lines_as_removed_and_added() {
+        assert_eq!(
+            diff("a\nb\nc\n", "b\nc\na\n"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n-a\n b\n c\n+a\n"
+        );
+    }
+
+    #[test]
+    fn diffs_against_empty_input() {
+        assert_eq!(diff("", "a\n"), "--- old\n+++ new\n@@ -0,0 +1 @@\n+a\n");
+        assert_eq!(diff("a\n", "a\n"), "--- old\n+++ new\n");
+    }
 }
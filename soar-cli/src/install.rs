@@ -1,102 +1,820 @@
 use std::{
-    collections::HashMap,
-    os::unix::fs,
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    hash::Hash,
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc, Mutex,
     },
+    time::Instant,
 };
 
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use rand::{distributions::Alphanumeric, Rng};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
 use rusqlite::Connection;
 use soar_core::{
-    config::get_config,
+    config::{get_config, Repository},
     database::{
+        history::{begin_transaction, finish_entry, get_last_failed, HistoryEntry},
         models::{InstalledPackage, Package},
-        packages::{get_installed_packages, get_packages, FilterOp, QueryOptions},
+        packages::{
+            get_installed_packages, get_package_names_matching, get_packages, FilterOp,
+            FilterValue, QueryOptions,
+        },
     },
     error::SoarError,
+    events::EventSink,
     package::{
-        formats::common::integrate_package,
-        install::{InstallTarget, PackageInstaller},
-        query::PackageQuery,
+        channel::Channel,
+        github::{
+            derive_asset_pattern, fetch_github_assets, github_package, parse_github_project,
+            record_github_source, GITHUB_REPO_NAME,
+        },
+        install::{
+            find_bin_conflict, install_package, pin_version, planned_bin_path, render_bin_name,
+            BinConflictPolicy, InstallOptions, InstallTarget,
+        },
+        query::{compare_versions, version_matches, PackageQuery},
     },
-    utils::calculate_checksum,
+    package_cache, popularity,
+    trust::{key_fingerprints, repository_trust, trust_repository, Trust},
+    utils::check_free_space,
     SoarResult,
 };
-use soar_dl::downloader::DownloadState;
-use tokio::sync::Semaphore;
-use tracing::{error, info, warn};
+use soar_dl::cancel;
+use tracing::{debug, error, info, warn};
 
 use crate::{
+    color::{Color, ColorExt},
+    download::choose_asset,
+    i18n::t,
+    interrupt::CancelOnInterrupt,
+    notes::print_notes,
+    post_hooks::{print_path_hint, run_hooks, Transaction},
     progress::{self, create_progress_bar},
+    scheduler::AdaptiveScheduler,
+    selection::{expand_available, is_glob},
     state::AppState,
-    utils::interactive_ask,
+    utils::{find_installed_matching, interactive_ask, suggest_similar_packages},
 };
 
+pub const DEFAULT_RENAME_TEMPLATE: &str = "{pkg_name}-{pkg_id}";
+
+/// Bin conflict resolutions keyed by `(repo_name, pkg_id)`.
+type ConflictResolutions = HashMap<(String, String), BinConflictPolicy>;
+
 #[derive(Clone)]
 pub struct InstallContext {
     pub multi_progress: Arc<MultiProgress>,
     pub total_progress_bar: ProgressBar,
-    pub semaphore: Arc<Semaphore>,
+    pub scheduler: Arc<AdaptiveScheduler>,
     pub installed_count: Arc<AtomicU64>,
     pub total_packages: usize,
     pub portable: Option<String>,
     pub portable_home: Option<String>,
     pub portable_config: Option<String>,
+    pub bin_conflict: BinConflictPolicy,
+    /// Per-package conflict resolutions chosen interactively.
+    pub bin_conflicts: Arc<ConflictResolutions>,
+    pub channel: Option<Channel>,
+    /// Unpack AppImages to run them without FUSE.
+    pub extract_appimage: bool,
+    /// Decides which hooks run once all packages are installed.
+    pub transaction: Transaction,
 }
 
+/// Creates the context for installing `total_packages` packages, running
+/// `jobs` at a time if given, or adapting up to the configured parallel limit.
 pub fn create_install_context(
     total_packages: usize,
-    parallel_limit: usize,
+    jobs: Option<usize>,
     portable: Option<String>,
     portable_home: Option<String>,
     portable_config: Option<String>,
 ) -> InstallContext {
-    let multi_progress = Arc::new(MultiProgress::new());
+    let multi_progress = Arc::new(progress::create_multi_progress());
     let total_progress_bar = multi_progress.add(ProgressBar::new(total_packages as u64));
     total_progress_bar.set_style(ProgressStyle::with_template("Installing {pos}/{len}").unwrap());
 
     InstallContext {
         multi_progress,
         total_progress_bar,
-        semaphore: Arc::new(Semaphore::new(parallel_limit)),
+        scheduler: Arc::new(match jobs {
+            Some(jobs) => AdaptiveScheduler::fixed(jobs),
+            None => AdaptiveScheduler::adaptive(get_config().parallel_limit.unwrap_or(1) as usize),
+        }),
         installed_count: Arc::new(AtomicU64::new(0)),
         total_packages,
         portable,
         portable_home,
         portable_config,
+        bin_conflict: BinConflictPolicy::Fail,
+        bin_conflicts: Arc::new(HashMap::new()),
+        channel: None,
+        extract_appimage: false,
+        transaction: Transaction::Install,
     }
 }
 
-pub async fn install_packages(
-    packages: &[String],
-    force: bool,
-    yes: bool,
-    portable: Option<String>,
-    portable_home: Option<String>,
-    portable_config: Option<String>,
+/// Options of `soar install`, shared by the commands installing packages.
+#[derive(Clone, Default)]
+pub struct InstallArgs {
+    /// Reinstall packages that are installed already.
+    pub force: bool,
+    /// Pick the first match instead of asking, and skip confirmations.
+    pub yes: bool,
+    /// Installs to run at a time, adapting up to the configured parallel
+    /// limit if unset.
+    pub jobs: Option<usize>,
+    pub bin_conflict: BinConflictPolicy,
+    pub channel: Option<Channel>,
+    pub portable: Option<String>,
+    pub portable_home: Option<String>,
+    pub portable_config: Option<String>,
+    pub extract_appimage: bool,
+    /// Match the package names as regular expressions.
+    pub regex: bool,
+    /// Print what would be installed without installing it.
+    pub dry_run: bool,
+}
+
+pub async fn install_packages(packages: &[String], args: InstallArgs) -> SoarResult<()> {
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+    let core_db = state.core_db().clone();
+
+    let (patterns, mut packages): (Vec<String>, Vec<String>) = packages
+        .iter()
+        .cloned()
+        .partition(|package| args.regex || is_glob(package));
+    if !patterns.is_empty() {
+        packages.extend(expand_available(repo_db.clone(), &patterns, args.regex)?);
+    }
+
+    let install_targets =
+        resolve_packages(repo_db, core_db.clone(), &packages, args.yes, args.force)?;
+
+    // versions installed together are pinned like those installed next to
+    // installed versions, the first of them included
+    let mut versions: HashMap<(&str, &str, &str), usize> = HashMap::new();
+    for target in &install_targets {
+        let package = &target.package;
+        *versions
+            .entry((&package.repo_name, &package.pkg_id, &package.pkg_name))
+            .or_default() += 1;
+    }
+    let pinned: Vec<Package> = install_targets
+        .iter()
+        .map(|target| &target.package)
+        .filter(|package| {
+            versions[&(&*package.repo_name, &*package.pkg_id, &*package.pkg_name)] > 1
+        })
+        .cloned()
+        .collect();
+
+    let dry_run = args.dry_run;
+    install_resolved(&state, install_targets, args).await?;
+    if !dry_run {
+        for package in &pinned {
+            pin_version(core_db.clone(), package)?;
+        }
+    }
+    Ok(())
+}
+
+/// Retries the packages that failed in the last install or update.
+pub async fn retry_failed_installs(args: InstallArgs) -> SoarResult<()> {
+    let failed = {
+        let state = AppState::new_cached().await?;
+        get_last_failed(state.core_db().clone())?
+    };
+    if failed.is_empty() {
+        info!("{}", t!("install-no-failures"));
+        return Ok(());
+    }
+
+    install_history_entries(failed, args).await
+}
+
+/// Installs the packages of history `entries` again, those installed from
+/// GitHub releases through the same route again.
+pub async fn install_history_entries(
+    entries: Vec<HistoryEntry>,
+    args: InstallArgs,
+) -> SoarResult<()> {
+    let (github, packages): (Vec<HistoryEntry>, Vec<HistoryEntry>) = entries
+        .into_iter()
+        .partition(|entry| entry.repo_name == GITHUB_REPO_NAME);
+    if !packages.is_empty() {
+        let packages: Vec<String> = packages
+            .iter()
+            .map(|entry| format!("{}#{}:{}", entry.pkg_name, entry.pkg_id, entry.repo_name))
+            .collect();
+        // failed updates leave the previous version installed
+        install_packages(
+            &packages,
+            InstallArgs {
+                force: true,
+                regex: false,
+                ..args.clone()
+            },
+        )
+        .await?;
+    }
+    for entry in github {
+        install_from_github(
+            &format!("github:{}", entry.pkg_id),
+            None,
+            InstallArgs {
+                force: true,
+                ..args.clone()
+            },
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Installs the release asset of a GitHub project given as
+/// `github:owner/repo[@tag]`, remembering the project and asset pattern for
+/// later updates.
+pub async fn install_from_github(
+    source: &str,
+    asset: Option<String>,
+    args: InstallArgs,
 ) -> SoarResult<()> {
+    if !source.starts_with("github:") {
+        return Err(SoarError::InvalidPackageQuery(format!(
+            "Unsupported install source {}, expected github:owner/repo",
+            source
+        )));
+    }
+    let (project, tag) = parse_github_project(source)?;
+
+    let state = AppState::new_cached().await?;
+    let core_db = state.core_db().clone();
+
+    let mut filters = HashMap::new();
+    filters.insert(
+        "repo_name".to_string(),
+        (FilterOp::Eq, GITHUB_REPO_NAME.to_string().into()).into(),
+    );
+    filters.insert(
+        "pkg_id".to_string(),
+        (FilterOp::Eq, project.clone().into()).into(),
+    );
+    let installed = get_installed_packages(
+        core_db.clone(),
+        QueryOptions {
+            limit: u32::MAX,
+            filters,
+            ..Default::default()
+        },
+    )?
+    .items;
+    // another tag is installed next to the installed ones
+    let existing_install = match tag {
+        Some(ref tag) => installed.iter().find(|pkg| pkg.version == *tag).cloned(),
+        None => installed.first().cloned(),
+    };
+    let previous = existing_install.as_ref().or(installed.first());
+
+    if let Some(ref existing) = existing_install {
+        if existing.is_installed {
+            if args.force {
+                warn!(
+                    "{}",
+                    t!("install-already-installed-reinstall", package = project)
+                );
+            } else {
+                warn!(
+                    "{}",
+                    t!("install-already-installed-skip", package = project)
+                );
+            }
+            if !args.force {
+                return Ok(());
+            }
+        }
+    }
+
+    let pattern = asset.or_else(|| previous.and_then(|existing| existing.asset_pattern.clone()));
+    let release_channel = args
+        .channel
+        .or(previous.map(|existing| existing.channel))
+        .unwrap_or_default();
+    info!("{}", t!("install-fetching-github", project = project));
+    let release = fetch_github_assets(
+        &project,
+        tag.as_deref(),
+        pattern.as_deref(),
+        release_channel,
+    )
+    .await?;
+    let selected = choose_asset(&release.assets, args.yes)?;
+    let pattern = pattern.unwrap_or_else(|| derive_asset_pattern(&selected.name, &release.tag));
+
+    let target = InstallTarget {
+        package: github_package(&project, &release.tag, &selected),
+        existing_install,
+    };
+    let dry_run = args.dry_run;
+    install_resolved(&state, vec![target], InstallArgs { jobs: None, ..args }).await?;
+    if dry_run {
+        return Ok(());
+    }
+
+    record_github_source(core_db, GITHUB_REPO_NAME, &project, &project, &pattern)
+}
+
+/// Installs `packages` again at the version they're installed at, from the
+/// files they were installed from if those were kept, e.g. to repair a
+/// damaged or deleted binary. Pins are left as they are.
+pub async fn reinstall_packages(packages: &[String], yes: bool) -> SoarResult<()> {
     let state = AppState::new().await?;
     let repo_db = state.repo_db().clone();
     let core_db = state.core_db().clone();
 
-    let install_targets = resolve_packages(repo_db, core_db.clone(), packages, yes, force)?;
+    let mut install_targets: Vec<InstallTarget> = Vec::new();
+    let mut failed = 0;
+    for package in packages {
+        let query = PackageQuery::try_from(package.as_str())?;
+        let installed = find_installed_matching(core_db.clone(), &query)?;
+        if installed.len() > 1 {
+            let candidates: Vec<String> = installed
+                .iter()
+                .map(|pkg| format!("{}#{}@{}", pkg.pkg_name, pkg.pkg_id, pkg.version))
+                .collect();
+            return Err(SoarError::InvalidPackageQuery(t!(
+                "installed-ambiguous",
+                package = package,
+                candidates = candidates.join(", ")
+            )));
+        }
+        let Some(existing) = installed.into_iter().next() else {
+            error!("{}", t!("remove-not-installed", package = package));
+            failed += 1;
+            continue;
+        };
+        if install_targets
+            .iter()
+            .any(|target| target.existing_install.as_ref().map(|pkg| pkg.id) == Some(existing.id))
+        {
+            continue;
+        }
 
-    let install_context = create_install_context(
+        let name = format!("{}#{}", existing.pkg_name, existing.pkg_id);
+        let target = if existing.upstream.is_some() {
+            github_reinstall_target(existing).await
+        } else {
+            repo_reinstall_target(repo_db.clone(), existing)
+        };
+        match target {
+            Ok(target) => install_targets.push(target),
+            Err(err) => {
+                error!("{}", t!("reinstall-failed", package = name, error = err));
+                failed += 1;
+            }
+        }
+    }
+
+    if !install_targets.is_empty() {
+        install_resolved(
+            &state,
+            install_targets,
+            InstallArgs {
+                yes,
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    if failed > 0 {
+        return Err(SoarError::Custom(t!(
+            "reinstall-failed-summary",
+            count = failed,
+            total = packages.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Reinstall of `existing` from its repository, at the version it's
+/// installed at. Once the repository has moved on, only possible from the
+/// files it was installed from, which are looked up under their checksum.
+fn repo_reinstall_target(
+    repo_db: Arc<Mutex<Connection>>,
+    existing: InstalledPackage,
+) -> SoarResult<InstallTarget> {
+    let mut filters: HashMap<String, _> = [
+        ("r.name", &existing.repo_name),
+        ("pkg_name", &existing.pkg_name),
+        ("pkg_id", &existing.pkg_id),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), (FilterOp::Eq, value.clone().into()).into()))
+    .collect();
+    let latest = get_packages(
+        repo_db.clone(),
+        QueryOptions {
+            limit: 1,
+            filters: filters.clone(),
+            ..Default::default()
+        },
+    )?
+    .items
+    .into_iter()
+    .next();
+
+    filters.insert(
+        "version".to_string(),
+        (FilterOp::Eq, existing.version.clone().into()).into(),
+    );
+    let current = get_packages(
+        repo_db,
+        QueryOptions {
+            limit: 1,
+            filters,
+            ..Default::default()
+        },
+    )?
+    .items
+    .into_iter()
+    .next();
+
+    let checksum = existing
+        .original_checksum
+        .clone()
+        .unwrap_or_else(|| existing.checksum.clone());
+    let package = match (current, latest) {
+        (Some(package), _) => package,
+        // the cached files are verified against the checksum before use
+        (None, Some(latest)) if package_cache::entry_path(&checksum)?.exists() => Package {
+            version: existing.version.clone(),
+            size: existing.original_size.unwrap_or(existing.size),
+            checksum,
+            ..latest
+        },
+        _ => {
+            return Err(SoarError::PackageNotFound(format!(
+                "{}@{}:{}",
+                existing.pkg_name, existing.version, existing.repo_name
+            )))
+        }
+    };
+
+    Ok(InstallTarget {
+        package,
+        existing_install: Some(existing),
+    })
+}
+
+/// Reinstall of `existing` from the GitHub release it was installed from.
+async fn github_reinstall_target(existing: InstalledPackage) -> SoarResult<InstallTarget> {
+    let project = existing.upstream.clone().unwrap_or_default();
+    let release = fetch_github_assets(
+        &project,
+        Some(&existing.version),
+        existing.asset_pattern.as_deref(),
+        existing.channel,
+    )
+    .await?;
+    let Some(asset) = release.assets.first() else {
+        return Err(SoarError::PackageNotFound(format!(
+            "{}@{}",
+            project, existing.version
+        )));
+    };
+
+    let package = github_package(&project, &release.tag, asset);
+    Ok(InstallTarget {
+        package: Package {
+            repo_name: existing.repo_name.clone(),
+            pkg: existing.pkg.clone(),
+            pkg_id: existing.pkg_id.clone(),
+            pkg_name: existing.pkg_name.clone(),
+            ..package
+        },
+        existing_install: Some(existing),
+    })
+}
+
+async fn install_resolved(
+    state: &AppState,
+    install_targets: Vec<InstallTarget>,
+    args: InstallArgs,
+) -> SoarResult<()> {
+    let core_db = state.core_db().clone();
+
+    if args.dry_run {
+        return print_install_plan(&install_targets, &args.bin_conflict, core_db);
+    }
+
+    let InstallArgs {
+        yes,
+        jobs,
+        bin_conflict,
+        channel,
+        portable,
+        portable_home,
+        portable_config,
+        extract_appimage,
+        ..
+    } = args;
+    let mut install_context = create_install_context(
         install_targets.len(),
-        state.config().parallel_limit.unwrap_or(1) as usize,
+        jobs,
         portable,
         portable_home,
         portable_config,
     );
 
-    perform_installation(install_context, install_targets, core_db).await
+    let install_targets = if matches!(bin_conflict, BinConflictPolicy::Fail) && !yes {
+        let (targets, resolutions) = resolve_bin_conflicts(install_targets, core_db.clone())?;
+        install_context.bin_conflicts = Arc::new(resolutions);
+        targets
+    } else {
+        install_targets
+    };
+    install_context.bin_conflict = bin_conflict;
+    install_context.channel = channel;
+    install_context.extract_appimage = extract_appimage;
+
+    confirm_trust(&install_targets, yes)?;
+    if !confirm_install(&install_targets, yes)? {
+        return Ok(());
+    }
+
+    perform_installation(install_context, install_targets, core_db).await?;
+    Ok(())
+}
+
+/// Asks to trust the newly added repositories `targets` are from, showing
+/// their URL and key fingerprint. With `yes` they are trusted without
+/// asking. Fails if one is declined or ruled out by its trust policy.
+pub fn confirm_trust(targets: &[InstallTarget], yes: bool) -> SoarResult<()> {
+    let config = get_config();
+    let mut seen = HashSet::new();
+    for target in targets {
+        let repo_name = &target.package.repo_name;
+        if !seen.insert(repo_name) {
+            continue;
+        }
+        let Ok(repo) = config.get_repository(repo_name) else {
+            continue;
+        };
+
+        match repository_trust(repo)? {
+            Trust::Trusted => {}
+            Trust::Refused => return Err(SoarError::UntrustedRepository(repo_name.clone())),
+            Trust::Unconfirmed => {
+                let fingerprints = key_fingerprints(repo)?;
+                warn!(
+                    repo_name = %repo_name,
+                    url = %repo.url,
+                    fingerprints = ?fingerprints,
+                    "{}",
+                    t!("trust-new-repo", repo = repo_name.color(Color::Accent))
+                );
+                info!("  {}", t!("trust-url", url = repo.url));
+                if fingerprints.is_empty() {
+                    info!("  {}", t!("trust-unsigned").color(Color::Warn));
+                }
+                for fingerprint in &fingerprints {
+                    info!("  {}", t!("trust-key", fingerprint = fingerprint));
+                }
+
+                if !yes {
+                    let response = interactive_ask(&t!("trust-confirm", repo = repo_name))?;
+                    if !response.to_lowercase().starts_with('y') {
+                        return Err(SoarError::UntrustedRepository(repo_name.clone()));
+                    }
+                }
+                trust_repository(repo)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the packages about to be installed with their download and
+/// installed sizes, and asks for confirmation unless `yes` is set.
+fn confirm_install(targets: &[InstallTarget], yes: bool) -> SoarResult<bool> {
+    if targets.is_empty() {
+        return Ok(true);
+    }
+
+    print_size_preview(targets);
+    if yes {
+        return Ok(true);
+    }
+
+    let response = interactive_ask(&t!("install-confirm"))?;
+    Ok(response.is_empty() || response.to_lowercase().starts_with('y'))
+}
+
+/// Prints what installing `targets` would download, link and replace
+/// without changing anything.
+pub fn print_install_plan(
+    targets: &[InstallTarget],
+    bin_conflict: &BinConflictPolicy,
+    core_db: Arc<Mutex<Connection>>,
+) -> SoarResult<()> {
+    if targets.is_empty() {
+        info!("{}", t!("install-nothing"));
+        return Ok(());
+    }
+
+    print_size_preview(targets);
+    for target in targets {
+        let package = &target.package;
+        let name = format!(
+            "{}#{}:{}",
+            package.pkg_name, package.pkg_id, package.repo_name
+        );
+        match &target.existing_install {
+            Some(existing) if existing.is_installed => info!(
+                pkg = %name,
+                version = %package.version,
+                "{}",
+                t!(
+                    "dry-run-replace",
+                    package = name.color(Color::Accent),
+                    old = existing.version,
+                    version = package.version
+                )
+            ),
+            _ => info!(
+                pkg = %name,
+                version = %package.version,
+                "{}",
+                t!(
+                    "dry-run-install",
+                    package = name.color(Color::Accent),
+                    version = package.version
+                )
+            ),
+        }
+        info!("  {}", t!("dry-run-download", url = package.download_url));
+        match planned_bin_path(target, bin_conflict, core_db.clone()) {
+            Ok(Some(bin_path)) => info!("  {}", t!("dry-run-link", path = bin_path.display())),
+            Ok(None) => info!("  {}", t!("dry-run-link-skip")),
+            Err(err) => warn!("  {}", t!("dry-run-conflict", error = err)),
+        }
+    }
+    info!("{}", t!("dry-run-done"));
+
+    Ok(())
+}
+
+fn download_size(package: &Package) -> u64 {
+    // ghcr packages are downloaded as an archive of ghcr_size bytes
+    package
+        .ghcr_pkg
+        .as_ref()
+        .and(package.ghcr_size)
+        .unwrap_or(package.size)
+}
+
+/// Fails before anything is downloaded if the packages filesystem can't
+/// hold the installed files of `targets` along with their downloads, which
+/// are staged next to them.
+fn check_disk_space(targets: &[InstallTarget]) -> SoarResult<()> {
+    let required = targets
+        .iter()
+        .map(|target| download_size(&target.package) + target.package.size)
+        .sum();
+    check_free_space(&[(get_config().get_packages_path()?, required)])
+}
+
+fn print_size_preview(targets: &[InstallTarget]) {
+    let rows: Vec<(String, &str, u64, u64)> = targets
+        .iter()
+        .map(|target| {
+            let package = &target.package;
+            (
+                format!(
+                    "{}#{}:{}",
+                    package.pkg_name, package.pkg_id, package.repo_name
+                ),
+                package.version.as_str(),
+                download_size(package),
+                package.size,
+            )
+        })
+        .collect();
+    let name_width = rows
+        .iter()
+        .map(|(name, ..)| name.len())
+        .chain([t!("install-preview-package").len()])
+        .max()
+        .unwrap_or_default();
+    let version_width = rows
+        .iter()
+        .map(|(_, version, ..)| version.len())
+        .chain([t!("install-preview-version").len()])
+        .max()
+        .unwrap_or_default();
+
+    let header = format!(
+        "{:<name_width$}  {:<version_width$}  {:>10}  {:>10}",
+        t!("install-preview-package"),
+        t!("install-preview-version"),
+        t!("install-preview-download"),
+        t!("install-preview-installed"),
+    );
+    info!("{}", header.color(Color::Label));
+    for (name, version, download_size, installed_size) in &rows {
+        info!(
+            pkg = %name,
+            version = %version,
+            download_size = %download_size,
+            installed_size = %installed_size,
+            "{}  {:<version_width$}  {:>10}  {:>10}",
+            format!("{:<name_width$}", name).color(Color::Accent),
+            version,
+            HumanBytes(*download_size).to_string(),
+            HumanBytes(*installed_size).to_string(),
+        );
+    }
+
+    let download_total: u64 = rows.iter().map(|(_, _, size, _)| size).sum();
+    let installed_total: u64 = rows.iter().map(|(_, _, _, size)| size).sum();
+    info!(
+        download_size = %download_total,
+        installed_size = %installed_total,
+        "{}",
+        t!(
+            "install-preview-total",
+            count = rows.len(),
+            download = HumanBytes(download_total),
+            installed = HumanBytes(installed_total)
+        )
+    );
 }
 
-fn resolve_packages(
+/// Asks how to handle each target whose bin name is already taken, dropping
+/// the targets the user chose not to install.
+fn resolve_bin_conflicts(
+    targets: Vec<InstallTarget>,
+    core_db: Arc<Mutex<Connection>>,
+) -> SoarResult<(Vec<InstallTarget>, ConflictResolutions)> {
+    let mut resolved = Vec::new();
+    let mut resolutions = HashMap::new();
+
+    for target in targets {
+        let conflict = find_bin_conflict(&target, core_db.clone())?;
+        // other versions of the package keep their link
+        let Some(conflict) = conflict.filter(|conflict| !conflict.other_version) else {
+            resolved.push(target);
+            continue;
+        };
+
+        let owner = conflict.owner.map_or_else(
+            || t!("install-bin-unmanaged"),
+            |o| t!("install-bin-owner", owner = o),
+        );
+        warn!(
+            "{}",
+            t!(
+                "install-bin-conflict",
+                path = conflict.bin_path.display(),
+                package = format!("{}#{}", target.package.pkg_name, target.package.pkg_id),
+                owner = owner
+            )
+        );
+
+        let policy = loop {
+            let response = interactive_ask(&t!("install-bin-conflict-prompt"))?;
+            match response.to_lowercase().as_str() {
+                "o" | "overwrite" => break Some(BinConflictPolicy::Overwrite),
+                "s" | "skip" => break Some(BinConflictPolicy::Skip),
+                "r" | "rename" => {
+                    let default_name = render_bin_name(DEFAULT_RENAME_TEMPLATE, &target.package);
+                    let name = interactive_ask(&t!("install-rename-prompt", name = default_name))?;
+                    let name = if name.is_empty() { default_name } else { name };
+                    break Some(BinConflictPolicy::Rename(name));
+                }
+                "a" | "abort" => break None,
+                _ => error!("{}", t!("invalid-selection")),
+            }
+        };
+
+        if let Some(policy) = policy {
+            resolutions.insert(
+                (
+                    target.package.repo_name.clone(),
+                    target.package.pkg_id.clone(),
+                ),
+                policy,
+            );
+            resolved.push(target);
+        }
+    }
+
+    Ok((resolved, resolutions))
+}
+
+pub fn resolve_packages(
     db: Arc<Mutex<Connection>>,
     core_db: Arc<Mutex<Connection>>,
     packages: &[String],
@@ -107,46 +825,110 @@ fn resolve_packages(
 
     for package in packages {
         let query = PackageQuery::try_from(package.as_str())?;
-        let filters = query.create_filter();
-
-        let options = QueryOptions {
-            limit: 1,
-            filters,
-            ..Default::default()
-        };
-
-        let installed_packages = get_installed_packages(core_db.clone(), options.clone())?.items;
-        let existing_install = if installed_packages.is_empty() {
-            None
-        } else {
-            Some(installed_packages.first().unwrap().clone())
-        };
+        let existing_install = find_installed_matching(core_db.clone(), &query)?
+            .into_iter()
+            .next();
 
         if let Some(ref existing) = existing_install {
             if existing.is_installed {
-                warn!(
-                    "{} is already installed - {}",
-                    package,
-                    if force { "reinstalling" } else { "skipping" }
-                );
+                if force {
+                    warn!(
+                        "{}",
+                        t!("install-already-installed-reinstall", package = package)
+                    );
+                } else {
+                    warn!(
+                        "{}",
+                        t!("install-already-installed-skip", package = package)
+                    );
+                }
                 if !force {
                     continue;
                 }
             }
         }
 
-        if let Some(package) = select_package(db.clone(), package, options, yes, &existing_install)?
+        let repo_options = QueryOptions {
+            filters: query.create_repo_filter(),
+            ..Default::default()
+        };
+        if let Some(package) =
+            select_package(db.clone(), package, repo_options, yes, &existing_install)?
         {
-            install_targets.push(InstallTarget {
-                package,
-                existing_install,
+            // the query may have resolved to a differently named package
+            let existing_install = match existing_install {
+                Some(existing) => Some(existing),
+                None => {
+                    // another version is installed next to the installed ones
+                    let version = query.version.as_ref().map(|_| package.version.as_str());
+                    let existing = find_installed(core_db.clone(), &package, version)?;
+                    if existing
+                        .as_ref()
+                        .is_some_and(|existing| existing.is_installed)
+                    {
+                        let name = format!("{}#{}", package.pkg_name, package.pkg_id);
+                        if !force {
+                            warn!("{}", t!("install-already-installed-skip", package = name));
+                            continue;
+                        }
+                        warn!(
+                            "{}",
+                            t!("install-already-installed-reinstall", package = name)
+                        );
+                    }
+                    existing
+                }
+            };
+            // the same version asked for twice, e.g. as `18` and `18.19.0`
+            let duplicate = install_targets.iter().any(|target: &InstallTarget| {
+                target.package.repo_name == package.repo_name
+                    && target.package.pkg_id == package.pkg_id
+                    && target.package.pkg_name == package.pkg_name
+                    && target.package.version == package.version
             });
+            if !duplicate {
+                install_targets.push(InstallTarget {
+                    package,
+                    existing_install,
+                });
+            }
         }
     }
 
     Ok(install_targets)
 }
 
+/// Install of `package`, at `version` if given.
+fn find_installed(
+    core_db: Arc<Mutex<Connection>>,
+    package: &Package,
+    version: Option<&str>,
+) -> SoarResult<Option<InstalledPackage>> {
+    let mut filters: HashMap<_, _> = [
+        ("repo_name", &package.repo_name),
+        ("pkg_name", &package.pkg_name),
+        ("pkg_id", &package.pkg_id),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), (FilterOp::Eq, value.clone().into()).into()))
+    .collect();
+    if let Some(version) = version {
+        filters.insert(
+            "version".to_string(),
+            (FilterOp::Eq, version.to_string().into()).into(),
+        );
+    }
+    let options = QueryOptions {
+        limit: 1,
+        filters,
+        ..Default::default()
+    };
+    Ok(get_installed_packages(core_db, options)?
+        .items
+        .into_iter()
+        .next())
+}
+
 fn select_package(
     db: Arc<Mutex<Connection>>,
     package_name: &str,
@@ -164,16 +946,41 @@ fn select_package(
             "pkg_name".to_string(),
             (FilterOp::Eq, existing.pkg_name.clone().into()).into(),
         );
+        filters.insert(
+            "pkg_id".to_string(),
+            (FilterOp::Eq, existing.pkg_id.clone().into()).into(),
+        );
         QueryOptions { filters, ..options }
     } else {
         options
     };
 
-    let pkgs = get_packages(db, options)?.items;
+    let mut pkgs = get_packages(db.clone(), options.clone())?.items;
+    if pkgs.is_empty() {
+        pkgs = find_by_other_names(db.clone(), options.clone())?;
+    }
+    if pkgs.is_empty() {
+        pkgs = find_by_version_prefix(db.clone(), options)?;
+    }
+
+    // prefer the configured collection over asking which one to install
+    if pkgs.len() > 1 {
+        if let Some(ref collection) = get_config().default_collection {
+            let preferred: Vec<Package> = pkgs
+                .iter()
+                .filter(|pkg| pkg.collection.as_ref() == Some(collection))
+                .cloned()
+                .collect();
+            if !preferred.is_empty() {
+                pkgs = preferred;
+            }
+        }
+    }
 
     match pkgs.len() {
         0 => {
-            error!("Package {package_name} not found");
+            error!("{}", t!("package-not-found", package = package_name));
+            suggest_similar_packages(db, package_name)?;
             Ok(None)
         }
         1 => Ok(pkgs.into_iter().next()),
@@ -182,15 +989,97 @@ fn select_package(
     }
 }
 
+/// Looks up the releases of the queried version when it's a prefix of their
+/// versions, e.g. `18` for `18.19.0`, keeping the newest of each package.
+fn find_by_version_prefix(
+    db: Arc<Mutex<Connection>>,
+    mut options: QueryOptions,
+) -> SoarResult<Vec<Package>> {
+    let Some(FilterValue::Single(version)) =
+        options.filters.remove("version").map(|filter| filter.value)
+    else {
+        return Ok(Vec::new());
+    };
+
+    // every version is needed to pick the newest release matching the prefix
+    options.limit = u32::MAX;
+    let packages = get_packages(db, options)?.items;
+    Ok(newest_matching(
+        packages,
+        &version,
+        |pkg| &pkg.version,
+        |pkg| {
+            (
+                pkg.repo_name.clone(),
+                pkg.pkg_id.clone(),
+                pkg.pkg_name.clone(),
+            )
+        },
+    ))
+}
+
+/// Keeps the newest of the `items` sharing a key whose version matches the
+/// `wanted` version or version prefix, newest first.
+fn newest_matching<T, K: Eq + Hash>(
+    mut items: Vec<T>,
+    wanted: &str,
+    version: impl Fn(&T) -> &str,
+    key: impl Fn(&T) -> K,
+) -> Vec<T> {
+    items.retain(|item| version_matches(wanted, version(item)));
+    items.sort_by(|a, b| compare_versions(version(b), version(a)));
+    let mut seen = HashSet::new();
+    items.retain(|item| seen.insert(key(item)));
+    items
+}
+
+/// Looks up the packages named like the queried name in another case, or
+/// aliased as it, warning about the name each resolved to.
+fn find_by_other_names(
+    db: Arc<Mutex<Connection>>,
+    mut options: QueryOptions,
+) -> SoarResult<Vec<Package>> {
+    let Some(FilterValue::Single(name)) = options
+        .filters
+        .get("pkg_name")
+        .map(|filter| filter.value.clone())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let names: Vec<String> = get_package_names_matching(db.clone(), &name)?
+        .into_iter()
+        .filter(|other| *other != name)
+        .collect();
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+    for other in &names {
+        warn!(
+            "{}",
+            t!("install-resolved-name", query = name, name = other)
+        );
+    }
+
+    options
+        .filters
+        .insert("pkg_name".to_string(), (FilterOp::In, names.into()).into());
+    Ok(get_packages(db, options)?.items)
+}
+
 fn select_package_interactively(
     pkgs: Vec<Package>,
     package_name: &str,
 ) -> SoarResult<Option<Package>> {
-    info!("Multiple packages found for {package_name}");
+    info!("{}", t!("install-multiple-found", package = package_name));
     for (idx, pkg) in pkgs.iter().enumerate() {
         info!(
-            "[{}] {}#{}-{}:{}",
+            "[{}] {}{}#{}-{}:{}",
             idx + 1,
+            pkg.collection
+                .as_ref()
+                .map(|collection| format!("{}/", collection))
+                .unwrap_or_default(),
             pkg.pkg_name,
             pkg.pkg_id,
             pkg.version,
@@ -204,57 +1093,229 @@ fn select_package_interactively(
 
 fn get_valid_selection(max: usize) -> SoarResult<usize> {
     loop {
-        let response = interactive_ask("Select a package: ")?;
+        let response = interactive_ask(&t!("install-select-prompt"))?;
         match response.parse::<usize>() {
             Ok(n) if n > 0 && n <= max => return Ok(n - 1),
-            _ => error!("Invalid selection, please try again."),
+            _ => error!("{}", t!("invalid-selection")),
         }
     }
 }
 
+/// Installs `targets` in parallel and returns the packages that were
+/// installed successfully.
 pub async fn perform_installation(
     ctx: InstallContext,
-    targets: Vec<InstallTarget>,
+    mut targets: Vec<InstallTarget>,
     core_db: Arc<Mutex<Connection>>,
-) -> SoarResult<()> {
+) -> SoarResult<Vec<Package>> {
     let mut handles = Vec::new();
     let fixed_width = 30;
 
     if targets.is_empty() {
-        info!("No packages to install");
-        return Ok(());
+        info!("{}", t!("install-nothing"));
+        return Ok(Vec::new());
     }
 
+    check_disk_space(&targets)?;
+
+    // small packages first so the user gets feedback early
+    targets.sort_by_key(|target| target.package.size);
+
+    let packages: Vec<&Package> = targets.iter().map(|target| &target.package).collect();
+    let transaction_id = begin_transaction(core_db.clone(), ctx.transaction.name(), &packages)
+        .inspect_err(|err| warn!("{}", t!("install-history-failed", error = err)))
+        .ok();
+
+    // versions of a package are installed one after another, so that each
+    // sees the others installed before it
+    let mut package_locks: HashMap<(String, String, String), Arc<tokio::sync::Mutex<()>>> =
+        HashMap::new();
+
+    let _interrupt = CancelOnInterrupt::install()?;
     for (idx, target) in targets.iter().enumerate() {
-        let handle =
-            spawn_installation_task(&ctx, target.clone(), core_db.clone(), idx, fixed_width).await;
+        if cancel::is_cancelled() {
+            break;
+        }
+        let package = &target.package;
+        let package_lock = package_locks
+            .entry((
+                package.repo_name.clone(),
+                package.pkg_id.clone(),
+                package.pkg_name.clone(),
+            ))
+            .or_default()
+            .clone();
+        let handle = spawn_installation_task(
+            &ctx,
+            target.clone(),
+            core_db.clone(),
+            transaction_id,
+            idx,
+            fixed_width,
+            package_lock,
+        )
+        .await;
         handles.push(handle);
     }
 
-    for handle in handles {
-        handle
+    let total = targets.len();
+    let mut installed = Vec::new();
+    let mut failed = Vec::new();
+    let mut first_failure = None;
+    let mut targets = targets.into_iter();
+    for (handle, target) in handles.into_iter().zip(targets.by_ref()) {
+        let result = handle
             .await
             .map_err(|err| SoarError::Custom(format!("Join handle error: {}", err)))?;
+        match result {
+            Ok(()) => installed.push(target.package),
+            Err(err) => {
+                first_failure.get_or_insert(err.kind());
+                failed.push((target.package, err.to_string()));
+            }
+        }
+    }
+    // the rest were never started
+    for target in targets {
+        let error = SoarError::Cancelled.to_string();
+        if let Some(transaction_id) = transaction_id {
+            record_outcome(
+                core_db.clone(),
+                transaction_id,
+                &target.package,
+                Some(&error),
+            );
+        }
+        failed.push((target.package, error));
     }
 
     ctx.total_progress_bar.finish_and_clear();
     info!(
-        "Installed {}/{} packages",
-        ctx.installed_count.load(Ordering::Relaxed),
-        ctx.total_packages
+        "{}",
+        t!(
+            "install-summary",
+            installed = ctx.installed_count.load(Ordering::Relaxed),
+            total = ctx.total_packages
+        )
     );
 
-    Ok(())
+    if !failed.is_empty() {
+        print_failures(&failed);
+    }
+
+    for package in &installed {
+        print_notes(package);
+    }
+
+    if !installed.is_empty() {
+        let packages = installed
+            .iter()
+            .map(|package| package.pkg_name.clone())
+            .collect::<Vec<_>>();
+        run_hooks(ctx.transaction, &packages);
+        print_path_hint()?;
+        report_installs(&installed).await;
+    }
+
+    if cancel::is_cancelled() {
+        return Err(SoarError::Cancelled);
+    }
+    if let Some(kind) = first_failure {
+        return Err(SoarError::InstallFailed {
+            failed: failed.len(),
+            total,
+            kind,
+        });
+    }
+
+    Ok(installed)
+}
+
+/// Prints the packages that failed to install along with why, and how to
+/// retry them.
+fn print_failures(failed: &[(Package, String)]) {
+    let rows: Vec<(String, &str)> = failed
+        .iter()
+        .map(|(package, error)| {
+            (
+                format!(
+                    "{}#{}:{}",
+                    package.pkg_name, package.pkg_id, package.repo_name
+                ),
+                error.as_str(),
+            )
+        })
+        .collect();
+    let name_width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain([t!("install-preview-package").len()])
+        .max()
+        .unwrap_or_default();
+
+    error!("{}", t!("install-failures", count = rows.len()));
+    let header = format!(
+        "{:<name_width$}  {}",
+        t!("install-preview-package"),
+        t!("install-failure-reason"),
+    );
+    info!("{}", header.color(Color::Label));
+    for (name, reason) in &rows {
+        info!(
+            pkg = %name,
+            error = %reason,
+            "{}  {}",
+            format!("{:<name_width$}", name).color(Color::Accent),
+            reason.color(Color::Error),
+        );
+    }
+    info!("{}", t!("install-retry-hint"));
+}
+
+/// Records the outcome of `package` in the history, so that it no longer
+/// counts as interrupted.
+fn record_outcome(
+    core_db: Arc<Mutex<Connection>>,
+    transaction_id: i64,
+    package: &Package,
+    error: Option<&str>,
+) {
+    if let Err(err) = finish_entry(core_db, transaction_id, package, error) {
+        warn!("{}", t!("install-history-failed", error = err));
+    }
+}
+
+/// Reports `packages` to the repositories they were installed from, if
+/// telemetry is enabled. Reports are best effort and never fail an install.
+async fn report_installs(packages: &[Package]) {
+    let repositories: Vec<Repository> = {
+        let config = get_config();
+        if !config.telemetry_enabled() {
+            return;
+        }
+        config
+            .enabled_repositories()
+            .filter(|repo| repo.stats_url.is_some())
+            .cloned()
+            .collect()
+    };
+    for repo in &repositories {
+        if let Err(err) = popularity::report_installs(repo, packages).await {
+            debug!("{}", err);
+        }
+    }
 }
 
 async fn spawn_installation_task(
     ctx: &InstallContext,
     target: InstallTarget,
     core_db: Arc<Mutex<Connection>>,
+    transaction_id: Option<i64>,
     idx: usize,
     fixed_width: usize,
-) -> tokio::task::JoinHandle<()> {
-    let permit = ctx.semaphore.clone().acquire_owned().await.unwrap();
+    package_lock: Arc<tokio::sync::Mutex<()>>,
+) -> tokio::task::JoinHandle<SoarResult<()>> {
+    let permit = ctx.scheduler.acquire().await;
     let progress_bar = ctx
         .multi_progress
         .insert_from_back(1, create_progress_bar());
@@ -273,114 +1334,112 @@ async fn spawn_installation_task(
     };
     progress_bar.set_message(message);
 
-    let progress_callback = Arc::new(move |state| {
-        progress::handle_progress(state, &progress_bar);
+    let events: EventSink = Arc::new(move |event| {
+        progress::handle_install_event(event, &progress_bar);
     });
 
     let total_pb = ctx.total_progress_bar.clone();
     let installed_count = ctx.installed_count.clone();
     let ctx = ctx.clone();
+    let size = target.package.size;
 
     tokio::spawn(async move {
-        let result = install_single_package(&ctx, target, progress_callback, core_db).await;
+        // failures are reported through the event sink
+        let package = target.package.clone();
+        let result = {
+            let _guard = package_lock.lock().await;
+            install_single_package(&ctx, target, events, core_db.clone()).await
+        };
+        drop(permit);
+        if let Some(transaction_id) = transaction_id {
+            let error = result.as_ref().err().map(|err| err.to_string());
+            record_outcome(core_db, transaction_id, &package, error.as_deref());
+        }
 
-        if let Err(err) = result {
-            error!("{err}");
-        } else {
+        if result.is_ok() {
             installed_count.fetch_add(1, Ordering::Relaxed);
             total_pb.inc(1);
+            ctx.scheduler.record(size);
         }
-
-        drop(permit);
+        result
     })
 }
 
-async fn install_single_package(
+pub async fn install_single_package(
     ctx: &InstallContext,
     target: InstallTarget,
-    progress_callback: Arc<dyn Fn(DownloadState) + Send + Sync>,
+    events: EventSink,
     core_db: Arc<Mutex<Connection>>,
 ) -> SoarResult<()> {
-    let (install_dir, real_bin, bin_name) = if let Some(ref existing) = target.existing_install {
-        let install_dir = PathBuf::from(&existing.installed_path);
-        let real_bin = install_dir.join(&target.package.pkg_name);
-        let bin_name = existing
-            .bin_path
-            .as_ref()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| {
-                get_config()
-                    .get_bin_path()
-                    .unwrap()
-                    .join(&target.package.pkg_name)
-            });
-
-        (install_dir, real_bin, bin_name)
-    } else {
-        let rand_str: String = rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(12)
-            .map(char::from)
-            .collect();
-
-        let install_dir = get_config().get_packages_path().unwrap().join(format!(
-            "{}-{}-{}",
-            target.package.pkg, target.package.pkg_id, rand_str
-        ));
-        let real_bin = install_dir.join(&target.package.pkg_name);
-        let bin_name = get_config()
-            .get_bin_path()
-            .unwrap()
-            .join(&target.package.pkg_name);
-
-        (install_dir, real_bin, bin_name)
+    let bin_conflict = ctx
+        .bin_conflicts
+        .get(&(
+            target.package.repo_name.clone(),
+            target.package.pkg_id.clone(),
+        ))
+        .unwrap_or(&ctx.bin_conflict)
+        .clone();
+    let options = InstallOptions {
+        portable: ctx.portable.clone(),
+        portable_home: ctx.portable_home.clone(),
+        portable_config: ctx.portable_config.clone(),
+        bin_conflict,
+        channel: ctx.channel,
+        extract_appimage: ctx.extract_appimage,
     };
 
-    if bin_name.exists() {
-        if let Err(err) = std::fs::remove_file(&bin_name) {
-            return Err(SoarError::Custom(format!(
-                "Failed to remove existing symlink: {}",
-                err
-            )));
-        }
-    }
-
-    if install_dir.exists() {
-        if let Err(err) = std::fs::remove_dir_all(&install_dir) {
-            return Err(SoarError::Custom(format!(
-                "Failed to clean up install directory {}: {}",
-                install_dir.display(),
-                err
-            )));
-        }
+    let package = target.package.clone();
+    debug!(
+        pkg_name = %package.pkg_name,
+        pkg_id = %package.pkg_id,
+        repo_name = %package.repo_name,
+        version = %package.version,
+        url = %package.download_url,
+        "Installing {}#{}",
+        package.pkg_name,
+        package.pkg_id
+    );
+    let started = Instant::now();
+    let result = install_package(&target, &options, Some(events), core_db).await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match &result {
+        Ok(()) => debug!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            elapsed_ms,
+            "Installed {}#{} in {}ms",
+            package.pkg_name,
+            package.pkg_id,
+            elapsed_ms
+        ),
+        Err(err) => debug!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            elapsed_ms,
+            error = %err,
+            "Failed to install {}#{}: {}",
+            package.pkg_name,
+            package.pkg_id,
+            err
+        ),
     }
+    result
+}
 
-    let installer = PackageInstaller::new(
-        &target,
-        &install_dir,
-        Some(progress_callback),
-        core_db,
-        false,
-    )
-    .await?;
-
-    installer.install().await?;
-
-    let final_checksum = calculate_checksum(&real_bin)?;
-    fs::symlink(&real_bin, &bin_name)?;
-
-    let (icon_path, desktop_path) = integrate_package(
-        &install_dir,
-        &target.package,
-        ctx.portable.clone(),
-        ctx.portable_home.clone(),
-        ctx.portable_config.clone(),
-    )
-    .await?;
-
-    installer
-        .record(&final_checksum, &bin_name, icon_path, desktop_path)
-        .await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(())
+    #[test]
+    fn picks_the_newest_release_matching_a_version_prefix() {
+        let packages = vec![
+            ("node", "18.9.1"),
+            ("node", "18.19.0"),
+            ("node", "180.0.0"),
+            ("node", "20.1.0"),
+            ("deno", "18.2.0"),
+        ];
+        let newest = newest_matching(packages, "18", |pkg| pkg.1, |pkg| pkg.0);
+        assert_eq!(newest, vec![("node", "18.19.0"), ("deno", "18.2.0")]);
+    }
 }
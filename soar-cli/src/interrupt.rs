@@ -0,0 +1,92 @@
+//! Ctrl-C handling for installs and downloads.
+//!
+//! The first SIGINT or SIGTERM cancels the running downloads, so installs
+//! stop before touching the previous version and clean up after themselves.
+//! A second one exits right away.
+
+use std::{
+    os::fd::{AsRawFd, IntoRawFd},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Once,
+    },
+    thread,
+};
+
+use nix::{
+    libc,
+    sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal},
+    unistd::{pipe, read},
+};
+use soar_core::SoarResult;
+use soar_dl::cancel;
+use tracing::warn;
+
+const SIGNALS: [Signal; 2] = [Signal::SIGINT, Signal::SIGTERM];
+
+/// Write end of the pipe the signal handler wakes the watcher thread with.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+static WATCHER: Once = Once::new();
+
+extern "C" fn handle_signal(_: libc::c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    // only async-signal-safe calls in here
+    unsafe {
+        libc::write(fd, [1u8].as_ptr().cast(), 1);
+    }
+}
+
+fn spawn_watcher() -> SoarResult<()> {
+    let (reader, writer) = pipe()?;
+    SIGNAL_PIPE.store(writer.into_raw_fd(), Ordering::Relaxed);
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 1];
+        while read(reader.as_raw_fd(), &mut buf).is_ok_and(|n| n > 0) {
+            if cancel::is_cancelled() {
+                std::process::exit(130);
+            }
+            warn!("Cancelling... press Ctrl-C again to exit immediately");
+            cancel::cancel();
+        }
+    });
+
+    Ok(())
+}
+
+/// Cancels downloads on SIGINT or SIGTERM while alive.
+///
+/// The previous handlers are restored when dropped.
+pub struct CancelOnInterrupt {
+    previous: Vec<(Signal, SigAction)>,
+}
+
+impl CancelOnInterrupt {
+    pub fn install() -> SoarResult<Self> {
+        let mut result = Ok(());
+        WATCHER.call_once(|| result = spawn_watcher());
+        result?;
+
+        let action = SigAction::new(
+            SigHandler::Handler(handle_signal),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        let mut previous = Vec::new();
+        for signal in SIGNALS {
+            let old = unsafe { sigaction(signal, &action) }?;
+            previous.push((signal, old));
+        }
+
+        Ok(Self { previous })
+    }
+}
+
+impl Drop for CancelOnInterrupt {
+    fn drop(&mut self) {
+        for (signal, action) in &self.previous {
+            let _ = unsafe { sigaction(*signal, action) };
+        }
+        cancel::reset();
+    }
+}
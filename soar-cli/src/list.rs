@@ -1,75 +1,194 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{stderr, stdin, IsTerminal},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use chrono::{TimeDelta, Utc};
 use indicatif::HumanBytes;
-use nu_ansi_term::Color::{Blue, Cyan, Green, Magenta, Red, Yellow};
+use rusqlite::Connection;
 use soar_core::{
     config::get_config,
-    database::packages::{
-        get_installed_packages, get_packages, FilterOp, PaginatedIterator, QueryOptions, SortOrder,
+    database::{
+        connection::Database,
+        models::Package,
+        packages::{
+            get_category_counts, get_installed_packages, get_installed_with_updates, get_packages,
+            get_packages_providing, get_tag_counts, Filter, FilterExpr, FilterOp,
+            PaginatedIterator, QueryOptions, SortOrder,
+        },
+        sync_state::last_synced,
     },
+    error::SoarError,
+    package::query::compare_versions,
     SoarResult,
 };
 use tracing::info;
 
-use crate::state::AppState;
+use crate::{
+    cli::{ListSort, SearchSort},
+    color::{Color, ColorExt},
+    i18n::t,
+    install::{install_packages, InstallArgs},
+    picker::pick_many,
+    state::AppState,
+    utils::format_age,
+};
+
+/// Filters on the categories and tags of packages, which only apply to the
+/// repository metadata.
+fn label_filters(category: Option<String>, tag: Option<String>) -> HashMap<String, Filter> {
+    let mut filters = HashMap::new();
+    if let Some(category) = category {
+        filters.insert(
+            "category".to_string(),
+            (FilterOp::Eq, category.into()).into(),
+        );
+    }
+    if let Some(tag) = tag {
+        filters.insert("tag".to_string(), (FilterOp::Eq, tag.into()).into());
+    }
+    filters
+}
+
+/// How search results are shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SearchOutput {
+    Lines,
+    /// Tab separated fields that stay the same across versions, for scripts.
+    Porcelain,
+    NamesOnly,
+    /// Picked from to install.
+    Pick,
+}
+
+impl SearchOutput {
+    /// Whether all results are shown unless limited explicitly, rather than
+    /// up to the configured search limit.
+    fn is_unlimited(self) -> bool {
+        self != SearchOutput::Lines
+    }
+}
+
+/// Whether `package` is installed: `+` if so, `?` if partially, `-` if not.
+fn install_status(core_db: Arc<Mutex<Connection>>, package: &Package) -> SoarResult<&'static str> {
+    let mut filters = HashMap::new();
+    filters.insert(
+        "repo_name".to_string(),
+        (FilterOp::Eq, package.repo_name.clone().into()).into(),
+    );
+    filters.insert(
+        "pkg_name".to_string(),
+        (FilterOp::Eq, package.pkg_name.clone().into()).into(),
+    );
+    let options = QueryOptions {
+        limit: 1,
+        filters,
+        ..Default::default()
+    };
+
+    let installed_pkgs = get_installed_packages(core_db, options)?.items;
+    Ok(match installed_pkgs.first() {
+        Some(installed) if installed.is_installed => "+",
+        Some(_) => "?",
+        None => "-",
+    })
+}
 
+#[allow(clippy::too_many_arguments)]
 pub async fn search_packages(
-    query: String,
+    query: Option<String>,
+    tag: Option<String>,
+    category: Option<String>,
     case_sensitive: bool,
     limit: Option<usize>,
+    offset: u32,
+    sort: SearchSort,
+    output: SearchOutput,
 ) -> SoarResult<()> {
-    let state = AppState::new().await?;
-    let repo_db = state.repo_db().clone();
-    let core_db = state.core_db().clone();
+    if output == SearchOutput::Pick && (!stdin().is_terminal() || !stderr().is_terminal()) {
+        return Err(SoarError::Custom(t!("pick-no-terminal")));
+    }
 
     let mut filters = HashMap::new();
-    if case_sensitive {
-        filters.insert(
-            "pkg_name".to_string(),
-            (FilterOp::Like, query.into()).into(),
-        );
-    } else {
-        filters.insert(
-            "pkg_name".to_string(),
-            (FilterOp::ILike, query.into()).into(),
-        );
+    let mut expression = None;
+    let mut rank_term = None;
+    if let Some(query) = query {
+        if FilterExpr::looks_like(&query) {
+            expression = Some(FilterExpr::parse(&query)?);
+        } else {
+            rank_term = Some(query.clone());
+            let op = if case_sensitive {
+                FilterOp::Like
+            } else {
+                FilterOp::ILike
+            };
+            filters.insert("search".to_string(), (op, query.into()).into());
+        }
     }
 
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+    let core_db = state.core_db().clone();
+
+    filters.extend(label_filters(category, tag));
     let packages = get_packages(
         repo_db,
         QueryOptions {
-            limit: limit.or(get_config().search_limit).unwrap_or(20) as u32,
-            filters: filters.clone(),
+            limit: if output.is_unlimited() {
+                limit.map_or(u32::MAX, |limit| limit as u32)
+            } else {
+                limit.or(get_config().search_limit).unwrap_or(20) as u32
+            },
+            filters,
+            expression,
+            offset,
+            sort_by: search_sort_fields(sort),
+            rank_term,
             ..Default::default()
         },
     )?;
-
-    for package in packages.items {
-        let mut filters = filters.clone();
-        filters.insert(
-            "repo_name".to_string(),
-            (FilterOp::Eq, package.repo_name.clone().into()).into(),
-        );
-        filters.insert(
-            "pkg_name".to_string(),
-            (FilterOp::Eq, package.pkg_name.clone().into()).into(),
-        );
-        let options = QueryOptions {
-            limit: 1,
-            filters,
-            ..Default::default()
-        };
-
-        let installed_pkgs = get_installed_packages(core_db.clone(), options)?.items;
-
-        let mut install_status = "-";
-        if !installed_pkgs.is_empty() {
-            if installed_pkgs.first().unwrap().is_installed {
-                install_status = "+";
-            } else {
-                install_status = "?";
+    let shown = packages.items.len() as u64;
+    match output {
+        SearchOutput::Lines => {}
+        SearchOutput::Pick => return pick_and_install(packages.items).await,
+        SearchOutput::NamesOnly => {
+            let mut seen = HashSet::new();
+            for package in packages.items {
+                if seen.insert(package.pkg_name.clone()) {
+                    println!("{}", package.pkg_name);
+                }
+            }
+            return Ok(());
+        }
+        SearchOutput::Porcelain => {
+            for package in packages.items {
+                let status = install_status(core_db.clone(), &package)?;
+                // one line per package, whatever the description holds
+                let description: String = package
+                    .description
+                    .chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .collect();
+                println!(
+                    "{}#{}:{}\t{}\t{}\t{}\t{}",
+                    package.pkg_name,
+                    package.pkg_id,
+                    package.repo_name,
+                    package.version,
+                    package.size,
+                    status,
+                    description
+                );
             }
+            return Ok(());
         }
+    }
+
+    for package in packages.items {
+        let install_status = install_status(core_db.clone(), &package)?;
 
         info!(
             pkg_name = %package.pkg_name,
@@ -79,27 +198,78 @@ pub async fn search_packages(
             repo_name = %package.repo_name,
             "[{}] {}#{}-{}:{} - {} ({})",
             install_status,
-            Blue.paint(package.pkg_name.clone()),
-            Cyan.paint(package.pkg_id.clone()),
-            Magenta.paint(package.version.clone()),
-            Cyan.paint(package.repo_name.clone()),
+            package.pkg_name.color(Color::Accent),
+            package.pkg_id.color(Color::Info),
+            package.version.color(Color::Value),
+            package.repo_name.color(Color::Info),
             package.description,
             HumanBytes(package.size)
         );
     }
 
-    info!(
-        "{}",
-        Red.paint(format!(
-            "Showing {} of {}",
-            std::cmp::min(packages.limit as u64, packages.total),
-            packages.total
-        ))
-    );
+    let showing = if offset == 0 || shown == 0 {
+        t!("search-showing", shown = shown, total = packages.total)
+    } else {
+        t!(
+            "search-showing-range",
+            from = offset as u64 + 1,
+            to = offset as u64 + shown,
+            total = packages.total
+        )
+    };
+    info!("{}", showing.color(Color::Label));
 
     Ok(())
 }
 
+/// Lets the user pick which of `packages` to install.
+async fn pick_and_install(packages: Vec<Package>) -> SoarResult<()> {
+    if packages.is_empty() {
+        info!("{}", t!("search-showing", shown = 0, total = 0));
+        return Ok(());
+    }
+    let items: Vec<String> = packages
+        .iter()
+        .map(|package| {
+            format!(
+                "{}#{}-{}:{} - {} ({})",
+                package.pkg_name.color(Color::Accent),
+                package.pkg_id.color(Color::Info),
+                package.version.color(Color::Value),
+                package.repo_name.color(Color::Info),
+                package.description,
+                HumanBytes(package.size)
+            )
+        })
+        .collect();
+    let Some(picked) = pick_many(&t!("pick-prompt"), &items)? else {
+        return Err(SoarError::Cancelled);
+    };
+    if picked.is_empty() {
+        info!("{}", t!("pick-none"));
+        return Ok(());
+    }
+
+    let selected: Vec<String> = picked
+        .into_iter()
+        .map(|idx| {
+            let package = &packages[idx];
+            format!(
+                "{}#{}:{}",
+                package.pkg_name, package.pkg_id, package.repo_name
+            )
+        })
+        .collect();
+    install_packages(
+        &selected,
+        InstallArgs {
+            yes: true,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
 pub async fn query_package(query: String) -> SoarResult<()> {
     let state = AppState::new().await?;
     let repo_db = state.repo_db().clone();
@@ -143,28 +313,28 @@ pub async fn query_package(query: String) -> SoarResult<()> {
             "{}\n",
             "{}",
             ),
-            Red.paint("Name"), Green.paint(package.pkg_name.clone()), Cyan.paint(package.pkg_id.clone()), Red.paint(package.repo_name.clone()),
-            Red.paint("Description"), Yellow.paint(package.description.clone()),
-            Red.paint("Homepages"), Blue.paint(serde_json::to_string_pretty(&package.homepages.clone()).unwrap()),
-            Red.paint("Sources"), Blue.paint(serde_json::to_string_pretty(&package.source_urls.clone()).unwrap()),
-            Red.paint("Version"), Magenta.paint(package.version.clone()),
-            Red.paint("Checksum"), Magenta.paint(package.checksum.clone()),
-            Red.paint("Size"), Magenta.paint(HumanBytes(package.size).to_string()),
-            Red.paint("Download URL"), Blue.paint(package.download_url.clone()),
+            "Name".color(Color::Label), package.pkg_name.color(Color::Accent), package.pkg_id.color(Color::Info), package.repo_name.color(Color::Info),
+            "Description".color(Color::Label), package.description.color(Color::Info),
+            "Homepages".color(Color::Label), serde_json::to_string_pretty(&package.homepages.clone()).unwrap().color(Color::Accent),
+            "Sources".color(Color::Label), serde_json::to_string_pretty(&package.source_urls.clone()).unwrap().color(Color::Accent),
+            "Version".color(Color::Label), package.version.color(Color::Value),
+            "Checksum".color(Color::Label), package.checksum.color(Color::Value),
+            "Size".color(Color::Label), HumanBytes(package.size).color(Color::Value),
+            "Download URL".color(Color::Label), package.download_url.color(Color::Accent),
             if let Some(ref build_date) = package.build_date {
-                format!("{}: {}", Red.paint("Build Date"), Magenta.paint(build_date.clone()))
+                format!("{}: {}", "Build Date".color(Color::Label), build_date.color(Color::Value))
             } else {
                 String::new()
             },
 
             if let Some(ref build_log) = package.build_log {
-                format!("{}: {}", Red.paint("Build Log"), Blue.paint(build_log.clone()))
+                format!("{}: {}", "Build Log".color(Color::Label), build_log.color(Color::Accent))
             } else {
                 String::new()
             },
 
             if let Some(ref build_script) = package.build_script {
-                format!("{}: {}", Red.paint("Build Script"), Blue.paint(build_script.clone()))
+                format!("{}: {}", "Build Script".color(Color::Label), build_script.color(Color::Accent))
             } else {
                 String::new()
             },
@@ -174,27 +344,245 @@ pub async fn query_package(query: String) -> SoarResult<()> {
     Ok(())
 }
 
-pub async fn list_packages(repo_name: Option<String>) -> SoarResult<()> {
+pub async fn find_providers(name: &str) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let repo_db = state.repo_db().clone();
+
+    let packages = get_packages_providing(repo_db, name)?;
+    if packages.is_empty() {
+        info!("{}", t!("search-no-provider", name = name));
+        return Ok(());
+    }
+
+    for package in packages {
+        info!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            version = %package.version,
+            repo_name = %package.repo_name,
+            "{}#{}-{}:{} - {}",
+            package.pkg_name.color(Color::Accent),
+            package.pkg_id.color(Color::Info),
+            package.version.color(Color::Value),
+            package.repo_name.color(Color::Info),
+            package.description
+        );
+    }
+
+    Ok(())
+}
+
+/// Lists the packages built within the last `days` days, newest first, to
+/// discover what was added to or updated in the repositories.
+pub async fn list_new_packages(days: u32, repo_name: Option<String>) -> SoarResult<()> {
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+
+    // build dates are RFC 3339 timestamps or plain dates, which both compare
+    // as strings against the date the window starts on
+    let since = (Utc::now() - TimeDelta::days(days.into()))
+        .format("%Y-%m-%d")
+        .to_string();
+    let mut filters = HashMap::new();
+    filters.insert(
+        "build_date".to_string(),
+        (FilterOp::Gte, since.clone().into()).into(),
+    );
+    if let Some(repo_name) = repo_name {
+        filters.insert(
+            "r.name".to_string(),
+            (FilterOp::Eq, repo_name.into()).into(),
+        );
+    }
+
+    let fetch_packages = |query_options: QueryOptions| get_packages(repo_db.clone(), query_options);
+    let package_iterator = PaginatedIterator::new(
+        &fetch_packages,
+        QueryOptions {
+            limit: 2000,
+            sort_by: sort_fields(ListSort::Date, "build_date"),
+            filters,
+            ..Default::default()
+        },
+    );
+
+    let mut found = false;
+    for result in package_iterator {
+        for package in result? {
+            found = true;
+            let build_date = package.build_date.unwrap_or_default();
+            info!(
+                pkg_name = %package.pkg_name,
+                pkg_id = %package.pkg_id,
+                version = %package.version,
+                repo_name = %package.repo_name,
+                build_date = %build_date,
+                description = %package.description,
+                "{}  {}#{}-{}:{} - {}",
+                build_date.get(..10).unwrap_or(&build_date).color(Color::Label),
+                package.pkg_name.color(Color::Accent),
+                package.pkg_id.color(Color::Info),
+                package.version.color(Color::Value),
+                package.repo_name.color(Color::Info),
+                package.description
+            );
+        }
+    }
+    if !found {
+        info!("{}", t!("whatsnew-none", since = since));
+    }
+
+    Ok(())
+}
+
+/// Lists the categories of the packages, or their tags with `tags`, along
+/// with how many packages are in each.
+pub async fn list_categories(tags: bool) -> SoarResult<()> {
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+
+    let counts = if tags {
+        get_tag_counts(repo_db)?
+    } else {
+        get_category_counts(repo_db)?
+    };
+    if counts.is_empty() {
+        let message = if tags {
+            t!("categories-no-tags")
+        } else {
+            t!("categories-none")
+        };
+        info!("{}", message);
+        return Ok(());
+    }
+
+    let width = counts.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, count) in counts {
+        info!(
+            name = %name,
+            packages = count,
+            "{} {:>6}",
+            format!("{:<width$}", name).color(Color::Accent),
+            count
+        );
+    }
+
+    Ok(())
+}
+
+/// Lists the configured repositories with their package counts and when
+/// their metadata was last synced.
+pub async fn list_repositories() -> SoarResult<()> {
+    let config = get_config();
+    let core_db = AppState::create_core_db(&config)?;
+
+    for repo in &config.repositories {
+        let metadata_db = repo.get_path()?.join("metadata.db");
+        let count = if fs::metadata(&metadata_db).is_ok_and(|meta| meta.len() > 0) {
+            let mut filters = HashMap::new();
+            filters.insert(
+                "r.name".to_string(),
+                (FilterOp::Eq, repo.name.clone().into()).into(),
+            );
+            get_packages(
+                Database::new(&metadata_db)?.conn,
+                QueryOptions {
+                    filters,
+                    limit: 1,
+                    ..Default::default()
+                },
+            )?
+            .total
+        } else {
+            0
+        };
+
+        let synced = last_synced(core_db.conn.clone(), &repo.name)?;
+        let age = match synced {
+            Some(time) => format_age(SystemTime::now().duration_since(time).unwrap_or_default())
+                .color(Color::Info),
+            None => t!("sync-never").color(Color::Warn),
+        };
+        let status = if repo.is_enabled() {
+            String::new()
+        } else {
+            format!("  {}", t!("repo-status-disabled").color(Color::Warn))
+        };
+        info!(
+            repo_name = %repo.name,
+            url = %repo.url,
+            enabled = repo.is_enabled(),
+            packages = count,
+            last_sync = synced
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_secs()),
+            "{} {:>8} packages  {}  {}{}",
+            format!("{:<20}", repo.name).color(Color::Accent),
+            count,
+            age,
+            repo.url,
+            status
+        );
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn list_packages(
+    repo_name: Option<String>,
+    collection: Option<String>,
+    category: Option<String>,
+    tag: Option<String>,
+    filter: Option<String>,
+    installed: bool,
+    updatable: bool,
+    sort: ListSort,
+) -> SoarResult<()> {
+    let expression = filter.as_deref().map(FilterExpr::parse).transpose()?;
     let state = AppState::new().await?;
     let repo_db = state.repo_db().clone();
     let core_db = state.core_db().clone();
 
+    if installed || updatable {
+        return list_installed_status(repo_db, core_db, repo_name, updatable, sort);
+    }
+
+    let installed_versions: HashMap<(String, String, String), (String, bool)> =
+        get_installed_packages(core_db.clone(), QueryOptions::default())?
+            .items
+            .into_iter()
+            .map(|p| {
+                (
+                    (p.repo_name, p.pkg_id, p.pkg_name),
+                    (p.version, p.is_installed),
+                )
+            })
+            .collect();
+
     let fetch_packages = |query_options: QueryOptions| get_packages(repo_db.clone(), query_options);
 
-    let mut filters = HashMap::new();
+    let mut filters = label_filters(category, tag);
     if let Some(repo_name) = repo_name {
         filters.insert(
             "r.name".to_string(),
             (FilterOp::Eq, repo_name.into()).into(),
         );
     }
+    if let Some(collection) = collection {
+        filters.insert(
+            "collection".to_string(),
+            (FilterOp::Eq, collection.into()).into(),
+        );
+    }
 
     let package_iterator = PaginatedIterator::new(
         &fetch_packages,
         QueryOptions {
             limit: 2000,
-            sort_by: vec![("pkg_name".into(), SortOrder::Asc)],
-            filters: filters.clone(),
+            sort_by: sort_fields(sort, "build_date"),
+            filters,
+            expression,
             ..Default::default()
         },
     );
@@ -202,36 +590,35 @@ pub async fn list_packages(repo_name: Option<String>) -> SoarResult<()> {
     for result in package_iterator {
         let packages = result?;
         for package in packages {
-            let mut filters = filters.clone();
-            filters.insert(
-                "repo_name".to_string(),
-                (FilterOp::Eq, package.repo_name.clone().into()).into(),
+            let key = (
+                package.repo_name.clone(),
+                package.pkg_id.clone(),
+                package.pkg_name.clone(),
             );
-            let options = QueryOptions {
-                filters,
-                ..Default::default()
+            let install_status = match installed_versions.get(&key) {
+                Some((_, false)) => "?",
+                Some((version, true)) if compare_versions(version, &package.version).is_lt() => "u",
+                Some((_, true)) => "+",
+                None => "-",
             };
 
-            let installed_pkgs = get_installed_packages(core_db.clone(), options)?.items;
-
-            let mut install_status = "-";
-            if !installed_pkgs.is_empty() {
-                if installed_pkgs.first().unwrap().is_installed {
-                    install_status = "+";
-                } else {
-                    install_status = "?";
-                }
-            }
-
             info!(
                 pkg_name = %package.pkg_name,
+                pkg_id = %package.pkg_id,
                 version = %package.version,
                 repo_name = %package.repo_name,
-                "[{}] {}-{}:{}",
+                pkg_type = %package.pkg_type,
+                collection = ?package.collection,
+                size = %package.size,
+                status = %install_status,
+                "[{}] {}#{}-{}:{} ({}) ({})",
                 install_status,
-                Red.paint(package.pkg_name.clone()),
-                package.version,
-                package.repo_name
+                package.pkg_name.color(Color::Accent),
+                package.pkg_id.color(Color::Info),
+                package.version.color(Color::Value),
+                package.repo_name,
+                package.pkg_type,
+                HumanBytes(package.size)
             );
         }
     }
@@ -239,6 +626,78 @@ pub async fn list_packages(repo_name: Option<String>) -> SoarResult<()> {
     Ok(())
 }
 
+fn sort_fields(sort: ListSort, date_field: &str) -> Vec<(String, SortOrder)> {
+    match sort {
+        ListSort::Name => vec![("pkg_name".into(), SortOrder::Asc)],
+        ListSort::Size => vec![
+            ("size".into(), SortOrder::Desc),
+            ("pkg_name".into(), SortOrder::Asc),
+        ],
+        ListSort::Date => vec![
+            (date_field.into(), SortOrder::Desc),
+            ("pkg_name".into(), SortOrder::Asc),
+        ],
+    }
+}
+
+fn search_sort_fields(sort: SearchSort) -> Vec<(String, SortOrder)> {
+    match sort {
+        // shorter names are closer to the query among equally good matches
+        SearchSort::Relevance => vec![
+            ("relevance".into(), SortOrder::Desc),
+            ("LENGTH(pkg_name)".into(), SortOrder::Asc),
+            ("pkg_name".into(), SortOrder::Asc),
+        ],
+        SearchSort::Name => sort_fields(ListSort::Name, "build_date"),
+        SearchSort::Size => sort_fields(ListSort::Size, "build_date"),
+        SearchSort::Date => sort_fields(ListSort::Date, "build_date"),
+    }
+}
+
+fn list_installed_status(
+    repo_db: Arc<Mutex<Connection>>,
+    core_db: Arc<Mutex<Connection>>,
+    repo_name: Option<String>,
+    updatable: bool,
+    sort: ListSort,
+) -> SoarResult<()> {
+    let packages = get_installed_with_updates(
+        repo_db,
+        core_db,
+        repo_name.as_deref(),
+        updatable,
+        &sort_fields(sort, "installed_date"),
+    )?;
+
+    for (package, newer) in packages {
+        let (install_status, available) = match newer {
+            Some(ref newer) => ("u", format!(" -> {}", newer.color(Color::Success))),
+            None => ("+", String::new()),
+        };
+
+        info!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            version = %package.version,
+            repo_name = %package.repo_name,
+            size = %package.size,
+            installed_date = ?package.installed_date,
+            status = %install_status,
+            "[{}] {}#{}-{}{}:{} ({}) ({})",
+            install_status,
+            package.pkg_name.color(Color::Accent),
+            package.pkg_id.color(Color::Info),
+            package.version.color(Color::Value),
+            available,
+            package.repo_name,
+            package.installed_date.clone().unwrap_or_default(),
+            HumanBytes(package.size)
+        );
+    }
+
+    Ok(())
+}
+
 pub async fn list_installed_packages(repo_name: Option<String>) -> SoarResult<()> {
     let state = AppState::new().await?;
     let core_db = state.core_db().clone();
@@ -270,7 +729,7 @@ pub async fn list_installed_packages(repo_name: Option<String>) -> SoarResult<()
                 installed_date = %package.installed_date.clone().unwrap(),
                 size = %package.size,
                 "{}-{}:{} ({}) ({})",
-                Red.paint(package.pkg_name.clone()),
+                package.pkg_name.color(Color::Accent),
                 package.version,
                 package.repo_name,
                 package.installed_date.clone().unwrap(),
@@ -1,4 +1,11 @@
-use nu_ansi_term::Color::{Blue, Magenta, Red, Yellow};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use soar_core::{error::SoarError, platform::home_state_path, SoarResult};
 use tracing::{Event, Level, Subscriber};
 use tracing_subscriber::{
     fmt::{
@@ -6,10 +13,15 @@ use tracing_subscriber::{
         format::{FmtSpan, Writer},
         FmtContext, FormatEvent, FormatFields, MakeWriter,
     },
+    layer::SubscriberExt,
     registry::LookupSpan,
+    EnvFilter, Layer, Registry,
 };
 
-use crate::cli::Args;
+use crate::{
+    cli::Args,
+    color::{Color, ColorExt},
+};
 
 #[derive(Default)]
 struct MessageVisitor {
@@ -41,17 +53,17 @@ where
         event.record(&mut visitor);
 
         match *event.metadata().level() {
-            Level::TRACE => write!(writer, "{} ", Magenta.paint("[TRACE]")),
-            Level::DEBUG => write!(writer, "{} ", Blue.paint("[DEBUG]")),
+            Level::TRACE => write!(writer, "{} ", "[TRACE]".color(Color::Value)),
+            Level::DEBUG => write!(writer, "{} ", "[DEBUG]".color(Color::Accent)),
             Level::INFO => write!(writer, ""),
-            Level::WARN => write!(writer, "{} ", Yellow.paint("[WARN]")),
-            Level::ERROR => write!(writer, "{} ", Red.paint("[ERROR]")),
+            Level::WARN => write!(writer, "{} ", "[WARN]".color(Color::Warn)),
+            Level::ERROR => write!(writer, "{} ", "[ERROR]".color(Color::Error)),
         }?;
 
         if let Some(message) = visitor.message {
             writeln!(writer, "{}", message)
         } else {
-            writeln!(writer, "")
+            writeln!(writer)
         }
     }
 }
@@ -86,7 +98,71 @@ impl<'a> MakeWriter<'a> for WriterBuilder {
     }
 }
 
-pub fn setup_logging(args: &Args) {
+/// Size at which the log file is rotated on startup.
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Number of rotated log files kept next to the current one.
+const LOG_BACKUPS: usize = 3;
+
+/// Writes to the log file with ANSI escape sequences stripped, since most
+/// messages are colored for the terminal.
+struct LogFileWriter {
+    file: File,
+}
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut plain = Vec::with_capacity(buf.len());
+        let mut bytes = buf.iter().peekable();
+        while let Some(&byte) = bytes.next() {
+            if byte == 0x1b && bytes.peek() == Some(&&b'[') {
+                // skip the parameters up to and including the final byte
+                for &b in bytes.by_ref() {
+                    if (0x40..=0x7e).contains(&b) && b != b'[' {
+                        break;
+                    }
+                }
+            } else {
+                plain.push(byte);
+            }
+        }
+        self.file.write_all(&plain)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Path of the log file, `$XDG_STATE_HOME/soar/soar.log`.
+pub fn log_file_path() -> PathBuf {
+    PathBuf::from(home_state_path())
+        .join("soar")
+        .join("soar.log")
+}
+
+/// Opens the log file for appending, first rotating it if it grew past
+/// [`MAX_LOG_SIZE`].
+fn open_log_file() -> io::Result<LogFileWriter> {
+    let path = log_file_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if fs::metadata(&path).is_ok_and(|meta| meta.len() > MAX_LOG_SIZE) {
+        let backup = |n: usize| path.with_extension(format!("log.{}", n));
+        for n in (1..LOG_BACKUPS).rev() {
+            let _ = fs::rename(backup(n), backup(n + 1));
+        }
+        fs::rename(&path, backup(1))?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    Ok(LogFileWriter { file })
+}
+
+pub fn setup_logging(args: &Args) -> SoarResult<()> {
     let filter_level = if args.quiet {
         Level::ERROR
     } else if args.verbose >= 2 {
@@ -97,8 +173,7 @@ pub fn setup_logging(args: &Args) {
         Level::INFO
     };
 
-    let builder = fmt::Subscriber::builder()
-        .with_env_filter(format!("soar={}", filter_level))
+    let console = fmt::layer()
         .with_target(false)
         .with_thread_ids(false)
         .with_thread_names(false)
@@ -108,12 +183,23 @@ pub fn setup_logging(args: &Args) {
         .with_writer(WriterBuilder::new())
         .compact()
         .without_time();
-
-    let subscriber: Box<dyn Subscriber + Send + Sync> = if args.json {
-        Box::new(builder.json().flatten_event(true).finish())
+    let console: Box<dyn Layer<Registry> + Send + Sync> = if args.json {
+        Box::new(console.json().flatten_event(true))
     } else {
-        Box::new(builder.event_format(CustomFormatter).finish())
+        Box::new(console.event_format(CustomFormatter))
     };
-
-    tracing::subscriber::set_global_default(subscriber).expect("Failed to set tracing subscriber");
+    let console = console.with_filter(EnvFilter::new(format!("soar={}", filter_level)));
+
+    // the log file always records debug output, regardless of the verbosity
+    let log_file = open_log_file().ok().map(|writer| {
+        fmt::layer()
+            .with_ansi(false)
+            .with_target(true)
+            .with_writer(Mutex::new(writer))
+            .with_filter(EnvFilter::new("soar=debug,soar_core=debug,soar_dl=debug"))
+    });
+
+    let subscriber = tracing_subscriber::registry().with(console).with(log_file);
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|err| SoarError::Custom(format!("Failed to set tracing subscriber: {}", err)))
 }
@@ -1,36 +1,113 @@
-use std::{env, io::Read};
+use std::{
+    env,
+    io::{self, IsTerminal, Read},
+    path::{Path, PathBuf},
+};
 
+use adopt::adopt_packages;
+use audit::audit_packages;
 use clap::Parser;
-use cli::Args;
-use download::download;
+use cli::{Args, EnvAction, ProgressMode, SearchSort};
+use color::init_colors;
+use daemon::run_daemon;
+use diff::diff_package;
+use discover::discover_packages;
+use download::{download, list_releases, process_cache_action};
+use ensure::ensure_state;
+use export::{export_packages, import_packages};
+use health::check_health;
+use hook::{command_not_found, print_hook};
+use init::init;
 use inspect::{inspect_log, InspectType};
-use install::install_packages;
-use list::{list_installed_packages, list_packages, query_package, search_packages};
+use install::{
+    install_from_github, install_packages, reinstall_packages, retry_failed_installs, InstallArgs,
+};
+use list::{
+    find_providers, list_categories, list_installed_packages, list_new_packages, list_packages,
+    list_repositories, query_package, search_packages, SearchOutput,
+};
 use logging::setup_logging;
-use remove::remove_packages;
+use manual::{generate_man, show_help};
+use notes::show_notes;
+use open::{open_package, OpenTarget};
+use popular::list_popular;
+use progress::init_progress;
+use readme::show_readme;
+use recovery::recover_interrupted;
+use remove::{collect_garbage, remove_packages};
+use repo::process_repo_action;
 use run::run_package;
+use sbom::generate_sbom;
 use self_actions::process_self_action;
+use shell::{install_path, print_path_line};
 use soar_core::{
-    config::{generate_default_config, get_config, set_current_profile},
+    config::{generate_default_config, get_config, set_current_profile, timeout_secs},
+    database::download_queue::clear_download_queue,
+    http::{client_config, external_downloaders},
+    package::{adopt::AdoptOptions, channel::Channel, install::BinConflictPolicy},
     utils::{cleanup_cache, remove_broken_symlinks, setup_required_paths},
     SoarResult,
 };
-use tracing::{error, info};
+use soar_dl::{
+    api_cache::configure_api_cache, external::configure_external, http_client::configure_client,
+};
+use state::{set_refresh, AppState};
+use stats::{show_disk_usage, show_stats};
+use switch::use_installed;
+use sync::sync_repositories;
+use system::{enter_install_root, enter_system_mode};
+use tracing::{debug, error, info};
 use update::update_packages;
+use utils::lock_instance;
+use verify::verify_packages;
 
+mod adopt;
+mod audit;
 mod cli;
+mod color;
+mod daemon;
+#[cfg(feature = "dbus")]
+mod dbus;
+mod diff;
+mod discover;
 mod download;
+mod ensure;
+mod export;
+mod health;
+mod hook;
+mod i18n;
+mod init;
 mod inspect;
 mod install;
+mod interrupt;
 mod list;
 mod logging;
+mod manual;
+mod metrics;
+mod notes;
+mod open;
+mod picker;
+mod popular;
+mod post_hooks;
 mod progress;
+mod readme;
+mod recovery;
 mod remove;
+mod repo;
 mod run;
+mod sbom;
+mod scheduler;
+mod selection;
 mod self_actions;
+mod shell;
 mod state;
+mod stats;
+mod switch;
+mod sync;
+mod system;
 mod update;
 mod utils;
+mod verify;
 
 async fn handle_cli() -> SoarResult<()> {
     let mut args = env::args().collect::<Vec<_>>();
@@ -54,22 +131,90 @@ async fn handle_cli() -> SoarResult<()> {
         }
     }
 
+    let raw_args = args.clone();
     let args = Args::parse_from(args);
 
-    setup_logging(&args);
-
+    init_colors(args.color, args.json, &get_config().theme);
+    // stdout isn't soar's to write progress to for programs started with
+    // `soar run` and the services, unless asked to
+    let owns_stdout = match args.command {
+        cli::Commands::Run { .. } | cli::Commands::Daemon { .. } => false,
+        #[cfg(feature = "dbus")]
+        cli::Commands::Dbus => false,
+        _ => true,
+    };
+    init_progress(match args.progress {
+        Some(mode) => mode,
+        None if args.quiet => ProgressMode::None,
+        None if owns_stdout && !io::stdout().is_terminal() => ProgressMode::Json,
+        None => ProgressMode::Bar,
+    });
     if let Some(ref profile) = args.profile {
         set_current_profile(profile)?;
     }
 
+    let root = args.root.clone().or_else(|| {
+        env::var_os("DESTDIR")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    });
+    // nothing is created or logged before the installation soar works on
+    // is known
+    if args.system {
+        enter_system_mode(&raw_args)?;
+    } else if let Some(root) = root {
+        enter_install_root(&root)?;
+    } else {
+        setup_required_paths()?;
+    }
+    setup_logging(&args)?;
+    debug!(
+        "Running soar {}",
+        env::args().skip(1).collect::<Vec<_>>().join(" ")
+    );
+    set_refresh(args.refresh);
+
+    let mut client_config = client_config(format!("soar/{}", env!("CARGO_PKG_VERSION")));
+    if let Some(secs) = args.connect_timeout {
+        client_config.connect_timeout = timeout_secs(secs);
+    }
+    if let Some(secs) = args.read_timeout {
+        client_config.read_timeout = timeout_secs(secs);
+    }
+    if let Some(secs) = args.download_timeout {
+        client_config.timeout = timeout_secs(secs);
+    }
+    let _ = configure_client(client_config);
+    let _ = configure_external(external_downloaders());
+    let _ = configure_api_cache(get_config().get_cache_path()?.join("api"));
+
+    let _lock = if args.command.is_mutating() {
+        let lock = lock_instance(args.wait)?;
+        recover_interrupted().await?;
+        Some(lock)
+    } else {
+        None
+    };
+
     match args.command {
         cli::Commands::Install {
             packages,
+            regex,
+            retry_failed,
+            from,
+            asset,
             force,
             yes,
+            jobs,
+            pre,
+            channel,
+            force_overwrite,
+            rename_on_conflict,
             portable,
             portable_home,
             portable_config,
+            extract_appimage,
+            dry_run,
         } => {
             if portable.is_some() && (portable_home.is_some() || portable_config.is_some()) {
                 error!("--portable cannot be used with --portable-home or --portable-config");
@@ -80,61 +225,285 @@ async fn handle_cli() -> SoarResult<()> {
             let portable_home = portable_home.map(|p| p.unwrap_or_default());
             let portable_config = portable_config.map(|p| p.unwrap_or_default());
 
-            install_packages(
-                &packages,
+            let bin_conflict = if force_overwrite {
+                BinConflictPolicy::Overwrite
+            } else if let Some(template) = rename_on_conflict {
+                BinConflictPolicy::Rename(
+                    template.unwrap_or_else(|| install::DEFAULT_RENAME_TEMPLATE.to_string()),
+                )
+            } else {
+                BinConflictPolicy::Fail
+            };
+            let channel = if pre {
+                Some(Channel::Prerelease)
+            } else {
+                channel.map(Channel::from)
+            };
+
+            let install_args = InstallArgs {
                 force,
                 yes,
+                jobs,
+                bin_conflict,
+                channel,
                 portable,
                 portable_home,
                 portable_config,
-            )
-            .await?;
+                extract_appimage,
+                regex,
+                dry_run,
+            };
+            if retry_failed {
+                retry_failed_installs(install_args).await?;
+            } else if let Some(source) = from {
+                install_from_github(&source, asset, install_args).await?;
+            } else {
+                install_packages(&packages, install_args).await?;
+            }
+        }
+        cli::Commands::Reinstall { packages, yes } => {
+            reinstall_packages(&packages, yes).await?;
         }
         cli::Commands::Search {
             query,
+            tag,
+            category,
             case_sensitive,
             limit,
+            offset,
+            sort,
+            install,
+            porcelain,
+            names_only,
+        } => {
+            let output = if install {
+                SearchOutput::Pick
+            } else if porcelain {
+                SearchOutput::Porcelain
+            } else if names_only {
+                SearchOutput::NamesOnly
+            } else {
+                SearchOutput::Lines
+            };
+            search_packages(
+                query,
+                tag,
+                category,
+                case_sensitive,
+                limit,
+                offset,
+                sort,
+                output,
+            )
+            .await?;
+        }
+        cli::Commands::Pick {
+            query,
+            tag,
+            category,
         } => {
-            search_packages(query, case_sensitive, limit).await?;
+            search_packages(
+                query,
+                tag,
+                category,
+                false,
+                None,
+                0,
+                SearchSort::Relevance,
+                SearchOutput::Pick,
+            )
+            .await?;
         }
         cli::Commands::Query { query } => {
             query_package(query).await?;
         }
-        cli::Commands::Remove { packages } => {
-            remove_packages(&packages).await?;
+        cli::Commands::Provides { name } => {
+            find_providers(&name).await?;
         }
-        cli::Commands::Sync => unreachable!(),
-        cli::Commands::Update { packages } => {
-            update_packages(packages).await?;
+        cli::Commands::WhatsNew { days, repo } => list_new_packages(days, repo).await?,
+        cli::Commands::Popular { repo, limit } => list_popular(repo, limit).await?,
+        cli::Commands::Discover {
+            count,
+            repo,
+            category,
+        } => discover_packages(count, repo, category).await?,
+        cli::Commands::Hook { shell } => print_hook(shell)?,
+        cli::Commands::CommandNotFound { command } => {
+            command_not_found(&command, args.wait).await?
         }
-        cli::Commands::ListInstalledPackages {
+        cli::Commands::Remove {
             packages,
+            regex,
+            yes,
+            dry_run,
+            purge,
+        } => {
+            remove_packages(&packages, regex, yes, dry_run, purge).await?;
+        }
+        cli::Commands::Sync { repo_name } => sync_repositories(repo_name).await?,
+        cli::Commands::Update {
+            packages,
+            jobs,
+            exclude,
+            repo,
+            collection,
+            defer,
+            dry_run,
+        } => {
+            update_packages(packages, jobs, exclude, repo, collection, defer, dry_run).await?;
+        }
+        cli::Commands::Notes { package } => show_notes(&package).await?,
+        cli::Commands::ListInstalledPackages {
+            packages: _,
             repo_name,
         } => {
             list_installed_packages(repo_name).await?;
         }
-        cli::Commands::ListPackages { repo_name } => {
-            list_packages(repo_name).await?;
+        cli::Commands::ListPackages {
+            repo_name,
+            repo,
+            installed,
+            collection,
+            category,
+            tag,
+            filter,
+            repos,
+            updatable,
+            sort,
+        } => {
+            if repos {
+                list_repositories().await?;
+            } else {
+                list_packages(
+                    repo_name.or(repo),
+                    collection,
+                    category,
+                    tag,
+                    filter,
+                    installed,
+                    updatable,
+                    sort,
+                )
+                .await?;
+            }
+        }
+        cli::Commands::Categories { tags } => list_categories(tags).await?,
+        cli::Commands::Adopt {
+            targets,
+            name,
+            version,
+            github,
+            copy,
+        } => {
+            if name.is_some() && targets.len() > 1 {
+                error!("--name can only be used when adopting a single file");
+                std::process::exit(1);
+            }
+            let options = AdoptOptions {
+                name,
+                version,
+                upstream: github,
+                copy,
+            };
+            adopt_packages(&targets, options).await?;
+        }
+        cli::Commands::Diff { package, against } => {
+            diff_package(&package, against.as_deref()).await?
+        }
+        cli::Commands::Export { with_versions } => export_packages(with_versions).await?,
+        cli::Commands::Import { file, yes } => import_packages(&file, yes).await?,
+        cli::Commands::Ensure { spec, prune, jobs } => ensure_state(&spec, prune, jobs).await?,
+        cli::Commands::Stats => show_stats().await?,
+        cli::Commands::Du { packages } => show_disk_usage(packages).await?,
+        cli::Commands::Verify { packages, repair } => verify_packages(packages, repair).await?,
+        cli::Commands::Audit { repo_name } => audit_packages(repo_name).await?,
+        cli::Commands::Sbom { format, output } => generate_sbom(format, output).await?,
+        cli::Commands::Log {
+            package,
+            raw,
+            save,
+            compare,
+        } => {
+            inspect_log(
+                &package,
+                InspectType::BuildLog,
+                raw,
+                save.as_deref().map(Path::new),
+                compare.as_deref(),
+            )
+            .await?
+        }
+        cli::Commands::Inspect {
+            package,
+            raw,
+            save,
+            compare,
+        } => {
+            inspect_log(
+                &package,
+                InspectType::BuildScript,
+                raw,
+                save.as_deref().map(Path::new),
+                compare.as_deref(),
+            )
+            .await?
         }
-        cli::Commands::Log { package } => inspect_log(&package, InspectType::BuildLog).await?,
-        cli::Commands::Inspect { package } => {
-            inspect_log(&package, InspectType::BuildScript).await?
+        cli::Commands::Readme { package, raw } => show_readme(&package, raw).await?,
+        cli::Commands::Open {
+            package,
+            source,
+            build_log,
+        } => {
+            let target = if build_log {
+                OpenTarget::BuildLog
+            } else if source {
+                OpenTarget::Source
+            } else {
+                OpenTarget::Homepage
+            };
+            open_package(&package, target).await?
         }
-        cli::Commands::Run { yes, command } => {
+        cli::Commands::Run { yes: _, command } => {
             run_package(command.as_ref()).await?;
         }
-        cli::Commands::Use { package } => unreachable!(),
+        cli::Commands::Use { package } => {
+            use_installed(&package).await?;
+        }
         cli::Commands::Download {
             links,
             yes,
+            auto,
             output,
+            output_template,
+            on_conflict,
             regex_patterns,
             match_keywords,
             exclude_keywords,
             github,
+            pre,
             gitlab,
+            gitlab_instance,
             ghcr,
+            jobs,
+            keep_going,
+            fail_fast,
+            resume_all,
+            no_cache,
+            require_attestation,
+            minisign_key,
+            oci_files,
+            list,
+            list_assets,
         } => {
+            if let Some(project) = list {
+                return list_releases(
+                    &project,
+                    list_assets.as_deref(),
+                    gitlab_instance.as_deref(),
+                    &oci_files,
+                )
+                .await;
+            }
             download(
                 links,
                 github,
@@ -144,29 +513,67 @@ async fn handle_cli() -> SoarResult<()> {
                 match_keywords,
                 exclude_keywords,
                 output,
+                output_template,
+                on_conflict,
+                gitlab_instance,
                 yes,
+                auto,
+                pre,
+                resume_all,
+                no_cache,
+                require_attestation,
+                minisign_key,
+                oci_files,
+                jobs.unwrap_or(1),
+                // whichever of the two was given last
+                fail_fast && !keep_going,
             )
             .await?;
         }
-        cli::Commands::Health => unreachable!(),
+        cli::Commands::Health => check_health()?,
         cli::Commands::DefConfig => generate_default_config()?,
-        cli::Commands::Env => {
+        cli::Commands::Help { topic } => show_help(&topic)?,
+        cli::Commands::GenerateMan { out_dir } => generate_man(&out_dir)?,
+        cli::Commands::Init {
+            repos,
+            shell,
+            no_path,
+            no_sync,
+            yes,
+        } => init(repos, shell, no_path, no_sync, yes).await?,
+        cli::Commands::Env {
+            action: Some(EnvAction::Path { shell }),
+        } => print_path_line(shell)?,
+        cli::Commands::Env {
+            action: Some(EnvAction::InstallPath { shell }),
+        } => install_path(shell)?,
+        cli::Commands::Env { action: None } => {
             let config = get_config();
             info!("SOAR_BIN={}", config.get_bin_path()?.display());
             info!("SOAR_DB={}", config.get_db_path()?.display());
             info!("SOAR_CACHE={}", config.get_cache_path()?.display());
+            if let Some(shared_cache) = config.get_shared_cache_path()? {
+                info!("SOAR_SHARED_CACHE={}", shared_cache.display());
+            }
             info!("SOAR_PACKAGE={}", config.get_packages_path()?.display());
             info!(
                 "SOAR_REPOSITORIES={}",
                 config.get_repositories_path()?.display()
             );
         }
+        cli::Commands::Daemon { socket, metrics } => run_daemon(socket, metrics).await?,
+        #[cfg(feature = "dbus")]
+        cli::Commands::Dbus => dbus::run_dbus_service().await?,
+        cli::Commands::Repo { action } => process_repo_action(action).await?,
+        cli::Commands::Cache { action } => process_cache_action(action)?,
         cli::Commands::SelfCmd { action } => {
             process_self_action(&action, self_bin, self_version).await?;
         }
+        cli::Commands::Gc => collect_garbage().await?,
         cli::Commands::Clean {
             cache,
             broken_symlinks,
+            downloads,
         } => {
             if cache {
                 cleanup_cache()?;
@@ -174,6 +581,11 @@ async fn handle_cli() -> SoarResult<()> {
             if broken_symlinks {
                 remove_broken_symlinks()?;
             }
+            if downloads {
+                let core_db = AppState::create_core_db(&get_config())?.conn;
+                let count = clear_download_queue(core_db)?;
+                info!("Dropped {} unfinished downloads", count);
+            }
         }
     }
 
@@ -182,9 +594,19 @@ async fn handle_cli() -> SoarResult<()> {
 
 #[tokio::main]
 async fn main() {
-    setup_required_paths().unwrap();
-
     if let Err(err) = handle_cli().await {
-        error!("{}", err);
+        let kind = err.kind();
+        // failed before logging was set up
+        if !tracing::dispatcher::has_been_set() {
+            eprintln!("{}", err);
+            std::process::exit(kind.exit_code());
+        }
+        error!(
+            kind = kind.as_str(),
+            exit_code = kind.exit_code(),
+            "{}",
+            err
+        );
+        std::process::exit(kind.exit_code());
     };
 }
@@ -0,0 +1,308 @@
+use std::{
+    fs,
+    io::{stdout, ErrorKind, IsTerminal},
+    path::Path,
+};
+
+use clap::{Arg, Command, CommandFactory};
+use soar_core::{error::SoarError, SoarResult};
+use tracing::info;
+
+use crate::{
+    cli::Args,
+    color::{Color, ColorExt},
+    i18n::t,
+    inspect::{spawn_pager, Sink},
+    readme::render_markdown,
+};
+
+/// Help topics for `soar help`, as name, summary and Markdown content.
+const TOPICS: &[(&str, &str, &str)] = &[
+    (
+        "repositories",
+        "Where packages come from and how to configure it",
+        include_str!("topics/repositories.md"),
+    ),
+    (
+        "portable",
+        "Keeping the data of AppImages apart",
+        include_str!("topics/portable.md"),
+    ),
+    (
+        "caching",
+        "What soar caches and how to clean it up",
+        include_str!("topics/caching.md"),
+    ),
+    (
+        "filters",
+        "Selecting packages with filter expressions",
+        include_str!("topics/filters.md"),
+    ),
+    (
+        "versions",
+        "Naming linked binaries and keeping several versions",
+        include_str!("topics/versions.md"),
+    ),
+];
+
+/// The command line definition of soar, named as the binary is.
+fn soar_command() -> Command {
+    let mut command = Args::command().name("soar").bin_name("soar");
+    command.build();
+    command
+}
+
+/// Shows the help topic `topic`, or the help of the command it names, e.g.
+/// `repo add`. Without a topic, the general help and the topics are listed.
+pub fn show_help(topic: &[String]) -> SoarResult<()> {
+    let mut command = soar_command();
+
+    let Some(first) = topic.first() else {
+        command.print_help()?;
+        println!("\n{}", t!("help-topics").color(Color::Label));
+        for (name, summary, _) in TOPICS {
+            println!("  {:<14}{}", name.color(Color::Accent), summary);
+        }
+        return Ok(());
+    };
+
+    if let Some((_, _, content)) = TOPICS.iter().find(|(name, ..)| name == first) {
+        let rendered = render_markdown(content);
+        let mut sink = if stdout().is_terminal() {
+            spawn_pager().unwrap_or_else(|| Sink::Stdout(stdout().lock()))
+        } else {
+            Sink::Stdout(stdout().lock())
+        };
+        match sink.write_all(rendered.as_bytes()) {
+            // the pager was closed before reaching the end
+            Err(err) if err.kind() != ErrorKind::BrokenPipe => return Err(err.into()),
+            _ => {}
+        }
+        sink.finish()?;
+        return Ok(());
+    }
+
+    let mut command = &mut command;
+    for name in topic {
+        command = command
+            .find_subcommand_mut(name)
+            .filter(|command| !command.is_hide_set())
+            .ok_or_else(|| SoarError::Custom(t!("help-unknown-topic", topic = topic.join(" "))))?;
+    }
+    command.print_long_help()?;
+    Ok(())
+}
+
+/// Escapes `text` for roff, so that it's printed as is.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .replace('-', "\\-")
+        .lines()
+        .map(|line| line.trim_end())
+        // lines starting with these would be taken for requests
+        .map(|line| {
+            if line.starts_with(['.', '\'']) {
+                format!("\\&{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Paragraphs of `text` as roff, separated by blank lines in the help.
+/// `indented` keeps them at the indent of an option they describe.
+fn paragraphs(text: &str, indented: bool) -> String {
+    text.split("\n\n")
+        .map(|paragraph| escape(paragraph.trim()))
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join(if indented { "\n.IP\n" } else { "\n.PP\n" })
+}
+
+/// How `arg` is written on the command line, e.g. `-j, --jobs <JOBS>`.
+fn arg_usage(arg: &Arg) -> String {
+    let mut names = Vec::new();
+    if let Some(short) = arg.get_short() {
+        names.push(format!("\\fB\\-{}\\fR", short));
+    }
+    if let Some(long) = arg.get_long() {
+        names.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+    }
+
+    let value = arg
+        .get_value_names()
+        .map(|names| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_else(|| arg.get_id().as_str().to_uppercase());
+    if names.is_empty() {
+        return format!("\\fI<{}>\\fR", escape(&value));
+    }
+    let mut usage = names.join(", ");
+    if arg.get_action().takes_values() {
+        usage.push_str(&format!(" \\fI<{}>\\fR", escape(&value)));
+    }
+    usage
+}
+
+/// Description of `arg` along with its possible and default values.
+fn arg_description(arg: &Arg) -> String {
+    let mut description = arg
+        .get_long_help()
+        .or(arg.get_help())
+        .map(|help| help.to_string())
+        .unwrap_or_default();
+
+    let values: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    // flags have possible values of true and false
+    if !values.is_empty() && arg.get_action().takes_values() {
+        description.push_str(&format!(" [possible values: {}]", values.join(", ")));
+    }
+    let defaults: Vec<String> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy().into_owned())
+        .collect();
+    if !defaults.is_empty() && arg.get_action().takes_values() {
+        description.push_str(&format!(" [default: {}]", defaults.join(", ")));
+    }
+    paragraphs(description.trim(), true)
+}
+
+/// Renders the man page of `command`, named `name` as in `soar-repo-add`.
+fn render_page(command: &Command, name: &str, parent: Option<&str>) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    let about = command
+        .get_about()
+        .map(|about| about.to_string())
+        .unwrap_or_default();
+    let mut page = format!(
+        ".TH {} 1 \"\" \"soar {}\" \"soar manual\"\n",
+        escape(&name.to_uppercase()),
+        escape(version)
+    );
+    page.push_str(&format!(
+        ".SH NAME\n{} \\- {}\n",
+        escape(name),
+        escape(&about)
+    ));
+
+    let usage = command.clone().render_usage().to_string();
+    let usage = usage.trim_start_matches("Usage:").trim();
+    page.push_str(&format!(".SH SYNOPSIS\n{}\n", escape(usage)));
+
+    // the summary is in the name already
+    if let Some(description) = command.get_long_about() {
+        page.push_str(&format!(
+            ".SH DESCRIPTION\n{}\n",
+            paragraphs(&description.to_string(), false)
+        ));
+    }
+
+    // global options are only described on the main page
+    let args: Vec<&Arg> = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .filter(|arg| parent.is_none() || !arg.is_global_set())
+        .filter(|arg| !matches!(arg.get_id().as_str(), "help" | "version"))
+        .collect();
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) =
+        args.into_iter().partition(|arg| arg.is_positional());
+    for (heading, args) in [("ARGUMENTS", positionals), ("OPTIONS", options)] {
+        if args.is_empty() {
+            continue;
+        }
+        page.push_str(&format!(".SH {}\n", heading));
+        for arg in args {
+            page.push_str(&format!(".TP\n{}\n", arg_usage(arg)));
+            let description = arg_description(arg);
+            if !description.is_empty() {
+                page.push_str(&format!("{}\n", description));
+            }
+        }
+    }
+
+    let subcommands: Vec<&Command> = command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .collect();
+    if !subcommands.is_empty() {
+        page.push_str(".SH COMMANDS\n");
+        for subcommand in &subcommands {
+            page.push_str(&format!(
+                ".TP\n\\fB{}\\-{}\\fR(1)\n{}\n",
+                escape(name),
+                escape(subcommand.get_name()),
+                escape(
+                    &subcommand
+                        .get_about()
+                        .map(|about| about.to_string())
+                        .unwrap_or_default()
+                )
+            ));
+        }
+    }
+
+    if parent.is_none() {
+        page.push_str(".SH TOPICS\nShow these with \\fBsoar help\\fR \\fItopic\\fR.\n");
+        for (topic, summary, _) in TOPICS {
+            page.push_str(&format!(".TP\n\\fB{}\\fR\n{}\n", topic, escape(summary)));
+        }
+    }
+
+    if let Some(parent) = parent {
+        page.push_str(&format!(".SH SEE ALSO\n\\fB{}\\fR(1)\n", escape(parent)));
+    }
+    page
+}
+
+/// Writes the pages of `command` and its subcommands to `dir`, returning
+/// how many were written.
+fn write_pages(
+    command: &Command,
+    name: &str,
+    parent: Option<&str>,
+    dir: &Path,
+) -> SoarResult<usize> {
+    fs::write(
+        dir.join(format!("{}.1", name)),
+        render_page(command, name, parent),
+    )?;
+    let mut count = 1;
+    for subcommand in command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+    {
+        let sub_name = format!("{}-{}", name, subcommand.get_name());
+        count += write_pages(subcommand, &sub_name, Some(name), dir)?;
+    }
+    Ok(count)
+}
+
+/// Writes man pages for soar and each of its commands to `dir`.
+pub fn generate_man(dir: &Path) -> SoarResult<()> {
+    let command = soar_command();
+
+    fs::create_dir_all(dir)?;
+    let count = write_pages(&command, "soar", None, dir)?;
+    info!(
+        "{}",
+        t!(
+            "man-written",
+            count = count,
+            dir = dir.display().color(Color::Accent)
+        )
+    );
+    Ok(())
+}
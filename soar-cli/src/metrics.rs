@@ -0,0 +1,237 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use soar_core::{
+    config::get_config,
+    database::packages::{get_installed_packages, FilterOp, QueryOptions},
+    events::InstallEvent,
+    utils::dir_size,
+    SoarResult,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::spawn_blocking,
+};
+use tracing::{error, info};
+
+use crate::state::AppState;
+
+/// Largest request head read before giving up on a client.
+const MAX_REQUEST: usize = 8192;
+
+/// Counters of what the daemon did since it started, exported in the
+/// Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    downloads: AtomicU64,
+    download_bytes: AtomicU64,
+    installs: AtomicU64,
+    install_failures: AtomicU64,
+    start_time: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Arc<Self> {
+        let metrics = Metrics::default();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        metrics.start_time.store(now, Ordering::Relaxed);
+        Arc::new(metrics)
+    }
+
+    /// Counts the downloads in the events of one package, given the
+    /// position its download was at before `event`.
+    pub fn record_event(&self, event: &InstallEvent, position: &AtomicU64) {
+        match event {
+            InstallEvent::DownloadStarted { .. } => position.store(0, Ordering::Relaxed),
+            // progress is reported as the position in the download
+            InstallEvent::DownloadProgress { downloaded, .. } => {
+                let previous = position.swap(*downloaded, Ordering::Relaxed);
+                self.download_bytes
+                    .fetch_add(downloaded.saturating_sub(previous), Ordering::Relaxed);
+            }
+            InstallEvent::DownloadComplete { .. } => {
+                self.downloads.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn record_install(&self, success: bool) {
+        let counter = if success {
+            &self.installs
+        } else {
+            &self.install_failures
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The metrics in the Prometheus text format, along with the cache size
+    /// and installed package count as of now.
+    async fn render(&self, state: &AppState) -> SoarResult<String> {
+        let cache_size = spawn_blocking(|| {
+            get_config()
+                .get_cache_path()
+                .map(|path| dir_size(path).unwrap_or(0))
+        })
+        .await
+        .map_err(|err| soar_core::error::SoarError::Custom(err.to_string()))??;
+
+        let mut filters = HashMap::new();
+        filters.insert(
+            "is_installed".to_string(),
+            (FilterOp::Eq, "1".to_string().into()).into(),
+        );
+        let installed = get_installed_packages(
+            state.core_db().clone(),
+            QueryOptions {
+                filters,
+                limit: 1,
+                ..Default::default()
+            },
+        )?
+        .total;
+
+        let metrics: [(&str, &str, &str, u64); 7] = [
+            (
+                "soar_downloads_total",
+                "counter",
+                "Package downloads completed",
+                self.downloads.load(Ordering::Relaxed),
+            ),
+            (
+                "soar_download_bytes_total",
+                "counter",
+                "Bytes downloaded for packages",
+                self.download_bytes.load(Ordering::Relaxed),
+            ),
+            (
+                "soar_installs_total",
+                "counter",
+                "Packages installed or updated",
+                self.installs.load(Ordering::Relaxed),
+            ),
+            (
+                "soar_install_failures_total",
+                "counter",
+                "Packages that failed to install or update",
+                self.install_failures.load(Ordering::Relaxed),
+            ),
+            (
+                "soar_cache_size_bytes",
+                "gauge",
+                "Size of the soar cache directory",
+                cache_size,
+            ),
+            (
+                "soar_packages_installed",
+                "gauge",
+                "Packages currently installed",
+                installed,
+            ),
+            (
+                "soar_daemon_start_time_seconds",
+                "gauge",
+                "When the daemon started, in seconds since the epoch",
+                self.start_time.load(Ordering::Relaxed),
+            ),
+        ];
+
+        let mut output = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} {}", name, kind);
+            let _ = writeln!(output, "{} {}", name, value);
+        }
+        Ok(output)
+    }
+}
+
+/// Serves the metrics over HTTP at `/metrics` on `addr`.
+pub async fn serve_metrics(addr: &str, metrics: Arc<Metrics>, state: AppState) -> SoarResult<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    error!("Metrics connection error: {}", err);
+                    continue;
+                }
+            };
+            let metrics = metrics.clone();
+            let state = state.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handle_request(stream, &metrics, &state).await {
+                    error!("Metrics connection error: {}", err);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+async fn handle_request(
+    mut stream: TcpStream,
+    metrics: &Metrics,
+    state: &AppState,
+) -> SoarResult<()> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || request.len() > MAX_REQUEST {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    // scrapers may add query parameters, which don't change anything
+    let path = path.split('?').next().unwrap_or(path);
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => match metrics.render(state).await {
+            Ok(body) => ("200 OK", "text/plain; version=0.0.4", body),
+            Err(err) => (
+                "500 Internal Server Error",
+                "text/plain",
+                format!("{}\n", err),
+            ),
+        },
+        (_, "/metrics") => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed\n".to_string(),
+        ),
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
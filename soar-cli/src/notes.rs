@@ -0,0 +1,132 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::Connection;
+use soar_core::{
+    database::{
+        models::Package,
+        packages::{get_installed_packages, get_packages, Filter, FilterOp, QueryOptions},
+    },
+    package::query::PackageQuery,
+    SoarResult,
+};
+use tracing::{info, warn};
+
+use crate::{
+    color::{Color, ColorExt},
+    i18n::t,
+    state::AppState,
+    utils::suggest_similar_packages,
+};
+
+/// Prints the notes of `package`, e.g. setup steps the user has to take
+/// after installing it. Prints nothing if the package has none.
+pub fn print_notes(package: &Package) {
+    let Some(notes) = package.notes.as_ref().filter(|notes| !notes.is_empty()) else {
+        return;
+    };
+
+    info!(
+        pkg_name = %package.pkg_name,
+        pkg_id = %package.pkg_id,
+        notes = ?notes,
+        "{}",
+        t!(
+            "notes-header",
+            package = format!("{}#{}", package.pkg_name, package.pkg_id).color(Color::Accent)
+        )
+    );
+    for note in notes {
+        info!("  {}", note);
+    }
+}
+
+/// Shows the notes of the installed packages matching `query` again, or of
+/// the matching repository packages if none are installed.
+pub async fn show_notes(query: &str) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let repo_db = state.repo_db().clone();
+    let query = PackageQuery::try_from(query)?;
+
+    let installed = get_installed_packages(
+        state.core_db().clone(),
+        QueryOptions {
+            filters: query.create_filter(),
+            limit: u32::MAX,
+            ..Default::default()
+        },
+    )?
+    .items;
+
+    let mut packages = Vec::new();
+    if installed.is_empty() {
+        packages.extend(find_package(repo_db.clone(), query.create_repo_filter())?);
+    } else {
+        for package in &installed {
+            let mut filters: HashMap<String, Filter> = [
+                ("r.name", &package.repo_name),
+                ("pkg_name", &package.pkg_name),
+                ("pkg_id", &package.pkg_id),
+                ("version", &package.version),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), (FilterOp::Eq, value.clone().into()).into()))
+            .collect();
+
+            // the installed version may be gone from the repository by now
+            let mut found = find_package(repo_db.clone(), filters.clone())?;
+            if found.is_none() {
+                filters.remove("version");
+                found = find_package(repo_db.clone(), filters)?;
+            }
+            packages.extend(found);
+        }
+    }
+
+    if packages.is_empty() {
+        warn!("{}", t!("package-not-found", package = query_name(&query)));
+        return suggest_similar_packages(repo_db, &query_name(&query));
+    }
+
+    for package in packages {
+        if package
+            .notes
+            .as_ref()
+            .is_some_and(|notes| !notes.is_empty())
+        {
+            print_notes(&package);
+        } else {
+            info!(
+                "{}",
+                t!(
+                    "notes-none",
+                    package = format!("{}#{}", package.pkg_name, package.pkg_id)
+                )
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn find_package(
+    repo_db: Arc<Mutex<Connection>>,
+    filters: HashMap<String, Filter>,
+) -> SoarResult<Option<Package>> {
+    let options = QueryOptions {
+        filters,
+        limit: 1,
+        ..Default::default()
+    };
+    Ok(get_packages(repo_db, options)?.items.into_iter().next())
+}
+
+fn query_name(query: &PackageQuery) -> String {
+    query
+        .name
+        .clone()
+        .or_else(|| query.pkg_id.clone())
+        .unwrap_or_default()
+}
@@ -0,0 +1,103 @@
+use std::{
+    env,
+    process::{Command, Stdio},
+};
+
+use soar_core::{
+    database::{
+        models::Package,
+        packages::{get_packages, QueryOptions},
+    },
+    package::query::PackageQuery,
+    SoarResult,
+};
+use tracing::{error, info};
+
+use crate::{state::AppState, utils::suggest_similar_packages};
+
+/// Page of a package to open.
+#[derive(Clone, Copy)]
+pub enum OpenTarget {
+    /// The homepage, or the source if there's none.
+    Homepage,
+    Source,
+    BuildLog,
+}
+
+fn first_url(urls: &Option<Vec<String>>) -> Option<&str> {
+    urls.iter()
+        .flatten()
+        .map(|url| url.trim())
+        .find(|url| !url.is_empty())
+}
+
+impl OpenTarget {
+    fn url(self, package: &Package) -> Option<&str> {
+        match self {
+            OpenTarget::Homepage => first_url(&package.homepages)
+                .or_else(|| first_url(&package.source_urls))
+                .or(package.pkg_webpage.as_deref()),
+            OpenTarget::Source => first_url(&package.source_urls),
+            OpenTarget::BuildLog => package.build_log.as_deref(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            OpenTarget::Homepage => "homepage",
+            OpenTarget::Source => "source URL",
+            OpenTarget::BuildLog => "build log",
+        }
+    }
+}
+
+/// Opens `url` with `$BROWSER`, or `xdg-open`.
+fn open_url(url: &str) -> std::io::Result<bool> {
+    let browser = env::var("BROWSER")
+        .ok()
+        .filter(|browser| !browser.trim().is_empty())
+        .unwrap_or_else(|| "xdg-open".to_string());
+    let mut args = browser.split_whitespace();
+    let program = args.next().unwrap_or("xdg-open");
+
+    Command::new(program)
+        .args(args)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+}
+
+/// Opens the `target` page of `package` in the browser.
+pub async fn open_package(package: &str, target: OpenTarget) -> SoarResult<()> {
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+
+    let query = PackageQuery::try_from(package)?;
+    let options = QueryOptions {
+        limit: 1,
+        filters: query.create_repo_filter(),
+        ..Default::default()
+    };
+    let packages = get_packages(repo_db.clone(), options)?;
+    let Some(package) = packages.items.first() else {
+        error!("Package {} not found", package);
+        return suggest_similar_packages(repo_db, package);
+    };
+
+    let Some(url) = target.url(package) else {
+        error!("No {} found for {}", target.describe(), package.pkg_name);
+        return Ok(());
+    };
+
+    info!("Opening {}", url);
+    match open_url(url) {
+        Ok(true) => {}
+        // the URL was printed, to open by hand
+        Ok(false) => error!("Failed to open {} in a browser", url),
+        Err(err) => error!("Failed to open {} in a browser: {}", url, err),
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeSet;
+
+use console::{strip_ansi_codes, truncate_str, Key, Term};
+use soar_core::SoarResult;
+
+use crate::{
+    color::{Color, ColorExt},
+    i18n::t,
+};
+
+/// Items shown at once at most, however tall the terminal is.
+const MAX_HEIGHT: usize = 20;
+
+/// Score of `text` against the words of `query`, lower is better, `None`
+/// if it doesn't match: each word has to occur in it in order, though not
+/// necessarily contiguously, ignoring case.
+fn fuzzy_score(text: &str, query: &str) -> Option<usize> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    for word in query.to_lowercase().split_whitespace() {
+        // the tightest span the word occurs in
+        let mut best: Option<usize> = None;
+        let first = word.chars().next()?;
+        for start in (0..text.len()).filter(|&i| text[i] == first) {
+            let mut pos = start;
+            let mut matched = true;
+            for c in word.chars().skip(1) {
+                match text[pos + 1..].iter().position(|&t| t == c) {
+                    Some(offset) => pos += offset + 1,
+                    None => {
+                        matched = false;
+                        break;
+                    }
+                }
+            }
+            if !matched {
+                break;
+            }
+            let span = pos - start;
+            if best.is_none_or(|best| span < best) {
+                best = Some(span);
+            }
+        }
+        score += best?;
+    }
+    Some(score)
+}
+
+struct Picker<'a> {
+    term: Term,
+    prompt: &'a str,
+    items: &'a [String],
+    plain: Vec<String>,
+    query: String,
+    /// Indices of the items matching the query, best first.
+    matches: Vec<usize>,
+    selected: BTreeSet<usize>,
+    cursor: usize,
+    scroll: usize,
+    drawn: usize,
+}
+
+impl Picker<'_> {
+    fn height(&self) -> usize {
+        let (rows, _) = self.term.size();
+        (rows as usize).saturating_sub(3).clamp(1, MAX_HEIGHT)
+    }
+
+    fn filter(&mut self) {
+        let mut scored: Vec<(usize, usize)> = self
+            .plain
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, text)| Some((fuzzy_score(text, &self.query)?, idx)))
+            .collect();
+        // stable, so equally good matches keep their order
+        scored.sort_by_key(|(score, _)| *score);
+        self.matches = scored.into_iter().map(|(_, idx)| idx).collect();
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let last = self.matches.len() - 1;
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+        let height = self.height();
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+    }
+
+    fn toggle(&mut self) {
+        if let Some(&idx) = self.matches.get(self.cursor) {
+            if !self.selected.remove(&idx) {
+                self.selected.insert(idx);
+            }
+        }
+    }
+
+    fn render(&mut self) -> std::io::Result<()> {
+        self.term.clear_last_lines(self.drawn)?;
+        let (_, columns) = self.term.size();
+        let width = columns as usize;
+
+        let mut lines = vec![
+            format!("{} {}", self.prompt.color(Color::Accent), self.query),
+            t!(
+                "pick-header",
+                shown = self.matches.len(),
+                total = self.items.len(),
+                selected = self.selected.len()
+            )
+            .color(Color::Label),
+        ];
+        let visible = self.matches.iter().skip(self.scroll).take(self.height());
+        for (offset, &idx) in visible.enumerate() {
+            let pointer = if self.scroll + offset == self.cursor {
+                ">".color(Color::Accent)
+            } else {
+                " ".to_string()
+            };
+            let mark = if self.selected.contains(&idx) {
+                "[x]".color(Color::Success)
+            } else {
+                "[ ]".to_string()
+            };
+            lines.push(format!("{} {} {}", pointer, mark, self.items[idx]));
+        }
+
+        for line in &lines {
+            self.term.write_line(&truncate_str(line, width, ""))?;
+        }
+        self.drawn = lines.len();
+        self.term.flush()
+    }
+
+    fn run(&mut self) -> std::io::Result<Option<Vec<usize>>> {
+        loop {
+            self.render()?;
+            match self.term.read_key_raw()? {
+                Key::Char(c) if !c.is_control() => {
+                    self.query.push(c);
+                    self.filter();
+                }
+                Key::Backspace => {
+                    self.query.pop();
+                    self.filter();
+                }
+                Key::ArrowUp => self.move_cursor(-1),
+                Key::ArrowDown => self.move_cursor(1),
+                Key::PageUp => self.move_cursor(-(self.height() as isize)),
+                Key::PageDown => self.move_cursor(self.height() as isize),
+                Key::Tab => {
+                    self.toggle();
+                    self.move_cursor(1);
+                }
+                Key::BackTab => {
+                    self.toggle();
+                    self.move_cursor(-1);
+                }
+                Key::Enter => {
+                    // the item under the cursor, if none were selected
+                    if self.selected.is_empty() {
+                        return Ok(Some(
+                            self.matches.get(self.cursor).copied().into_iter().collect(),
+                        ));
+                    }
+                    return Ok(Some(self.selected.iter().copied().collect()));
+                }
+                Key::Escape | Key::CtrlC => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Lets the user pick any number of `items` in the terminal, narrowing them
+/// down by typing, like fzf.
+///
+/// Returns the indices of the picked items in their original order, `None`
+/// if cancelled.
+pub fn pick_many(prompt: &str, items: &[String]) -> SoarResult<Option<Vec<usize>>> {
+    let mut picker = Picker {
+        term: Term::stderr(),
+        prompt,
+        items,
+        plain: items
+            .iter()
+            .map(|item| strip_ansi_codes(item).into_owned())
+            .collect(),
+        query: String::new(),
+        matches: Vec::new(),
+        selected: BTreeSet::new(),
+        cursor: 0,
+        scroll: 0,
+        drawn: 0,
+    };
+    picker.filter();
+
+    picker.term.hide_cursor()?;
+    let result = picker.run();
+    // the list is cleared either way, what was picked is reported after
+    picker.term.clear_last_lines(picker.drawn)?;
+    picker.term.show_cursor()?;
+    Ok(result?)
+}
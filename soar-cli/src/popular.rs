@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use soar_core::{
+    config::{get_config, Repository},
+    database::packages::{get_packages, FilterOp, QueryOptions},
+    popularity::fetch_stats,
+    SoarResult,
+};
+use tracing::{error, info};
+
+use crate::{
+    color::{Color, ColorExt},
+    i18n::t,
+    state::AppState,
+};
+
+/// Lists the `limit` most installed packages, going by the install counts
+/// the repositories publish.
+pub async fn list_popular(repo_name: Option<String>, limit: usize) -> SoarResult<()> {
+    let repositories: Vec<Repository> = {
+        let config = get_config();
+        config
+            .enabled_repositories()
+            .filter(|repo| repo_name.as_ref().is_none_or(|name| repo.name == *name))
+            .filter(|repo| repo.stats_url.is_some())
+            .cloned()
+            .collect()
+    };
+    if repositories.is_empty() {
+        info!("{}", t!("popular-no-stats"));
+        return Ok(());
+    }
+
+    let mut stats = Vec::new();
+    for repo in &repositories {
+        match fetch_stats(repo).await {
+            Ok(repo_stats) => stats.extend(
+                repo_stats
+                    .into_iter()
+                    .map(|package| (repo.name.clone(), package)),
+            ),
+            Err(err) => error!("{}", err),
+        }
+    }
+    stats.sort_by_key(|(_, package)| std::cmp::Reverse(package.installs));
+
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+    let mut shown = 0;
+    for (repo_name, package_stats) in stats {
+        if shown == limit {
+            break;
+        }
+        // counts can outlive the packages they were collected for
+        let mut filters = HashMap::new();
+        filters.insert(
+            "r.name".to_string(),
+            (FilterOp::Eq, repo_name.into()).into(),
+        );
+        filters.insert(
+            "pkg_id".to_string(),
+            (FilterOp::Eq, package_stats.pkg_id.into()).into(),
+        );
+        filters.insert(
+            "pkg_name".to_string(),
+            (FilterOp::Eq, package_stats.pkg_name.into()).into(),
+        );
+        let options = QueryOptions {
+            limit: 1,
+            filters,
+            ..Default::default()
+        };
+        let Some(package) = get_packages(repo_db.clone(), options)?.items.pop() else {
+            continue;
+        };
+
+        shown += 1;
+        info!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            repo_name = %package.repo_name,
+            installs = package_stats.installs,
+            description = %package.description,
+            "{:>8}  {}#{}:{} - {}",
+            package_stats.installs,
+            package.pkg_name.color(Color::Accent),
+            package.pkg_id.color(Color::Info),
+            package.repo_name.color(Color::Info),
+            package.description
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::{env, path::Path, process::Command};
+
+use soar_core::{
+    config::{get_config, get_install_root},
+    SoarResult,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    color::{Color, ColorExt},
+    i18n::t,
+};
+
+/// Kind of change a transaction made to the installed packages.
+#[derive(Clone, Copy, Default)]
+pub enum Transaction {
+    #[default]
+    Install,
+    Update,
+    Remove,
+}
+
+impl Transaction {
+    /// Name the transaction is recorded under in the history.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transaction::Install => "install",
+            Transaction::Update => "update",
+            Transaction::Remove => "remove",
+        }
+    }
+
+    fn hook_name(&self) -> &'static str {
+        match self {
+            Transaction::Install => "post_install",
+            Transaction::Update => "post_update",
+            Transaction::Remove => "post_remove",
+        }
+    }
+}
+
+/// Runs the hooks configured for `transaction` with the names of the
+/// affected `packages`. Failing hooks are reported but don't fail the
+/// transaction.
+pub fn run_hooks(transaction: Transaction, packages: &[String]) {
+    // they act on this system, not the root installs are made into
+    if get_install_root().is_some() {
+        return;
+    }
+    let config = get_config();
+    let hooks = match transaction {
+        Transaction::Install => &config.hooks.post_install,
+        Transaction::Update => &config.hooks.post_update,
+        Transaction::Remove => &config.hooks.post_remove,
+    };
+
+    for hook in hooks {
+        debug!(
+            hook = transaction.hook_name(),
+            command = %hook,
+            "Running {} hook: {}",
+            transaction.hook_name(),
+            hook
+        );
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        let status = command
+            .arg(hook)
+            .env("SOAR_HOOK", transaction.hook_name())
+            .env("SOAR_PACKAGES", packages.join(" "))
+            .status();
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => warn!(
+                "{}",
+                t!(
+                    "hook-failed",
+                    hook = transaction.hook_name(),
+                    command = hook,
+                    error = status
+                )
+            ),
+            Err(err) => warn!(
+                "{}",
+                t!(
+                    "hook-failed",
+                    hook = transaction.hook_name(),
+                    command = hook,
+                    error = err
+                )
+            ),
+        }
+    }
+}
+
+/// Tells the user how to add the bin directory to `PATH` if it isn't on it.
+pub fn print_path_hint() -> SoarResult<()> {
+    if get_install_root().is_some() {
+        return Ok(());
+    }
+    let bin_path = get_config().get_bin_path()?;
+    if !is_on_path(&bin_path) {
+        info!(
+            "{}",
+            t!("path-hint", path = bin_path.display().color(Color::Accent))
+        );
+    }
+    Ok(())
+}
+
+/// Whether `dir` is one of the directories in `PATH`.
+pub fn is_on_path(dir: &Path) -> bool {
+    env::var_os("PATH").is_some_and(|paths| env::split_paths(&paths).any(|path| path == dir))
+}
@@ -1,8 +1,59 @@
-use indicatif::{HumanBytes, ProgressBar, ProgressState, ProgressStyle};
+use std::{
+    io::{stderr, stdout, IsTerminal, Write},
+    sync::OnceLock,
+};
+
+use indicatif::{
+    HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use soar_core::events::InstallEvent;
 use soar_dl::downloader::DownloadState;
+use tracing::error;
+
+use crate::cli::ProgressMode;
+
+/// Bytes between progress lines of downloads of unknown size.
+const JSON_PROGRESS_STEP: u64 = 1024 * 1024;
+
+static PROGRESS_MODE: OnceLock<ProgressMode> = OnceLock::new();
+
+/// Sets how progress is shown. Bars are only drawn on a terminal, they're
+/// hidden otherwise.
+pub fn init_progress(mode: ProgressMode) {
+    let mode = match mode {
+        ProgressMode::Bar if !stderr().is_terminal() => ProgressMode::None,
+        mode => mode,
+    };
+    let _ = PROGRESS_MODE.set(mode);
+}
+
+fn progress_mode() -> ProgressMode {
+    PROGRESS_MODE.get().copied().unwrap_or(ProgressMode::Bar)
+}
+
+fn json_progress() -> bool {
+    progress_mode() == ProgressMode::Json
+}
+
+/// Multi-progress display for the bars of parallel jobs, hidden unless
+/// bars are shown.
+pub fn create_multi_progress() -> MultiProgress {
+    match progress_mode() {
+        ProgressMode::Bar => MultiProgress::new(),
+        ProgressMode::Json | ProgressMode::None => {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        }
+    }
+}
 
 pub fn create_progress_bar() -> ProgressBar {
-    let progress_bar = ProgressBar::new(0);
+    let progress_bar = match progress_mode() {
+        ProgressMode::Bar => ProgressBar::new(0),
+        // still tracks the position, to compute the rate and ETA from
+        ProgressMode::Json | ProgressMode::None => ProgressBar::hidden(),
+    };
     let style = ProgressStyle::with_template(
         "{msg} [{wide_bar:.green/white}] {speed:14} {computed_bytes:22}",
     )
@@ -37,14 +88,133 @@ fn calculate_speed(pos: u64, elapsed: f64) -> u64 {
     }
 }
 
+/// Prints a JSON progress line for the download shown on `progress_bar`,
+/// named by its message.
+fn emit(progress_bar: &ProgressBar, event: &str, fields: Value) {
+    #[derive(Serialize)]
+    struct Line<'a> {
+        event: &'a str,
+        name: &'a str,
+        #[serde(flatten)]
+        fields: Value,
+    }
+
+    let message = progress_bar.message();
+    let line = Line {
+        event,
+        name: message.trim(),
+        fields,
+    };
+    if let Ok(line) = serde_json::to_string(&line) {
+        let _ = writeln!(stdout().lock(), "{}", line);
+    }
+}
+
+/// Seconds rounded to tenths, for readability.
+fn secs(secs: f64) -> f64 {
+    (secs * 10.0).round() / 10.0
+}
+
+fn start(progress_bar: &ProgressBar, total: u64) {
+    progress_bar.set_length(total);
+    if json_progress() {
+        emit(progress_bar, "start", json!({ "total": total }));
+    }
+}
+
+/// Moves `progress_bar` to `position`. As JSON, a line is printed at each
+/// percent, or [`JSON_PROGRESS_STEP`] if the size is unknown.
+fn advance(progress_bar: &ProgressBar, position: u64) {
+    let previous = progress_bar.position();
+    progress_bar.set_position(position);
+    if !json_progress() {
+        return;
+    }
+
+    let total = progress_bar.length().unwrap_or(0);
+    let step = if total > 0 {
+        total.div_ceil(100)
+    } else {
+        JSON_PROGRESS_STEP
+    };
+    if position / step == previous / step {
+        return;
+    }
+    let rate = progress_bar.per_sec();
+    let eta = (total > position && rate > 0.0).then(|| secs((total - position) as f64 / rate));
+    emit(
+        progress_bar,
+        "progress",
+        json!({
+            "downloaded": position,
+            "total": total,
+            "rate": rate as u64,
+            "eta": eta,
+        }),
+    );
+}
+
+fn complete(progress_bar: &ProgressBar) {
+    progress_bar.finish();
+    if json_progress() {
+        emit(
+            progress_bar,
+            "complete",
+            json!({
+                "downloaded": progress_bar.position(),
+                "elapsed": secs(progress_bar.elapsed().as_secs_f64()),
+            }),
+        );
+    }
+}
+
+/// Reports the download shown on `progress_bar` as failed with `error`.
+pub fn fail(progress_bar: &ProgressBar, error: &str) {
+    progress_bar.finish_and_clear();
+    if json_progress() {
+        emit(progress_bar, "failed", json!({ "error": error }));
+    }
+}
+
 pub fn handle_progress(state: DownloadState, progress_bar: &ProgressBar) {
     match state {
-        DownloadState::Preparing(total) => {
-            progress_bar.set_length(total);
+        DownloadState::Preparing(total) => start(progress_bar, total),
+        DownloadState::Progress(progress) => advance(progress_bar, progress),
+        DownloadState::Complete => complete(progress_bar),
+        // the bar shows the layers together, JSON tells when each is done
+        DownloadState::LayerProgress {
+            layer,
+            downloaded,
+            total,
+        } => {
+            if json_progress() && downloaded >= total {
+                emit(
+                    progress_bar,
+                    "layer-complete",
+                    json!({ "layer": layer, "downloaded": downloaded }),
+                );
+            }
         }
-        DownloadState::Progress(progress) => {
-            progress_bar.set_position(progress);
+    }
+}
+
+pub fn handle_install_event(event: InstallEvent, progress_bar: &ProgressBar) {
+    match event {
+        InstallEvent::DownloadStarted { total, .. } => start(progress_bar, total),
+        InstallEvent::DownloadProgress { downloaded, .. } => advance(progress_bar, downloaded),
+        InstallEvent::DownloadComplete { .. } => complete(progress_bar),
+        InstallEvent::Failed {
+            pkg_name,
+            pkg_id,
+            error,
+        } => {
+            if json_progress() {
+                emit(progress_bar, "failed", json!({ "error": error }));
+            }
+            error!("{}#{}: {}", pkg_name, pkg_id, error)
         }
-        DownloadState::Complete => progress_bar.finish(),
+        InstallEvent::Resolved { .. }
+        | InstallEvent::Verified { .. }
+        | InstallEvent::Linked { .. } => {}
     }
 }
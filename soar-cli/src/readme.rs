@@ -0,0 +1,227 @@
+use std::{
+    env,
+    io::{stdout, ErrorKind, IsTerminal},
+    sync::LazyLock,
+};
+
+use regex::Regex;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use soar_core::{
+    database::packages::{get_packages, QueryOptions},
+    http::{client, send_with_retry},
+    package::query::PackageQuery,
+    SoarResult,
+};
+use tracing::{debug, error, info};
+
+use crate::{
+    color::{Color, ColorExt},
+    inspect::{spawn_pager, Sink},
+    state::AppState,
+    utils::suggest_similar_packages,
+};
+
+static IMAGE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap());
+static LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\(([^)\s]+)[^)]*\)").unwrap());
+static AUTOLINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<(https?://[^>]+)>").unwrap());
+static TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"</?[a-zA-Z][^>]*>").unwrap());
+static STRONG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*\*([^*]+)\*\*|__([^_]+)__").unwrap());
+static CODE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`([^`]+)`").unwrap());
+
+/// URL the README of the project at `url` can be fetched from, if it's a
+/// GitHub or GitLab project or a Markdown file.
+fn readme_url(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+    let lower = url.to_lowercase();
+    if lower.ends_with(".md") || lower.ends_with(".markdown") {
+        return Some(url.to_string());
+    }
+
+    let path = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.");
+    if let Some(project) = path.strip_prefix("github.com/") {
+        let mut parts = project.split('/');
+        let (owner, repo) = (parts.next()?, parts.next()?);
+        let repo = repo.trim_end_matches(".git");
+        return Some(format!(
+            "https://api.github.com/repos/{}/{}/readme",
+            owner, repo
+        ));
+    }
+    if let Some(project) = path.strip_prefix("gitlab.com/") {
+        // subpages of the project come after `/-/`
+        let project = project.split("/-/").next()?.trim_end_matches(".git");
+        return Some(format!(
+            "https://gitlab.com/{}/-/raw/HEAD/README.md",
+            project
+        ));
+    }
+    None
+}
+
+/// Fetches the README at `url`, `None` if there's none.
+async fn fetch_readme(url: &str) -> SoarResult<Option<String>> {
+    let client = client();
+    let token = env::var("GITHUB_TOKEN").ok();
+    let github = url.starts_with("https://api.github.com/");
+    let resp = send_with_retry(|| {
+        let mut request = client.get(url);
+        if github {
+            request = request.header(ACCEPT, "application/vnd.github.raw");
+            if let Some(token) = &token {
+                request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+            }
+        }
+        request
+    })
+    .await?;
+    if !resp.status().is_success() {
+        debug!("No README at {} [{}]", url, resp.status());
+        return Ok(None);
+    }
+    Ok(Some(resp.text().await?))
+}
+
+/// Renders the inline markup of a line: images are dropped, links show
+/// their target and code is highlighted.
+fn render_inline(line: &str) -> String {
+    let line = IMAGE_RE.replace_all(line, "");
+    let line = AUTOLINK_RE.replace_all(&line, "$1");
+    let line = TAG_RE.replace_all(&line, "");
+    let line = LINK_RE.replace_all(&line, |caps: &regex::Captures| {
+        // e.g. what's left of a linked badge
+        if caps[1].trim().is_empty() {
+            String::new()
+        } else if caps[1] == caps[2] {
+            caps[2].to_string()
+        } else {
+            format!("{} ({})", &caps[1], &caps[2])
+        }
+    });
+    let line = STRONG_RE.replace_all(&line, |caps: &regex::Captures| {
+        caps.get(1)
+            .or(caps.get(2))
+            .map_or(String::new(), |text| text.as_str().color(Color::Value))
+    });
+    CODE_RE
+        .replace_all(&line, |caps: &regex::Captures| caps[1].color(Color::Info))
+        .into_owned()
+}
+
+/// Renders Markdown for the terminal, covering what READMEs mostly use:
+/// headings, lists, quotes, code and links.
+pub fn render_markdown(markdown: &str) -> String {
+    let mut output = String::new();
+    let mut in_code = false;
+    let mut blank = true;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            output.push_str(&format!("    {}\n", line.color(Color::Info)));
+            blank = false;
+            continue;
+        }
+
+        let rendered = if let Some(heading) = trimmed
+            .strip_prefix('#')
+            .map(|rest| rest.trim_start_matches('#'))
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+        {
+            let heading = render_inline(heading.trim().trim_end_matches('#'));
+            if !blank {
+                output.push('\n');
+            }
+            heading.trim().to_uppercase().color(Color::Accent)
+        } else if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        {
+            let indent = line.len() - trimmed.len();
+            format!("{}• {}", " ".repeat(indent), render_inline(item))
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            format!("│ {}", render_inline(quote.trim_start())).color(Color::Info)
+        } else if !trimmed.is_empty() && trimmed.chars().all(|c| "-*_ ".contains(c)) {
+            "─".repeat(40)
+        } else {
+            render_inline(line).trim_end().to_string()
+        };
+
+        // lines of only HTML leave nothing behind
+        if rendered.trim().is_empty() {
+            if !blank && (trimmed.is_empty() || !TAG_RE.is_match(trimmed)) {
+                output.push('\n');
+                blank = true;
+            }
+            continue;
+        }
+        output.push_str(&rendered);
+        output.push('\n');
+        blank = false;
+    }
+    output
+}
+
+/// Shows the README of the upstream project of `package`, found from its
+/// source URLs and homepages, rendered in a pager, or as is with `raw`.
+pub async fn show_readme(package: &str, raw: bool) -> SoarResult<()> {
+    let state = AppState::new().await?;
+    let repo_db = state.repo_db().clone();
+
+    let query = PackageQuery::try_from(package)?;
+    let options = QueryOptions {
+        limit: 1,
+        filters: query.create_repo_filter(),
+        ..Default::default()
+    };
+    let packages = get_packages(repo_db.clone(), options)?;
+    let Some(package) = packages.items.first() else {
+        error!("Package {} not found", package);
+        return suggest_similar_packages(repo_db, package);
+    };
+
+    let urls = package
+        .source_urls
+        .iter()
+        .chain(package.homepages.iter())
+        .flatten()
+        .filter_map(|url| readme_url(url));
+    let mut readme = None;
+    for url in urls {
+        if let Some(content) = fetch_readme(&url).await? {
+            info!("Fetched README from {}", url);
+            readme = Some(content);
+            break;
+        }
+    }
+    let Some(readme) = readme else {
+        error!("No README found for {}", package.pkg_name);
+        return Ok(());
+    };
+
+    let content = if raw {
+        readme
+    } else {
+        render_markdown(&readme)
+    };
+    let mut sink = if raw || !stdout().is_terminal() {
+        Sink::Stdout(stdout().lock())
+    } else {
+        spawn_pager().unwrap_or_else(|| Sink::Stdout(stdout().lock()))
+    };
+    match sink.write_all(content.as_bytes()) {
+        // the pager was closed before reaching the end
+        Err(err) if err.kind() != ErrorKind::BrokenPipe => return Err(err.into()),
+        _ => {}
+    }
+    sink.finish()?;
+    Ok(())
+}
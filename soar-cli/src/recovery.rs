@@ -0,0 +1,74 @@
+use std::io::{stdin, stdout, IsTerminal};
+
+use soar_core::{
+    config::get_config,
+    database::history::{close_incomplete, get_incomplete},
+    package::install::roll_back_install,
+    SoarResult,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    i18n::t,
+    install::{install_history_entries, InstallArgs},
+    state::AppState,
+    utils::interactive_ask,
+};
+
+/// Checks for an install or update that was interrupted, e.g. by soar being
+/// killed, reports the packages it didn't finish and offers to resume or
+/// roll it back. Without a terminal to ask on, it's left for later.
+pub async fn recover_interrupted() -> SoarResult<()> {
+    let core_db = AppState::create_core_db(&get_config())?.conn;
+    let pending = get_incomplete(core_db.clone())?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    warn!("{}", t!("recovery-interrupted", count = pending.len()));
+    for entry in &pending {
+        warn!(
+            kind = entry.kind,
+            "  {}#{}:{} {}", entry.pkg_name, entry.pkg_id, entry.repo_name, entry.version
+        );
+    }
+    if !stdin().is_terminal() || !stdout().is_terminal() {
+        return Ok(());
+    }
+
+    let response = interactive_ask(&t!("recovery-prompt"))?;
+    match response.to_lowercase().as_str() {
+        "r" | "resume" => {
+            close_incomplete(core_db, &t!("recovery-interrupted-reason"))?;
+            install_history_entries(pending, InstallArgs::default()).await?;
+        }
+        "b" | "back" => {
+            let mut failed = false;
+            for entry in &pending {
+                if let Err(err) = roll_back_install(
+                    core_db.clone(),
+                    &entry.repo_name,
+                    &entry.pkg_id,
+                    &entry.pkg_name,
+                ) {
+                    error!(
+                        "{}",
+                        t!(
+                            "recovery-rollback-failed",
+                            package = format!("{}#{}", entry.pkg_name, entry.pkg_id),
+                            error = err
+                        )
+                    );
+                    failed = true;
+                }
+            }
+            // kept pending to try again next time
+            if !failed {
+                close_incomplete(core_db, &t!("recovery-interrupted-reason"))?;
+                info!("{}", t!("recovery-rolled-back", count = pending.len()));
+            }
+        }
+        _ => info!("{}", t!("recovery-later")),
+    }
+    Ok(())
+}
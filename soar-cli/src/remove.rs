@@ -1,43 +1,338 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use futures::{stream, StreamExt};
+use indicatif::HumanBytes;
+use rusqlite::Connection;
 use soar_core::{
-    database::packages::{get_installed_packages, QueryOptions},
-    package::{query::PackageQuery, remove::PackageRemover},
+    config::get_config,
+    database::models::InstalledPackage,
+    error::SoarError,
+    package::{
+        query::PackageQuery,
+        remove::{leftover_data, purge, PackageRemover},
+        switch::default_bin_path,
+    },
+    store::gc,
+    utils::dir_size,
     SoarResult,
 };
-use tracing::{info, warn};
+use tracing::{debug, error, info};
 
-use crate::state::AppState;
+use crate::{
+    color::{Color, ColorExt},
+    i18n::t,
+    post_hooks::{run_hooks, Transaction},
+    selection::{expand_installed, is_glob},
+    state::AppState,
+    utils::{find_installed_matching, interactive_ask},
+};
 
-pub async fn remove_packages(packages: &[String]) -> SoarResult<()> {
+pub async fn remove_packages(
+    packages: &[String],
+    regex: bool,
+    yes: bool,
+    dry_run: bool,
+    purge: bool,
+) -> SoarResult<()> {
     let state = AppState::new().await?;
+    let core_db = state.core_db().clone();
 
-    for package in packages {
-        let core_db = state.core_db().clone();
+    let (patterns, mut packages): (Vec<String>, Vec<String>) = packages
+        .iter()
+        .cloned()
+        .partition(|package| regex || is_glob(package));
+    if !patterns.is_empty() {
+        packages.extend(expand_installed(core_db.clone(), &patterns, regex)?);
+    }
 
+    let mut targets: Vec<RemovalTarget> = Vec::new();
+    let mut not_installed = 0;
+    for package in &packages {
         let query = PackageQuery::try_from(package.as_str())?;
-        let filters = query.create_filter();
-        let options = QueryOptions {
-            filters,
-            ..Default::default()
-        };
-
-        let installed_pkgs = get_installed_packages(core_db.clone(), options)?.items;
+        let installed = find_installed_matching(core_db.clone(), &query)?;
+        if installed.len() > 1 {
+            let candidates: Vec<String> = installed
+                .iter()
+                .map(|pkg| format!("{}#{}@{}", pkg.pkg_name, pkg.pkg_id, pkg.version))
+                .collect();
+            return Err(SoarError::InvalidPackageQuery(t!(
+                "installed-ambiguous",
+                package = package,
+                candidates = candidates.join(", ")
+            )));
+        }
+        let installed_pkg = installed.into_iter().next();
+        match installed_pkg {
+            Some(pkg) => {
+                if !targets.iter().any(|target| target.package.id == pkg.id) {
+                    let leftovers = if purge {
+                        leftover_data(&pkg)?
+                    } else {
+                        Vec::new()
+                    };
+                    targets.push(RemovalTarget {
+                        package: pkg,
+                        leftovers,
+                    });
+                }
+            }
+            None => {
+                error!("{}", t!("remove-not-installed", package = package));
+                not_installed += 1;
+            }
+        }
+    }
 
-        if installed_pkgs.is_empty() {
-            warn!("Package {} is not installed.", package);
-            continue;
+    if dry_run {
+        for target in &targets {
+            print_removal(target);
+        }
+        info!("{}", t!("dry-run-done"));
+    } else if confirm_removal(&targets, yes)? {
+        // installs linked under the name of their package, which other
+        // versions may be linked under instead
+        let named: Vec<InstalledPackage> = targets
+            .iter()
+            .map(|target| &target.package)
+            .filter(|pkg| {
+                pkg.bin_path.as_ref().is_some_and(|bin_path| {
+                    default_bin_path(pkg).is_ok_and(|path| path == Path::new(bin_path))
+                })
+            })
+            .cloned()
+            .collect();
+        let total = targets.len();
+        let (removed, failed) = remove_concurrently(targets, core_db.clone()).await;
+        if !removed.is_empty() {
+            run_hooks(Transaction::Remove, &removed);
+        }
+        for pkg in &named {
+            print_other_versions(core_db.clone(), pkg)?;
         }
+        if failed > 0 {
+            return Err(SoarError::Custom(t!(
+                "remove-failed-summary",
+                count = failed,
+                total = total
+            )));
+        }
+    }
+
+    if not_installed > 0 {
+        return Err(SoarError::Custom(t!(
+            "remove-not-installed-summary",
+            count = not_installed,
+            total = packages.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Points out the versions of `removed` that are still installed, one of
+/// which can take the name it was linked under.
+fn print_other_versions(
+    core_db: Arc<Mutex<Connection>>,
+    removed: &InstalledPackage,
+) -> SoarResult<()> {
+    let query = PackageQuery {
+        collection: None,
+        name: Some(removed.pkg_name.clone()),
+        repo_name: Some(removed.repo_name.clone()),
+        pkg_id: Some(removed.pkg_id.clone()),
+        version: None,
+    };
+    let remaining = find_installed_matching(core_db, &query)?;
+    if remaining.is_empty() || remaining.iter().any(|pkg| pkg.bin_path == removed.bin_path) {
+        return Ok(());
+    }
+    let versions: Vec<&str> = remaining.iter().map(|pkg| pkg.version.as_str()).collect();
+    info!(
+        "{}",
+        t!(
+            "remove-other-versions",
+            package = removed.pkg_name,
+            versions = versions.join(", ")
+        )
+    );
+    Ok(())
+}
 
-        let installed_pkg = installed_pkgs.first().unwrap();
-        if !installed_pkg.is_installed {
-            warn!("Package {} is not installed.", package);
-            continue;
+/// An installed package to remove.
+struct RemovalTarget {
+    package: InstalledPackage,
+    /// Data left behind by the package that is wiped along with it.
+    leftovers: Vec<PathBuf>,
+}
+
+fn path_size(path: &Path) -> u64 {
+    if path.is_dir() {
+        dir_size(path).unwrap_or(0)
+    } else {
+        path.metadata().map(|meta| meta.len()).unwrap_or(0)
+    }
+}
+
+/// Lists the packages about to be removed with where they're installed,
+/// the data that is wiped with them and the space they take up, and asks
+/// to go on unless `yes` is set.
+fn confirm_removal(targets: &[RemovalTarget], yes: bool) -> SoarResult<bool> {
+    if targets.is_empty() {
+        return Ok(false);
+    }
+
+    info!("{}", t!("select-remove-header"));
+    let mut total = 0;
+    for RemovalTarget { package, leftovers } in targets {
+        let size = dir_size(&package.installed_path).unwrap_or(package.size);
+        total += size;
+        info!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            version = %package.version,
+            path = %package.installed_path,
+            size = size,
+            "  {} {}  {}  {}",
+            format!("{}#{}:{}", package.pkg_name, package.pkg_id, package.repo_name)
+                .color(Color::Accent),
+            package.version.color(Color::Value),
+            package.installed_path,
+            HumanBytes(size)
+        );
+        if !leftovers.is_empty() {
+            for path in [&package.desktop_path, &package.icon_path]
+                .into_iter()
+                .flatten()
+            {
+                info!("    {}", t!("dry-run-unlink", path = path));
+            }
+        }
+        for path in leftovers {
+            let size = path_size(path);
+            total += size;
+            info!(
+                path = %path.display(),
+                size = size,
+                "    {}  {}",
+                t!("remove-wipe", path = path.display()),
+                HumanBytes(size)
+            );
         }
+    }
+    if yes {
+        return Ok(true);
+    }
 
-        let remover = PackageRemover::new(installed_pkg.clone(), core_db).await;
-        remover.remove().await?;
+    let response = interactive_ask(&t!(
+        "remove-confirm",
+        count = targets.len(),
+        size = HumanBytes(total)
+    ))?;
+    Ok(response.is_empty() || response.to_lowercase().starts_with('y'))
+}
+
+/// Removes `targets` a few at a time, as removals mostly wait on the disk,
+/// and returns the names of those that were removed along with how many
+/// failed.
+async fn remove_concurrently(
+    targets: Vec<RemovalTarget>,
+    core_db: Arc<Mutex<Connection>>,
+) -> (Vec<String>, usize) {
+    let jobs = get_config().parallel_limit.unwrap_or(4).max(1) as usize;
+    let results: Vec<_> = stream::iter(targets)
+        .map(|RemovalTarget { package, leftovers }| {
+            let core_db = core_db.clone();
+            tokio::spawn(async move {
+                debug!(
+                    pkg_name = %package.pkg_name,
+                    pkg_id = %package.pkg_id,
+                    version = %package.version,
+                    path = %package.installed_path,
+                    "Removing {}#{}",
+                    package.pkg_name,
+                    package.pkg_id
+                );
+                let remover = PackageRemover::new(package.clone(), core_db).await;
+                let result = match remover.remove().await {
+                    Ok(()) => purge(&leftovers),
+                    Err(err) => Err(err),
+                };
+                match result {
+                    Ok(()) => {
+                        info!("{}", t!("remove-done", package = package.pkg_name));
+                        Some(package.pkg_name)
+                    }
+                    Err(err) => {
+                        error!(
+                            "{}",
+                            t!("remove-failed", package = package.pkg_name, error = err)
+                        );
+                        None
+                    }
+                }
+            })
+        })
+        .buffer_unordered(jobs)
+        .collect()
+        .await;
 
-        info!("Removed {}", installed_pkg.pkg_name);
+    let mut removed = Vec::new();
+    let mut failed = 0;
+    for result in results {
+        match result {
+            Ok(Some(name)) => removed.push(name),
+            Ok(None) => failed += 1,
+            Err(err) => {
+                error!("{}", t!("remove-task-failed", error = err));
+                failed += 1;
+            }
+        }
     }
+    (removed, failed)
+}
 
+fn print_removal(RemovalTarget { package, leftovers }: &RemovalTarget) {
+    let name = format!(
+        "{}#{}:{}",
+        package.pkg_name, package.pkg_id, package.repo_name
+    );
+    info!(
+        pkg = %name,
+        version = %package.version,
+        "{}",
+        t!(
+            "dry-run-remove",
+            package = name.color(Color::Accent),
+            version = package.version
+        )
+    );
+    info!("  {}", t!("dry-run-delete", path = package.installed_path));
+    let links = [
+        &package.bin_path,
+        &package.desktop_path,
+        &package.icon_path,
+        &package.appstream_path,
+    ];
+    for path in links.into_iter().flatten() {
+        info!("  {}", t!("dry-run-unlink", path = path));
+    }
+    for path in leftovers {
+        info!("  {}", t!("remove-wipe", path = path.display()));
+    }
+}
+
+/// Removes the entries of the package store that no installed package
+/// refers to anymore.
+pub async fn collect_garbage() -> SoarResult<()> {
+    let state = AppState::new().await?;
+    let (count, freed) = gc(state.core_db().clone())?;
+    info!(
+        count = %count,
+        freed = %freed,
+        "{}",
+        t!("gc-done", count = count, size = HumanBytes(freed))
+    );
     Ok(())
 }
@@ -0,0 +1,122 @@
+use std::{collections::HashMap, fs, slice};
+
+use soar_core::{
+    config::{self, get_config, Repository},
+    database::{
+        packages::{get_installed_packages, FilterOp, QueryOptions},
+        sync_state::{is_stale, remove_sync_state},
+    },
+    trust::mark_untrusted,
+    SoarResult,
+};
+use tracing::{info, warn};
+
+use crate::{cli::RepoAction, i18n::t, list::list_repositories, state::AppState};
+
+pub async fn process_repo_action(action: RepoAction) -> SoarResult<()> {
+    match action {
+        RepoAction::Add {
+            name,
+            url,
+            pubkeys,
+            collection,
+        } => add_repository(name, url, pubkeys, collection).await,
+        RepoAction::Remove { name } => remove_repository(&name),
+        RepoAction::Enable { name } => enable_repository(&name).await,
+        RepoAction::Disable { name } => {
+            config::set_repository_enabled(&name, false)?;
+            info!(repo_name = %name, "{}", t!("repo-disabled", repo = name));
+            Ok(())
+        }
+        RepoAction::List => list_repositories().await,
+    }
+}
+
+/// Adds the repository to the config and fetches its metadata. If that
+/// fails the repository is removed again, so a bad URL doesn't break every
+/// following command.
+async fn add_repository(
+    name: String,
+    url: String,
+    pubkeys: Vec<String>,
+    collection: Option<String>,
+) -> SoarResult<()> {
+    let repo = Repository {
+        name,
+        url,
+        pubkeys,
+        enabled: None,
+        collection,
+        trust_policy: None,
+        headers: HashMap::new(),
+        stats_url: None,
+    };
+    config::add_repository(&repo)?;
+
+    let core_db = AppState::create_core_db(&get_config())?;
+    if let Err(err) = AppState::sync(core_db.conn.clone(), slice::from_ref(&repo), true).await {
+        config::remove_repository(&repo.name)?;
+        let _ = fs::remove_dir_all(repo.get_path()?);
+        remove_sync_state(core_db.conn, &repo.name)?;
+        return Err(err);
+    }
+    mark_untrusted(&repo)?;
+
+    info!(repo_name = %repo.name, "{}", t!("repo-added", repo = repo.name));
+    Ok(())
+}
+
+/// Removes the repository from the config along with its metadata. Packages
+/// installed from it stay installed.
+fn remove_repository(name: &str) -> SoarResult<()> {
+    let repo_path = get_config().get_repository(name)?.get_path()?;
+    config::remove_repository(name)?;
+
+    if repo_path.exists() {
+        fs::remove_dir_all(&repo_path)?;
+    }
+    let core_db = AppState::create_core_db(&get_config())?;
+    remove_sync_state(core_db.conn.clone(), name)?;
+
+    info!(repo_name = %name, "{}", t!("repo-removed", repo = name));
+
+    let mut filters = HashMap::new();
+    filters.insert(
+        "repo_name".to_string(),
+        (FilterOp::Eq, name.to_string().into()).into(),
+    );
+    let installed = get_installed_packages(
+        core_db.conn,
+        QueryOptions {
+            filters,
+            limit: 1,
+            ..Default::default()
+        },
+    )?
+    .total;
+    if installed > 0 {
+        warn!(
+            "{}",
+            t!("repo-installed-kept", count = installed, repo = name)
+        );
+    }
+
+    Ok(())
+}
+
+/// Enables the repository, fetching its metadata if it's missing or stale.
+async fn enable_repository(name: &str) -> SoarResult<()> {
+    config::set_repository_enabled(name, true)?;
+
+    let repo = get_config().get_repository(name)?.clone();
+    let core_db = AppState::create_core_db(&get_config())?;
+    let ttl = get_config().metadata_ttl();
+    let has_metadata =
+        fs::metadata(repo.get_path()?.join("metadata.db")).is_ok_and(|meta| meta.len() > 0);
+    if !has_metadata || is_stale(core_db.conn.clone(), name, ttl)? {
+        AppState::sync(core_db.conn, slice::from_ref(&repo), false).await?;
+    }
+
+    info!(repo_name = %name, "{}", t!("repo-enabled", repo = name));
+    Ok(())
+}
@@ -4,18 +4,21 @@ use soar_core::{
     database::{
         models::Package,
         packages::{get_packages, QueryOptions},
+        stats::record_run,
     },
     error::SoarError,
+    http::with_failover,
     package::query::PackageQuery,
-    utils::calculate_checksum,
+    utils::{calculate_checksum, check_free_space},
     SoarResult,
 };
-use soar_dl::downloader::{DownloadOptions, Downloader};
+use soar_dl::downloader::{DownloadOptions, Downloader, ProgressCallback};
+use tracing::warn;
 
 use crate::{
     progress::{self, create_progress_bar},
     state::AppState,
-    utils::interactive_ask,
+    utils::{interactive_ask, suggest_similar_packages},
 };
 
 pub async fn run_package(command: &[String]) -> SoarResult<()> {
@@ -30,14 +33,15 @@ pub async fn run_package(command: &[String]) -> SoarResult<()> {
     };
 
     let query = PackageQuery::try_from(package_name.as_str())?;
-    let filters = query.create_filter();
+    let filters = query.create_repo_filter();
     let options = QueryOptions {
         filters,
         ..Default::default()
     };
-    let packages: Vec<Package> = get_packages(repo_db, options)?.items;
+    let packages: Vec<Package> = get_packages(repo_db.clone(), options)?.items;
 
     if packages.is_empty() {
+        suggest_similar_packages(repo_db, package_name)?;
         return Err(SoarError::PackageNotFound(package_name.clone()));
     }
 
@@ -46,20 +50,27 @@ pub async fn run_package(command: &[String]) -> SoarResult<()> {
     fs::create_dir_all(&cache_bin)?;
 
     let output_path = cache_bin.join(&package.pkg_name);
-    if !output_path.exists() {
+    let cache_hit = output_path.exists();
+    if let Err(err) = record_run(state.core_db().clone(), &package.pkg_name, cache_hit) {
+        warn!("Failed to record run statistics: {}", err);
+    }
+    if !cache_hit {
+        check_free_space(&[(cache_bin.clone(), package.size)])?;
         let progress_bar = create_progress_bar();
-        let progress_callback = Arc::new(move |state| {
+        let progress_callback: ProgressCallback = Arc::new(move |state| {
             progress::handle_progress(state, &progress_bar);
         });
 
         let downloader = Downloader::default();
-        let options = DownloadOptions {
-            url: package.download_url.clone(),
-            output_path: Some(output_path.to_string_lossy().to_string()),
-            progress_callback: Some(progress_callback),
-        };
-
-        downloader.download(options).await?;
+        with_failover(&package.download_url, |url| async {
+            let options = DownloadOptions::builder(url)
+                .output_path(&output_path)
+                .progress_callback(progress_callback.clone())
+                .build()?;
+            downloader.download(options).await?;
+            Ok(())
+        })
+        .await?;
 
         let checksum = calculate_checksum(&output_path)?;
         if checksum != package.checksum {
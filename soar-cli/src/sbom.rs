@@ -0,0 +1,90 @@
+use std::{collections::HashMap, fs};
+
+use soar_core::{
+    database::packages::{get_installed_packages, get_packages, FilterOp, QueryOptions},
+    sbom::{cyclonedx_document, spdx_document, SbomComponent},
+    SoarResult,
+};
+use tracing::info;
+
+use crate::{cli::SbomFormat, state::AppState};
+
+pub async fn generate_sbom(format: SbomFormat, output: Option<String>) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let repo_db = state.repo_db().clone();
+    let core_db = state.core_db().clone();
+
+    let mut filters = HashMap::new();
+    filters.insert(
+        "is_installed".to_string(),
+        (FilterOp::Eq, "1".to_string().into()).into(),
+    );
+    let packages = get_installed_packages(
+        core_db,
+        QueryOptions {
+            filters,
+            limit: u32::MAX,
+            ..Default::default()
+        },
+    )?
+    .items;
+
+    let mut components = Vec::with_capacity(packages.len());
+    for package in packages {
+        let mut filters = HashMap::new();
+        filters.insert(
+            "r.name".to_string(),
+            (FilterOp::Eq, package.repo_name.clone().into()).into(),
+        );
+        filters.insert(
+            "pkg_name".to_string(),
+            (FilterOp::Eq, package.pkg_name.clone().into()).into(),
+        );
+        filters.insert(
+            "pkg_id".to_string(),
+            (FilterOp::Eq, package.pkg_id.clone().into()).into(),
+        );
+        filters.insert(
+            "version".to_string(),
+            (FilterOp::Eq, package.version.clone().into()).into(),
+        );
+        // the metadata may have moved on to a newer version since install
+        let download_url = get_packages(
+            repo_db.clone(),
+            QueryOptions {
+                limit: 1,
+                filters,
+                ..Default::default()
+            },
+        )?
+        .items
+        .into_iter()
+        .next()
+        .map(|p| p.download_url);
+
+        components.push(SbomComponent {
+            pkg_name: package.pkg_name,
+            pkg_id: package.pkg_id,
+            version: package.version,
+            repo_name: package.repo_name,
+            download_url,
+            checksum: package.checksum,
+        });
+    }
+
+    let document = match format {
+        SbomFormat::Spdx => spdx_document(&components),
+        SbomFormat::Cyclonedx => cyclonedx_document(&components),
+    };
+    let document = serde_json::to_string_pretty(&document).unwrap();
+
+    match output {
+        Some(path) => {
+            fs::write(&path, document)?;
+            info!("Wrote SBOM for {} packages to {}", components.len(), path);
+        }
+        None => println!("{document}"),
+    }
+
+    Ok(())
+}
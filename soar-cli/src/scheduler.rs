@@ -0,0 +1,124 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Minimum time between two concurrency adjustments, so a burst of tiny
+/// packages finishing together doesn't cause the limit to swing.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Relative change in throughput needed before concurrency is adjusted.
+const GROWTH_THRESHOLD: f64 = 1.1;
+const SHRINK_THRESHOLD: f64 = 0.8;
+
+struct SchedulerState {
+    limit: usize,
+    sample_start: Instant,
+    sample_bytes: u64,
+    last_throughput: f64,
+}
+
+/// Concurrency limiter for package installs that adapts to the measured
+/// download throughput.
+///
+/// An adaptive scheduler starts at half of `max_jobs` and opens another slot
+/// while adding jobs keeps increasing throughput, backing off when throughput
+/// drops. A fixed one always runs the number of jobs it was created with.
+pub struct AdaptiveScheduler {
+    semaphore: Arc<Semaphore>,
+    max_jobs: usize,
+    adaptive: bool,
+    state: Mutex<SchedulerState>,
+}
+
+impl AdaptiveScheduler {
+    /// Scheduler running up to `max_jobs` jobs, as many as pay off.
+    pub fn adaptive(max_jobs: usize) -> Self {
+        let max_jobs = max_jobs.max(1);
+        Self::new(max_jobs.div_ceil(2), max_jobs, true)
+    }
+
+    /// Scheduler always running `jobs` jobs, e.g. as asked for with `--jobs`.
+    pub fn fixed(jobs: usize) -> Self {
+        let jobs = jobs.max(1);
+        Self::new(jobs, jobs, false)
+    }
+
+    fn new(limit: usize, max_jobs: usize, adaptive: bool) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            max_jobs,
+            adaptive,
+            state: Mutex::new(SchedulerState {
+                limit,
+                sample_start: Instant::now(),
+                sample_bytes: 0,
+                last_throughput: 0.0,
+            }),
+        }
+    }
+
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.semaphore.clone().acquire_owned().await.unwrap()
+    }
+
+    /// Records a finished job that downloaded `bytes` and adjusts the
+    /// concurrency limit once enough time has passed since the last sample.
+    pub fn record(&self, bytes: u64) {
+        if !self.adaptive {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.sample_bytes = state.sample_bytes.saturating_add(bytes);
+
+        let elapsed = state.sample_start.elapsed();
+        if elapsed < SAMPLE_INTERVAL {
+            return;
+        }
+
+        let throughput = state.sample_bytes as f64 / elapsed.as_secs_f64();
+        if throughput >= state.last_throughput * GROWTH_THRESHOLD {
+            if state.limit < self.max_jobs {
+                state.limit += 1;
+                self.semaphore.add_permits(1);
+            }
+        } else if throughput < state.last_throughput * SHRINK_THRESHOLD && state.limit > 1 {
+            // only shrink when a slot is idle, otherwise retry on the next sample
+            if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+                permit.forget();
+                state.limit -= 1;
+            }
+        }
+
+        state.last_throughput = throughput;
+        state.sample_start = Instant::now();
+        state.sample_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_scheduler_runs_the_requested_jobs() {
+        let scheduler = AdaptiveScheduler::fixed(4);
+        assert_eq!(scheduler.semaphore.available_permits(), 4);
+
+        scheduler.state.lock().unwrap().sample_start -= SAMPLE_INTERVAL;
+        scheduler.record(u64::MAX);
+        assert_eq!(scheduler.semaphore.available_permits(), 4);
+    }
+
+    #[test]
+    fn adaptive_scheduler_grows_with_throughput() {
+        let scheduler = AdaptiveScheduler::adaptive(4);
+        assert_eq!(scheduler.semaphore.available_permits(), 2);
+
+        scheduler.state.lock().unwrap().sample_start -= SAMPLE_INTERVAL;
+        scheduler.record(1 << 20);
+        assert_eq!(scheduler.semaphore.available_permits(), 3);
+    }
+}
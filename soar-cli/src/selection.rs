@@ -0,0 +1,119 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+
+use regex::Regex;
+use rusqlite::Connection;
+use soar_core::{
+    database::packages::{get_installed_packages, get_packages, QueryOptions},
+    error::SoarError,
+    package::query::PackageQuery,
+    utils::glob_match,
+    SoarResult,
+};
+use tracing::warn;
+
+use crate::i18n::t;
+
+/// Whether `package` selects packages by a glob pattern on their name, e.g.
+/// `ripgrep*`, rather than naming one.
+pub fn is_glob(package: &str) -> bool {
+    package.contains(['*', '?'])
+}
+
+enum Pattern {
+    Glob(PackageQuery),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn new(pattern: &str, regex: bool) -> SoarResult<Self> {
+        if regex {
+            return Regex::new(pattern)
+                .map(Pattern::Regex)
+                .map_err(|err| SoarError::InvalidPackageQuery(err.to_string()));
+        }
+        Ok(Pattern::Glob(PackageQuery::try_from(pattern)?))
+    }
+
+    fn options(&self, repo: bool) -> QueryOptions {
+        let filters = match self {
+            // the name is matched afterwards, the rest of the query narrows
+            // down the candidates
+            Pattern::Glob(query) => {
+                let mut filters = if repo {
+                    query.create_repo_filter()
+                } else {
+                    query.create_filter()
+                };
+                filters.remove("pkg_name");
+                filters
+            }
+            Pattern::Regex(_) => Default::default(),
+        };
+        QueryOptions {
+            filters,
+            ..Default::default()
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Pattern::Glob(query) => query
+                .name
+                .as_ref()
+                .is_none_or(|pattern| glob_match(pattern, name)),
+            Pattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Expands `patterns` to the `name#pkg_id:repo` queries of the matching
+/// packages in the repositories.
+pub fn expand_available(
+    repo_db: Arc<Mutex<Connection>>,
+    patterns: &[String],
+    regex: bool,
+) -> SoarResult<Vec<String>> {
+    let mut selected = BTreeSet::new();
+    for pattern in patterns {
+        let matcher = Pattern::new(pattern, regex)?;
+        let matches: BTreeSet<String> = get_packages(repo_db.clone(), matcher.options(true))?
+            .items
+            .into_iter()
+            .filter(|pkg| matcher.matches(&pkg.pkg_name))
+            .map(|pkg| format!("{}#{}:{}", pkg.pkg_name, pkg.pkg_id, pkg.repo_name))
+            .collect();
+        if matches.is_empty() {
+            warn!("{}", t!("select-no-match", pattern = pattern));
+        }
+        selected.extend(matches);
+    }
+    Ok(selected.into_iter().collect())
+}
+
+/// Expands `patterns` to the `name#pkg_id:repo` queries of the matching
+/// installed packages.
+pub fn expand_installed(
+    core_db: Arc<Mutex<Connection>>,
+    patterns: &[String],
+    regex: bool,
+) -> SoarResult<Vec<String>> {
+    let mut selected = BTreeSet::new();
+    for pattern in patterns {
+        let matcher = Pattern::new(pattern, regex)?;
+        let matches: BTreeSet<String> =
+            get_installed_packages(core_db.clone(), matcher.options(false))?
+                .items
+                .into_iter()
+                .filter(|pkg| pkg.is_installed && matcher.matches(&pkg.pkg_name))
+                .map(|pkg| format!("{}#{}:{}", pkg.pkg_name, pkg.pkg_id, pkg.repo_name))
+                .collect();
+        if matches.is_empty() {
+            warn!("{}", t!("select-no-match", pattern = pattern));
+        }
+        selected.extend(matches);
+    }
+    Ok(selected.into_iter().collect())
+}
@@ -45,11 +45,9 @@ pub async fn process_self_action(
                     })
                     .unwrap();
                 let downloader = Downloader::default();
-                let options = DownloadOptions {
-                    url: asset.download_url().to_string(),
-                    output_path: Some(self_bin),
-                    progress_callback: None,
-                };
+                let options = DownloadOptions::builder(asset.download_url())
+                    .output_path(self_bin)
+                    .build()?;
                 downloader.download(options).await?;
                 info!("Soar updated to {}", release.tag_name());
             } else {
@@ -0,0 +1,115 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use soar_core::{
+    config::get_config,
+    error::SoarError,
+    platform::{home_config_path, home_path},
+    SoarResult,
+};
+use tracing::info;
+
+use crate::{
+    cli::HookShell,
+    color::{Color, ColorExt},
+    i18n::t,
+};
+
+/// Lines around the block soar manages in shell startup files.
+const BLOCK_START: &str = "# >>> soar >>>";
+const BLOCK_END: &str = "# <<< soar <<<";
+
+/// Shell `PATH` is set up for, as given or from `$SHELL`.
+pub fn detect_shell(shell: Option<HookShell>) -> Option<HookShell> {
+    shell.or_else(|| {
+        let shell = env::var("SHELL").ok()?;
+        match Path::new(&shell).file_name()?.to_str()? {
+            "bash" => Some(HookShell::Bash),
+            "zsh" => Some(HookShell::Zsh),
+            "fish" => Some(HookShell::Fish),
+            _ => None,
+        }
+    })
+}
+
+/// Line adding `bin_path` to `PATH` in `shell`, POSIX shell syntax if it's
+/// unknown.
+pub fn path_line(shell: Option<HookShell>, bin_path: &Path) -> String {
+    match shell {
+        Some(HookShell::Fish) => format!("fish_add_path \"{}\"", bin_path.display()),
+        _ => format!("export PATH=\"{}:$PATH\"", bin_path.display()),
+    }
+}
+
+/// Startup file of `shell` to set `PATH` in.
+pub fn rc_file(shell: HookShell) -> PathBuf {
+    let home = PathBuf::from(home_path());
+    match shell {
+        HookShell::Bash => home.join(".bashrc"),
+        HookShell::Zsh => env::var_os("ZDOTDIR")
+            .map(PathBuf::from)
+            .unwrap_or(home)
+            .join(".zshrc"),
+        // fish reads every file in conf.d, so soar gets its own
+        HookShell::Fish => PathBuf::from(home_config_path()).join("fish/conf.d/soar.fish"),
+    }
+}
+
+/// Writes `line` to the block soar manages in `rc_file`, replacing what the
+/// block held before or appending it if there's none. Returns whether the
+/// file changed.
+pub fn write_rc_block(rc_file: &Path, line: &str) -> SoarResult<bool> {
+    let content = match fs::read_to_string(rc_file) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let block = format!("{}\n{}\n{}", BLOCK_START, line, BLOCK_END);
+
+    let updated = match (content.find(BLOCK_START), content.find(BLOCK_END)) {
+        (Some(start), Some(end)) if start < end => format!(
+            "{}{}{}",
+            &content[..start],
+            block,
+            &content[end + BLOCK_END.len()..]
+        ),
+        _ if content.is_empty() => format!("{}\n", block),
+        _ => format!("{}\n\n{}\n", content.trim_end_matches('\n'), block),
+    };
+    if updated == content {
+        return Ok(false);
+    }
+
+    if let Some(parent) = rc_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // written through, as startup files are often links into a dotfiles repo
+    fs::write(rc_file, updated)?;
+    Ok(true)
+}
+
+/// Prints the line adding the bin directory to `PATH` in `shell`.
+pub fn print_path_line(shell: Option<HookShell>) -> SoarResult<()> {
+    let bin_path = get_config().get_bin_path()?;
+    println!("{}", path_line(detect_shell(shell), &bin_path));
+    Ok(())
+}
+
+/// Adds the bin directory to `PATH` in the startup file of `shell`. Running
+/// it again only updates the block it wrote.
+pub fn install_path(shell: Option<HookShell>) -> SoarResult<()> {
+    let Some(shell) = detect_shell(shell) else {
+        return Err(SoarError::Custom(t!("env-unknown-shell")));
+    };
+    let bin_path = get_config().get_bin_path()?;
+    let rc_file = rc_file(shell);
+    let rc = rc_file.display().color(Color::Accent);
+    if write_rc_block(&rc_file, &path_line(Some(shell), &bin_path))? {
+        info!("{}", t!("env-path-added", file = rc));
+    } else {
+        info!("{}", t!("env-path-configured", file = rc));
+    }
+    Ok(())
+}
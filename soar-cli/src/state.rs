@@ -1,17 +1,37 @@
 use std::{
     fs::{self, File},
     path::PathBuf,
-    sync::{Arc, Mutex, RwLockReadGuard},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLockReadGuard,
+    },
 };
 
 use rusqlite::Connection;
 use soar_core::{
-    config::{get_config, Config},
+    config::{get_config, Config, Repository},
     constants::CORE_MIGRATIONS,
-    database::{connection::Database, migration::MigrationManager},
+    database::{connection::Database, migration::MigrationManager, sync_state::is_stale},
+    error::SoarError,
     metadata::fetch_metadata,
+    signing::trust_key,
     SoarResult,
 };
+use tracing::warn;
+
+use crate::utils::interactive_ask;
+
+/// Whether metadata is fetched again even if it's younger than the
+/// configured `metadata_ttl`, set by `--refresh`.
+static REFRESH: AtomicBool = AtomicBool::new(false);
+
+pub fn set_refresh(refresh: bool) {
+    REFRESH.store(refresh, Ordering::Relaxed);
+}
+
+pub fn is_refresh() -> bool {
+    REFRESH.load(Ordering::Relaxed)
+}
 
 #[derive(Clone)]
 pub struct AppState {
@@ -19,51 +39,99 @@ pub struct AppState {
 }
 
 struct AppStateInner {
-    config: RwLockReadGuard<'static, Config>,
     repo_db: Database,
     core_db: Database,
 }
 
 impl AppState {
+    /// Opens the databases, syncing the repositories whose metadata is older
+    /// than the configured `metadata_ttl` first, or all of them with
+    /// `--refresh`.
     pub async fn new() -> SoarResult<Self> {
-        let config = get_config();
+        let core_db = Self::create_core_db(&get_config())?;
+        let refresh = is_refresh();
+        let stale = {
+            let config = get_config();
+            let ttl = config.metadata_ttl();
+            let mut stale = Vec::new();
+            for repo in config.enabled_repositories() {
+                if refresh || is_stale(core_db.conn.clone(), &repo.name, ttl)? {
+                    stale.push(repo.clone());
+                }
+            }
+            stale
+        };
 
-        Self::init_repo_dbs(&config).await?;
-        let repo_db = Self::create_repo_db(&config)?;
-        let core_db = Self::create_core_db(&config)?;
+        Self::sync(core_db.conn.clone(), &stale, refresh).await?;
+        let repo_db = Self::create_repo_db(&get_config())?;
 
         Ok(Self {
-            inner: Arc::new(AppStateInner {
-                config,
-                repo_db,
-                core_db,
-            }),
+            inner: Arc::new(AppStateInner { repo_db, core_db }),
         })
     }
 
-    async fn init_repo_dbs(config: &RwLockReadGuard<'_, Config>) -> SoarResult<()> {
-        for repo in &config.repositories {
+    /// Like [`AppState::new`], but reuses the metadata already on disk and only
+    /// fetches repositories that have none yet.
+    pub async fn new_cached() -> SoarResult<Self> {
+        let missing: Vec<Repository> = get_config()
+            .enabled_repositories()
+            .filter(|repo| {
+                repo.get_path()
+                    .and_then(|path| Ok(fs::metadata(path.join("metadata.db"))?))
+                    .map_or(true, |meta| meta.len() == 0)
+            })
+            .cloned()
+            .collect();
+
+        let core_db = Self::create_core_db(&get_config())?;
+        Self::sync(core_db.conn.clone(), &missing, false).await?;
+        let repo_db = Self::create_repo_db(&get_config())?;
+
+        Ok(Self {
+            inner: Arc::new(AppStateInner { repo_db, core_db }),
+        })
+    }
+
+    /// Fetches the metadata of `repositories`, recording the sync state in
+    /// `core_db`. Without `force`, metadata whose ETag hasn't changed is kept.
+    pub async fn sync(
+        core_db: Arc<Mutex<Connection>>,
+        repositories: &[Repository],
+        force: bool,
+    ) -> SoarResult<()> {
+        for repo in repositories {
             let db_file = repo.get_path()?.join("metadata.db");
             if !db_file.exists() {
                 fs::create_dir_all(repo.get_path()?)?;
                 File::create(&db_file)?;
             }
-            fetch_metadata(repo.clone()).await?;
+            match fetch_metadata(repo.clone(), core_db.clone(), force).await {
+                Err(SoarError::UntrustedKey { repo_name, key }) => {
+                    warn!("Signing key of repository {} has changed!", repo_name);
+                    warn!("New key: {}", key);
+                    let response = interactive_ask("Trust the new key? (y/N) ")?;
+                    if !response.to_lowercase().starts_with('y') {
+                        return Err(SoarError::UntrustedKey { repo_name, key });
+                    }
+                    trust_key(repo, &key)?;
+                    fetch_metadata(repo.clone(), core_db.clone(), force).await?;
+                }
+                result => result?,
+            }
         }
         Ok(())
     }
 
     fn create_repo_db(config: &RwLockReadGuard<'_, Config>) -> SoarResult<Database> {
         let repo_paths: Vec<PathBuf> = config
-            .repositories
-            .iter()
+            .enabled_repositories()
             .map(|r| r.get_path().unwrap().join("metadata.db"))
             .collect();
 
         Database::new_multi(repo_paths.as_ref())
     }
 
-    fn create_core_db(config: &RwLockReadGuard<'_, Config>) -> SoarResult<Database> {
+    pub fn create_core_db(config: &RwLockReadGuard<'_, Config>) -> SoarResult<Database> {
         let core_db_file = config.get_db_path()?.join("soar.db");
         if !core_db_file.exists() {
             File::create(&core_db_file)?;
@@ -75,8 +143,8 @@ impl AppState {
         Database::new(&core_db_file)
     }
 
-    pub fn config(&self) -> &Config {
-        &self.inner.config
+    pub fn config(&self) -> RwLockReadGuard<'static, Config> {
+        get_config()
     }
 
     pub fn repo_db(&self) -> &Arc<Mutex<Connection>> {
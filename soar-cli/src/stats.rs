@@ -0,0 +1,252 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use indicatif::HumanBytes;
+use soar_core::{
+    database::{
+        models::InstalledPackage,
+        packages::{get_installed_packages, Filter, FilterOp, QueryOptions},
+        stats::get_run_stats,
+        sync_state::last_synced,
+    },
+    package::query::PackageQuery,
+    utils::dir_size,
+    SoarResult,
+};
+use tracing::info;
+
+use crate::{
+    color::{Color, ColorExt},
+    state::AppState,
+    update::find_update,
+    utils::format_age,
+};
+
+/// Summarizes installed packages, disk usage, `soar run` cache efficiency,
+/// repository sync times and pending updates.
+pub async fn show_stats() -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let core_db = state.core_db().clone();
+    let repo_db = state.repo_db().clone();
+
+    let mut filters = HashMap::new();
+    filters.insert(
+        "is_installed".to_string(),
+        (FilterOp::Eq, "1".to_string().into()).into(),
+    );
+    let options = QueryOptions {
+        filters,
+        limit: u32::MAX,
+        ..Default::default()
+    };
+    let installed = get_installed_packages(core_db.clone(), options)?.items;
+
+    let mut per_repo: BTreeMap<&str, (usize, u64)> = BTreeMap::new();
+    let mut packages_size = 0;
+    for package in &installed {
+        let size = dir_size(&package.installed_path).unwrap_or(package.size);
+        let entry = per_repo.entry(&package.repo_name).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+        packages_size += size;
+    }
+
+    info!("{}", "Installed packages".color(Color::Label));
+    for (repo_name, (count, size)) in &per_repo {
+        info!(
+            repo_name = %repo_name,
+            count = %count,
+            size = %size,
+            "  {:<20} {:>6} ({})",
+            repo_name,
+            count,
+            HumanBytes(*size)
+        );
+    }
+
+    let cache_size = state
+        .config()
+        .get_cache_path()
+        .ok()
+        .and_then(|path| dir_size(path).ok())
+        .unwrap_or(0);
+    info!(
+        packages_size = %packages_size,
+        cache_size = %cache_size,
+        "{}: {} (packages {}, cache {})",
+        "Disk usage".color(Color::Label),
+        HumanBytes(packages_size + cache_size),
+        HumanBytes(packages_size),
+        HumanBytes(cache_size)
+    );
+
+    let (hits, misses) = get_run_stats(core_db)?;
+    let hit_rate = if hits + misses > 0 {
+        hits as f64 * 100.0 / (hits + misses) as f64
+    } else {
+        0.0
+    };
+    info!(
+        hits = %hits,
+        misses = %misses,
+        hit_rate = %format!("{:.1}", hit_rate),
+        "{}: {} hits, {} misses ({:.1}%)",
+        "Run cache".color(Color::Label),
+        hits,
+        misses,
+        hit_rate
+    );
+
+    info!("{}", "Last sync".color(Color::Label));
+    for repo in state.config().enabled_repositories() {
+        match last_synced(state.core_db().clone(), &repo.name)? {
+            Some(time) => info!(
+                repo_name = %repo.name,
+                last_sync = %time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+                "  {:<20} {}",
+                repo.name,
+                format_age(SystemTime::now().duration_since(time).unwrap_or_default()).color(Color::Info)
+            ),
+            None => info!(
+                repo_name = %repo.name,
+                "  {:<20} {}",
+                repo.name,
+                "never".color(Color::Warn)
+            ),
+        }
+    }
+
+    let mut outdated = Vec::new();
+    for package in &installed {
+        if package.upstream.is_some() {
+            continue;
+        }
+        let filters: HashMap<String, Filter> = [
+            ("r.name", package.repo_name.clone()),
+            ("pkg_name", package.pkg_name.clone()),
+            ("pkg_id", package.pkg_id.clone()),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), (FilterOp::Eq, value.into()).into()))
+        .collect();
+        if let Some(latest) = find_update(repo_db.clone(), filters, package)? {
+            outdated.push((package, latest.version));
+        }
+    }
+
+    info!(
+        outdated = %outdated.len(),
+        "{}: {} of {} packages",
+        "Updates available".color(Color::Label),
+        outdated.len(),
+        installed.len()
+    );
+    for (package, latest) in outdated {
+        info!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            version = %package.version,
+            latest = %latest,
+            "  {}#{} {} -> {}",
+            package.pkg_name,
+            package.pkg_id,
+            package.version,
+            latest.color(Color::Success)
+        );
+    }
+
+    Ok(())
+}
+
+/// Shows the disk usage of installed packages, largest first, along with
+/// the space saved by stripping and compressing their binaries.
+pub async fn show_disk_usage(packages: Option<Vec<String>>) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let core_db = state.core_db().clone();
+
+    let queries = match packages {
+        Some(packages) => packages
+            .iter()
+            .map(|p| Ok(PackageQuery::try_from(p.as_str())?.create_filter()))
+            .collect::<SoarResult<Vec<_>>>()?,
+        None => vec![HashMap::new()],
+    };
+    let mut installed = Vec::new();
+    for mut filters in queries {
+        filters.insert(
+            "is_installed".to_string(),
+            (FilterOp::Eq, "1".to_string().into()).into(),
+        );
+        let options = QueryOptions {
+            filters,
+            limit: u32::MAX,
+            ..Default::default()
+        };
+        installed.extend(get_installed_packages(core_db.clone(), options)?.items);
+    }
+
+    let mut usage: Vec<(InstalledPackage, u64, u64)> = installed
+        .into_iter()
+        .map(|package| {
+            let size = dir_size(&package.installed_path).unwrap_or(package.size);
+            let saved = saved_space(&package);
+            (package, size, saved)
+        })
+        .collect();
+    usage.sort_by_key(|(_, size, _)| std::cmp::Reverse(*size));
+
+    let mut total_size = 0;
+    let mut total_saved = 0;
+    for (package, size, saved) in &usage {
+        total_size += size;
+        total_saved += saved;
+        let name = format!(
+            "{}#{}:{}",
+            package.pkg_name, package.pkg_id, package.repo_name
+        );
+        let savings = if *saved > 0 {
+            format!(" (saved {})", HumanBytes(*saved).color(Color::Success))
+        } else {
+            String::new()
+        };
+        info!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            repo_name = %package.repo_name,
+            size = %size,
+            saved = %saved,
+            "  {}  {}{}",
+            format!("{:>12}", HumanBytes(*size).to_string()).color(Color::Value),
+            name.color(Color::Accent),
+            savings
+        );
+    }
+
+    info!(
+        size = %total_size,
+        saved = %total_saved,
+        "{}: {} in {} packages, {} saved by stripping and compressing",
+        "Total".color(Color::Label),
+        HumanBytes(total_size),
+        usage.len(),
+        HumanBytes(total_saved)
+    );
+
+    Ok(())
+}
+
+/// Bytes saved by stripping or compressing the binary of `package`,
+/// measured against its current size on disk.
+fn saved_space(package: &InstalledPackage) -> u64 {
+    let Some(original_size) = package.original_size else {
+        return 0;
+    };
+    let bin = Path::new(&package.installed_path).join(&package.pkg_name);
+    fs::metadata(bin)
+        .map(|meta| original_size.saturating_sub(meta.len()))
+        .unwrap_or(0)
+}
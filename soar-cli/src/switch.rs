@@ -0,0 +1,69 @@
+use soar_core::{
+    error::SoarError,
+    package::{query::PackageQuery, switch::use_package},
+    SoarResult,
+};
+use tracing::info;
+
+use crate::{
+    color::{Color, ColorExt},
+    i18n::t,
+    state::AppState,
+    utils::find_installed_matching,
+};
+
+/// Gives the install matching `query` the plain bin name of its package,
+/// e.g. one of the versions installed side by side with `node@<version>`.
+pub async fn use_installed(query: &str) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let core_db = state.core_db().clone();
+    let package_query = PackageQuery::try_from(query)?;
+
+    let installed = find_installed_matching(core_db.clone(), &package_query)?;
+
+    let package = match installed.as_slice() {
+        [] => {
+            return Err(SoarError::Custom(t!(
+                "remove-not-installed",
+                package = query
+            )))
+        }
+        [package] => package,
+        _ => {
+            let candidates: Vec<String> = installed
+                .iter()
+                .map(|pkg| format!("{}#{}@{}", pkg.pkg_name, pkg.pkg_id, pkg.version))
+                .collect();
+            return Err(SoarError::InvalidPackageQuery(t!(
+                "installed-ambiguous",
+                package = query,
+                candidates = candidates.join(", ")
+            )));
+        }
+    };
+
+    let switched = use_package(core_db, package)?;
+    let name = format!(
+        "{}#{}@{}",
+        package.pkg_name, package.pkg_id, package.version
+    );
+    info!(
+        pkg_name = %package.pkg_name,
+        pkg_id = %package.pkg_id,
+        version = %package.version,
+        bin_path = %switched.bin_path.display(),
+        "{}",
+        t!(
+            "use-linked",
+            package = name.color(Color::Accent),
+            path = switched.bin_path.display()
+        )
+    );
+    if let Some((displaced, path)) = switched.displaced {
+        info!(
+            "{}",
+            t!("use-displaced", package = displaced, path = path.display())
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::slice;
+
+use soar_core::{config::get_config, SoarResult};
+use tracing::{error, info};
+
+use crate::{
+    i18n::t,
+    state::{is_refresh, AppState},
+};
+
+/// Fetches the metadata of the repository `repo_name`, or of all enabled ones,
+/// regardless of its age. Unchanged metadata is only imported again with
+/// `--refresh`.
+///
+/// A repository failing to sync doesn't stop the others; the first error is
+/// returned once all were tried.
+pub async fn sync_repositories(repo_name: Option<String>) -> SoarResult<()> {
+    let repositories: Vec<_> = {
+        let config = get_config();
+        match repo_name {
+            Some(name) => vec![config.get_repository(&name)?.clone()],
+            None => config.enabled_repositories().cloned().collect(),
+        }
+    };
+
+    let core_db = AppState::create_core_db(&get_config())?;
+    let mut first_error = None;
+    for repo in &repositories {
+        match AppState::sync(core_db.conn.clone(), slice::from_ref(repo), is_refresh()).await {
+            Ok(()) => info!(
+                repo_name = %repo.name,
+                "{}",
+                t!("sync-done", repo = repo.name)
+            ),
+            Err(err) => {
+                error!(
+                    repo_name = %repo.name,
+                    "{}",
+                    t!("sync-failed", repo = repo.name, error = err)
+                );
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use nix::unistd::geteuid;
+use soar_core::{
+    config::{get_config, set_install_root, set_system_mode},
+    error::SoarError,
+    platform::is_writable,
+    utils::setup_required_paths,
+    SoarResult,
+};
+
+/// Programs tried, in order, to rerun soar with root privileges.
+const ELEVATORS: &[&str] = &["sudo", "pkexec"];
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Switches to the system-wide install under the system prefix.
+///
+/// If the current user can't write to the prefix, soar is run again with
+/// `args` through sudo, or pkexec for polkit, and this process exits with
+/// its status.
+pub fn enter_system_mode(args: &[String]) -> SoarResult<()> {
+    set_system_mode(true);
+
+    let prefix = get_config().get_system_prefix()?;
+    let root = prefix.join("lib").join("soar");
+    if is_writable(&root) && is_writable(&prefix.join("bin")) {
+        return setup_required_paths();
+    }
+
+    if geteuid().is_root() {
+        return Err(SoarError::ElevationFailed(format!(
+            "{} is not writable",
+            prefix.display()
+        )));
+    }
+
+    let Some(elevator) = ELEVATORS.iter().find_map(|name| find_in_path(name)) else {
+        return Err(SoarError::ElevationFailed(format!(
+            "{} is not writable and neither sudo nor pkexec is available",
+            prefix.display()
+        )));
+    };
+
+    // logging isn't set up until the installation is entered
+    eprintln!(
+        "{} is not writable, rerunning with {}",
+        prefix.display(),
+        elevator.display()
+    );
+
+    // pkexec resets the environment and requires an absolute path, so the
+    // prefix is passed on explicitly through env
+    let self_exe = env::current_exe()?;
+    let status = Command::new(&elevator)
+        .arg("env")
+        .arg(format!("SOAR_SYSTEM_PREFIX={}", prefix.display()))
+        .arg(self_exe)
+        .args(args.iter().skip(1))
+        .status()
+        .map_err(|err| {
+            SoarError::ElevationFailed(format!("failed to run {}: {}", elevator.display(), err))
+        })?;
+
+    process::exit(status.code().unwrap_or(1));
+}
+
+/// Switches to installing into the root filesystem at `root`, creating it if
+/// it's missing.
+pub fn enter_install_root(root: &Path) -> SoarResult<()> {
+    fs::create_dir_all(root)?;
+    // absolute, so that paths are recorded the same wherever soar ran from
+    set_install_root(root.canonicalize()?);
+    setup_required_paths()
+}
@@ -1,105 +1,291 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
+use rusqlite::Connection;
 use soar_core::{
-    config::get_config,
-    database::packages::{get_installed_packages, get_packages, FilterOp, QueryOptions},
-    package::{install::InstallTarget, query::PackageQuery},
+    database::{
+        models::{InstalledPackage, Package},
+        packages::{get_installed_packages, get_packages, Filter, FilterOp, QueryOptions},
+    },
+    package::{
+        github::{fetch_github_assets, github_package},
+        install::{BinConflictPolicy, InstallTarget},
+        query::{compare_versions, PackageQuery},
+    },
+    platform::processes_using,
+    utils::glob_match,
     SoarResult,
 };
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
-    install::{create_install_context, perform_installation},
+    i18n::t,
+    install::{confirm_trust, create_install_context, perform_installation, print_install_plan},
+    post_hooks::Transaction,
     state::AppState,
 };
 
-pub async fn update_packages(packages: Option<Vec<String>>) -> SoarResult<()> {
+pub async fn update_packages(
+    packages: Option<Vec<String>>,
+    jobs: Option<usize>,
+    exclude: Vec<String>,
+    repo_name: Option<String>,
+    collection: Option<String>,
+    defer: bool,
+    dry_run: bool,
+) -> SoarResult<()> {
     let state = AppState::new().await?;
     let core_db = state.core_db();
+
+    let mut update_targets =
+        resolve_update_targets(&state, packages, exclude, repo_name, collection).await?;
+
+    if defer {
+        update_targets.retain(|target| !target.existing_install.as_ref().is_some_and(is_running));
+    }
+
+    if update_targets.is_empty() {
+        info!("{}", t!("update-nothing"));
+        return Ok(());
+    }
+
+    if dry_run {
+        return print_install_plan(&update_targets, &BinConflictPolicy::Fail, core_db.clone());
+    }
+
+    let mut ctx = create_install_context(update_targets.len(), jobs, None, None, None);
+    ctx.transaction = Transaction::Update;
+
+    confirm_trust(&update_targets, false)?;
+    perform_installation(ctx, update_targets, core_db.clone()).await?;
+
+    Ok(())
+}
+
+/// Finds the updates of the installed packages matching `packages`, or of all
+/// installed packages, honouring exclusions, pins and release channels.
+pub async fn resolve_update_targets(
+    state: &AppState,
+    packages: Option<Vec<String>>,
+    exclude: Vec<String>,
+    repo_name: Option<String>,
+    collection: Option<String>,
+) -> SoarResult<Vec<InstallTarget>> {
+    let core_db = state.core_db();
     let repo_db = state.repo_db();
 
+    // configured exclusions only apply to bulk updates
+    let exclude = if packages.is_none() {
+        let mut patterns = state.config().update.exclude.clone();
+        patterns.extend(exclude);
+        patterns
+    } else {
+        exclude
+    };
+
     let mut update_targets = Vec::new();
 
     if let Some(packages) = packages {
         for package in packages {
             let query = PackageQuery::try_from(package.as_str())?;
-            let filters = query.create_filter();
             let options = QueryOptions {
-                filters: filters.clone(),
+                filters: query.create_filter(),
                 limit: 1,
                 ..Default::default()
             };
             let installed_pkgs = get_installed_packages(core_db.clone(), options)?.items;
 
             for pkg in installed_pkgs {
-                let mut filters = filters.clone();
-                filters.insert(
-                    "version".to_string(),
-                    (FilterOp::Gt, pkg.version.clone().into()).into(),
-                );
-                let options = QueryOptions {
-                    filters,
-                    ..Default::default()
-                };
-                let updated = get_packages(repo_db.clone(), options)?.items;
-                if updated.len() > 0 {
+                if is_excluded(&pkg, &exclude) {
+                    continue;
+                }
+                if pkg.upstream.is_some() {
+                    update_targets.extend(github_update_target(pkg).await);
+                    continue;
+                }
+                let filters = update_filters(&pkg, None);
+                if let Some(package) = find_update(repo_db.clone(), filters, &pkg)? {
                     update_targets.push(InstallTarget {
-                        package: updated.first().unwrap().clone(),
+                        package,
                         existing_install: Some(pkg),
                     })
                 }
             }
         }
     } else {
-        let installed_pkgs =
-            get_installed_packages(core_db.clone(), QueryOptions::default())?.items;
-        for pkg in installed_pkgs {
-            let mut filters = HashMap::new();
-
-            filters.insert(
+        let mut installed_filters = HashMap::new();
+        if let Some(repo_name) = repo_name {
+            installed_filters.insert(
                 "repo_name".to_string(),
-                (FilterOp::Eq, pkg.repo_name.clone().into()).into(),
+                (FilterOp::Eq, repo_name.into()).into(),
             );
-            filters.insert(
-                "pkg_name".to_string(),
-                (FilterOp::Eq, pkg.pkg_name.clone().into()).into(),
-            );
-            filters.insert(
-                "pkg_id".to_string(),
-                (FilterOp::Eq, pkg.pkg_id.clone().into()).into(),
-            );
-            filters.insert(
-                "version".to_string(),
-                (FilterOp::Gt, pkg.version.clone().into()).into(),
-            );
-            let options = QueryOptions {
-                filters,
-                ..Default::default()
-            };
-            let updated = get_packages(repo_db.clone(), options)?.items;
-            if updated.len() > 0 {
+        }
+        let options = QueryOptions {
+            filters: installed_filters,
+            ..Default::default()
+        };
+        let installed_pkgs = get_installed_packages(core_db.clone(), options)?.items;
+        for pkg in installed_pkgs {
+            if is_excluded(&pkg, &exclude) || is_pinned(&pkg) {
+                continue;
+            }
+            if pkg.upstream.is_some() {
+                // packages from GitHub aren't in any collection
+                if collection.is_none() {
+                    update_targets.extend(github_update_target(pkg).await);
+                }
+                continue;
+            }
+            let filters = update_filters(&pkg, collection.as_deref());
+            if let Some(package) = find_update(repo_db.clone(), filters, &pkg)? {
                 update_targets.push(InstallTarget {
-                    package: updated.first().unwrap().clone(),
+                    package,
                     existing_install: Some(pkg),
                 })
             }
         }
     }
 
-    if update_targets.is_empty() {
-        info!("No packages to update.");
-        return Ok(());
+    Ok(update_targets)
+}
+
+/// Filters for the packages in the repository metadata that can update `pkg`.
+fn update_filters(pkg: &InstalledPackage, collection: Option<&str>) -> HashMap<String, Filter> {
+    let mut filters = HashMap::new();
+    // installs don't record their collection, the update has to be in it
+    if let Some(collection) = collection {
+        filters.insert(
+            "collection".to_string(),
+            (FilterOp::Eq, collection.to_string().into()).into(),
+        );
     }
+    filters.insert(
+        "r.name".to_string(),
+        (FilterOp::Eq, pkg.repo_name.clone().into()).into(),
+    );
+    filters.insert(
+        "pkg_name".to_string(),
+        (FilterOp::Eq, pkg.pkg_name.clone().into()).into(),
+    );
+    filters.insert(
+        "pkg_id".to_string(),
+        (FilterOp::Eq, pkg.pkg_id.clone().into()).into(),
+    );
+    filters
+}
 
-    let ctx = create_install_context(
-        update_targets.len(),
-        get_config().parallel_limit.unwrap_or(1) as usize,
-        None,
-        None,
-        None,
+fn is_excluded(pkg: &InstalledPackage, patterns: &[String]) -> bool {
+    let excluded = patterns
+        .iter()
+        .any(|pattern| glob_match(pattern, &pkg.pkg_name) || glob_match(pattern, &pkg.pkg_id));
+    if excluded {
+        info!(
+            "{}",
+            t!(
+                "update-excluded",
+                package = format!("{}#{}", pkg.pkg_name, pkg.pkg_id)
+            )
+        );
+    }
+    excluded
+}
+
+fn is_pinned(pkg: &InstalledPackage) -> bool {
+    if pkg.pinned {
+        info!(
+            "{}",
+            t!(
+                "update-pinned",
+                package = format!("{}#{}", pkg.pkg_name, pkg.pkg_id)
+            )
+        );
+    }
+    pkg.pinned
+}
+
+fn is_running(pkg: &InstalledPackage) -> bool {
+    let pids = processes_using(Path::new(&pkg.installed_path));
+    if pids.is_empty() {
+        return false;
+    }
+    let pids = pids
+        .iter()
+        .map(|pid| pid.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    info!(
+        "{}",
+        t!(
+            "update-deferred",
+            package = format!("{}#{}", pkg.pkg_name, pkg.pkg_id),
+            pids = pids
+        )
     );
+    true
+}
 
-    perform_installation(ctx, update_targets, core_db.clone()).await?;
+/// Finds the newest version of `pkg` matching `filters` that is allowed by
+/// the channel it follows.
+pub fn find_update(
+    repo_db: Arc<Mutex<Connection>>,
+    filters: HashMap<String, Filter>,
+    pkg: &InstalledPackage,
+) -> SoarResult<Option<Package>> {
+    let options = QueryOptions {
+        filters,
+        ..Default::default()
+    };
+    Ok(get_packages(repo_db, options)?
+        .items
+        .into_iter()
+        .filter(|candidate| {
+            compare_versions(&candidate.version, &pkg.version).is_gt()
+                && pkg.channel.accepts(&candidate.version)
+        })
+        .max_by(|a, b| compare_versions(&a.version, &b.version)))
+}
 
-    Ok(())
+/// Checks the upstream GitHub project of `pkg` for a newer release, skipping
+/// the package with a warning if it can't be resolved unambiguously.
+async fn github_update_target(pkg: InstalledPackage) -> Option<InstallTarget> {
+    let project = pkg.upstream.clone()?;
+    let release = match fetch_github_assets(
+        &project,
+        None,
+        pkg.asset_pattern.as_deref(),
+        pkg.channel,
+    )
+    .await
+    {
+        Ok(release) => release,
+        Err(err) => {
+            warn!(
+                "{}",
+                t!("update-check-failed", project = project, error = err)
+            );
+            return None;
+        }
+    };
+    if release.tag == pkg.version {
+        return None;
+    }
+    if release.assets.len() > 1 && pkg.asset_pattern.is_none() {
+        warn!("{}", t!("update-ambiguous-asset", project = project));
+        return None;
+    }
+
+    let package = github_package(&project, &release.tag, &release.assets[0]);
+    Some(InstallTarget {
+        package: Package {
+            repo_name: pkg.repo_name.clone(),
+            pkg: pkg.pkg.clone(),
+            pkg_id: pkg.pkg_id.clone(),
+            pkg_name: pkg.pkg_name.clone(),
+            ..package
+        },
+        existing_install: Some(pkg),
+    })
 }
@@ -1,6 +1,22 @@
-use std::io::Write;
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use soar_core::SoarResult;
+use rusqlite::Connection;
+use soar_core::{
+    database::{
+        models::InstalledPackage,
+        packages::{get_installed_packages, get_similar_package_names, QueryOptions},
+    },
+    lock::InstanceLock,
+    package::query::{version_matches, PackageQuery},
+    SoarResult,
+};
+use tracing::info;
+
+use crate::i18n::t;
 
 pub fn interactive_ask(ques: &str) -> SoarResult<String> {
     print!("{}", ques);
@@ -12,3 +28,79 @@ pub fn interactive_ask(ques: &str) -> SoarResult<String> {
 
     Ok(response.trim().to_owned())
 }
+
+/// Takes the instance lock, waiting for the process holding it if `wait` is
+/// set and failing otherwise.
+pub fn lock_instance(wait: bool) -> SoarResult<InstanceLock> {
+    if !wait {
+        return InstanceLock::acquire_or_fail();
+    }
+    if let Some(lock) = InstanceLock::try_acquire()? {
+        return Ok(lock);
+    }
+
+    match InstanceLock::holder() {
+        Some(pid) => info!("Waiting for another soar process (pid {}) to finish", pid),
+        None => info!("Waiting for another soar process to finish"),
+    }
+    InstanceLock::acquire()
+}
+
+/// Formats how long ago something happened, e.g. `5m ago`.
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// Suggests the package names closest to the one in `query` after it
+/// matched nothing.
+pub fn suggest_similar_packages(repo_db: Arc<Mutex<Connection>>, query: &str) -> SoarResult<()> {
+    let Some(name) = PackageQuery::try_from(query)?.name else {
+        return Ok(());
+    };
+    let suggestions = get_similar_package_names(repo_db, &name, 3)?;
+    if !suggestions.is_empty() {
+        info!(
+            suggestions = ?suggestions,
+            "{}",
+            t!("package-did-you-mean", suggestions = suggestions.join(", "))
+        );
+    }
+    Ok(())
+}
+
+/// Installs matching `query`, its version also matching the versions it's a
+/// prefix of, e.g. `18` for `18.19.0`, unless one matches it exactly.
+pub fn find_installed_matching(
+    core_db: Arc<Mutex<Connection>>,
+    query: &PackageQuery,
+) -> SoarResult<Vec<InstalledPackage>> {
+    let mut filters = query.create_filter();
+    filters.remove("version");
+    let mut installed: Vec<InstalledPackage> = get_installed_packages(
+        core_db,
+        QueryOptions {
+            filters,
+            limit: u32::MAX,
+            ..Default::default()
+        },
+    )?
+    .items
+    .into_iter()
+    .filter(|pkg| pkg.is_installed)
+    .collect();
+
+    if let Some(ref wanted) = query.version {
+        if installed.iter().any(|pkg| pkg.version == *wanted) {
+            installed.retain(|pkg| pkg.version == *wanted);
+        } else {
+            installed.retain(|pkg| version_matches(wanted, &pkg.version));
+        }
+    }
+    Ok(installed)
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::Connection;
+use soar_core::{
+    database::{
+        models::{InstalledPackage, Package},
+        packages::{get_installed_packages, get_packages, FilterOp, QueryOptions},
+    },
+    error::SoarError,
+    package::{
+        install::InstallTarget,
+        query::{compare_versions, PackageQuery},
+    },
+    utils::calculate_checksum,
+    SoarResult,
+};
+use tracing::{info, warn};
+
+use crate::{
+    color::{Color, ColorExt},
+    install::{confirm_trust, create_install_context, perform_installation},
+    state::AppState,
+};
+
+enum VerifyStatus {
+    Ok,
+    Missing,
+    Modified(String),
+}
+
+/// Re-hashes installed packages and compares them against the checksum
+/// recorded at install time.
+///
+/// With `repair`, affected packages are downloaded again. Fails with
+/// [`SoarError::InvalidChecksum`] if any package is left unverified.
+pub async fn verify_packages(packages: Option<Vec<String>>, repair: bool) -> SoarResult<()> {
+    let state = AppState::new_cached().await?;
+    let core_db = state.core_db().clone();
+    let repo_db = state.repo_db().clone();
+
+    let mut installed = Vec::new();
+    let queries = match packages {
+        Some(packages) => packages
+            .iter()
+            .map(|p| Ok(PackageQuery::try_from(p.as_str())?.create_filter()))
+            .collect::<SoarResult<Vec<_>>>()?,
+        None => vec![HashMap::new()],
+    };
+    for mut filters in queries {
+        filters.insert(
+            "is_installed".to_string(),
+            (FilterOp::Eq, "1".to_string().into()).into(),
+        );
+        let options = QueryOptions {
+            filters,
+            limit: u32::MAX,
+            ..Default::default()
+        };
+        installed.extend(get_installed_packages(core_db.clone(), options)?.items);
+    }
+
+    let mut broken: Vec<InstalledPackage> = Vec::new();
+    for package in installed {
+        let status = verify_package(&package);
+        let label = match status {
+            VerifyStatus::Ok => "OK".color(Color::Success),
+            VerifyStatus::Missing => "MISSING".color(Color::Error),
+            VerifyStatus::Modified(_) => "MODIFIED".color(Color::Error),
+        };
+
+        info!(
+            pkg_name = %package.pkg_name,
+            pkg_id = %package.pkg_id,
+            version = %package.version,
+            repo_name = %package.repo_name,
+            expected = %package.checksum,
+            actual = match status {
+                VerifyStatus::Modified(ref checksum) => checksum.as_str(),
+                _ => "",
+            },
+            "[{}] {}#{}-{}:{}",
+            label,
+            package.pkg_name.color(Color::Accent),
+            package.pkg_id.color(Color::Info),
+            package.version.color(Color::Value),
+            package.repo_name
+        );
+
+        if !matches!(status, VerifyStatus::Ok) {
+            broken.push(package);
+        }
+    }
+
+    if broken.is_empty() {
+        return Ok(());
+    }
+
+    if !repair {
+        warn!(
+            "{} package(s) failed verification. Run with --repair to reinstall them.",
+            broken.len()
+        );
+        return Err(SoarError::InvalidChecksum);
+    }
+
+    let mut targets = Vec::new();
+    for package in broken {
+        match find_repair_source(repo_db.clone(), &package)? {
+            Some(source) => {
+                if source.version != package.version {
+                    warn!(
+                        "{} {} is no longer available, repairing with {}",
+                        package.pkg_name,
+                        package.version,
+                        source.version.color(Color::Warn)
+                    );
+                }
+                targets.push(InstallTarget {
+                    package: source,
+                    existing_install: Some(package),
+                });
+            }
+            None => {
+                warn!(
+                    "{}#{} is no longer available in {}, cannot repair",
+                    package.pkg_name, package.pkg_id, package.repo_name
+                );
+            }
+        }
+    }
+
+    let ctx = create_install_context(targets.len(), None, None, None, None);
+    confirm_trust(&targets, false)?;
+    perform_installation(ctx, targets, core_db).await?;
+    Ok(())
+}
+
+fn verify_package(package: &InstalledPackage) -> VerifyStatus {
+    let path = PathBuf::from(&package.installed_path).join(&package.pkg_name);
+    match calculate_checksum(&path) {
+        Ok(checksum) if checksum == package.checksum => VerifyStatus::Ok,
+        Ok(checksum) => VerifyStatus::Modified(checksum),
+        Err(_) => VerifyStatus::Missing,
+    }
+}
+
+/// Finds the repository package to reinstall from, preferring the installed
+/// version and falling back to the newest one.
+fn find_repair_source(
+    repo_db: Arc<Mutex<Connection>>,
+    package: &InstalledPackage,
+) -> SoarResult<Option<Package>> {
+    let mut filters = HashMap::new();
+    filters.insert(
+        "r.name".to_string(),
+        (FilterOp::Eq, package.repo_name.clone().into()).into(),
+    );
+    filters.insert(
+        "pkg_name".to_string(),
+        (FilterOp::Eq, package.pkg_name.clone().into()).into(),
+    );
+    filters.insert(
+        "pkg_id".to_string(),
+        (FilterOp::Eq, package.pkg_id.clone().into()).into(),
+    );
+
+    let mut candidates = get_packages(
+        repo_db,
+        QueryOptions {
+            filters,
+            ..Default::default()
+        },
+    )?
+    .items;
+
+    if let Some(idx) = candidates
+        .iter()
+        .position(|candidate| candidate.version == package.version)
+    {
+        return Ok(Some(candidates.swap_remove(idx)));
+    }
+    Ok(candidates
+        .into_iter()
+        .max_by(|a, b| compare_versions(&a.version, &b.version)))
+}
@@ -0,0 +1,12 @@
+CREATE TABLE history (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  transaction_id INTEGER NOT NULL,
+  kind TEXT NOT NULL,
+  repo_name TEXT NOT NULL,
+  pkg_id TEXT NOT NULL,
+  pkg_name TEXT NOT NULL,
+  version TEXT NOT NULL,
+  error TEXT,
+  date TEXT NOT NULL DEFAULT (datetime())
+);
+CREATE INDEX idx_history_transaction ON history (transaction_id);
@@ -0,0 +1 @@
+ALTER TABLE history ADD COLUMN pending BOOLEAN NOT NULL DEFAULT false;
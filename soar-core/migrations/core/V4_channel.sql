@@ -0,0 +1 @@
+ALTER TABLE packages ADD COLUMN channel TEXT NOT NULL DEFAULT 'stable';
@@ -0,0 +1,5 @@
+CREATE TABLE run_stats (
+  pkg_name TEXT PRIMARY KEY,
+  hits INTEGER NOT NULL DEFAULT 0,
+  misses INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,5 @@
+CREATE TABLE sync_state (
+  repo_name TEXT PRIMARY KEY,
+  etag TEXT,
+  fetched_at INTEGER NOT NULL
+);
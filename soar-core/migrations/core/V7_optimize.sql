@@ -0,0 +1,2 @@
+ALTER TABLE packages ADD COLUMN original_checksum TEXT;
+ALTER TABLE packages ADD COLUMN original_size BIGINT;
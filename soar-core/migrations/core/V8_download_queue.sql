@@ -0,0 +1,8 @@
+CREATE TABLE download_queue (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  url TEXT NOT NULL,
+  output_path TEXT NOT NULL,
+  oci BOOLEAN NOT NULL DEFAULT false,
+  downloaded BIGINT NOT NULL DEFAULT 0,
+  total BIGINT NOT NULL DEFAULT 0
+);
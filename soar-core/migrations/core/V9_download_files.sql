@@ -0,0 +1 @@
+ALTER TABLE download_queue ADD COLUMN oci_files TEXT;
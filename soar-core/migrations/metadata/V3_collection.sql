@@ -0,0 +1,3 @@
+ALTER TABLE packages ADD COLUMN collection TEXT;
+
+CREATE INDEX idx_packages_collection ON packages (collection);
@@ -0,0 +1,7 @@
+CREATE TABLE aliases (
+  package_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  FOREIGN KEY (package_id) REFERENCES packages (id)
+);
+
+CREATE INDEX idx_aliases_name ON aliases (name);
@@ -0,0 +1 @@
+ALTER TABLE packages ADD COLUMN torrent TEXT;
@@ -0,0 +1,23 @@
+CREATE TABLE categories (
+  package_id INTEGER NOT NULL,
+  name TEXT NOT NULL COLLATE NOCASE,
+  FOREIGN KEY (package_id) REFERENCES packages (id)
+);
+
+CREATE INDEX idx_categories_name ON categories (name);
+
+CREATE TABLE tags (
+  package_id INTEGER NOT NULL,
+  name TEXT NOT NULL COLLATE NOCASE,
+  FOREIGN KEY (package_id) REFERENCES packages (id)
+);
+
+CREATE INDEX idx_tags_name ON tags (name);
+
+INSERT INTO categories (package_id, name)
+SELECT p.id, c.value FROM packages p, json_each(p.categories) c
+WHERE json_valid(p.categories) AND c.type = 'text';
+
+INSERT INTO tags (package_id, name)
+SELECT p.id, t.value FROM packages p, json_each(p.tags) t
+WHERE json_valid(p.tags) AND t.type = 'text';
@@ -0,0 +1,469 @@
+//! Verification of release assets against what the release publishes with
+//! them.
+//!
+//! Releases commonly carry checksum files, one per asset (`<asset>.sha256`)
+//! or one for all of them (`SHA256SUMS`, `checksums.txt`), and sometimes
+//! minisign signatures (`<asset>.minisig`). GitHub releases may also have
+//! artifact attestations, sigstore bundles tying the asset to the workflow
+//! that built it, which are checked with the `gh` CLI.
+//!
+//! Checksums only catch corruption, since whoever can replace the asset can
+//! replace its checksum too; signatures and attestations also prove where
+//! the asset comes from.
+
+use std::{
+    env,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use blake2::{Blake2b512, Digest};
+use reqwest::{header::AUTHORIZATION, StatusCode};
+use ring::{
+    digest::{Context, SHA256},
+    signature::{UnparsedPublicKey, ED25519},
+};
+
+use crate::{
+    error::SoarError,
+    http::{client, send_with_retry},
+    SoarResult,
+};
+
+/// Files of a release that an asset can be verified with.
+#[derive(Debug, Clone, Default)]
+pub struct AssetSources {
+    /// GitHub project, as `owner/repo`, the asset was released from.
+    pub github_project: Option<String>,
+    /// URLs of the checksum files that may list the asset.
+    pub checksums: Vec<String>,
+    /// URL of the minisign signature of the asset.
+    pub minisig: Option<String>,
+}
+
+impl AssetSources {
+    /// Picks out the files verifying the asset `name` from the `(name, url)`
+    /// pairs of the assets of its release.
+    pub fn discover<'a>(
+        name: &str,
+        assets: impl IntoIterator<Item = (&'a str, &'a str)>,
+        github_project: Option<&str>,
+    ) -> Self {
+        let mut sources = Self {
+            github_project: github_project.map(String::from),
+            ..Default::default()
+        };
+        for (asset, url) in assets {
+            if asset == format!("{}.minisig", name) {
+                sources.minisig = Some(url.to_string());
+            } else if is_checksum_file(name, asset) {
+                sources.checksums.push(url.to_string());
+            }
+        }
+        sources
+    }
+}
+
+fn is_checksum_file(name: &str, asset: &str) -> bool {
+    if asset == format!("{}.sha256", name) || asset == format!("{}.sha256sum", name) {
+        return true;
+    }
+    let lower = asset.to_lowercase();
+    let stem = lower.strip_suffix(".txt").unwrap_or(&lower);
+    stem.ends_with("sha256sums") || stem.ends_with("checksums")
+}
+
+/// Outcome of one kind of check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// The release doesn't publish anything to check against.
+    Missing,
+    /// Something is published but couldn't be checked, for lack of a key
+    /// or of the `gh` CLI.
+    Unchecked,
+    Passed,
+}
+
+/// What an asset was verified with.
+#[derive(Debug, Clone, Copy)]
+pub struct AssetVerification {
+    pub checksum: Check,
+    pub signature: Check,
+    pub attestation: Check,
+}
+
+impl AssetVerification {
+    /// Whether the origin of the asset is proven, not just its integrity.
+    pub fn is_authenticated(&self) -> bool {
+        self.signature == Check::Passed || self.attestation == Check::Passed
+    }
+}
+
+/// Verifies the asset `name` downloaded to `path` against `sources`,
+/// checking minisign signatures with `minisign_key`.
+///
+/// Anything that is checked and doesn't match is an error.
+pub async fn verify_asset(
+    path: &Path,
+    name: &str,
+    sources: &AssetSources,
+    minisign_key: Option<&str>,
+) -> SoarResult<AssetVerification> {
+    let sha256 = {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || sha256_file(&path))
+            .await
+            .map_err(|err| SoarError::Custom(err.to_string()))??
+    };
+
+    let mut checksum = Check::Missing;
+    for url in &sources.checksums {
+        let Some(listing) = fetch_text(url, None).await? else {
+            continue;
+        };
+        let Some(expected) = find_checksum(&listing, name) else {
+            continue;
+        };
+        if !expected.eq_ignore_ascii_case(&sha256) {
+            return Err(SoarError::InvalidChecksum);
+        }
+        checksum = Check::Passed;
+        break;
+    }
+
+    let signature = match (&sources.minisig, minisign_key) {
+        (None, _) => Check::Missing,
+        (Some(_), None) => Check::Unchecked,
+        (Some(url), Some(key)) => {
+            let signature = fetch_text(url, None)
+                .await?
+                .ok_or_else(|| SoarError::FailedToFetchRemote(url.clone()))?;
+            let path = path.to_path_buf();
+            let key = key.to_string();
+            let verified =
+                tokio::task::spawn_blocking(move || verify_minisign(&path, &key, &signature))
+                    .await
+                    .map_err(|err| SoarError::Custom(err.to_string()))??;
+            if !verified {
+                return Err(SoarError::SignatureVerificationFailed(name.to_string()));
+            }
+            Check::Passed
+        }
+    };
+
+    let attestation = match &sources.github_project {
+        None => Check::Missing,
+        Some(project) => verify_attestation(path, project, &sha256, name).await?,
+    };
+
+    Ok(AssetVerification {
+        checksum,
+        signature,
+        attestation,
+    })
+}
+
+async fn fetch_text(url: &str, token: Option<&str>) -> SoarResult<Option<String>> {
+    let client = client();
+    let resp = send_with_retry(|| {
+        let request = client.get(url);
+        match token {
+            Some(token) => request.header(AUTHORIZATION, format!("Bearer {}", token)),
+            None => request,
+        }
+    })
+    .await?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        return Err(SoarError::FailedToFetchRemote(url.to_string()));
+    }
+    Ok(Some(resp.text().await?))
+}
+
+fn sha256_file(path: &Path) -> SoarResult<String> {
+    let mut file = File::open(path)?;
+    let mut context = Context::new(&SHA256);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        context.update(&buf[..n]);
+    }
+    Ok(context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// SHA-256 of `name` in a checksum listing, either in the `sha256sum`
+/// format, `<hash>  <file>`, or the BSD one, `SHA256 (<file>) = <hash>`.
+/// A listing of a single bare hash is taken to be for `name`.
+fn find_checksum(listing: &str, name: &str) -> Option<String> {
+    let is_sha256 = |hash: &str| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+    let matches = |file: &str| {
+        let file = file.trim_start_matches('*');
+        file.rsplit('/').next() == Some(name)
+    };
+
+    let mut bare = Vec::new();
+    for line in listing.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("SHA256 (") {
+            if let Some((file, hash)) = rest.split_once(") = ") {
+                if matches(file) && is_sha256(hash.trim()) {
+                    return Some(hash.trim().to_string());
+                }
+            }
+            continue;
+        }
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let (Some(hash), file) = (parts.next(), parts.next().map(str::trim)) else {
+            continue;
+        };
+        if !is_sha256(hash) {
+            continue;
+        }
+        match file {
+            Some(file) if !file.is_empty() => {
+                if matches(file) {
+                    return Some(hash.to_string());
+                }
+            }
+            _ => bare.push(hash),
+        }
+    }
+
+    match bare.as_slice() {
+        [hash] => Some(hash.to_string()),
+        _ => None,
+    }
+}
+
+/// Key id and ed25519 key of a minisign public key, given either as the
+/// base64 key or the contents of its `.pub` file.
+fn parse_minisign_key(key: &str) -> Option<([u8; 8], Vec<u8>)> {
+    let line = key
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))?;
+    let key = STANDARD.decode(line).ok()?;
+    if key.len() != 42 || &key[..2] != b"Ed" {
+        return None;
+    }
+    Some((key[2..10].try_into().ok()?, key[10..].to_vec()))
+}
+
+/// Checks the minisign `signature` of the file at `path` with `key`.
+///
+/// Both the signature of the file and the global one, over the signature
+/// and its trusted comment, have to hold.
+fn verify_minisign(path: &Path, key: &str, signature: &str) -> SoarResult<bool> {
+    let invalid = || SoarError::Custom("Invalid minisign key or signature".to_string());
+    let (key_id, key) = parse_minisign_key(key).ok_or_else(invalid)?;
+
+    let mut lines = signature.lines().map(str::trim);
+    let (Some(_), Some(sig), Some(comment), Some(global)) =
+        (lines.next(), lines.next(), lines.next(), lines.next())
+    else {
+        return Err(invalid());
+    };
+    let sig = STANDARD.decode(sig).map_err(|_| invalid())?;
+    let comment = comment
+        .strip_prefix("trusted comment: ")
+        .ok_or_else(invalid)?;
+    let global = STANDARD.decode(global).map_err(|_| invalid())?;
+    if sig.len() != 74 || sig[2..10] != key_id {
+        return Ok(false);
+    }
+
+    let message = match &sig[..2] {
+        // legacy signatures are of the file itself
+        b"Ed" => std::fs::read(path)?,
+        // prehashed ones of its BLAKE2b-512 hash
+        b"ED" => {
+            let mut file = File::open(path)?;
+            let mut hasher = Blake2b512::new();
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let n = file.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+            }
+            hasher.finalize().to_vec()
+        }
+        _ => return Err(invalid()),
+    };
+
+    let public_key = UnparsedPublicKey::new(&ED25519, &key);
+    let mut signed_comment = sig[10..].to_vec();
+    signed_comment.extend_from_slice(comment.as_bytes());
+    Ok(public_key.verify(&message, &sig[10..]).is_ok()
+        && public_key.verify(&signed_comment, &global).is_ok())
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Checks the GitHub artifact attestations of the asset with digest
+/// `sha256` released from `project`.
+///
+/// Whether there are any is looked up with the API, verifying them is left
+/// to `gh attestation verify`.
+async fn verify_attestation(
+    path: &Path,
+    project: &str,
+    sha256: &str,
+    name: &str,
+) -> SoarResult<Check> {
+    let token = env::var("GITHUB_TOKEN").ok();
+    let url = format!(
+        "https://api.github.com/repos/{}/attestations/sha256:{}",
+        project, sha256
+    );
+    let attested = match fetch_text(&url, token.as_deref()).await {
+        Ok(body) => body.is_some_and(|body| body.contains("\"bundle\"")),
+        // e.g. rate limited, which mustn't fail the download
+        Err(_) => return Ok(Check::Unchecked),
+    };
+    if !attested {
+        return Ok(Check::Missing);
+    }
+    let Some(gh) = find_in_path("gh") else {
+        return Ok(Check::Unchecked);
+    };
+
+    let mut command = Command::new(gh);
+    command
+        .args(["attestation", "verify", "--repo", project])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let status = tokio::task::spawn_blocking(move || command.status())
+        .await
+        .map_err(|err| SoarError::Custom(err.to_string()))??;
+    if !status.success() {
+        return Err(SoarError::SignatureVerificationFailed(format!(
+            "{} (attestation)",
+            name
+        )));
+    }
+    Ok(Check::Passed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const HASH_A: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    const HASH_B: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    const KEY: &str = "untrusted comment: minisign public key 0123456789ABCDEF
+RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4
+";
+    const LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQBI0VniavN74v2WOmBOle0LBkIIP1u4niogFn/aG/yO864kCfJAT6XQrVoBAGWraveZZkrf9Qdz/yvMtOZEZTpR85hNi2Vugw=
+trusted comment: timestamp:1700000000\tfile:hello.txt
+uG08vvaBwsNrqfXhEOJ6KRp1gDZlCKqYqD2RS3+6nIMWJqwx6fMK+j28LSTIWUPFAp4HL0gjAVexcfetssAhCg==
+";
+    const PREHASHED_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBI0VniavN7zfAIPaFO7gdIMzS6GG7ICuYHcPOAdvJcrtKKJoNfBzkxqOdx5u4jKXntzjFkdqGUjBjTArGzDpV6l4+IMhs5gs=
+trusted comment: timestamp:1700000000\tfile:hello.txt
+5YaL3cf9QRGiAyHFF2x4xPzfdmMx2jXAl5mxLpFZhtvhkt/XVuKQKdS8Fd9mJhLPW0bSPTLrppzplXu068DaAg==
+";
+
+    fn signed_file(contents: &[u8]) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents).unwrap();
+        file
+    }
+
+    #[test]
+    fn finds_checksums_in_sha256sum_listings() {
+        let listing = format!("{}  tool-x86_64\n{} *dist/tool-aarch64\n", HASH_A, HASH_B);
+        assert_eq!(
+            find_checksum(&listing, "tool-x86_64").as_deref(),
+            Some(HASH_A)
+        );
+        assert_eq!(
+            find_checksum(&listing, "tool-aarch64").as_deref(),
+            Some(HASH_B)
+        );
+        assert_eq!(find_checksum(&listing, "tool-riscv64"), None);
+    }
+
+    #[test]
+    fn finds_checksums_in_bsd_listings() {
+        let listing = format!(
+            "SHA256 (tool-x86_64) = {}\nSHA256 (tool-aarch64) = {}\n",
+            HASH_A, HASH_B
+        );
+        assert_eq!(
+            find_checksum(&listing, "tool-aarch64").as_deref(),
+            Some(HASH_B)
+        );
+        assert_eq!(find_checksum(&listing, "tool"), None);
+    }
+
+    #[test]
+    fn takes_a_single_bare_hash_for_the_asset() {
+        assert_eq!(
+            find_checksum(&format!("{}\n", HASH_A), "tool").as_deref(),
+            Some(HASH_A)
+        );
+        assert_eq!(
+            find_checksum(&format!("{}\n{}\n", HASH_A, HASH_B), "tool"),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_minisign_keys_with_or_without_comment() {
+        let (key_id, key) = parse_minisign_key(KEY).unwrap();
+        assert_eq!(key_id, [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+        assert_eq!(key.len(), 32);
+        assert_eq!(
+            parse_minisign_key(KEY.lines().nth(1).unwrap()),
+            Some((key_id, key))
+        );
+        assert_eq!(parse_minisign_key("untrusted comment: nothing\n"), None);
+        assert_eq!(parse_minisign_key("RWQBI0Vn"), None);
+    }
+
+    #[test]
+    fn verifies_legacy_and_prehashed_signatures() {
+        let file = signed_file(b"hello soar\n");
+        assert!(verify_minisign(file.path(), KEY, LEGACY_SIGNATURE).unwrap());
+        assert!(verify_minisign(file.path(), KEY, PREHASHED_SIGNATURE).unwrap());
+    }
+
+    #[test]
+    fn rejects_tampered_files_and_signatures() {
+        let tampered = signed_file(b"hello soar!\n");
+        assert!(!verify_minisign(tampered.path(), KEY, LEGACY_SIGNATURE).unwrap());
+        assert!(!verify_minisign(tampered.path(), KEY, PREHASHED_SIGNATURE).unwrap());
+
+        let file = signed_file(b"hello soar\n");
+        let comment = PREHASHED_SIGNATURE.replace("timestamp:1700000000", "timestamp:1800000000");
+        assert!(!verify_minisign(file.path(), KEY, &comment).unwrap());
+        let other_key = PREHASHED_SIGNATURE.replace("RUQBI0VniavN7", "RUQBI0VniavN8");
+        assert!(!verify_minisign(file.path(), KEY, &other_key).unwrap());
+    }
+}
@@ -1,19 +1,41 @@
 use std::{
     collections::{HashMap, HashSet},
-    fs,
-    path::PathBuf,
-    sync::{LazyLock, RwLock, RwLockReadGuard},
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock, RwLock, RwLockReadGuard,
+    },
+    time::Duration,
 };
 
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Url,
+};
 use serde::{Deserialize, Serialize};
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, Item, Table};
 
 use crate::{
+    constants::DEFAULT_IPFS_GATEWAYS,
     error::SoarError,
-    utils::{build_path, get_platform, home_config_path, home_data_path},
+    http::header_map,
+    platform::{get_platform, home_config_path, home_data_path},
+    utils::{build_path, write_atomic},
 };
 
 type Result<T> = std::result::Result<T, SoarError>;
 
+const DEFAULT_METADATA_TTL: u64 = 3 * 60 * 60;
+const DEFAULT_CONNECT_TIMEOUT: u64 = 30;
+const DEFAULT_READ_TIMEOUT: u64 = 60;
+
+/// Converts a timeout in seconds to a duration, 0 meaning no limit.
+pub fn timeout_secs(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Profile {
     pub root_path: String,
@@ -51,6 +73,10 @@ impl Profile {
     fn get_repositories_path(&self) -> PathBuf {
         build_path(&self.root_path).unwrap().join("repos")
     }
+
+    fn get_store_path(&self) -> PathBuf {
+        build_path(&self.root_path).unwrap().join("store")
+    }
 }
 
 /// Struct representing a repository configuration.
@@ -61,12 +87,247 @@ pub struct Repository {
 
     /// Metadata URL.
     pub url: String,
+
+    /// Base64 encoded ed25519 public keys allowed to sign the metadata.
+    /// If empty, the key published by the repository is trusted on first use.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pubkeys: Vec<String>,
+
+    /// Whether the repository is used. Disabled repositories keep their
+    /// metadata but aren't synced or searched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+
+    /// Collection of the packages whose metadata doesn't name one, e.g.
+    /// `bin`. Packages without either are grouped by their type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+
+    /// Whether packages from the repository are trusted. Defaults to
+    /// `prompt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust_policy: Option<TrustPolicy>,
+
+    /// HTTP headers sent when fetching the metadata and packages of the
+    /// repository, e.g. a token its CDN requires.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+
+    /// Endpoint serving the install counts of the packages of the
+    /// repository for `soar popular`. Installs are reported to it when
+    /// `telemetry` is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats_url: Option<String>,
+}
+
+/// How far packages from a repository are trusted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustPolicy {
+    /// Trust all packages of the repository.
+    Always,
+    /// Ask before the first install from the repository after it was added.
+    #[default]
+    Prompt,
+    /// Only trust the repository if its metadata is signed, without asking.
+    Never,
+}
+
+impl TrustPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrustPolicy::Always => "always",
+            TrustPolicy::Prompt => "prompt",
+            TrustPolicy::Never => "never",
+        }
+    }
+}
+
+/// How the links to the binaries of installed packages are named.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BinNaming {
+    /// The package name, e.g. `node`.
+    #[default]
+    #[serde(rename = "name")]
+    Name,
+    /// The package name followed by its pkg_id, e.g. `node-nodejs`, so
+    /// that variants of a package don't take each other's name.
+    #[serde(rename = "name-variant")]
+    NameVariant,
+    /// The package name followed by its version, e.g. `node@20.11.0`.
+    #[serde(rename = "name@version")]
+    NameVersion,
+}
+
+impl BinNaming {
+    /// Template the bin name is rendered from, see
+    /// [`render_bin_name`](crate::package::install::render_bin_name).
+    pub fn template(&self) -> &'static str {
+        match self {
+            BinNaming::Name => "{pkg_name}",
+            BinNaming::NameVariant => "{pkg_name}-{pkg_id}",
+            BinNaming::NameVersion => "{pkg_name}@{version}",
+        }
+    }
+
+    /// Template for a version installed next to another version of the
+    /// package, which keeps the name of [`BinNaming::template`].
+    pub fn versioned_template(&self) -> &'static str {
+        match self {
+            BinNaming::Name | BinNaming::NameVersion => "{pkg_name}@{version}",
+            BinNaming::NameVariant => "{pkg_name}-{pkg_id}@{version}",
+        }
+    }
 }
 
 impl Repository {
     pub fn get_path(&self) -> Result<PathBuf> {
         Ok(get_config().get_repositories_path()?.join(&self.name))
     }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Headers to send with the requests for the repository.
+    pub fn header_map(&self) -> HeaderMap {
+        header_map(&self.headers).unwrap_or_default()
+    }
+}
+
+/// Settings for `soar update`.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct UpdateConfig {
+    /// Glob patterns of packages skipped by bulk updates.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Fetch only the changed blocks of a package when its repository
+    /// publishes a `.zsync` file next to it. Enabled by default.
+    pub delta: Option<bool>,
+}
+
+/// Post-install processing of ELF binaries to save disk space. Both steps
+/// are off by default and skipped if the tool isn't installed.
+///
+/// ```toml
+/// [optimize]
+/// strip = true
+///
+/// [optimize.packages.ffmpeg]
+/// upx = true
+/// ```
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct OptimizeConfig {
+    /// Remove symbols and debug info with `strip`
+    pub strip: Option<bool>,
+
+    /// Compress with `upx`
+    pub upx: Option<bool>,
+
+    /// Overrides keyed by package name or ID
+    #[serde(default)]
+    pub packages: HashMap<String, OptimizeOverride>,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct OptimizeOverride {
+    pub strip: Option<bool>,
+    pub upx: Option<bool>,
+}
+
+impl OptimizeConfig {
+    /// Whether to strip and whether to compress the package, in that order.
+    /// Overrides for the package name take precedence over those for its ID.
+    pub fn for_package(&self, pkg_name: &str, pkg_id: &str) -> (bool, bool) {
+        let overrides = [pkg_name, pkg_id].map(|key| self.packages.get(key));
+        let pick = |get: fn(&OptimizeOverride) -> Option<bool>, default: Option<bool>| {
+            overrides
+                .iter()
+                .flatten()
+                .find_map(|o| get(o))
+                .or(default)
+                .unwrap_or(false)
+        };
+        (pick(|o| o.strip, self.strip), pick(|o| o.upx, self.upx))
+    }
+}
+
+/// Commands run after transactions, each through `sh -c`. The affected
+/// packages are passed space separated in `SOAR_PACKAGES`.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct HooksConfig {
+    /// Run after packages are installed
+    #[serde(default)]
+    pub post_install: Vec<String>,
+
+    /// Run after packages are updated
+    #[serde(default)]
+    pub post_update: Vec<String>,
+
+    /// Run after packages are removed
+    #[serde(default)]
+    pub post_remove: Vec<String>,
+}
+
+/// External program that downloads the URLs matching `pattern` instead of
+/// the built-in client.
+///
+/// ```toml
+/// [[downloaders]]
+/// pattern = "^https://cdn\\.example\\.com/"
+/// command = ["curl", "-fsSL", "-o", "{output}", "{url}"]
+/// ```
+#[derive(Clone, Deserialize, Serialize)]
+pub struct DownloaderConfig {
+    /// Regular expression matched against the URL
+    pub pattern: String,
+
+    /// Program and its arguments. `{url}` is replaced by the URL and
+    /// `{output}` by the file to write to.
+    pub command: Vec<String>,
+}
+
+/// Self-hosted GitLab installation releases can be downloaded from.
+///
+/// ```toml
+/// [[gitlab_instances]]
+/// url = "https://gitlab.example.com"
+/// token = "glpat-..."
+/// ```
+#[derive(Clone, Deserialize, Serialize)]
+pub struct GitlabInstance {
+    /// Base URL of the instance
+    pub url: String,
+
+    /// Access token for the API, e.g. to see private projects
+    pub token: Option<String>,
+}
+
+/// Colors of the CLI output. Each accepts a color name (e.g. `red`,
+/// `light_blue`), a 256-color index or a `#rrggbb` hex code.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ThemeConfig {
+    /// Successful results and newer versions
+    pub success: Option<String>,
+
+    /// Warnings
+    pub warn: Option<String>,
+
+    /// Errors and failed checks
+    pub error: Option<String>,
+
+    /// Package names, paths and URLs
+    pub accent: Option<String>,
+
+    /// Package IDs and repository names
+    pub info: Option<String>,
+
+    /// Versions, sizes and checksums
+    pub value: Option<String>,
+
+    /// Field labels and headings
+    pub label: Option<String>,
 }
 
 /// Application's configuration
@@ -98,6 +359,107 @@ pub struct Config {
     #[serde(skip_serializing)]
     pub search_limit: Option<usize>,
 
+    /// Number of times to retry failed network operations
+    #[serde(skip_serializing)]
+    pub retries: Option<u32>,
+
+    /// Initial delay in milliseconds between retries, doubled on each attempt
+    #[serde(skip_serializing)]
+    pub retry_backoff: Option<u64>,
+
+    /// User-Agent sent with HTTP requests instead of `soar/<version>`
+    #[serde(skip_serializing)]
+    pub user_agent: Option<String>,
+
+    /// HTTP headers sent with every request
+    #[serde(default, skip_serializing)]
+    pub headers: HashMap<String, String>,
+
+    /// Seconds to wait for a connection to be established, 0 for no limit
+    #[serde(skip_serializing)]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds to wait for a server to send more data before giving up on
+    /// it, 0 for no limit
+    #[serde(skip_serializing)]
+    pub read_timeout: Option<u64>,
+
+    /// Seconds a single request or download may take in total, 0 for no
+    /// limit
+    #[serde(skip_serializing)]
+    pub download_timeout: Option<u64>,
+
+    /// Seconds cached repository metadata is used for before it's synced
+    /// again. Use `--refresh` or `soar sync` to sync earlier.
+    #[serde(skip_serializing)]
+    pub metadata_ttl: Option<u64>,
+
+    /// Update settings
+    #[serde(default, skip_serializing)]
+    pub update: UpdateConfig,
+
+    /// Output colors
+    #[serde(default, skip_serializing)]
+    pub theme: ThemeConfig,
+
+    /// Stripping and compressing of installed binaries
+    #[serde(default, skip_serializing)]
+    pub optimize: OptimizeConfig,
+
+    /// Commands run after installs, updates and removals, e.g. to rehash
+    /// the shell or regenerate completions
+    #[serde(default, skip_serializing)]
+    pub hooks: HooksConfig,
+
+    /// Self-hosted GitLab instances. Links to projects on them are
+    /// downloaded from their releases like GitLab projects.
+    #[serde(default, skip_serializing)]
+    pub gitlab_instances: Vec<GitlabInstance>,
+
+    /// External programs used for downloads, the first one whose pattern
+    /// matches the URL is used
+    #[serde(default, skip_serializing)]
+    pub downloaders: Vec<DownloaderConfig>,
+
+    /// Collection preferred when a package exists in several, instead of
+    /// asking which one to install
+    #[serde(skip_serializing)]
+    pub default_collection: Option<String>,
+
+    /// Prefix of system-wide installs made with `--system`. Binaries are
+    /// linked into `<prefix>/bin` and everything else lives in
+    /// `<prefix>/lib/soar`. Overridden by `SOAR_SYSTEM_PREFIX`.
+    #[serde(skip_serializing)]
+    pub system_prefix: Option<String>,
+
+    /// Gateways `ipfs://CID` download URLs are fetched through, tried in
+    /// order until one of them has the content
+    #[serde(skip_serializing)]
+    pub ipfs_gateways: Option<Vec<String>>,
+
+    /// Keep the files packages are installed from in the cache, so that
+    /// reinstalling them works offline. Manage them with `soar cache`.
+    #[serde(skip_serializing)]
+    pub keep_downloads: Option<bool>,
+
+    /// Directory the users of the host share the files packages are
+    /// installed from in, e.g. `/var/cache/soar`, instead of each keeping
+    /// their own. Packages are always kept there when set, while installs
+    /// stay per user. Overridden by `SOAR_SHARED_CACHE`.
+    #[serde(skip_serializing)]
+    pub shared_cache: Option<String>,
+
+    /// Report installs to the `stats_url` of their repository, as the
+    /// package and version only. Off unless enabled, and always off when
+    /// `DO_NOT_TRACK` is set.
+    #[serde(skip_serializing)]
+    pub telemetry: Option<bool>,
+
+    /// How the links to the binaries of packages are named: `name`,
+    /// `name-variant` or `name@version`. Defaults to `name`.
+    #[serde(skip_serializing)]
+    pub bin_naming: Option<BinNaming>,
+
     /// Default profile to use
     pub default_profile: String,
 }
@@ -106,9 +468,25 @@ pub fn init() {
     let _ = &*CONFIG;
 }
 
+/// Path of the configuration file.
+pub fn config_path() -> PathBuf {
+    PathBuf::from(home_config_path())
+        .join("soar")
+        .join("config.toml")
+}
+
+/// Loads the configuration file again, e.g. after it was edited.
+pub fn reload_config() -> Result<()> {
+    let config = Config::new()?;
+    *CONFIG.write().unwrap() = config;
+    Ok(())
+}
+
 pub static CONFIG: LazyLock<RwLock<Config>> =
     LazyLock::new(|| RwLock::new(Config::new().expect("Failed to initialize config")));
 pub static CURRENT_PROFILE: LazyLock<RwLock<Option<String>>> = LazyLock::new(|| RwLock::new(None));
+static SYSTEM_MODE: AtomicBool = AtomicBool::new(false);
+static INSTALL_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
 
 pub fn get_config() -> RwLockReadGuard<'static, Config> {
     CONFIG.read().unwrap()
@@ -132,15 +510,62 @@ pub fn set_current_profile(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Switches all paths to the system-wide install under the system prefix,
+/// which has its own packages, bin directory, repositories and core database.
+pub fn set_system_mode(enabled: bool) {
+    SYSTEM_MODE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_system_mode() -> bool {
+    SYSTEM_MODE.load(Ordering::Relaxed)
+}
+
+/// Installs into the root filesystem at `root`, e.g. of a container image,
+/// laid out like the system-wide install under the system prefix inside it.
+/// Repository metadata and caches stay with the current user.
+pub fn set_install_root(root: PathBuf) {
+    *INSTALL_ROOT.write().unwrap() = Some(root);
+    set_system_mode(true);
+}
+
+pub fn get_install_root() -> Option<PathBuf> {
+    INSTALL_ROOT.read().unwrap().clone()
+}
+
+/// `path` as seen from inside the install root, which is how paths are
+/// recorded in its database. Unchanged without an install root.
+///
+/// Fails if the path isn't valid UTF-8, as it couldn't be recorded as is.
+pub fn path_in_root(path: &Path) -> Result<String> {
+    let path = match get_install_root() {
+        Some(root) => match path.strip_prefix(&root) {
+            Ok(relative) => Path::new("/").join(relative),
+            Err(_) => path.to_path_buf(),
+        },
+        None => path.to_path_buf(),
+    };
+    path.into_os_string()
+        .into_string()
+        .map_err(|path| SoarError::NonUtf8Path(path.to_string_lossy().into_owned()))
+}
+
+/// The path recorded as `path` inside the install root, as seen from here.
+/// Unchanged without an install root.
+pub fn path_from_root(path: &str) -> String {
+    match get_install_root() {
+        Some(root) => root
+            .join(path.trim_start_matches('/'))
+            .to_string_lossy()
+            .into_owned(),
+        None => path.to_string(),
+    }
+}
+
 impl Config {
     /// Creates a new configuration by loading it from the configuration file.
     /// If the configuration file is not found, it uses the default configuration.
     pub fn new() -> Result<Self> {
-        let home_config = home_config_path();
-        let pkg_config = PathBuf::from(home_config).join("soar");
-        let config_path = pkg_config.join("config.toml");
-
-        let mut config = match fs::read_to_string(&config_path) {
+        let mut config = match fs::read_to_string(config_path()) {
             Ok(content) => match toml::from_str(&content) {
                 Ok(c) => Ok(c),
                 Err(_) => Err(SoarError::InvalidConfig),
@@ -159,13 +584,35 @@ impl Config {
 
         let mut seen = HashSet::new();
         for repo in &config.repositories {
-            if repo.name == "local" {
+            if !is_valid_repository_name(&repo.name)
+                || !seen.insert(&repo.name)
+                || header_map(&repo.headers).is_err()
+            {
+                return Err(SoarError::InvalidConfig);
+            }
+        }
+        for instance in &config.gitlab_instances {
+            let invalid_token = instance
+                .token
+                .as_deref()
+                .is_some_and(|token| HeaderValue::from_str(&format!("Bearer {}", token)).is_err());
+            if Url::parse(&instance.url).is_err() || invalid_token {
                 return Err(SoarError::InvalidConfig);
             }
-            if !seen.insert(&repo.name) {
+        }
+        for downloader in &config.downloaders {
+            if Regex::new(&downloader.pattern).is_err() || downloader.command.is_empty() {
                 return Err(SoarError::InvalidConfig);
             }
         }
+        if header_map(&config.headers).is_err()
+            || config
+                .user_agent
+                .as_deref()
+                .is_some_and(|agent| HeaderValue::from_str(agent).is_err())
+        {
+            return Err(SoarError::InvalidConfig);
+        }
 
         Ok(config)
     }
@@ -180,18 +627,85 @@ impl Config {
         self.profile.get(name).ok_or(SoarError::InvalidConfig)
     }
 
+    /// Repositories that aren't disabled.
+    pub fn enabled_repositories(&self) -> impl Iterator<Item = &Repository> {
+        self.repositories.iter().filter(|repo| repo.is_enabled())
+    }
+
+    pub fn get_repository(&self, name: &str) -> Result<&Repository> {
+        self.repositories
+            .iter()
+            .find(|repo| repo.name == name)
+            .ok_or_else(|| SoarError::RepositoryNotFound(name.to_string()))
+    }
+
+    /// Age after which cached repository metadata is synced again.
+    pub fn metadata_ttl(&self) -> Duration {
+        Duration::from_secs(self.metadata_ttl.unwrap_or(DEFAULT_METADATA_TTL))
+    }
+
+    /// Time to wait for a connection, `None` if unlimited.
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        timeout_secs(self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT))
+    }
+
+    /// Time to wait for more data from a server, `None` if unlimited.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        timeout_secs(self.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT))
+    }
+
+    /// Time a single request or download may take, `None` if unlimited.
+    pub fn download_timeout(&self) -> Option<Duration> {
+        timeout_secs(self.download_timeout.unwrap_or(0))
+    }
+
+    /// Whether installs are reported to the repositories.
+    pub fn telemetry_enabled(&self) -> bool {
+        let do_not_track = env::var("DO_NOT_TRACK").is_ok_and(|value| !value.is_empty());
+        self.telemetry.unwrap_or(false) && !do_not_track
+    }
+
+    /// Directory of the cache shared between users, if there is one.
+    pub fn get_shared_cache_path(&self) -> Result<Option<PathBuf>> {
+        match env::var("SOAR_SHARED_CACHE") {
+            Ok(path) if !path.is_empty() => build_path(&path).map(Some),
+            _ => self.shared_cache.as_deref().map(build_path).transpose(),
+        }
+    }
+
+    pub fn get_system_prefix(&self) -> Result<PathBuf> {
+        let prefix = match env::var("SOAR_SYSTEM_PREFIX") {
+            Ok(prefix) if !prefix.is_empty() => build_path(&prefix)?,
+            _ => build_path(self.system_prefix.as_deref().unwrap_or("/usr/local"))?,
+        };
+        Ok(match get_install_root() {
+            Some(root) => root.join(prefix.strip_prefix("/").unwrap_or(&prefix)),
+            None => prefix,
+        })
+    }
+
+    fn get_system_root(&self) -> Result<PathBuf> {
+        Ok(self.get_system_prefix()?.join("lib").join("soar"))
+    }
+
     pub fn get_root_path(&self) -> Result<PathBuf> {
-        Ok(build_path(
-            &self.get_profile(&get_current_profile())?.root_path,
-        )?)
+        if is_system_mode() {
+            return self.get_system_root();
+        }
+        build_path(&self.get_profile(&get_current_profile())?.root_path)
     }
 
     pub fn get_bin_path(&self) -> Result<PathBuf> {
+        if is_system_mode() {
+            return Ok(self.get_system_prefix()?.join("bin"));
+        }
         Ok(self.default_profile()?.get_bin_path())
     }
 
     pub fn get_db_path(&self) -> Result<PathBuf> {
-        if let Some(soar_db) = &self.db_path {
+        if is_system_mode() {
+            Ok(self.get_system_root()?.join("db"))
+        } else if let Some(soar_db) = &self.db_path {
             build_path(soar_db)
         } else {
             Ok(self.default_profile()?.get_db_path())
@@ -199,18 +713,46 @@ impl Config {
     }
 
     pub fn get_packages_path(&self) -> Result<PathBuf> {
+        if is_system_mode() {
+            return Ok(self.get_system_root()?.join("packages"));
+        }
         Ok(self
             .get_profile(&get_current_profile())?
             .get_packages_path())
     }
 
     pub fn get_cache_path(&self) -> Result<PathBuf> {
+        // kept out of the install root, which only gets the installs
+        if is_system_mode() && get_install_root().is_none() {
+            return Ok(self.get_system_root()?.join("cache"));
+        }
         Ok(self.get_profile(&get_current_profile())?.get_cache_path())
     }
 
     pub fn get_repositories_path(&self) -> Result<PathBuf> {
+        if is_system_mode() && get_install_root().is_none() {
+            return Ok(self.get_system_root()?.join("repos"));
+        }
         Ok(self.default_profile()?.get_repositories_path())
     }
+
+    /// Directory desktop files and icons are integrated into: the data
+    /// directory of the user, or `share` under the system prefix when
+    /// installing into a root.
+    pub fn get_share_path(&self) -> Result<PathBuf> {
+        if get_install_root().is_some() {
+            return Ok(self.get_system_prefix()?.join("share"));
+        }
+        Ok(PathBuf::from(home_data_path()))
+    }
+
+    /// Content-addressed store shared by the installs of all profiles.
+    pub fn get_store_path(&self) -> Result<PathBuf> {
+        if is_system_mode() {
+            return Ok(self.get_system_root()?.join("store"));
+        }
+        Ok(self.default_profile()?.get_store_path())
+    }
 }
 
 impl Default for Config {
@@ -233,30 +775,216 @@ impl Default for Config {
             repositories: vec![Repository {
                 name: "bincache".to_owned(),
                 url: format!("https://raw.githubusercontent.com/pkgforge/metadata/refs/heads/main/bincache/data/{}.json", get_platform()),
+                pubkeys: Vec::new(),
+                enabled: None,
+                collection: Some("bin".to_owned()),
+                trust_policy: None,
+                headers: HashMap::new(),
+                stats_url: None,
             }, Repository {
                 name: "pkgcache".to_owned(),
                 url: format!("https://raw.githubusercontent.com/pkgforge/metadata/refs/heads/main/pkgcache/data/{}.json", get_platform()),
+                pubkeys: Vec::new(),
+                enabled: None,
+                collection: Some("pkg".to_owned()),
+                trust_policy: None,
+                headers: HashMap::new(),
+                stats_url: None,
             }],
             parallel: Some(true),
             parallel_limit: Some(4),
             search_limit: Some(20),
+            user_agent: None,
+            headers: HashMap::new(),
+            retries: Some(3),
+            retry_backoff: Some(500),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            read_timeout: Some(DEFAULT_READ_TIMEOUT),
+            download_timeout: Some(0),
+            metadata_ttl: Some(DEFAULT_METADATA_TTL),
+            update: UpdateConfig::default(),
+            theme: ThemeConfig::default(),
+            optimize: OptimizeConfig::default(),
+            hooks: HooksConfig::default(),
+            gitlab_instances: Vec::new(),
+            downloaders: Vec::new(),
+            default_collection: None,
+            system_prefix: Some("/usr/local".to_string()),
+            ipfs_gateways: Some(DEFAULT_IPFS_GATEWAYS.map(String::from).to_vec()),
+            keep_downloads: None,
+            shared_cache: None,
+            telemetry: None,
+            bin_naming: None,
         }
     }
 }
 
 pub fn generate_default_config() -> Result<()> {
-    let home_config = home_config_path();
-    let config_path = PathBuf::from(home_config).join("soar").join("config.toml");
+    generate_config(None)
+}
+
+/// Writes the default configuration, keeping only the default repositories
+/// named in `repositories` if given.
+pub fn generate_config(repositories: Option<&[String]>) -> Result<()> {
+    let config_path = config_path();
 
     if config_path.exists() {
         return Err(SoarError::ConfigAlreadyExists);
     }
 
+    let mut def_config = Config::default();
+    if let Some(names) = repositories {
+        if let Some(name) = names.iter().find(|name| {
+            !def_config
+                .repositories
+                .iter()
+                .any(|repo| &repo.name == *name)
+        }) {
+            return Err(SoarError::RepositoryNotFound(name.clone()));
+        }
+        def_config
+            .repositories
+            .retain(|repo| names.contains(&repo.name));
+    }
+
     fs::create_dir_all(config_path.parent().unwrap())?;
 
-    let def_config = Config::default();
     let serialized = toml::to_string_pretty(&def_config)?;
     fs::write(&config_path, &serialized)?;
 
     Ok(())
 }
+
+/// Whether `name` can be used for a repository. Names are used as directory
+/// names, and `local` is reserved for packages installed from files.
+pub fn is_valid_repository_name(name: &str) -> bool {
+    name != "local"
+        && !name.starts_with('.')
+        && !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Applies `edit` to the repositories in the configuration file, writing the
+/// default configuration first if there is none, and loads the result.
+///
+/// The file is edited in place so comments and formatting are kept.
+fn edit_repositories<F>(edit: F) -> Result<()>
+where
+    F: FnOnce(&mut ArrayOfTables) -> Result<()>,
+{
+    let path = config_path();
+    if !path.exists() {
+        generate_default_config()?;
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let mut doc: DocumentMut = content.parse().map_err(|_| SoarError::InvalidConfig)?;
+    let item = doc
+        .entry("repositories")
+        .or_insert(Item::ArrayOfTables(ArrayOfTables::new()));
+    // repositories may also be written as an inline array of tables
+    if !item.is_array_of_tables() {
+        let repositories = std::mem::take(item)
+            .into_array_of_tables()
+            .map_err(|_| SoarError::InvalidConfig)?;
+        *item = Item::ArrayOfTables(repositories);
+    }
+    let repositories = item
+        .as_array_of_tables_mut()
+        .ok_or(SoarError::InvalidConfig)?;
+
+    edit(repositories)?;
+
+    write_atomic(&path, doc.to_string())?;
+    if let Err(err) = reload_config() {
+        write_atomic(&path, content)?;
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn find_repository(repositories: &ArrayOfTables, name: &str) -> Option<usize> {
+    repositories
+        .iter()
+        .position(|table| table.get("name").and_then(|name| name.as_str()) == Some(name))
+}
+
+/// Adds `repo` to the configuration file.
+pub fn add_repository(repo: &Repository) -> Result<()> {
+    if !is_valid_repository_name(&repo.name) {
+        return Err(SoarError::InvalidRepositoryName(repo.name.clone()));
+    }
+
+    edit_repositories(|repositories| {
+        if find_repository(repositories, &repo.name).is_some() {
+            return Err(SoarError::Conflict(format!(
+                "Repository {} already exists",
+                repo.name
+            )));
+        }
+
+        let mut table = Table::new();
+        table.insert("name", value(&repo.name));
+        table.insert("url", value(&repo.url));
+        if !repo.pubkeys.is_empty() {
+            table.insert("pubkeys", value(Array::from_iter(&repo.pubkeys)));
+        }
+        if let Some(enabled) = repo.enabled {
+            table.insert("enabled", value(enabled));
+        }
+        if let Some(ref collection) = repo.collection {
+            table.insert("collection", value(collection));
+        }
+        if let Some(trust_policy) = repo.trust_policy {
+            table.insert("trust_policy", value(trust_policy.as_str()));
+        }
+        repositories.push(table);
+        Ok(())
+    })
+}
+
+/// Removes the repository `name` from the configuration file.
+pub fn remove_repository(name: &str) -> Result<()> {
+    edit_repositories(|repositories| {
+        let idx = find_repository(repositories, name)
+            .ok_or_else(|| SoarError::RepositoryNotFound(name.to_string()))?;
+        repositories.remove(idx);
+        Ok(())
+    })
+}
+
+/// Enables or disables the repository `name` in the configuration file.
+pub fn set_repository_enabled(name: &str, enabled: bool) -> Result<()> {
+    edit_repositories(|repositories| {
+        let idx = find_repository(repositories, name)
+            .ok_or_else(|| SoarError::RepositoryNotFound(name.to_string()))?;
+        let table = repositories.get_mut(idx).unwrap();
+        if enabled {
+            table.remove("enabled");
+        } else {
+            table.insert("enabled", value(false));
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    use super::*;
+
+    #[test]
+    fn refuses_to_record_non_utf8_paths() {
+        assert_eq!(
+            path_in_root(Path::new("/opt/soar/bin/foo")).unwrap(),
+            "/opt/soar/bin/foo"
+        );
+        assert!(matches!(
+            path_in_root(Path::new(OsStr::from_bytes(b"/opt/caf\xe9"))),
+            Err(SoarError::NonUtf8Path(_))
+        ));
+    }
+}
@@ -8,6 +8,13 @@ pub const PNG_MAGIC_BYTES: [u8; 8] = [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a,
 pub const SVG_MAGIC_BYTES: [u8; 4] = [0x3c, 0x73, 0x76, 0x67];
 pub const XML_MAGIC_BYTES: [u8; 5] = [0x3c, 0x3f, 0x78, 0x6d, 0x6c];
 
+/// Gateways `ipfs://` URLs are fetched through if none are configured.
+pub const DEFAULT_IPFS_GATEWAYS: [&str; 3] = [
+    "https://ipfs.io",
+    "https://dweb.link",
+    "https://gateway.pinata.cloud",
+];
+
 pub const CAP_SYS_ADMIN: i32 = 21;
 pub const CAP_MKNOD: i32 = 27;
 
@@ -3,14 +3,47 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use rusqlite::Connection;
+use regex::Regex;
+use rusqlite::{functions::FunctionFlags, Connection};
 
-use crate::error::SoarError;
+use crate::{error::SoarError, package::query::compare_versions};
 
 use super::{models::RemotePackage, repository::PackageRepository, statements::DbStatements};
 
 type Result<T> = std::result::Result<T, SoarError>;
 
+/// Adds the `REGEXP` operator, which SQLite leaves to the application, for
+/// the `~` of filter expressions.
+fn register_regexp(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            // compiled once per statement
+            let regex = ctx.get_or_create_aux(
+                0,
+                |pattern| -> std::result::Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                    Ok(Regex::new(pattern.as_str()?)?)
+                },
+            )?;
+            let text = ctx
+                .get_raw(1)
+                .as_str()
+                .map_err(|err| rusqlite::Error::UserFunctionError(err.into()))?;
+            Ok(regex.is_match(text))
+        },
+    )?;
+    Ok(())
+}
+
+/// Adds the `version` collation, which orders versions like
+/// [`compare_versions`], so `1.10` sorts after `1.9`.
+fn register_version_collation(conn: &Connection) -> Result<()> {
+    conn.create_collation("version", compare_versions)?;
+    Ok(())
+}
+
 pub struct Database {
     pub conn: Arc<Mutex<Connection>>,
 }
@@ -19,12 +52,19 @@ impl Database {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
         let conn = Connection::open(path)?;
+        register_regexp(&conn)?;
+        register_version_collation(&conn)?;
         let conn = Arc::new(Mutex::new(conn));
         Ok(Database { conn })
     }
 
     pub fn new_multi<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
-        let conn = Connection::open(&paths[0])?;
+        let Some(first) = paths.first() else {
+            return Err(SoarError::Custom("No repositories enabled".into()));
+        };
+        let conn = Connection::open(first)?;
+        register_regexp(&conn)?;
+        register_version_collation(&conn)?;
         conn.execute("PRAGMA case_sensitive_like = ON;", [])?;
 
         for (idx, path) in paths.iter().enumerate().skip(1) {
@@ -55,7 +95,7 @@ impl Database {
         {
             let statements = DbStatements::new(&tx)?;
             let mut repo = PackageRepository::new(&tx, statements, repo_name);
-            repo.import_packages(&metadata, etag)?;
+            repo.import_packages(metadata, etag)?;
         }
         tx.commit()?;
         Ok(())
@@ -0,0 +1,96 @@
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection};
+
+use crate::{error::SoarError, SoarResult};
+
+/// A `soar dl` download that hasn't completed yet.
+#[derive(Debug, Clone)]
+pub struct QueuedDownload {
+    pub id: i64,
+    /// URL as given, e.g. `ipfs://` URLs aren't resolved to a gateway.
+    pub url: String,
+    /// Absolute output path. A trailing `/` names the directory the file is
+    /// saved to under its name in the URL.
+    pub output_path: String,
+    /// Whether `url` is an OCI reference rather than a direct link.
+    pub oci: bool,
+    /// Globs selecting the files of the OCI artifact to download, all of
+    /// them if empty.
+    pub oci_files: Vec<String>,
+    /// Bytes downloaded when progress was last recorded.
+    pub downloaded: u64,
+    /// Total size, or 0 if unknown.
+    pub total: u64,
+}
+
+/// Adds a download to the queue, returning its ID.
+pub fn queue_download(
+    db: Arc<Mutex<Connection>>,
+    url: &str,
+    output_path: &str,
+    oci: bool,
+    oci_files: &[String],
+) -> SoarResult<i64> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let oci_files = (!oci_files.is_empty()).then(|| oci_files.join("\n"));
+    conn.execute(
+        "INSERT INTO download_queue (url, output_path, oci, oci_files) VALUES (?1, ?2, ?3, ?4)",
+        params![url, output_path, oci, oci_files],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Records the progress of the queued download `id`.
+pub fn record_download_progress(
+    db: Arc<Mutex<Connection>>,
+    id: i64,
+    downloaded: u64,
+    total: u64,
+) -> SoarResult<()> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    conn.execute(
+        "UPDATE download_queue SET downloaded = ?1, total = ?2 WHERE id = ?3",
+        params![downloaded, total, id],
+    )?;
+    Ok(())
+}
+
+/// Removes the download `id` from the queue once it has completed.
+pub fn finish_download(db: Arc<Mutex<Connection>>, id: i64) -> SoarResult<()> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    conn.execute("DELETE FROM download_queue WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Returns the queued downloads in the order they were added.
+pub fn get_queued_downloads(db: Arc<Mutex<Connection>>) -> SoarResult<Vec<QueuedDownload>> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, url, output_path, oci, oci_files, downloaded, total FROM download_queue
+        ORDER BY id",
+    )?;
+    let downloads = stmt
+        .query_map([], |row| {
+            let oci_files: Option<String> = row.get(4)?;
+            Ok(QueuedDownload {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                output_path: row.get(2)?,
+                oci: row.get(3)?,
+                oci_files: oci_files
+                    .map(|files| files.lines().map(String::from).collect())
+                    .unwrap_or_default(),
+                downloaded: row.get(5)?,
+                total: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(downloads)
+}
+
+/// Empties the queue, returning how many downloads were dropped.
+pub fn clear_download_queue(db: Arc<Mutex<Connection>>) -> SoarResult<usize> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    Ok(conn.execute("DELETE FROM download_queue", [])?)
+}
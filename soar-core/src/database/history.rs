@@ -0,0 +1,129 @@
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection};
+
+use crate::{database::models::Package, error::SoarError, SoarResult};
+
+/// Outcome of a package in an install or update transaction.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub transaction_id: i64,
+    /// Kind of transaction, e.g. `install` or `update`.
+    pub kind: String,
+    pub repo_name: String,
+    pub pkg_id: String,
+    pub pkg_name: String,
+    pub version: String,
+    /// Why the package failed, `None` if it succeeded.
+    pub error: Option<String>,
+}
+
+/// Records the start of a transaction of `kind` installing `packages`,
+/// returning its ID. Each package stays pending until its outcome is
+/// recorded with [`finish_entry`], so a transaction that was killed can be
+/// told apart.
+pub fn begin_transaction(
+    db: Arc<Mutex<Connection>>,
+    kind: &str,
+    packages: &[&Package],
+) -> SoarResult<i64> {
+    let mut conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let tx = conn.transaction()?;
+    let transaction_id: i64 = tx.query_row(
+        "SELECT COALESCE(MAX(transaction_id), 0) + 1 FROM history",
+        [],
+        |row| row.get(0),
+    )?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO history (
+                transaction_id, kind, repo_name, pkg_id, pkg_name, version, pending
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, true)",
+        )?;
+        for package in packages {
+            stmt.execute(params![
+                transaction_id,
+                kind,
+                package.repo_name,
+                package.pkg_id,
+                package.pkg_name,
+                package.version
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(transaction_id)
+}
+
+/// Records the outcome of `package` in the transaction `transaction_id`,
+/// `error` telling why it failed.
+pub fn finish_entry(
+    db: Arc<Mutex<Connection>>,
+    transaction_id: i64,
+    package: &Package,
+    error: Option<&str>,
+) -> SoarResult<()> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    conn.execute(
+        "UPDATE history SET pending = false, error = ?1
+        WHERE transaction_id = ?2 AND repo_name = ?3 AND pkg_id = ?4 AND pkg_name = ?5",
+        params![
+            error,
+            transaction_id,
+            package.repo_name,
+            package.pkg_id,
+            package.pkg_name
+        ],
+    )?;
+    Ok(())
+}
+
+/// Returns the packages of transactions that were interrupted before their
+/// outcome was recorded.
+pub fn get_incomplete(db: Arc<Mutex<Connection>>) -> SoarResult<Vec<HistoryEntry>> {
+    query_entries(db, "pending = true")
+}
+
+/// Records the pending packages of interrupted transactions as failed with
+/// `error`, once they have been dealt with.
+pub fn close_incomplete(db: Arc<Mutex<Connection>>, error: &str) -> SoarResult<()> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    conn.execute(
+        "UPDATE history SET pending = false, error = ?1 WHERE pending = true",
+        [error],
+    )?;
+    Ok(())
+}
+
+/// Returns the packages that failed in the most recent transaction.
+pub fn get_last_failed(db: Arc<Mutex<Connection>>) -> SoarResult<Vec<HistoryEntry>> {
+    query_entries(
+        db,
+        "transaction_id = (SELECT MAX(transaction_id) FROM history) AND error IS NOT NULL",
+    )
+}
+
+fn query_entries(db: Arc<Mutex<Connection>>, condition: &str) -> SoarResult<Vec<HistoryEntry>> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT transaction_id, kind, repo_name, pkg_id, pkg_name, version, error FROM history
+        WHERE {}
+        ORDER BY id",
+        condition
+    ))?;
+    let entries = stmt
+        .query_map([], |row| {
+            Ok(HistoryEntry {
+                transaction_id: row.get(0)?,
+                kind: row.get(1)?,
+                repo_name: row.get(2)?,
+                pkg_id: row.get(3)?,
+                pkg_name: row.get(4)?,
+                version: row.get(5)?,
+                error: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
@@ -29,12 +29,11 @@ impl MigrationManager {
     }
 
     fn get_current_version(&self) -> rusqlite::Result<i32> {
-        let version = self.conn.query_row(
+        self.conn.query_row(
             "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
             [],
             |row| row.get(0),
-        );
-        version
+        )
     }
 
     fn run_migration(&mut self, migration: &Migration) -> rusqlite::Result<()> {
@@ -91,20 +90,25 @@ impl MigrationManager {
 
         migrations.sort_by_key(|m| m.version);
 
-        let mut expected_version = 1;
-        for migration in &migrations {
+        for (expected_version, migration) in (1..).zip(migrations.iter()) {
             if migration.version != expected_version {
                 return Err(SoarError::Custom(format!(
                     "Invalid migration sequence. Expected version {}, found {}",
                     expected_version, migration.version
                 )));
             }
-            expected_version += 1;
         }
 
         Ok(migrations)
     }
 
+    /// Whether any migration in `dir` has not been applied yet.
+    pub fn has_pending(&self, dir: Dir) -> SoarResult<bool> {
+        let migrations = Self::load_migrations_from_dir(dir)?;
+        let current_version = self.get_current_version()?;
+        Ok(migrations.iter().any(|m| m.version > current_version))
+    }
+
     pub fn migrate_from_dir(&mut self, dir: Dir) -> SoarResult<()> {
         let migrations = Self::load_migrations_from_dir(dir)?;
         let current_version = self.get_current_version()?;
@@ -1,6 +1,10 @@
 pub mod connection;
+pub mod download_queue;
+pub mod history;
 pub mod migration;
 pub mod models;
 pub mod packages;
 pub mod repository;
 pub mod statements;
+pub mod stats;
+pub mod sync_state;
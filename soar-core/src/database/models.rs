@@ -1,6 +1,8 @@
 use rusqlite::types::Value;
 use serde::{Deserialize, Serialize};
 
+use crate::package::channel::Channel;
+
 #[derive(Debug, Clone)]
 pub struct Package {
     pub id: u64,
@@ -31,6 +33,11 @@ pub struct Package {
     pub build_date: Option<String>,
     pub build_script: Option<String>,
     pub build_log: Option<String>,
+    /// Group of packages this one belongs to, e.g. `bin` or `appimage`.
+    pub collection: Option<String>,
+    /// Magnet link or `.torrent` URL to download the package from if
+    /// `download_url` fails.
+    pub torrent: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -53,6 +60,15 @@ pub struct InstalledPackage {
     pub pinned: bool,
     pub is_installed: bool,
     pub installed_with_family: bool,
+    /// Upstream project of a package adopted from outside soar.
+    pub upstream: Option<String>,
+    /// Pattern selecting the release asset of a package installed from GitHub.
+    pub asset_pattern: Option<String>,
+    pub channel: Channel,
+    /// Checksum of the binary before it was stripped or compressed.
+    pub original_checksum: Option<String>,
+    /// Size of the binary before it was stripped or compressed.
+    pub original_size: Option<u64>,
 }
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
@@ -87,7 +103,7 @@ pub struct RemotePackage {
     #[serde(alias = "maintainer")]
     pub maintainers: Vec<String>,
 
-    #[serde(alias = "note")]
+    #[serde(alias = "note", alias = "caveats")]
     pub notes: Option<Vec<String>>,
 
     #[serde(alias = "tag")]
@@ -103,7 +119,18 @@ pub struct RemotePackage {
     pub categories: Vec<String>,
 
     pub provides: Vec<String>,
+
+    /// Other names the package can be installed by, e.g. `rg` for ripgrep.
+    #[serde(default, alias = "alias")]
+    pub aliases: Vec<String>,
+
     pub icon: Option<String>,
     pub desktop: Option<String>,
     pub app_id: Option<String>,
+
+    #[serde(default, alias = "_collection")]
+    pub collection: Option<String>,
+
+    #[serde(default)]
+    pub torrent: Option<String>,
 }
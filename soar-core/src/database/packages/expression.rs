@@ -0,0 +1,446 @@
+//! Filter expressions selecting packages from the repository metadata, such
+//! as `name~^rip && size<10MB && repo=bincache`.
+//!
+//! An expression compares fields of the packages with values, and combines
+//! the comparisons with `&&`, `||`, `!` and parentheses. It's compiled to a
+//! SQL condition, so the selection happens in the metadata database rather
+//! than on the listed packages.
+
+use std::fmt::Display;
+
+use regex::Regex;
+use rusqlite::ToSql;
+
+use crate::{error::SoarError, SoarResult};
+
+/// Field of a package a filter expression compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Id,
+    Repo,
+    Version,
+    Description,
+    Type,
+    Collection,
+    Size,
+    Date,
+    Category,
+    Tag,
+    Bin,
+}
+
+impl Field {
+    /// Names the fields are written as in expressions.
+    pub const NAMES: &[(&str, Field)] = &[
+        ("name", Field::Name),
+        ("id", Field::Id),
+        ("repo", Field::Repo),
+        ("version", Field::Version),
+        ("description", Field::Description),
+        ("desc", Field::Description),
+        ("type", Field::Type),
+        ("collection", Field::Collection),
+        ("size", Field::Size),
+        ("date", Field::Date),
+        ("category", Field::Category),
+        ("tag", Field::Tag),
+        ("bin", Field::Bin),
+    ];
+
+    fn from_name(name: &str) -> Option<Field> {
+        Self::NAMES
+            .iter()
+            .find(|(field_name, _)| name.eq_ignore_ascii_case(field_name))
+            .map(|(_, field)| *field)
+    }
+
+    /// Column of a package the field is stored in, as a non-null value.
+    fn column(self) -> &'static str {
+        match self {
+            Field::Name => "p.pkg_name",
+            Field::Id => "p.pkg_id",
+            Field::Repo => "r.name",
+            Field::Version => "p.version",
+            Field::Description => "IFNULL(p.description, '')",
+            Field::Type => "IFNULL(p.pkg_type, '')",
+            Field::Collection => "IFNULL(p.collection, '')",
+            Field::Size => "p.size",
+            Field::Date => "IFNULL(p.build_date, '')",
+            Field::Category => "categories",
+            Field::Tag => "tags",
+            Field::Bin => "provides",
+        }
+    }
+
+    /// Whether the field is a set of labels rather than a single value.
+    fn is_label(self) -> bool {
+        matches!(self, Field::Category | Field::Tag | Field::Bin)
+    }
+}
+
+/// How a field is compared with a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    /// Matches a regular expression, ignoring case.
+    Match,
+    NotMatch,
+}
+
+impl CompareOp {
+    /// Operators by how they're written, longest first to be matched
+    /// greedily.
+    const SYMBOLS: &[(&str, CompareOp)] = &[
+        ("==", CompareOp::Eq),
+        ("!=", CompareOp::NotEq),
+        ("!~", CompareOp::NotMatch),
+        ("<=", CompareOp::Lte),
+        (">=", CompareOp::Gte),
+        ("=", CompareOp::Eq),
+        ("~", CompareOp::Match),
+        ("<", CompareOp::Lt),
+        (">", CompareOp::Gt),
+    ];
+
+    /// The operator with the opposite outcome, where there is one.
+    fn negated(self) -> CompareOp {
+        match self {
+            CompareOp::NotEq => CompareOp::Eq,
+            CompareOp::NotMatch => CompareOp::Match,
+            op => op,
+        }
+    }
+
+    fn to_sql(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::NotEq => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Lte => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Gte => ">=",
+            CompareOp::Match => "REGEXP",
+            CompareOp::NotMatch => "NOT REGEXP",
+        }
+    }
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = CompareOp::SYMBOLS
+            .iter()
+            .find(|(_, op)| op == self)
+            .map_or("", |(symbol, _)| symbol);
+        f.write_str(symbol)
+    }
+}
+
+/// A parsed filter expression.
+#[derive(Debug, Clone)]
+pub enum FilterExpr {
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    Not(Box<FilterExpr>),
+    Compare {
+        field: Field,
+        op: CompareOp,
+        value: ExprValue,
+    },
+}
+
+/// Value a field is compared with.
+#[derive(Debug, Clone)]
+pub enum ExprValue {
+    Text(String),
+    Bytes(u64),
+}
+
+impl FilterExpr {
+    /// Parses `input` as a filter expression.
+    pub fn parse(input: &str) -> SoarResult<FilterExpr> {
+        let mut parser = Parser { input, pos: 0 };
+        let expr = parser.parse_or()?;
+        parser.skip_whitespace();
+        if parser.pos < input.len() {
+            return Err(parser.error("expected && or ||"));
+        }
+        Ok(expr)
+    }
+
+    /// Whether `input` is meant as a filter expression rather than a plain
+    /// search term, i.e. starts with a comparison or combines several.
+    pub fn looks_like(input: &str) -> bool {
+        if input.contains("&&") || input.contains("||") {
+            return true;
+        }
+        let input = input.trim_start_matches(|c: char| c == '!' || c == '(' || c.is_whitespace());
+        let name_len = input
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(input.len());
+        let rest = input[name_len..].trim_start();
+        name_len > 0
+            && CompareOp::SYMBOLS
+                .iter()
+                .any(|(symbol, _)| rest.starts_with(symbol))
+    }
+
+    /// The expression as a SQL condition on the packages `p` and the
+    /// repository `r` of `shard`, pushing its values to `params`.
+    pub fn to_sql(&self, shard: &str, params: &mut Vec<Box<dyn ToSql>>) -> String {
+        match self {
+            FilterExpr::And(left, right) => format!(
+                "({} AND {})",
+                left.to_sql(shard, params),
+                right.to_sql(shard, params)
+            ),
+            FilterExpr::Or(left, right) => format!(
+                "({} OR {})",
+                left.to_sql(shard, params),
+                right.to_sql(shard, params)
+            ),
+            FilterExpr::Not(expr) => format!("NOT {}", expr.to_sql(shard, params)),
+            FilterExpr::Compare { field, op, value } => {
+                match value {
+                    ExprValue::Text(text)
+                        if matches!(op, CompareOp::Match | CompareOp::NotMatch) =>
+                    {
+                        params.push(Box::new(format!("(?i){}", text)));
+                    }
+                    ExprValue::Text(text) => params.push(Box::new(text.clone())),
+                    ExprValue::Bytes(bytes) => params.push(Box::new(*bytes)),
+                }
+                if field.is_label() {
+                    // packages with a label matching, or without any for
+                    // the negated operators
+                    let membership = if op.negated() == *op { "IN" } else { "NOT IN" };
+                    format!(
+                        "p.id {} (SELECT package_id FROM {}.{} WHERE name {} ?)",
+                        membership,
+                        shard,
+                        field.column(),
+                        op.negated().to_sql()
+                    )
+                } else {
+                    format!("{} {} ?", field.column(), op.to_sql())
+                }
+            }
+        }
+    }
+}
+
+/// Parses sizes such as `10MB`, `1.5GiB` or `512k`, where the bare unit
+/// letters are binary multiples like in `du -h`.
+fn parse_size(value: &str) -> Option<u64> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u64.pow(2),
+        "gb" => 1000u64.pow(3),
+        "tb" => 1000u64.pow(4),
+        "k" | "kib" => 1024,
+        "m" | "mib" => 1024u64.pow(2),
+        "g" | "gib" => 1024u64.pow(3),
+        "t" | "tib" => 1024u64.pow(4),
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Display) -> SoarError {
+        // columns are counted from 1, as editors do
+        SoarError::InvalidFilter(format!("{} at column {}", message, self.pos + 1))
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `token` if the input continues with it.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> SoarResult<FilterExpr> {
+        let mut expr = self.parse_and()?;
+        while self.eat("||") {
+            expr = FilterExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> SoarResult<FilterExpr> {
+        let mut expr = self.parse_unary()?;
+        while self.eat("&&") {
+            expr = FilterExpr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> SoarResult<FilterExpr> {
+        if self.eat("!") {
+            return Ok(FilterExpr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.parse_or()?;
+            if !self.eat(")") {
+                return Err(self.error("expected )"));
+            }
+            return Ok(expr);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> SoarResult<FilterExpr> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let name_len = self
+            .rest()
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(self.rest().len());
+        if name_len == 0 {
+            return Err(self.error("expected a field"));
+        }
+        let name = &self.input[start..start + name_len];
+        let Some(field) = Field::from_name(name) else {
+            let names: Vec<_> = Field::NAMES.iter().map(|(name, _)| *name).collect();
+            return Err(self.error(format!(
+                "unknown field {}, expected one of {}",
+                name,
+                names.join(", ")
+            )));
+        };
+        self.pos += name_len;
+
+        self.skip_whitespace();
+        let Some(&(symbol, op)) = CompareOp::SYMBOLS
+            .iter()
+            .find(|(symbol, _)| self.rest().starts_with(symbol))
+        else {
+            return Err(self.error(format!("expected an operator after {}", name)));
+        };
+        let op_pos = self.pos;
+        self.pos += symbol.len();
+
+        let value_pos = self.pos;
+        let text = self.parse_value()?;
+        let value = match field {
+            Field::Size => {
+                if matches!(op, CompareOp::Match | CompareOp::NotMatch) {
+                    self.pos = op_pos;
+                    return Err(self.error(format!("size can't be compared with {}", op)));
+                }
+                let Some(bytes) = parse_size(&text) else {
+                    self.pos = value_pos;
+                    return Err(self.error(format!("invalid size {}", text)));
+                };
+                ExprValue::Bytes(bytes)
+            }
+            _ => {
+                if field.is_label()
+                    && matches!(
+                        op,
+                        CompareOp::Lt | CompareOp::Lte | CompareOp::Gt | CompareOp::Gte
+                    )
+                {
+                    self.pos = op_pos;
+                    return Err(self.error(format!("{} can't be compared with {}", name, op)));
+                }
+                if matches!(op, CompareOp::Match | CompareOp::NotMatch) {
+                    if let Err(err) = Regex::new(&text) {
+                        // the last line of syntax errors says what's wrong,
+                        // the others point at it
+                        let err = err.to_string();
+                        let reason = err.lines().last().unwrap_or_default();
+                        self.pos = value_pos;
+                        return Err(self.error(format!(
+                            "invalid regular expression, {}",
+                            reason.trim_start_matches("error: ")
+                        )));
+                    }
+                }
+                ExprValue::Text(text)
+            }
+        };
+
+        Ok(FilterExpr::Compare { field, op, value })
+    }
+
+    /// Parses a quoted value, or a bare one up to whitespace, `&&`, `||`
+    /// or a closing parenthesis it doesn't open itself.
+    fn parse_value(&mut self) -> SoarResult<String> {
+        self.skip_whitespace();
+        let input = self.input;
+        let rest = &input[self.pos..];
+        if let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            let mut value = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            value.push(escaped);
+                        }
+                    }
+                    c if c == quote => {
+                        self.pos += i + 1;
+                        return Ok(value);
+                    }
+                    c => value.push(c),
+                }
+            }
+            return Err(self.error(format!("unterminated {}", quote)));
+        }
+
+        let mut depth = 0;
+        let mut end = rest.len();
+        for (i, c) in rest.char_indices() {
+            let next = &rest[i..];
+            if c.is_whitespace() || next.starts_with("&&") || next.starts_with("||") {
+                end = i;
+                break;
+            }
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    end = i;
+                    break;
+                }
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+        if end == 0 {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += end;
+        Ok(rest[..end].to_string())
+    }
+}
@@ -1,5 +1,7 @@
+mod expression;
 mod models;
 mod query;
 
+pub use expression::*;
 pub use models::*;
 pub use query::*;
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use super::FilterExpr;
+
 #[derive(Debug, Clone)]
 pub enum FilterOp {
     Eq,
@@ -84,6 +86,14 @@ pub struct QueryOptions {
     pub limit: u32,
     pub filters: HashMap<String, Filter>,
     pub sort_by: Vec<(String, SortOrder)>,
+    /// Filter expression the packages have to match as well, only for the
+    /// repository metadata.
+    pub expression: Option<FilterExpr>,
+    /// Rows to skip before the first page.
+    pub offset: u32,
+    /// Term to score the packages against, as the `relevance` column to
+    /// sort by, only for the repository metadata.
+    pub rank_term: Option<String>,
 }
 
 impl Default for QueryOptions {
@@ -93,6 +103,9 @@ impl Default for QueryOptions {
             limit: u32::MAX,
             filters: HashMap::new(),
             sort_by: Vec::new(),
+            expression: None,
+            offset: 0,
+            rank_term: None,
         }
     }
 }
@@ -3,6 +3,7 @@ use std::sync::{Arc, Mutex};
 use rusqlite::{Connection, Row, ToSql};
 
 use crate::{
+    config::path_from_root,
     database::{
         models::{InstalledPackage, Package},
         packages::SortOrder,
@@ -54,7 +55,7 @@ impl PackageQuery {
 
         let page = self.options.page;
         let limit = self.options.limit;
-        let has_next = (page as u64 * limit as u64) < total;
+        let has_next = (page as u64 * limit as u64 + self.options.offset as u64) < total;
 
         Ok(PaginatedResponse {
             items,
@@ -83,11 +84,17 @@ impl PackageQuery {
         let shard_queries: Vec<String> = shards
             .iter()
             .map(|shard| {
+                // an extra column after those of the package, which are
+                // mapped by position
+                let relevance = match &self.options.rank_term {
+                    Some(term) => relevance_sql(shard, term, &mut params),
+                    None => "0".to_string(),
+                };
                 let select_clause = format!(
-                    "SELECT p.*, r.name FROM {0}.packages p JOIN {0}.repository r",
-                    shard
+                    "SELECT p.*, r.name, {1} AS relevance FROM {0}.packages p JOIN {0}.repository r",
+                    shard, relevance
                 );
-                self.build_shard_query(&select_clause, &mut params)
+                self.build_shard_query(shard, &select_clause, &mut params)
             })
             .collect();
 
@@ -117,7 +124,7 @@ impl PackageQuery {
 
         let page = self.options.page;
         let limit = self.options.limit;
-        let offset = (page - 1) * limit;
+        let offset = (page - 1) * limit + self.options.offset;
         final_query.push_str(" LIMIT ? OFFSET ?");
         params.push(Box::new(self.options.limit));
         params.push(Box::new(offset));
@@ -127,16 +134,34 @@ impl PackageQuery {
 
     fn build_shard_query(
         &self,
+        shard: &str,
         select_clause: &str,
         params: &mut Vec<Box<dyn rusqlite::ToSql>>,
     ) -> String {
         let mut conditions = Vec::new();
 
         for (field, filter) in &self.options.filters {
-            if let Some(condition) = self.build_filter_condition(field, filter, params) {
+            let condition = match field.as_str() {
+                // packages labelled with the value, through the indexed label
+                // tables rather than the JSON columns
+                "category" => {
+                    let table = format!("{}.categories", shard);
+                    self.build_label_condition(&table, filter, params)
+                }
+                "tag" => {
+                    let table = format!("{}.tags", shard);
+                    self.build_label_condition(&table, filter, params)
+                }
+                "search" => self.build_search_condition(shard, filter, params),
+                _ => self.build_filter_condition(field, filter, params),
+            };
+            if let Some(condition) = condition {
                 conditions.push(condition);
             }
         }
+        if let Some(expression) = &self.options.expression {
+            conditions.push(expression.to_sql(shard, params));
+        }
 
         let where_clause = if conditions.is_empty() {
             String::new()
@@ -157,7 +182,7 @@ impl PackageQuery {
                     "SELECT COUNT(*) as cnt FROM {0}.packages p JOIN {0}.repository r",
                     shard
                 );
-                self.build_shard_query(&select_clause, &mut params)
+                self.build_shard_query(shard, &select_clause, &mut params)
             })
             .collect();
 
@@ -169,6 +194,49 @@ impl PackageQuery {
         (query, params)
     }
 
+    /// Packages with each word of the search term in their name,
+    /// description or the names of the commands they provide.
+    fn build_search_condition(
+        &self,
+        shard: &str,
+        filter: &Filter,
+        params: &mut Vec<Box<dyn ToSql>>,
+    ) -> Option<String> {
+        let FilterValue::Single(term) = &filter.value else {
+            return None;
+        };
+        let conditions: Vec<String> = term
+            .split_whitespace()
+            .filter_map(|word| {
+                let filter = Filter {
+                    operator: filter.operator.clone(),
+                    value: FilterValue::Single(word.to_string()),
+                };
+                let name = self.build_filter_condition("p.pkg_name", &filter, params)?;
+                let description = self.build_filter_condition("p.description", &filter, params)?;
+                let provides = self.build_filter_condition("name", &filter, params)?;
+                Some(format!(
+                    "({} OR {} OR p.id IN (SELECT package_id FROM {}.provides WHERE {}))",
+                    name, description, shard, provides
+                ))
+            })
+            .collect();
+        (!conditions.is_empty()).then(|| conditions.join(" AND "))
+    }
+
+    fn build_label_condition(
+        &self,
+        table: &str,
+        filter: &Filter,
+        params: &mut Vec<Box<dyn ToSql>>,
+    ) -> Option<String> {
+        let condition = self.build_filter_condition("name", filter, params)?;
+        Some(format!(
+            "p.id IN (SELECT package_id FROM {} WHERE {})",
+            table, condition
+        ))
+    }
+
     fn build_filter_condition(
         &self,
         field: &str,
@@ -235,7 +303,7 @@ impl PackageQuery {
         let (count_query, count_params) = {
             let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
             let select_clause = "SELECT COUNT(*) FROM packages p";
-            let query = self.build_shard_query(&select_clause, &mut params);
+            let query = self.build_shard_query("main", select_clause, &mut params);
             (query, params)
         };
         let mut count_stmt = conn.prepare(&count_query)?;
@@ -247,7 +315,7 @@ impl PackageQuery {
 
         let page = self.options.page;
         let limit = self.options.limit;
-        let has_next = (page as u64 * limit as u64) < total;
+        let has_next = (page as u64 * limit as u64 + self.options.offset as u64) < total;
 
         Ok(PaginatedResponse {
             items,
@@ -261,7 +329,7 @@ impl PackageQuery {
     fn build_installed_query(&self) -> SoarResult<(String, Vec<Box<dyn rusqlite::ToSql>>)> {
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
         let select_clause = "SELECT p.* FROM packages p";
-        let mut query = self.build_shard_query(select_clause, &mut params);
+        let mut query = self.build_shard_query("main", select_clause, &mut params);
 
         if !self.options.sort_by.is_empty() {
             let sort_clauses: Vec<String> = self
@@ -283,7 +351,7 @@ impl PackageQuery {
             query.push_str(&sort_clauses.join(", "));
         }
 
-        let offset = (self.options.page - 1) * self.options.limit;
+        let offset = (self.options.page - 1) * self.options.limit + self.options.offset;
         query.push_str(" LIMIT ? OFFSET ?");
         params.push(Box::new(self.options.limit));
         params.push(Box::new(offset));
@@ -292,6 +360,42 @@ impl PackageQuery {
     }
 }
 
+/// Scores how well a package matches the words of `term`: matches in the
+/// name count the most, then those in the names of the commands it
+/// provides, then those in its description.
+fn relevance_sql(shard: &str, term: &str, params: &mut Vec<Box<dyn ToSql>>) -> String {
+    let scores: Vec<String> = term
+        .to_lowercase()
+        .split_whitespace()
+        .map(|word| {
+            for _ in 0..6 {
+                params.push(Box::new(word.to_string()));
+            }
+            format!(
+                "CASE
+                    WHEN LOWER(p.pkg_name) = ? THEN 8
+                    WHEN INSTR(LOWER(p.pkg_name), ?) = 1 THEN 6
+                    WHEN INSTR(LOWER(p.pkg_name), ?) > 0 THEN 4
+                    ELSE 0
+                END + CASE
+                    WHEN EXISTS (SELECT 1 FROM {0}.provides WHERE package_id = p.id AND LOWER(name) = ?) THEN 3
+                    WHEN EXISTS (SELECT 1 FROM {0}.provides WHERE package_id = p.id AND INSTR(LOWER(name), ?) > 0) THEN 2
+                    ELSE 0
+                END + CASE
+                    WHEN INSTR(LOWER(IFNULL(p.description, '')), ?) > 0 THEN 1
+                    ELSE 0
+                END",
+                shard
+            )
+        })
+        .collect();
+    if scores.is_empty() {
+        "0".to_string()
+    } else {
+        format!("({})", scores.join(" + "))
+    }
+}
+
 fn map_package(row: &Row) -> rusqlite::Result<Package> {
     let parse_json_vec = |idx: usize| -> rusqlite::Result<Option<Vec<String>>> {
         let value: String = row.get(idx)?;
@@ -332,7 +436,9 @@ fn map_package(row: &Row) -> rusqlite::Result<Package> {
         build_date: row.get(24)?,
         build_script: row.get(25)?,
         build_log: row.get(26)?,
-        repo_name: row.get(27)?,
+        collection: row.get(27)?,
+        torrent: row.get(28)?,
+        repo_name: row.get(29)?,
     })
 }
 
@@ -396,6 +502,287 @@ pub fn get_packages(
     PackageQuery::new(db, options).execute()
 }
 
+/// Finds packages that provide the command `name`, either as their package
+/// name or through their `provides` list.
+pub fn get_packages_providing(db: Arc<Mutex<Connection>>, name: &str) -> SoarResult<Vec<Package>> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let shards: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .collect();
+
+    let shard_queries: Vec<String> = shards
+        .iter()
+        .map(|shard| {
+            format!(
+                "SELECT DISTINCT p.*, r.name FROM {0}.packages p JOIN {0}.repository r
+                LEFT JOIN {0}.provides pr ON pr.package_id = p.id
+                WHERE p.pkg_name = ?1 OR pr.name = ?1",
+                shard
+            )
+        })
+        .collect();
+    let query = format!(
+        "{} ORDER BY pkg_name, pkg_id",
+        shard_queries.join("\nUNION\n")
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let packages = stmt
+        .query_map([name], map_package)?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(packages)
+}
+
+/// Names of the packages called `name` ignoring case, or aliased as `name`.
+pub fn get_package_names_matching(
+    db: Arc<Mutex<Connection>>,
+    name: &str,
+) -> SoarResult<Vec<String>> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let shards: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .collect();
+
+    let shard_queries: Vec<String> = shards
+        .iter()
+        .map(|shard| {
+            format!(
+                "SELECT p.pkg_name FROM {0}.packages p
+                LEFT JOIN {0}.aliases a ON a.package_id = p.id
+                WHERE LOWER(p.pkg_name) = LOWER(?1) OR LOWER(a.name) = LOWER(?1)",
+                shard
+            )
+        })
+        .collect();
+    let query = format!("{} ORDER BY pkg_name", shard_queries.join("\nUNION\n"));
+
+    let mut stmt = conn.prepare(&query)?;
+    let names = stmt
+        .query_map([name], |row| row.get(0))?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(names)
+}
+
+/// Finds up to `limit` package names or aliases that look like `name`,
+/// closest first, to suggest when nothing matches it.
+pub fn get_similar_package_names(
+    db: Arc<Mutex<Connection>>,
+    name: &str,
+    limit: usize,
+) -> SoarResult<Vec<String>> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let shards: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .collect();
+
+    let shard_queries: Vec<String> = shards
+        .iter()
+        .map(|shard| {
+            format!(
+                "SELECT pkg_name FROM {0}.packages
+                UNION
+                SELECT name FROM {0}.aliases",
+                shard
+            )
+        })
+        .collect();
+
+    let mut stmt = conn.prepare(&shard_queries.join("\nUNION\n"))?;
+    let name = name.to_lowercase();
+    let mut candidates: Vec<(f64, String)> = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter_map(Result::ok)
+        .filter_map(|candidate| {
+            let score = strsim::jaro_winkler(&name, &candidate.to_lowercase());
+            (score >= 0.8).then_some((score, candidate))
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    candidates.dedup_by(|a, b| a.1.eq_ignore_ascii_case(&b.1));
+    Ok(candidates
+        .into_iter()
+        .take(limit)
+        .map(|(_, candidate)| candidate)
+        .collect())
+}
+
+/// Lists the commands provided by `pkg_id` at `version` in `repo_name`.
+pub fn get_package_provides(
+    db: Arc<Mutex<Connection>>,
+    repo_name: &str,
+    pkg_id: &str,
+    version: &str,
+) -> SoarResult<Vec<String>> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let shards: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .collect();
+
+    let shard_queries: Vec<String> = shards
+        .iter()
+        .map(|shard| {
+            format!(
+                "SELECT pr.name FROM {0}.provides pr
+                JOIN {0}.packages p ON p.id = pr.package_id
+                JOIN {0}.repository r
+                WHERE r.name = ?1 AND p.pkg_id = ?2 AND p.version = ?3",
+                shard
+            )
+        })
+        .collect();
+    let query = format!("{} ORDER BY 1", shard_queries.join("\nUNION\n"));
+
+    let mut stmt = conn.prepare(&query)?;
+    let provides = stmt
+        .query_map([repo_name, pkg_id, version], |row| row.get(0))?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(provides)
+}
+
+/// Categories of the packages, with how many packages are in each, largest
+/// first.
+pub fn get_category_counts(db: Arc<Mutex<Connection>>) -> SoarResult<Vec<(String, u64)>> {
+    get_label_counts(db, "categories")
+}
+
+/// Tags of the packages, with how many packages have each, largest first.
+pub fn get_tag_counts(db: Arc<Mutex<Connection>>) -> SoarResult<Vec<(String, u64)>> {
+    get_label_counts(db, "tags")
+}
+
+fn get_label_counts(db: Arc<Mutex<Connection>>, table: &str) -> SoarResult<Vec<(String, u64)>> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let shards: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .collect();
+
+    let shard_queries: Vec<String> = shards
+        .iter()
+        .map(|shard| {
+            format!(
+                "SELECT name, COUNT(DISTINCT package_id) AS cnt FROM {0}.{1} GROUP BY name",
+                shard, table
+            )
+        })
+        .collect();
+    let query = format!(
+        "SELECT name, SUM(cnt) AS total FROM ({}) GROUP BY name COLLATE NOCASE
+        ORDER BY total DESC, name COLLATE NOCASE",
+        shard_queries.join("\nUNION ALL\n")
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let counts = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(counts)
+}
+
+/// Installed packages, each with the newest version of it in the repository
+/// metadata if that is newer, in one query over the metadata with the core
+/// database attached.
+pub fn get_installed_with_updates(
+    repo_db: Arc<Mutex<Connection>>,
+    core_db: Arc<Mutex<Connection>>,
+    repo_name: Option<&str>,
+    updatable_only: bool,
+    sort_by: &[(String, SortOrder)],
+) -> SoarResult<Vec<(InstalledPackage, Option<String>)>> {
+    let core_path = core_db
+        .lock()
+        .map_err(|_| SoarError::PoisonError)?
+        .path()
+        .map(String::from)
+        .ok_or_else(|| SoarError::Custom("Core database has no file".into()))?;
+
+    let conn = repo_db.lock().map_err(|_| SoarError::PoisonError)?;
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let shards: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .collect();
+
+    let shard_queries: Vec<String> = shards
+        .iter()
+        .map(|shard| {
+            format!(
+                "SELECT r.name AS repo_name, p.pkg_id, p.pkg_name, p.version
+                FROM {0}.packages p JOIN {0}.repository r",
+                shard
+            )
+        })
+        .collect();
+
+    let mut conditions = vec!["i.is_installed = 1".to_string()];
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(repo_name) = repo_name {
+        conditions.push("i.repo_name = ?".to_string());
+        params.push(Box::new(repo_name.to_string()));
+    }
+    let mut query = format!(
+        "WITH available AS ({}),
+        installed AS (
+            SELECT i.*, (
+                SELECT MAX(a.version COLLATE version) FROM available a
+                WHERE a.repo_name = i.repo_name AND a.pkg_id = i.pkg_id
+                    AND a.pkg_name = i.pkg_name AND a.version COLLATE version > i.version
+            ) AS newer_version
+            FROM core.packages i WHERE {}
+        )
+        SELECT * FROM installed",
+        shard_queries.join("\nUNION ALL\n"),
+        conditions.join(" AND ")
+    );
+    if updatable_only {
+        query.push_str(" WHERE newer_version IS NOT NULL");
+    }
+    if !sort_by.is_empty() {
+        let sort_clauses: Vec<String> = sort_by
+            .iter()
+            .map(|(field, order)| {
+                format!(
+                    "{} {}",
+                    field,
+                    match order {
+                        SortOrder::Asc => "ASC",
+                        SortOrder::Desc => "DESC",
+                    }
+                )
+            })
+            .collect();
+        query.push_str(" ORDER BY ");
+        query.push_str(&sort_clauses.join(", "));
+    }
+
+    // attached only for this query, the other queries go through every
+    // attached database as a repository
+    conn.execute("ATTACH DATABASE ?1 AS core", [&core_path])?;
+    let result = conn.prepare(&query).and_then(|mut stmt| {
+        let params_ref: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        stmt.query_map(params_ref.as_slice(), |row| {
+            Ok((map_installed_package(row)?, row.get("newer_version")?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+    });
+    conn.execute("DETACH DATABASE core", [])?;
+    Ok(result?)
+}
+
 pub fn get_installed_packages(
     db: Arc<Mutex<Connection>>,
     options: QueryOptions,
@@ -403,7 +790,14 @@ pub fn get_installed_packages(
     PackageQuery::new(db, options).execute_installed()
 }
 
+/// Maps a row of the packages of the core database, with the paths recorded
+/// in it as seen from inside the install root if there is one.
 pub fn map_installed_package(row: &Row) -> rusqlite::Result<InstalledPackage> {
+    let path = |idx| -> rusqlite::Result<Option<String>> {
+        Ok(row
+            .get::<_, Option<String>>(idx)?
+            .map(|path| path_from_root(&path)))
+    };
     Ok(InstalledPackage {
         id: row.get(0)?,
         repo_name: row.get(1)?,
@@ -413,15 +807,20 @@ pub fn map_installed_package(row: &Row) -> rusqlite::Result<InstalledPackage> {
         version: row.get(5)?,
         size: row.get(6)?,
         checksum: row.get(7)?,
-        installed_path: row.get(8)?,
+        installed_path: path_from_root(&row.get::<_, String>(8)?),
         installed_date: row.get(9)?,
-        bin_path: row.get(10)?,
-        icon_path: row.get(11)?,
-        desktop_path: row.get(12)?,
-        appstream_path: row.get(13)?,
+        bin_path: path(10)?,
+        icon_path: path(11)?,
+        desktop_path: path(12)?,
+        appstream_path: path(13)?,
         profile: row.get(14)?,
         pinned: row.get(15)?,
         is_installed: row.get(16)?,
         installed_with_family: row.get(17)?,
+        upstream: row.get(18)?,
+        asset_pattern: row.get(19)?,
+        channel: row.get::<_, String>(20)?.parse().unwrap_or_default(),
+        original_checksum: row.get(21)?,
+        original_size: row.get(22)?,
     })
 }
@@ -70,8 +70,32 @@ impl<'a> PackageRepository<'a> {
             package.build_date,
             package.build_script,
             package.build_log,
+            package.collection,
+            package.torrent,
         ])?;
 
+        let package_id = self.tx.last_insert_rowid();
+        for provide in &package.provides {
+            self.statements
+                .provides_insert
+                .execute(params![package_id, provide])?;
+        }
+        for alias in &package.aliases {
+            self.statements
+                .aliases_insert
+                .execute(params![package_id, alias])?;
+        }
+        for category in &package.categories {
+            self.statements
+                .categories_insert
+                .execute(params![package_id, category])?;
+        }
+        for tag in package.tags.iter().flatten() {
+            self.statements
+                .tags_insert
+                .execute(params![package_id, tag])?;
+        }
+
         Ok(())
     }
 }
@@ -4,6 +4,10 @@ pub struct DbStatements<'a> {
     pub repo_insert: Statement<'a>,
     pub repo_check: Statement<'a>,
     pub package_insert: Statement<'a>,
+    pub provides_insert: Statement<'a>,
+    pub aliases_insert: Statement<'a>,
+    pub categories_insert: Statement<'a>,
+    pub tags_insert: Statement<'a>,
 }
 
 impl<'a> DbStatements<'a> {
@@ -17,15 +21,22 @@ impl<'a> DbStatements<'a> {
                     pkg_webpage, app_id, description, version, download_url,
                     size, ghcr_pkg, ghcr_size, checksum, homepages, notes,
                     source_urls, tags, categories, icon, desktop, build_id,
-                    build_date, build_script, build_log
+                    build_date, build_script, build_log, collection,
+                    torrent
                 )
                 VALUES
                 (
                     ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13,
                     ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25,
-                    ?26
+                    ?26, ?27, ?28
                 )",
             )?,
+            provides_insert: tx
+                .prepare("INSERT INTO provides (package_id, name) VALUES (?1, ?2)")?,
+            aliases_insert: tx.prepare("INSERT INTO aliases (package_id, name) VALUES (?1, ?2)")?,
+            categories_insert: tx
+                .prepare("INSERT INTO categories (package_id, name) VALUES (?1, ?2)")?,
+            tags_insert: tx.prepare("INSERT INTO tags (package_id, name) VALUES (?1, ?2)")?,
         })
     }
 }
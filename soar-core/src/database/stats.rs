@@ -0,0 +1,29 @@
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection};
+
+use crate::{error::SoarError, SoarResult};
+
+/// Counts a `soar run` of `pkg_name`, `cache_hit` telling whether the binary
+/// was already in the cache.
+pub fn record_run(db: Arc<Mutex<Connection>>, pkg_name: &str, cache_hit: bool) -> SoarResult<()> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    conn.execute(
+        "INSERT INTO run_stats (pkg_name, hits, misses) VALUES (?1, ?2, ?3)
+        ON CONFLICT(pkg_name) DO UPDATE SET
+            hits = hits + excluded.hits,
+            misses = misses + excluded.misses",
+        params![pkg_name, cache_hit as u64, !cache_hit as u64],
+    )?;
+    Ok(())
+}
+
+/// Returns the total number of cache hits and misses of `soar run`.
+pub fn get_run_stats(db: Arc<Mutex<Connection>>) -> SoarResult<(u64, u64)> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    Ok(conn.query_row(
+        "SELECT COALESCE(SUM(hits), 0), COALESCE(SUM(misses), 0) FROM run_stats",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?)
+}
@@ -0,0 +1,70 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{error::SoarError, SoarResult};
+
+/// Last successful sync of a repository's metadata.
+pub struct SyncState {
+    /// ETag of the metadata that was fetched.
+    pub etag: Option<String>,
+    /// When the metadata was last fetched or found to be up to date.
+    pub fetched_at: SystemTime,
+}
+
+pub fn get_sync_state(
+    db: Arc<Mutex<Connection>>,
+    repo_name: &str,
+) -> SoarResult<Option<SyncState>> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    Ok(conn
+        .query_row(
+            "SELECT etag, fetched_at FROM sync_state WHERE repo_name = ?1",
+            [repo_name],
+            |row| {
+                Ok(SyncState {
+                    etag: row.get(0)?,
+                    fetched_at: UNIX_EPOCH + Duration::from_secs(row.get(1)?),
+                })
+            },
+        )
+        .optional()?)
+}
+
+/// Records that the metadata of `repo_name` with `etag` was just synced.
+pub fn record_sync(db: Arc<Mutex<Connection>>, repo_name: &str, etag: &str) -> SoarResult<()> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    conn.execute(
+        "INSERT INTO sync_state (repo_name, etag, fetched_at) VALUES (?1, ?2, ?3)
+        ON CONFLICT(repo_name) DO UPDATE SET
+            etag = excluded.etag,
+            fetched_at = excluded.fetched_at",
+        params![repo_name, etag, now],
+    )?;
+    Ok(())
+}
+
+/// Forgets the sync state of `repo_name`, so its metadata is fetched again
+/// on next use.
+pub fn remove_sync_state(db: Arc<Mutex<Connection>>, repo_name: &str) -> SoarResult<()> {
+    let conn = db.lock().map_err(|_| SoarError::PoisonError)?;
+    conn.execute("DELETE FROM sync_state WHERE repo_name = ?1", [repo_name])?;
+    Ok(())
+}
+
+/// When the metadata of `repo_name` was last synced, or `None` if it never
+/// was.
+pub fn last_synced(db: Arc<Mutex<Connection>>, repo_name: &str) -> SoarResult<Option<SystemTime>> {
+    Ok(get_sync_state(db, repo_name)?.map(|state| state.fetched_at))
+}
+
+/// Whether the metadata of `repo_name` was never synced or is older than
+/// `ttl`.
+pub fn is_stale(db: Arc<Mutex<Connection>>, repo_name: &str, ttl: Duration) -> SoarResult<bool> {
+    Ok(last_synced(db, repo_name)?
+        .is_none_or(|synced| synced.elapsed().map_or(true, |age| age >= ttl)))
+}
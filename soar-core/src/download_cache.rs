@@ -0,0 +1,237 @@
+//! Cache of the files downloaded with `soar dl`.
+//!
+//! Files are kept once under their checksum in `blobs/`, and each URL has an
+//! entry in `entries/` naming the blob along with the ETag and Last-Modified
+//! validators it was downloaded with. Downloading the URL again only takes a
+//! HEAD request to check the validators are still current, after which the
+//! blob is copied to the output path. Copies rather than links, so that
+//! editing one download doesn't change the others.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::header::{ETAG, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::get_config,
+    error::SoarError,
+    http::{client, send_with_retry},
+    utils::{calculate_checksum, replace_atomic},
+    SoarResult,
+};
+
+/// A cached download.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    /// Name the file was saved under.
+    pub name: String,
+    pub checksum: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the entry was stored or last used, in seconds since the epoch.
+    pub last_used: u64,
+}
+
+/// Validators of the current version of a remote file.
+#[derive(Debug, Clone)]
+pub struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Whether `entry` was downloaded from this version of the file.
+    fn matches(&self, entry: &CacheEntry) -> bool {
+        // Last-Modified only counts if there are no ETags to compare
+        match (&self.etag, &entry.etag) {
+            (Some(etag), Some(cached)) => etag == cached,
+            (None, None) => {
+                self.last_modified.is_some() && self.last_modified == entry.last_modified
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Looks up the validators of `url`, `None` if it has none or can't be
+/// reached, in which case it can't be cached.
+pub async fn fetch_validators(url: &str) -> Option<Validators> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return None;
+    }
+    let client = client();
+    let resp = send_with_retry(|| client.head(url)).await.ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+    };
+    let validators = Validators {
+        // weak ETags are good enough to tell versions apart
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    (validators.etag.is_some() || validators.last_modified.is_some()).then_some(validators)
+}
+
+fn cache_dir() -> SoarResult<PathBuf> {
+    Ok(get_config().get_cache_path()?.join("downloads"))
+}
+
+fn entry_path(dir: &Path, url: &str) -> PathBuf {
+    let key = blake3::hash(url.as_bytes()).to_hex();
+    dir.join("entries").join(format!("{}.json", key))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+fn write_entry(path: &Path, entry: &CacheEntry) -> SoarResult<()> {
+    let content = serde_json::to_vec(entry)
+        .map_err(|err| SoarError::Custom(format!("Failed to serialize cache entry: {}", err)))?;
+    replace_atomic(path, |temp| fs::write(temp, &content))
+}
+
+/// Restores the cached download of `url` to `output_path`, a directory if
+/// it ends with a slash, if it's still the version `validators` describe.
+///
+/// Returns where the file was restored to, `None` on a cache miss.
+pub fn restore(
+    url: &str,
+    validators: &Validators,
+    output_path: &str,
+) -> SoarResult<Option<PathBuf>> {
+    let dir = cache_dir()?;
+    let path = entry_path(&dir, url);
+    let Some(mut entry) = read_entry(&path) else {
+        return Ok(None);
+    };
+    if !validators.matches(&entry) {
+        return Ok(None);
+    }
+
+    // the downloaded file the blob is linked to may have been modified in
+    // place, taking the blob along with it
+    let blob = dir.join("blobs").join(&entry.checksum);
+    if !calculate_checksum(&blob).is_ok_and(|checksum| checksum == entry.checksum) {
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&blob);
+        return Ok(None);
+    }
+
+    let target = if output_path.ends_with('/') {
+        Path::new(output_path).join(&entry.name)
+    } else {
+        PathBuf::from(output_path)
+    };
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    replace_atomic(&target, |temp| fs::copy(&blob, temp).map(|_| ()))?;
+
+    entry.last_used = now();
+    write_entry(&path, &entry)?;
+    Ok(Some(target))
+}
+
+/// Caches the file at `path`, downloaded from `url` at the version
+/// `validators` describe.
+pub fn insert(url: &str, validators: &Validators, path: &Path) -> SoarResult<()> {
+    let dir = cache_dir()?;
+    let checksum = calculate_checksum(path)?;
+    let blobs = dir.join("blobs");
+    fs::create_dir_all(&blobs)?;
+    let blob = blobs.join(&checksum);
+    if !blob.is_file() {
+        // copied where hard links aren't possible
+        replace_atomic(&blob, |temp| {
+            fs::hard_link(path, temp).or_else(|_| fs::copy(path, temp).map(|_| ()))
+        })?;
+    }
+
+    let entry = CacheEntry {
+        url: url.to_string(),
+        name: path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        checksum,
+        size: fs::metadata(path)?.len(),
+        etag: validators.etag.clone(),
+        last_modified: validators.last_modified.clone(),
+        last_used: now(),
+    };
+    let path = entry_path(&dir, url);
+    fs::create_dir_all(path.parent().unwrap())?;
+    write_entry(&path, &entry)
+}
+
+/// Cached downloads, most recently used first.
+pub fn entries() -> SoarResult<Vec<CacheEntry>> {
+    let Ok(dir) = fs::read_dir(cache_dir()?.join("entries")) else {
+        return Ok(Vec::new());
+    };
+    let mut entries: Vec<CacheEntry> = dir
+        .filter_map(|entry| read_entry(&entry.ok()?.path()))
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+    Ok(entries)
+}
+
+/// Removes the downloads not used within `max_age`, or all of them without
+/// one, returning how many were removed and the bytes freed.
+pub fn prune(max_age: Option<Duration>) -> SoarResult<(usize, u64)> {
+    let dir = cache_dir()?;
+    let cutoff = max_age.map(|age| now().saturating_sub(age.as_secs()));
+
+    let mut removed = 0;
+    let mut kept = HashSet::new();
+    if let Ok(entries) = fs::read_dir(dir.join("entries")) {
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+            match read_entry(&path) {
+                Some(entry) if cutoff.is_some_and(|cutoff| entry.last_used >= cutoff) => {
+                    kept.insert(entry.checksum);
+                }
+                _ => {
+                    fs::remove_file(&path)?;
+                    removed += 1;
+                }
+            }
+        }
+    }
+
+    // blobs are shared between URLs serving the same file
+    let mut freed = 0;
+    if let Ok(blobs) = fs::read_dir(dir.join("blobs")) {
+        for blob in blobs.filter_map(Result::ok) {
+            if kept.contains(blob.file_name().to_string_lossy().as_ref()) {
+                continue;
+            }
+            let size = blob.metadata().map(|meta| meta.len()).unwrap_or(0);
+            fs::remove_file(blob.path())?;
+            freed += size;
+        }
+    }
+
+    Ok((removed, freed))
+}
@@ -1,4 +1,6 @@
-use std::error::Error;
+use std::{error::Error, fmt::Display, io};
+
+use soar_dl::error::{DownloadError, PlatformError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -28,10 +30,10 @@ pub enum SoarError {
     ReqwestError(#[from] reqwest::Error),
 
     #[error("Download failed: {0}")]
-    DownloadError(#[from] soar_dl::error::DownloadError),
+    DownloadError(#[from] DownloadError),
 
     #[error("{0}")]
-    PlatformError(soar_dl::error::PlatformError),
+    PlatformError(PlatformError),
 
     #[error("Squashy Error: {0}")]
     SquishyError(#[from] squishy::error::SquishyError),
@@ -51,6 +53,9 @@ pub enum SoarError {
     #[error("Invalid path specified")]
     InvalidPath,
 
+    #[error("Path is not valid UTF-8: {0}")]
+    NonUtf8Path(String),
+
     #[error("Thread lock poison error")]
     PoisonError,
 
@@ -63,17 +68,222 @@ pub enum SoarError {
     #[error("Configuration file already exists")]
     ConfigAlreadyExists,
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Signature verification failed for {0}")]
+    SignatureVerificationFailed(String),
+
+    #[error("Signing key of repository {repo_name} has changed to {key}")]
+    UntrustedKey { repo_name: String, key: String },
+
+    #[error("{0} installed package(s) have known vulnerabilities")]
+    VulnerabilitiesFound(usize),
+
     #[error("Invalid package query: {0}")]
     InvalidPackageQuery(String),
 
+    #[error("Invalid filter expression: {0}")]
+    InvalidFilter(String),
+
     #[error("{0}")]
     Custom(String),
 
     #[error("Invalid profile: {0}")]
     InvalidProfile(String),
+
+    #[error("Repository {0} not found")]
+    RepositoryNotFound(String),
+
+    #[error("Invalid repository name: {0}")]
+    InvalidRepositoryName(String),
+
+    #[error("Invalid package name: {0:?}")]
+    InvalidPackageName(String),
+
+    #[error("Failed to gain privileges: {0}")]
+    ElevationFailed(String),
+
+    #[error(
+        "Another soar process{} is running, use --wait to wait for it",
+        .0.map(|pid| format!(" (pid {})", pid)).unwrap_or_default()
+    )]
+    Locked(Option<u32>),
+
+    #[error("Repository {0} is not trusted")]
+    UntrustedRepository(String),
+
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    /// Some packages of a batch failed, the first of them with `kind`.
+    #[error("Failed to install {failed} of {total} packages")]
+    InstallFailed {
+        failed: usize,
+        total: usize,
+        kind: ErrorKind,
+    },
+
+    #[error("Not enough free space on {path}: {required} needed, {available} available")]
+    InsufficientSpace {
+        path: String,
+        required: String,
+        available: String,
+    },
+}
+
+/// Broad failure category of a [`SoarError`].
+///
+/// Each kind maps to a distinct process exit code so scripts can branch on
+/// the category of failure without parsing messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Other,
+    InvalidInput,
+    Config,
+    NotFound,
+    Network,
+    ChecksumMismatch,
+    Conflict,
+    PermissionDenied,
+    Database,
+    Io,
+    Vulnerable,
+    Signature,
+    Cancelled,
+}
+
+impl ErrorKind {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::InvalidInput => 2,
+            ErrorKind::Config => 3,
+            ErrorKind::NotFound => 4,
+            ErrorKind::Network => 5,
+            ErrorKind::ChecksumMismatch => 6,
+            ErrorKind::Conflict => 7,
+            ErrorKind::PermissionDenied => 8,
+            ErrorKind::Database => 9,
+            ErrorKind::Io => 10,
+            ErrorKind::Vulnerable => 11,
+            ErrorKind::Signature => 12,
+            // as if killed by SIGINT
+            ErrorKind::Cancelled => 130,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Other => "other",
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::Config => "config",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Network => "network",
+            ErrorKind::ChecksumMismatch => "checksum_mismatch",
+            ErrorKind::Conflict => "conflict",
+            ErrorKind::PermissionDenied => "permission_denied",
+            ErrorKind::Database => "database",
+            ErrorKind::Io => "io",
+            ErrorKind::Vulnerable => "vulnerable",
+            ErrorKind::Signature => "signature",
+            ErrorKind::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn io_error_kind(err: &io::Error) -> ErrorKind {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+        io::ErrorKind::AlreadyExists => ErrorKind::Conflict,
+        _ => ErrorKind::Io,
+    }
+}
+
+fn download_error_kind(err: &DownloadError) -> ErrorKind {
+    match err {
+        DownloadError::InvalidUrl { .. } | DownloadError::InvalidOptions(_) => {
+            ErrorKind::InvalidInput
+        }
+        DownloadError::IoError(err) => io_error_kind(err),
+        DownloadError::NoMatchingFiles { .. } => ErrorKind::NotFound,
+        DownloadError::ResourceError { status, .. } if status.as_u16() == 404 => {
+            ErrorKind::NotFound
+        }
+        DownloadError::NetworkError { .. }
+        | DownloadError::ResourceError { .. }
+        | DownloadError::InvalidResponse
+        | DownloadError::InvalidZsync(_)
+        | DownloadError::TorrentError(_)
+        | DownloadError::ExternalError(_) => ErrorKind::Network,
+        DownloadError::DigestMismatch { .. } => ErrorKind::ChecksumMismatch,
+        DownloadError::Cancelled => ErrorKind::Cancelled,
+    }
 }
 
 impl SoarError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::IoError(err) => io_error_kind(err),
+            Self::Errno(nix::errno::Errno::EACCES) | Self::Errno(nix::errno::Errno::EPERM) => {
+                ErrorKind::PermissionDenied
+            }
+            Self::Errno(_) | Self::SystemTimeError(_) | Self::InsufficientSpace { .. } => {
+                ErrorKind::Io
+            }
+            Self::ReqwestError(_) | Self::FailedToFetchRemote(_) => ErrorKind::Network,
+            Self::DownloadError(err) => download_error_kind(err),
+            Self::PlatformError(err) => match err {
+                PlatformError::DownloadError(err) => download_error_kind(err),
+                PlatformError::InvalidInput(_) | PlatformError::InvalidOptions(_) => {
+                    ErrorKind::InvalidInput
+                }
+                PlatformError::NoMatchingAssets { .. }
+                | PlatformError::NoRelease { .. }
+                | PlatformError::RepositoryNotFound { .. } => ErrorKind::NotFound,
+                PlatformError::ApiError { .. }
+                | PlatformError::InvalidResponse
+                | PlatformError::RateLimited { .. } => ErrorKind::Network,
+            },
+            Self::RusqliteError(_) | Self::DatabaseError(_) => ErrorKind::Database,
+            Self::PackageNotFound(_) | Self::RepositoryNotFound(_) => ErrorKind::NotFound,
+            Self::InvalidChecksum => ErrorKind::ChecksumMismatch,
+            Self::VarError(_)
+            | Self::TomlError(_)
+            | Self::InvalidConfig
+            | Self::InvalidProfile(_) => ErrorKind::Config,
+            Self::ConfigAlreadyExists | Self::Conflict(_) | Self::Locked(_) => ErrorKind::Conflict,
+            Self::InvalidPath
+            | Self::NonUtf8Path(_)
+            | Self::InvalidPackageQuery(_)
+            | Self::InvalidFilter(_)
+            | Self::InvalidRepositoryName(_)
+            | Self::InvalidPackageName(_) => ErrorKind::InvalidInput,
+            Self::VulnerabilitiesFound(_) => ErrorKind::Vulnerable,
+            Self::Cancelled => ErrorKind::Cancelled,
+            Self::InstallFailed { kind, .. } => *kind,
+            Self::ElevationFailed(_) => ErrorKind::PermissionDenied,
+            Self::SignatureVerificationFailed(_)
+            | Self::UntrustedKey { .. }
+            | Self::UntrustedRepository(_) => ErrorKind::Signature,
+            Self::SquishyError(_)
+            | Self::ImageError(_)
+            | Self::PackageIntegrationFailed(_)
+            | Self::PoisonError
+            | Self::Custom(_) => ErrorKind::Other,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.kind().exit_code()
+    }
+
     pub fn message(&self) -> String {
         self.to_string()
     }
@@ -106,8 +316,8 @@ impl<T> From<std::sync::PoisonError<T>> for SoarError {
     }
 }
 
-impl From<soar_dl::error::PlatformError> for SoarError {
-    fn from(value: soar_dl::error::PlatformError) -> Self {
+impl From<PlatformError> for SoarError {
+    fn from(value: PlatformError) -> Self {
         Self::PlatformError(value)
     }
 }
@@ -0,0 +1,99 @@
+use std::{path::PathBuf, sync::Arc};
+
+use serde::Serialize;
+use soar_dl::downloader::DownloadState;
+
+/// Typed events emitted while resolving and installing packages.
+///
+/// Every event carries the `pkg_name` and `pkg_id` of the package it refers
+/// to so consumers handling several packages at once can tell them apart.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum InstallEvent {
+    Resolved {
+        pkg_name: String,
+        pkg_id: String,
+        version: String,
+        repo_name: String,
+    },
+    DownloadStarted {
+        pkg_name: String,
+        pkg_id: String,
+        total: u64,
+    },
+    DownloadProgress {
+        pkg_name: String,
+        pkg_id: String,
+        downloaded: u64,
+    },
+    DownloadComplete {
+        pkg_name: String,
+        pkg_id: String,
+    },
+    Verified {
+        pkg_name: String,
+        pkg_id: String,
+        checksum: String,
+    },
+    Linked {
+        pkg_name: String,
+        pkg_id: String,
+        bin_path: PathBuf,
+    },
+    Failed {
+        pkg_name: String,
+        pkg_id: String,
+        error: String,
+    },
+}
+
+impl InstallEvent {
+    pub fn pkg_name(&self) -> &str {
+        match self {
+            InstallEvent::Resolved { pkg_name, .. }
+            | InstallEvent::DownloadStarted { pkg_name, .. }
+            | InstallEvent::DownloadProgress { pkg_name, .. }
+            | InstallEvent::DownloadComplete { pkg_name, .. }
+            | InstallEvent::Verified { pkg_name, .. }
+            | InstallEvent::Linked { pkg_name, .. }
+            | InstallEvent::Failed { pkg_name, .. } => pkg_name,
+        }
+    }
+
+    pub fn pkg_id(&self) -> &str {
+        match self {
+            InstallEvent::Resolved { pkg_id, .. }
+            | InstallEvent::DownloadStarted { pkg_id, .. }
+            | InstallEvent::DownloadProgress { pkg_id, .. }
+            | InstallEvent::DownloadComplete { pkg_id, .. }
+            | InstallEvent::Verified { pkg_id, .. }
+            | InstallEvent::Linked { pkg_id, .. }
+            | InstallEvent::Failed { pkg_id, .. } => pkg_id,
+        }
+    }
+
+    /// Translates a downloader state into the matching download event,
+    /// `None` for the progress of single OCI layers, which the overall
+    /// progress already covers.
+    pub fn from_download_state(pkg_name: &str, pkg_id: &str, state: DownloadState) -> Option<Self> {
+        let pkg_name = pkg_name.to_string();
+        let pkg_id = pkg_id.to_string();
+        Some(match state {
+            DownloadState::Preparing(total) => InstallEvent::DownloadStarted {
+                pkg_name,
+                pkg_id,
+                total,
+            },
+            DownloadState::Progress(downloaded) => InstallEvent::DownloadProgress {
+                pkg_name,
+                pkg_id,
+                downloaded,
+            },
+            DownloadState::LayerProgress { .. } => return None,
+            DownloadState::Complete => InstallEvent::DownloadComplete { pkg_name, pkg_id },
+        })
+    }
+}
+
+/// Caller-provided receiver for [`InstallEvent`]s.
+pub type EventSink = Arc<dyn Fn(InstallEvent) + Send + Sync>;
@@ -0,0 +1,239 @@
+use std::{collections::HashMap, future::Future, time::Duration};
+
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, RequestBuilder, Response, StatusCode,
+};
+use soar_dl::{
+    error::{DownloadError, PlatformError},
+    external::ExternalDownloader,
+    http_client::{shared_client, ClientConfig},
+};
+
+use crate::{
+    config::get_config,
+    constants::DEFAULT_IPFS_GATEWAYS,
+    error::{ErrorKind, SoarError},
+    SoarResult,
+};
+
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_BACKOFF_MS: u64 = 500;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Returns the HTTP client shared with soar-dl.
+pub fn client() -> Client {
+    shared_client()
+}
+
+/// Parses configured header names and values.
+pub fn header_map(headers: &HashMap<String, String>) -> SoarResult<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let invalid = || SoarError::Custom(format!("Invalid HTTP header: {}", name));
+        map.insert(
+            HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+            HeaderValue::from_str(value).map_err(|_| invalid())?,
+        );
+    }
+    Ok(map)
+}
+
+/// Settings for the shared HTTP client with the configured user agent,
+/// headers and timeouts. `user_agent` is used unless one is configured.
+pub fn client_config(user_agent: String) -> ClientConfig {
+    let config = get_config();
+    ClientConfig {
+        user_agent: config.user_agent.clone().unwrap_or(user_agent),
+        headers: header_map(&config.headers)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(name, value)| Some((name?, value)))
+            .collect(),
+        connect_timeout: config.connect_timeout(),
+        read_timeout: config.read_timeout(),
+        timeout: config.download_timeout(),
+        ..Default::default()
+    }
+}
+
+/// External downloaders in the order they're configured.
+pub fn external_downloaders() -> Vec<ExternalDownloader> {
+    get_config()
+        .downloaders
+        .iter()
+        .filter_map(|downloader| {
+            Some(ExternalDownloader {
+                pattern: Regex::new(&downloader.pattern).ok()?,
+                command: downloader.command.clone(),
+            })
+        })
+        .collect()
+}
+
+/// How often and how patiently failed network operations are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_RETRIES,
+            initial_backoff: Duration::from_millis(DEFAULT_BACKOFF_MS),
+            max_backoff: MAX_BACKOFF,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_config() -> Self {
+        let config = get_config();
+        Self {
+            max_retries: config.retries.unwrap_or(DEFAULT_RETRIES),
+            initial_backoff: Duration::from_millis(
+                config.retry_backoff.unwrap_or(DEFAULT_BACKOFF_MS),
+            ),
+            max_backoff: MAX_BACKOFF,
+        }
+    }
+
+    /// Delay before the given retry attempt (starting at 0), doubling each
+    /// time up to `max_backoff`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+/// Whether a response with this status is worth retrying.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+fn is_retryable_reqwest(err: &reqwest::Error) -> bool {
+    if let Some(status) = err.status() {
+        return is_retryable_status(status);
+    }
+    err.is_connect() || err.is_timeout() || err.is_request() || err.is_body()
+}
+
+fn is_retryable_download(err: &DownloadError) -> bool {
+    match err {
+        DownloadError::NetworkError { source } => is_retryable_reqwest(source),
+        DownloadError::ResourceError { status, .. } => is_retryable_status(*status),
+        DownloadError::InvalidUrl { .. }
+        | DownloadError::InvalidOptions(_)
+        | DownloadError::DigestMismatch { .. }
+        | DownloadError::NoMatchingFiles { .. }
+        | DownloadError::IoError(_)
+        | DownloadError::InvalidResponse
+        | DownloadError::InvalidZsync(_)
+        | DownloadError::TorrentError(_)
+        // external programs retry on their own
+        | DownloadError::ExternalError(_)
+        | DownloadError::Cancelled => false,
+    }
+}
+
+/// Whether an error is transient, i.e. the operation may succeed if retried.
+pub fn is_retryable(err: &SoarError) -> bool {
+    match err {
+        SoarError::ReqwestError(err) => is_retryable_reqwest(err),
+        SoarError::DownloadError(err) => is_retryable_download(err),
+        SoarError::PlatformError(PlatformError::DownloadError(err)) => is_retryable_download(err),
+        SoarError::PlatformError(PlatformError::ApiError { status }) => {
+            is_retryable_status(*status)
+        }
+        _ => false,
+    }
+}
+
+/// Runs `op` until it succeeds, fails with a non-retryable error, or the
+/// configured number of retries is exhausted.
+pub async fn with_retry<T, F, Fut>(mut op: F) -> SoarResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = SoarResult<T>>,
+{
+    let policy = RetryPolicy::from_config();
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(err) if attempt < policy.max_retries && is_retryable(&err) => {
+                tokio::time::sleep(policy.backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// URLs `url` can be downloaded from: one per configured gateway, in order,
+/// for `ipfs://` URLs, or just `url` itself otherwise.
+pub fn source_urls(url: &str) -> Vec<String> {
+    let Some(path) = url.strip_prefix("ipfs://") else {
+        return vec![url.to_string()];
+    };
+
+    let config = get_config();
+    let gateways: Vec<&str> = match config.ipfs_gateways {
+        Some(ref gateways) => gateways.iter().map(String::as_str).collect(),
+        None => DEFAULT_IPFS_GATEWAYS.to_vec(),
+    };
+    gateways
+        .into_iter()
+        .map(|gateway| format!("{}/ipfs/{}", gateway.trim_end_matches('/'), path))
+        .collect()
+}
+
+/// Runs `op` with each URL `url` can be downloaded from, see
+/// [`source_urls`], until it succeeds. Each URL is retried as with
+/// [`with_retry`] before failing over to the next one.
+pub async fn with_failover<T, F, Fut>(url: &str, mut op: F) -> SoarResult<T>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = SoarResult<T>>,
+{
+    let mut last_err = None;
+    for url in source_urls(url) {
+        match with_retry(|| op(url.clone())).await {
+            Ok(value) => return Ok(value),
+            Err(err) if err.kind() == ErrorKind::Cancelled => return Err(err),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| SoarError::Custom(format!("No IPFS gateways to fetch {}", url))))
+}
+
+/// Sends the request built by `build`, retrying on transient connection
+/// errors and retryable response statuses.
+///
+/// The last response is returned as is once retries are exhausted, so the
+/// caller is still responsible for checking its status.
+pub async fn send_with_retry<F>(build: F) -> SoarResult<Response>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let policy = RetryPolicy::from_config();
+    let mut attempt = 0;
+    loop {
+        let result = build().send().await;
+        let retry = match &result {
+            Ok(resp) => is_retryable_status(resp.status()),
+            Err(err) => is_retryable_reqwest(err),
+        };
+        if !retry || attempt >= policy.max_retries {
+            return Ok(result?);
+        }
+        tokio::time::sleep(policy.backoff(attempt)).await;
+        attempt += 1;
+    }
+}
@@ -0,0 +1,178 @@
+//! Package indexes for simple static-file repositories.
+//!
+//! Instead of the metadata published by the pkgforge repositories, a
+//! repository can serve a package index as JSON or TOML from any web server,
+//! e.g. GitHub Pages. The index lists its packages under `packages`:
+//!
+//! ```toml
+//! [[packages]]
+//! name = "hello"
+//! version = "1.0.0"
+//! download_url = "https://example.com/hello-1.0.0-x86_64"
+//! description = "Prints a greeting"
+//! bsum = "<blake3 checksum of the download>"
+//! size = 12345
+//! ```
+//!
+//! or in JSON, `{"packages": [{"name": "hello", ...}]}`. Only `name`,
+//! `version` and `download_url` are required, see [`IndexPackage`] for the
+//! other fields. Fields taking a list also accept a single value.
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{database::models::RemotePackage, error::SoarError, SoarResult};
+
+#[derive(Deserialize)]
+struct PackageIndex {
+    #[serde(default)]
+    packages: Vec<IndexPackage>,
+}
+
+/// A package of a package index.
+#[derive(Deserialize)]
+pub struct IndexPackage {
+    /// Name of the package, also used as its binary name.
+    #[serde(alias = "pkg_name")]
+    pub name: String,
+
+    /// Identifies packages sharing a name. Defaults to the name.
+    #[serde(alias = "pkg_id")]
+    pub id: Option<String>,
+
+    pub version: String,
+
+    /// URL of the binary or archive to install.
+    pub download_url: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    /// blake3 checksum of the download. Not verified if missing.
+    #[serde(default, alias = "checksum")]
+    pub bsum: String,
+
+    /// Size of the download in bytes.
+    pub size: Option<u64>,
+
+    /// Package format, e.g. `appimage`. Defaults to `static`.
+    #[serde(alias = "pkg_type")]
+    pub format: Option<String>,
+
+    #[serde(default, alias = "homepage", deserialize_with = "one_or_many")]
+    pub homepages: Vec<String>,
+
+    #[serde(default, alias = "src_url", deserialize_with = "one_or_many")]
+    pub src_urls: Vec<String>,
+
+    #[serde(default, alias = "note", deserialize_with = "one_or_many")]
+    pub notes: Vec<String>,
+
+    #[serde(default, alias = "tag", deserialize_with = "one_or_many")]
+    pub tags: Vec<String>,
+
+    #[serde(default, alias = "category", deserialize_with = "one_or_many")]
+    pub categories: Vec<String>,
+
+    /// Other binaries the package provides.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub provides: Vec<String>,
+
+    /// Other names the package can be installed by.
+    #[serde(default, alias = "alias", deserialize_with = "one_or_many")]
+    pub aliases: Vec<String>,
+
+    /// URL of the icon used for desktop integration.
+    pub icon: Option<String>,
+
+    /// URL of the desktop entry used for desktop integration.
+    pub desktop: Option<String>,
+
+    pub app_id: Option<String>,
+
+    /// When the package was built, as an RFC 3339 timestamp or a
+    /// `YYYY-MM-DD` date.
+    pub build_date: Option<String>,
+
+    /// Group of packages this one belongs to, e.g. `bin` or `appimage`.
+    /// Defaults to the `collection` of the repository config.
+    pub collection: Option<String>,
+
+    /// Magnet link or URL of a `.torrent` file to download the package from
+    /// if `download_url` fails. Needs soar built with the `torrent` feature.
+    pub torrent: Option<String>,
+
+    /// Whether the package is hidden from installs.
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+impl From<IndexPackage> for RemotePackage {
+    fn from(package: IndexPackage) -> Self {
+        RemotePackage {
+            disabled: package.disabled.to_string(),
+            pkg: package.name.clone(),
+            pkg_id: package.id.unwrap_or_else(|| package.name.clone()),
+            pkg_name: package.name,
+            pkg_type: package.format.unwrap_or_else(|| "static".to_string()),
+            description: package.description,
+            version: package.version,
+            download_url: package.download_url,
+            size_raw: package.size.unwrap_or_default().to_string(),
+            src_urls: package.src_urls,
+            homepages: package.homepages,
+            notes: (!package.notes.is_empty()).then_some(package.notes),
+            tags: (!package.tags.is_empty()).then_some(package.tags),
+            bsum: package.bsum,
+            categories: package.categories,
+            provides: package.provides,
+            aliases: package.aliases,
+            icon: package.icon,
+            desktop: package.desktop,
+            app_id: package.app_id,
+            build_date: package.build_date,
+            collection: package.collection,
+            torrent: package.torrent,
+            ..Default::default()
+        }
+    }
+}
+
+/// Parses a package index, in JSON if it's an object and TOML otherwise.
+pub fn parse_index(content: &[u8]) -> SoarResult<Vec<RemotePackage>> {
+    let is_json = content
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| *byte == b'{');
+    let index: PackageIndex = if is_json {
+        serde_json::from_slice(content)
+            .map_err(|err| SoarError::Custom(format!("Failed to parse package index: {}", err)))?
+    } else {
+        let content = std::str::from_utf8(content)
+            .map_err(|err| SoarError::Custom(format!("Failed to parse package index: {}", err)))?;
+        toml::from_str(content)
+            .map_err(|err| SoarError::Custom(format!("Failed to parse package index: {}", err)))?
+    };
+
+    Ok(index
+        .packages
+        .into_iter()
+        .map(RemotePackage::from)
+        .collect())
+}
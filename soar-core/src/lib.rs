@@ -1,11 +1,24 @@
 use error::SoarError;
 
+pub mod attestation;
 pub mod config;
 pub mod constants;
 pub mod database;
+pub mod download_cache;
 pub mod error;
+pub mod events;
+pub mod http;
+pub mod index;
+pub mod lock;
 pub mod metadata;
 pub mod package;
+pub mod package_cache;
+pub mod platform;
+pub mod popularity;
+pub mod sbom;
+pub mod signing;
+pub mod store;
+pub mod trust;
 pub mod utils;
 
 pub type SoarResult<T> = std::result::Result<T, SoarError>;
@@ -0,0 +1,79 @@
+//! Advisory lock keeping soar processes from modifying the same installation
+//! at once.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Seek, Write},
+    path::PathBuf,
+    process,
+};
+
+use nix::{
+    errno::Errno,
+    fcntl::{Flock, FlockArg},
+};
+
+use crate::{config::get_config, error::SoarError, SoarResult};
+
+/// Exclusive lock on the installation, released when dropped.
+pub struct InstanceLock {
+    _lock: Flock<File>,
+}
+
+fn lock_path() -> SoarResult<PathBuf> {
+    Ok(get_config().get_db_path()?.join("soar.lock"))
+}
+
+fn open_lock_file() -> SoarResult<File> {
+    let path = lock_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?)
+}
+
+impl InstanceLock {
+    /// Takes the lock, or returns `None` if another process holds it.
+    pub fn try_acquire() -> SoarResult<Option<Self>> {
+        match Flock::lock(open_lock_file()?, FlockArg::LockExclusiveNonblock) {
+            Ok(lock) => Ok(Some(Self::hold(lock)?)),
+            Err((_, Errno::EWOULDBLOCK)) => Ok(None),
+            Err((_, errno)) => Err(errno.into()),
+        }
+    }
+
+    /// Takes the lock, blocking until other processes release it.
+    pub fn acquire() -> SoarResult<Self> {
+        match Flock::lock(open_lock_file()?, FlockArg::LockExclusive) {
+            Ok(lock) => Self::hold(lock),
+            Err((_, errno)) => Err(errno.into()),
+        }
+    }
+
+    /// Takes the lock if it's free, failing with [`SoarError::Locked`]
+    /// otherwise.
+    pub fn acquire_or_fail() -> SoarResult<Self> {
+        Self::try_acquire()?.ok_or_else(|| SoarError::Locked(Self::holder()))
+    }
+
+    /// ID of the process holding the lock, if it's known.
+    pub fn holder() -> Option<u32> {
+        fs::read_to_string(lock_path().ok()?)
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    fn hold(mut lock: Flock<File>) -> SoarResult<Self> {
+        lock.set_len(0)?;
+        lock.rewind()?;
+        write!(lock, "{}", process::id())?;
+        Ok(Self { _lock: lock })
+    }
+}
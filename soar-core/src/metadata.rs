@@ -1,52 +1,146 @@
-use std::fs::{self, File};
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use flate2::read::MultiGzDecoder;
 
-use reqwest::header::{self, HeaderMap};
-use rusqlite::Connection;
+use reqwest::{header, StatusCode};
+use rusqlite::{params, Connection};
 
 use crate::{
     config::Repository,
     constants::METADATA_MIGRATIONS,
-    database::{connection::Database, migration::MigrationManager, models::RemotePackage},
+    database::{
+        connection::Database,
+        migration::MigrationManager,
+        models::RemotePackage,
+        sync_state::{get_sync_state, record_sync},
+    },
     error::SoarError,
+    http::{client, send_with_retry},
+    index::parse_index,
+    signing::verify_metadata,
+    utils::write_atomic,
     SoarResult,
 };
 
-pub async fn fetch_metadata(repo: Repository) -> SoarResult<()> {
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// Parses fetched metadata, which is either the JSON array published by the
+/// pkgforge repositories or a [package index](crate::index).
+fn parse_metadata(content: &[u8]) -> SoarResult<Vec<RemotePackage>> {
+    // a JSON array opens with `[{` or `[]`, TOML tables with `[name]`
+    let mut bytes = content.iter().filter(|byte| !byte.is_ascii_whitespace());
+    let is_array = bytes.next() == Some(&b'[') && matches!(bytes.next(), Some(b'{' | b']'));
+    if is_array {
+        serde_json::from_slice(content)
+            .map_err(|err| SoarError::Custom(format!("Failed to parse metadata: {}", err)))
+    } else {
+        parse_index(content)
+    }
+}
+
+/// Fetches the metadata of `repo` into its metadata database, unless the
+/// remote ETag matches the one recorded in the sync state of `core_db`.
+///
+/// The metadata can be the pkgforge JSON, a package index or a prebuilt
+/// metadata database, either of them optionally compressed with zstd or
+/// gzip. Compressed transfers are negotiated with the server, falling back
+/// to the plain file.
+///
+/// With `force`, the metadata is fetched and imported again regardless.
+pub async fn fetch_metadata(
+    repo: Repository,
+    core_db: Arc<Mutex<Connection>>,
+    force: bool,
+) -> SoarResult<()> {
     let repo_path = repo.get_path()?;
     if !repo_path.is_dir() {
         return Err(SoarError::InvalidPath);
     }
 
-    let client = reqwest::Client::new();
+    let metadata_db = repo_path.join("metadata.db");
+    let conn = Connection::open(&metadata_db)?;
+    // metadata imported by an older schema is missing data, so refetch it
+    let outdated = MigrationManager::new(conn)?.has_pending(METADATA_MIGRATIONS)?;
+    let etag = match get_sync_state(core_db.clone(), &repo.name)? {
+        Some(state) if !force && !outdated => state.etag,
+        _ => None,
+    };
 
-    let mut header_map = HeaderMap::new();
+    let client = client();
+
+    let mut header_map = repo.header_map();
     header_map.insert(header::CACHE_CONTROL, "no-cache".parse().unwrap());
     header_map.insert(header::PRAGMA, "no-cache".parse().unwrap());
+    header_map.insert(header::ACCEPT_ENCODING, "zstd, gzip".parse().unwrap());
+    if let Some(etag) = etag.as_ref().and_then(|etag| etag.parse().ok()) {
+        header_map.insert(header::IF_NONE_MATCH, etag);
+    }
 
-    let resp = client.get(&repo.url).headers(header_map).send().await?;
+    let resp = send_with_retry(|| client.get(&repo.url).headers(header_map.clone())).await?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        if let Some(etag) = etag {
+            record_sync(core_db, &repo.name, &etag)?;
+        }
+        return Ok(());
+    }
     if !resp.status().is_success() {
         return Err(SoarError::FailedToFetchRemote(repo.url));
     }
 
-    let metadata_db = repo_path.join("metadata.db");
+    let remote_etag = resp
+        .headers()
+        .get(header::ETAG)
+        .and_then(|remote_etag| remote_etag.to_str().ok())
+        .map(String::from);
+    if let Some(remote_etag) = remote_etag
+        .as_ref()
+        .filter(|remote| etag.as_ref() == Some(*remote))
+    {
+        record_sync(core_db, &repo.name, remote_etag)?;
+        return Ok(());
+    }
 
-    let conn = Connection::open(&metadata_db)?;
-    let etag: String = conn
-        .query_row("SELECT etag FROM repository", [], |row| row.get(0))
-        .unwrap_or_default();
-
-    let etag = if let Some(remote_etag) = resp.headers().get(header::ETAG) {
-        let remote_etag = remote_etag.to_str().unwrap();
-        if etag == remote_etag {
-            return Ok(());
+    let transfer_encoded = resp.headers().contains_key(header::CONTENT_ENCODING);
+    let mut content = resp.bytes().await?.to_vec();
+    if transfer_encoded {
+        content = decompress(content)?;
+    }
+
+    // static file hosts may not send an ETag, so tell versions apart by hash
+    let remote_etag = remote_etag.unwrap_or_else(|| blake3::hash(&content).to_hex().to_string());
+    if etag.as_deref() == Some(remote_etag.as_str()) {
+        record_sync(core_db, &repo.name, &remote_etag)?;
+        return Ok(());
+    }
+
+    // verify before touching the existing metadata so it stays usable, the
+    // signature covers the file as published, i.e. still compressed
+    verify_metadata(&repo, &content).await?;
+    let content = decompress(content)?;
+
+    if content.starts_with(SQLITE_MAGIC) {
+        import_database(&metadata_db, &content, &repo, &remote_etag)?;
+        record_sync(core_db, &repo.name, &remote_etag)?;
+        return Ok(());
+    }
+
+    let mut remote_metadata = parse_metadata(&content)?;
+    for package in &mut remote_metadata {
+        if package.collection.is_none() {
+            package.collection = Some(
+                repo.collection
+                    .clone()
+                    .unwrap_or_else(|| package.pkg_type.clone()),
+            );
         }
-        remote_etag.to_string()
-    } else {
-        return Err(SoarError::Custom(
-            "etag is required in metadata response header.".to_string(),
-        ));
-    };
-    let _ = conn.close();
+    }
 
     let _ = fs::remove_file(&metadata_db);
     File::create(&metadata_db)?;
@@ -55,10 +149,50 @@ pub async fn fetch_metadata(repo: Repository) -> SoarResult<()> {
     let mut manager = MigrationManager::new(conn)?;
     manager.migrate_from_dir(METADATA_MIGRATIONS)?;
 
-    let remote_metadata: Vec<RemotePackage> = resp.json().await?;
+    let db = Database::new(&metadata_db)?;
+    db.from_remote_metadata(remote_metadata.as_ref(), &repo.name, &remote_etag)?;
+    record_sync(core_db, &repo.name, &remote_etag)?;
+
+    Ok(())
+}
 
-    let db = Database::new(metadata_db)?;
-    db.from_remote_metadata(remote_metadata.as_ref(), &repo.name, &etag)?;
+/// Decompresses zstd or gzip compressed `content`, telling the formats apart
+/// by their magic bytes. Anything else is returned as is.
+fn decompress(content: Vec<u8>) -> SoarResult<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    if content.starts_with(ZSTD_MAGIC) {
+        zstd::Decoder::new(content.as_slice())?.read_to_end(&mut decompressed)?;
+    } else if content.starts_with(GZIP_MAGIC) {
+        MultiGzDecoder::new(content.as_slice()).read_to_end(&mut decompressed)?;
+    } else {
+        return Ok(content);
+    }
+    Ok(decompressed)
+}
+
+/// Replaces the metadata database with `content`, a metadata database built
+/// by the repository, and names it after the repository.
+fn import_database(
+    metadata_db: &Path,
+    content: &[u8],
+    repo: &Repository,
+    etag: &str,
+) -> SoarResult<()> {
+    write_atomic(metadata_db, content)?;
+
+    let conn = Connection::open(metadata_db)?;
+    let mut manager = MigrationManager::new(conn)?;
+    manager.migrate_from_dir(METADATA_MIGRATIONS)?;
 
+    let conn = Connection::open(metadata_db)?;
+    conn.execute("DELETE FROM repository", [])?;
+    conn.execute(
+        "INSERT INTO repository (name, etag) VALUES (?1, ?2)",
+        params![repo.name, etag],
+    )?;
+    conn.execute(
+        "UPDATE packages SET collection = COALESCE(?1, pkg_type) WHERE collection IS NULL",
+        [&repo.collection],
+    )?;
     Ok(())
 }
@@ -0,0 +1,174 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use rusqlite::{prepare_and_bind, Connection};
+
+use crate::{
+    config::get_config,
+    error::SoarError,
+    platform::{exe_name, link_binary, make_executable},
+    utils::{calculate_checksum, is_valid_file_name, replace_atomic},
+    SoarResult,
+};
+
+/// Repository name recorded for packages adopted from outside soar.
+pub const ADOPTED_REPO_NAME: &str = "local";
+
+/// Options for registering an existing binary with soar.
+#[derive(Clone, Default)]
+pub struct AdoptOptions {
+    /// Name to register the package under, defaults to the file name.
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// Upstream GitHub project as `owner/repo`, used to look up updates.
+    pub upstream: Option<String>,
+    /// Copy the file instead of moving it into soar's packages directory.
+    pub copy: bool,
+}
+
+/// Registered result of [`adopt_package`].
+#[derive(Debug, Clone)]
+pub struct AdoptedPackage {
+    pub pkg_name: String,
+    pub installed_path: PathBuf,
+    pub bin_path: PathBuf,
+    pub checksum: String,
+}
+
+/// Resolves `target` to an existing file, looking it up in `PATH` if it
+/// isn't a path.
+fn resolve_target(target: &str) -> SoarResult<PathBuf> {
+    let path = Path::new(target);
+    if target.contains('/') || path.exists() {
+        return Ok(path.canonicalize()?);
+    }
+
+    env::var_os("PATH")
+        .and_then(|paths| {
+            env::split_paths(&paths)
+                .map(|dir| dir.join(target))
+                .find(|candidate| candidate.is_file())
+        })
+        .map(|path| path.canonicalize())
+        .transpose()?
+        .ok_or_else(|| SoarError::PackageNotFound(target.to_string()))
+}
+
+/// Moves an externally downloaded binary or AppImage under soar's management
+/// and links it into the bin directory like an installed package.
+pub fn adopt_package(
+    target: &str,
+    options: &AdoptOptions,
+    core_db: Arc<Mutex<Connection>>,
+) -> SoarResult<AdoptedPackage> {
+    let source = resolve_target(target)?;
+    let metadata = fs::metadata(&source)?;
+    if !metadata.is_file() {
+        return Err(SoarError::Custom(format!(
+            "{} is not a file",
+            source.display()
+        )));
+    }
+
+    let config = get_config();
+    let packages_path = config.get_packages_path()?;
+    if source.starts_with(&packages_path) {
+        return Err(SoarError::Conflict(format!(
+            "{} is already managed by soar",
+            source.display()
+        )));
+    }
+
+    if let Some(ref upstream) = options.upstream {
+        if upstream.split('/').filter(|part| !part.is_empty()).count() != 2 {
+            return Err(SoarError::InvalidPackageQuery(format!(
+                "upstream must be in owner/repo form, got {}",
+                upstream
+            )));
+        }
+    }
+
+    let pkg_name = match options.name {
+        Some(ref name) => name.clone(),
+        None => source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or(SoarError::InvalidPath)?,
+    };
+    if !is_valid_file_name(&pkg_name) {
+        return Err(SoarError::InvalidPackageName(pkg_name));
+    }
+
+    let bin_path = config.get_bin_path()?.join(exe_name(&pkg_name));
+    // adopting a file that already sits in the bin directory frees its name
+    if bin_path.symlink_metadata().is_ok() && bin_path != source {
+        return Err(SoarError::Conflict(format!(
+            "{} already exists",
+            bin_path.display()
+        )));
+    }
+
+    let rand_str: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect();
+    let install_dir = packages_path.join(format!("{}-adopted-{}", pkg_name, rand_str));
+    fs::create_dir_all(&install_dir)?;
+    let real_bin = install_dir.join(&pkg_name);
+
+    if options.copy {
+        fs::copy(&source, &real_bin)?;
+    } else if fs::rename(&source, &real_bin).is_err() {
+        // rename fails across filesystems
+        fs::copy(&source, &real_bin)?;
+        fs::remove_file(&source)?;
+    }
+    make_executable(&real_bin)?;
+
+    replace_atomic(&bin_path, |temp| link_binary(&real_bin, temp))?;
+
+    let checksum = calculate_checksum(&real_bin)?;
+    let size = metadata.len();
+    let repo_name = ADOPTED_REPO_NAME;
+    let pkg_id = options
+        .upstream
+        .clone()
+        .unwrap_or_else(|| ADOPTED_REPO_NAME.to_string());
+    let version = options
+        .version
+        .clone()
+        .unwrap_or_else(|| "unknown".to_string());
+    let installed_path = install_dir.to_string_lossy();
+    let bin = bin_path.to_string_lossy();
+    let upstream = options.upstream.clone();
+    let profile = config.default_profile.clone();
+
+    let conn = core_db.lock()?;
+    let mut stmt = prepare_and_bind!(
+        conn,
+        "INSERT INTO packages (
+            repo_name, pkg, pkg_id, pkg_name, version, size, checksum,
+            installed_path, installed_date, bin_path, profile, is_installed,
+            upstream
+        )
+        VALUES
+        (
+            $repo_name, $pkg_name, $pkg_id, $pkg_name, $version, $size, $checksum,
+            $installed_path, datetime(), $bin, $profile, true,
+            $upstream
+        )"
+    );
+    stmt.raw_execute()?;
+
+    Ok(AdoptedPackage {
+        pkg_name,
+        installed_path: install_dir,
+        bin_path,
+        checksum,
+    })
+}
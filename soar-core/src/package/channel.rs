@@ -0,0 +1,75 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::error::SoarError;
+
+/// Release channel a package follows when it is updated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Channel {
+    /// Only stable releases.
+    #[default]
+    Stable,
+    /// Stable releases and prereleases.
+    Prerelease,
+    /// Whatever is newest, including nightly builds.
+    Nightly,
+}
+
+const PRERELEASE_MARKERS: &[&str] = &["alpha", "beta", "rc", "pre", "preview", "dev"];
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Prerelease => "prerelease",
+            Channel::Nightly => "nightly",
+        }
+    }
+
+    /// Guesses the channel a version belongs to from its name.
+    pub fn of_version(version: &str) -> Self {
+        let version = version.to_lowercase();
+        if version.contains("nightly") {
+            return Channel::Nightly;
+        }
+
+        let is_prerelease = version
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|part| {
+                let part = part.trim_end_matches(|c: char| c.is_ascii_digit());
+                PRERELEASE_MARKERS.contains(&part)
+            });
+        if is_prerelease {
+            Channel::Prerelease
+        } else {
+            Channel::Stable
+        }
+    }
+
+    /// Whether a package following this channel may update to `version`.
+    pub fn accepts(&self, version: &str) -> bool {
+        match self {
+            Channel::Stable => Channel::of_version(version) == Channel::Stable,
+            Channel::Prerelease => Channel::of_version(version) != Channel::Nightly,
+            Channel::Nightly => true,
+        }
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Channel {
+    type Err = SoarError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(Channel::Stable),
+            "prerelease" => Ok(Channel::Prerelease),
+            "nightly" => Ok(Channel::Nightly),
+            _ => Err(SoarError::Custom(format!("Invalid channel: {}", s))),
+        }
+    }
+}
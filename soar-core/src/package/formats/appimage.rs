@@ -1,14 +1,93 @@
 use std::{
-    fs,
-    path::{Path, PathBuf},
+    fs::{self, File, Permissions},
+    io::BufReader,
+    os::unix::fs::{symlink, PermissionsExt},
+    path::{Component, Path, PathBuf},
 };
 
 use squishy::{appimage::AppImage, EntryKind};
 
 use crate::{
-    constants::PNG_MAGIC_BYTES, database::models::Package, utils::calc_magic_bytes, SoarResult,
+    constants::PNG_MAGIC_BYTES, database::models::Package, error::SoarError,
+    utils::calc_magic_bytes, SoarResult,
 };
 
+use super::{get_file_type, PackageFormat};
+
+/// Directory an AppImage is unpacked into inside its install directory,
+/// named like the one `--appimage-extract` creates.
+pub const APPIMAGE_EXTRACT_DIR: &str = "squashfs-root";
+
+pub fn is_appimage<P: AsRef<Path>>(file_path: P) -> SoarResult<bool> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    Ok(get_file_type(&mut reader) == PackageFormat::AppImage)
+}
+
+/// Unpacks the squashfs of the AppImage at `file_path` into
+/// [`APPIMAGE_EXTRACT_DIR`] inside `install_dir`, so it can run without FUSE.
+///
+/// Returns the path of the binary to link: the file `AppRun` points to, or
+/// `AppRun` itself if it's a regular file such as a launcher script.
+pub fn extract_appimage<P: AsRef<Path>>(install_dir: P, file_path: P) -> SoarResult<PathBuf> {
+    let root = install_dir.as_ref().join(APPIMAGE_EXTRACT_DIR);
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+    fs::create_dir_all(&root)?;
+
+    let appimage = AppImage::new(None, &file_path, None)?;
+    let squashfs = &appimage.squashfs;
+    for entry in squashfs.entries() {
+        let Ok(relative) = entry.path.strip_prefix("/") else {
+            continue;
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(SoarError::PackageIntegrationFailed(format!(
+                "AppImage contains an invalid path: {}",
+                entry.path.display()
+            )));
+        }
+
+        let dest = root.join(relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match entry.kind {
+            EntryKind::Directory => {
+                fs::create_dir_all(&dest)?;
+                let mode = u32::from(entry.header.permissions) | 0o700;
+                fs::set_permissions(&dest, Permissions::from_mode(mode))?;
+            }
+            EntryKind::File(basic_file) => {
+                squashfs.write_file_with_permissions(basic_file, &dest, entry.header)?;
+            }
+            EntryKind::Symlink(target) => {
+                // squishy prefixes link targets with a slash, relative or not
+                symlink(target.strip_prefix("/").unwrap_or(&target), &dest)?;
+            }
+            EntryKind::Unknown => {}
+        }
+    }
+
+    let app_run = root.join("AppRun");
+    let resolved = fs::canonicalize(&app_run)
+        .map_err(|_| SoarError::PackageIntegrationFailed("AppImage has no AppRun".to_string()))?;
+    let root = fs::canonicalize(&root)?;
+    if !resolved.starts_with(&root) || !resolved.is_file() {
+        return Err(SoarError::PackageIntegrationFailed(
+            "AppRun of the AppImage points outside of it".to_string(),
+        ));
+    }
+
+    Ok(resolved)
+}
+
 pub async fn integrate_appimage<P: AsRef<Path>>(
     install_dir: P,
     file_path: P,
@@ -27,7 +106,7 @@ pub async fn integrate_appimage<P: AsRef<Path>>(
     if icon.is_none() {
         if let Some(entry) = appimage.find_icon() {
             if let EntryKind::File(basic_file) = entry.kind {
-                let dest = format!("{}/{}.DirIcon", install_dir.display(), package.pkg_name);
+                let dest = install_dir.join(format!("{}.DirIcon", package.pkg_name));
                 let _ = squashfs.write_file(basic_file, &dest);
 
                 let magic_bytes = calc_magic_bytes(&dest, 8)?;
@@ -36,10 +115,10 @@ pub async fn integrate_appimage<P: AsRef<Path>>(
                 } else {
                     "svg"
                 };
-                let final_path = format!("{}/{}.{ext}", install_dir.display(), package.pkg_name);
+                let final_path = install_dir.join(format!("{}.{ext}", package.pkg_name));
                 fs::rename(&dest, &final_path)?;
 
-                *icon = Some(PathBuf::from(&final_path));
+                *icon = Some(final_path);
             }
         }
     }
@@ -47,9 +126,9 @@ pub async fn integrate_appimage<P: AsRef<Path>>(
     if desktop.is_none() {
         if let Some(entry) = appimage.find_desktop() {
             if let EntryKind::File(basic_file) = entry.kind {
-                let dest = format!("{}/{}.desktop", install_dir.display(), package.pkg_name);
+                let dest = install_dir.join(format!("{}.desktop", package.pkg_name));
                 let _ = squashfs.write_file(basic_file, &dest);
-                *desktop = Some(PathBuf::from(&dest));
+                *desktop = Some(dest);
             }
         }
     }
@@ -59,19 +138,13 @@ pub async fn integrate_appimage<P: AsRef<Path>>(
             let file_name = if entry
                 .path
                 .file_name()
-                .unwrap()
-                .to_string_lossy()
-                .contains("appdata")
+                .is_some_and(|name| name.to_string_lossy().contains("appdata"))
             {
                 "appdata"
             } else {
                 "metainfo"
             };
-            let dest = format!(
-                "{}/{}.{file_name}.xml",
-                install_dir.display(),
-                package.pkg_name
-            );
+            let dest = install_dir.join(format!("{}.{file_name}.xml", package.pkg_name));
             let _ = squashfs.write_file(basic_file, &dest);
         }
     }
@@ -1,6 +1,6 @@
 use std::{
     fs::{self, File},
-    io::{BufReader, BufWriter, Write},
+    io::BufReader,
     path::{Path, PathBuf},
 };
 
@@ -10,11 +10,11 @@ use regex::Regex;
 use soar_dl::downloader::{DownloadOptions, Downloader};
 
 use crate::{
-    config::get_config,
+    config::{get_config, path_in_root},
     constants::PNG_MAGIC_BYTES,
     database::models::Package,
     error::SoarError,
-    utils::{calc_magic_bytes, create_symlink, home_data_path},
+    utils::{calc_magic_bytes, create_symlink, write_atomic},
     SoarResult,
 };
 
@@ -71,10 +71,9 @@ pub async fn symlink_icon<P: AsRef<Path>>(real_path: P, pkg_name: &str) -> SoarR
     }
 
     let ext = real_path.extension().unwrap_or_default().to_string_lossy();
-    let final_path = PathBuf::from(format!(
-        "{}/icons/hicolor/{w}x{h}/apps/{pkg_name}-soar.{ext}",
-        home_data_path()
-    ));
+    let final_path = get_config()
+        .get_share_path()?
+        .join(format!("icons/hicolor/{w}x{h}/apps/{pkg_name}-soar.{ext}"));
 
     if let Some(parent) = final_path.parent() {
         fs::create_dir_all(parent)?;
@@ -96,32 +95,27 @@ pub async fn symlink_desktop<P: AsRef<Path>>(
         ))
     })?;
 
+    let bin_path = path_in_root(&get_config().get_bin_path()?.join(&package.pkg_name))?;
     let final_content = {
         let re = Regex::new(r"(?m)^(Icon|Exec|TryExec)=(.*)").unwrap();
 
         re.replace_all(&content, |caps: &regex::Captures| match &caps[1] {
             "Icon" => format!("Icon={}", package.pkg_name),
-            "Exec" | "TryExec" => {
-                format!(
-                    "{}={}/{}",
-                    &caps[1],
-                    get_config().get_bin_path().unwrap().display(),
-                    package.pkg_name
-                )
-            }
+            "Exec" => format!("Exec={}", quote_exec_arg(&bin_path)),
+            "TryExec" => format!("TryExec={}", bin_path),
             _ => unreachable!(),
         })
         .to_string()
     };
 
-    let mut writer = BufWriter::new(File::create(real_path)?);
-    writer.write_all(final_content.as_bytes())?;
+    write_atomic(real_path, final_content)?;
 
-    let final_path = PathBuf::from(format!(
-        "{}/applications/{}-soar.desktop",
-        home_data_path(),
-        package.pkg_name
-    ));
+    let final_path = get_config()
+        .get_share_path()?
+        .join(format!("applications/{}-soar.desktop", package.pkg_name));
+    if let Some(parent) = final_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
 
     create_symlink(real_path, &final_path)?;
     Ok(final_path)
@@ -141,11 +135,9 @@ pub async fn integrate_remote<P: AsRef<Path>>(
     let downloader = Downloader::default();
 
     if let Some(icon_url) = icon_url {
-        let options = DownloadOptions {
-            url: icon_url.clone(),
-            output_path: Some(icon_output_path.to_string_lossy().to_string()),
-            progress_callback: None,
-        };
+        let options = DownloadOptions::builder(icon_url)
+            .output_path(&icon_output_path)
+            .build()?;
         downloader.download(options).await?;
 
         let ext = if calc_magic_bytes(icon_output_path, 8)? == PNG_MAGIC_BYTES {
@@ -157,20 +149,18 @@ pub async fn integrate_remote<P: AsRef<Path>>(
     }
 
     if let Some(desktop_url) = desktop_url {
-        let options = DownloadOptions {
-            url: desktop_url.clone(),
-            output_path: Some(desktop_output_path.to_string_lossy().to_string()),
-            progress_callback: None,
-        };
+        let options = DownloadOptions::builder(desktop_url)
+            .output_path(&desktop_output_path)
+            .build()?;
         downloader.download(options).await?;
     } else {
         let content = create_default_desktop_entry(&package.pkg, &package.pkg_name, "Utility");
-        fs::write(&desktop_output_path, &content)?;
+        write_atomic(&desktop_output_path, &content)?;
     }
 
     try_join!(
         symlink_icon(&icon_output_path, &package.pkg_name),
-        symlink_desktop(&desktop_output_path, &package)
+        symlink_desktop(&desktop_output_path, package)
     )?;
 
     Ok(())
@@ -221,6 +211,30 @@ pub fn setup_portable_dir<P: AsRef<Path>>(
     Ok(())
 }
 
+/// Quotes `arg` for the `Exec` key of a desktop entry, which is split into
+/// arguments on spaces and has its own escaping rules.
+fn quote_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(',
+        ')', '`',
+    ];
+
+    let arg = arg.replace('%', "%%");
+    if !arg.contains(RESERVED) {
+        return arg;
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    // backslashes are escaped once more as the value is also a string
+    quoted.replace('\\', "\\\\")
+}
+
 fn create_default_desktop_entry(bin_name: &str, name: &str, categories: &str) -> Vec<u8> {
     format!(
         "[Desktop Entry]\n\
@@ -267,7 +281,7 @@ pub async fn integrate_package<P: AsRef<Path>>(
     let file_type = get_file_type(&mut reader);
 
     match file_type {
-        super::PackageFormat::AppImage => {
+        super::PackageFormat::AppImage
             if integrate_appimage(
                 install_dir,
                 &bin_path,
@@ -276,10 +290,9 @@ pub async fn integrate_package<P: AsRef<Path>>(
                 &mut desktop_path,
             )
             .await
-            .is_ok()
-            {
-                setup_portable_dir(bin_path, package, portable, portable_home, portable_config)?;
-            }
+            .is_ok() =>
+        {
+            setup_portable_dir(bin_path, package, portable, portable_home, portable_config)?;
         }
         super::PackageFormat::FlatImage => {
             setup_portable_dir(bin_path, package, None, None, portable_config)?;
@@ -291,8 +304,28 @@ pub async fn integrate_package<P: AsRef<Path>>(
         icon_path = Some(symlink_icon(path, &package.pkg_name).await?);
     }
     if let Some(ref path) = desktop_path {
-        desktop_path = Some(symlink_desktop(path, &package).await?);
+        desktop_path = Some(symlink_desktop(path, package).await?);
     }
 
     Ok((icon_path, desktop_path))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_plain_exec_args_unquoted() {
+        assert_eq!(quote_exec_arg("/usr/bin/foo"), "/usr/bin/foo");
+        assert_eq!(quote_exec_arg("100%"), "100%%");
+    }
+
+    #[test]
+    fn quotes_exec_args_with_reserved_characters() {
+        assert_eq!(quote_exec_arg("/opt/my app/foo"), "\"/opt/my app/foo\"");
+        assert_eq!(quote_exec_arg("a$b"), "\"a\\\\$b\"");
+        assert_eq!(quote_exec_arg("a\"b"), "\"a\\\\\"b\"");
+        assert_eq!(quote_exec_arg("a\\b"), "\"a\\\\\\\\b\"");
+        assert_eq!(quote_exec_arg("50% off"), "\"50%% off\"");
+    }
+}
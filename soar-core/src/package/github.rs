@@ -0,0 +1,208 @@
+use std::sync::{Arc, Mutex};
+
+use regex::Regex;
+use rusqlite::{params, Connection};
+use soar_dl::{
+    github::{Github, GithubAsset, GithubRelease},
+    platform::{PlatformDownloadOptions, Release, ReleaseHandler},
+};
+
+use crate::{
+    database::models::Package,
+    error::SoarError,
+    http::with_retry,
+    package::channel::Channel,
+    platform::{arch_pattern, foreign_os_keywords, os_pattern},
+    SoarResult,
+};
+
+/// Repository name recorded for packages installed straight from GitHub
+/// releases.
+pub const GITHUB_REPO_NAME: &str = "github";
+
+/// Asset name fragments that never denote a standalone binary we can install.
+const EXCLUDED_ASSETS: &[&str] = &[
+    "sha256", "sha512", "checksum", ".sig", ".asc", ".pem", ".sbom", ".json", ".txt", ".deb",
+    ".rpm", ".apk", ".msi", ".dmg", ".pkg", ".zip", ".tar", ".tgz", ".gz", ".xz", ".zst", ".bz2",
+    ".7z",
+];
+
+/// Release assets of a GitHub project matching an asset pattern.
+pub struct GithubAssets {
+    pub project: String,
+    pub tag: String,
+    pub assets: Vec<GithubAsset>,
+}
+
+fn default_asset_patterns() -> Vec<Regex> {
+    vec![
+        Regex::new(&format!("(?i){}", arch_pattern())).unwrap(),
+        Regex::new(&format!("(?i){}", os_pattern())).unwrap(),
+    ]
+}
+
+/// Asset name fragments excluded when no asset pattern is given.
+fn excluded_assets() -> Vec<String> {
+    let mut excluded: Vec<String> = EXCLUDED_ASSETS
+        .iter()
+        .chain(foreign_os_keywords())
+        .map(|s| s.to_string())
+        .collect();
+    if !cfg!(windows) {
+        excluded.push(".exe".to_string());
+    }
+    excluded
+}
+
+/// Parses `owner/repo` with an optional `@tag` suffix.
+pub fn parse_github_project(project: &str) -> SoarResult<(String, Option<String>)> {
+    let project = project.strip_prefix("github:").unwrap_or(project).trim();
+    let (project, tag) = match project.split_once('@') {
+        Some((project, tag)) if !tag.trim().is_empty() => (project, Some(tag.trim().to_string())),
+        _ => (project.trim_end_matches('@'), None),
+    };
+
+    if project.split('/').filter(|part| !part.is_empty()).count() != 2 {
+        return Err(SoarError::InvalidPackageQuery(format!(
+            "GitHub project must be in owner/repo form, got {}",
+            project
+        )));
+    }
+
+    Ok((project.to_string(), tag))
+}
+
+/// Fetches the release `tag`, or the newest release on `channel`, of
+/// `project` and returns its assets matching `pattern`.
+///
+/// Without a pattern, standalone Linux binaries for the current architecture
+/// are selected.
+pub async fn fetch_github_assets(
+    project: &str,
+    tag: Option<&str>,
+    pattern: Option<&str>,
+    channel: Channel,
+) -> SoarResult<GithubAssets> {
+    let handler = ReleaseHandler::<Github>::new();
+    let releases: Vec<GithubRelease> = with_retry(|| async {
+        handler
+            .fetch_releases::<GithubRelease>(project)
+            .await
+            .map_err(SoarError::from)
+    })
+    .await?;
+
+    let release = match tag {
+        Some(tag) => releases.iter().find(|release| release.tag_name() == tag),
+        None => match channel {
+            Channel::Stable => releases.iter().find(|release| !release.is_prerelease()),
+            Channel::Prerelease => releases
+                .iter()
+                .find(|release| Channel::of_version(release.tag_name()) != Channel::Nightly),
+            Channel::Nightly => releases.first(),
+        },
+    };
+    let Some(release) = release else {
+        return Err(SoarError::PackageNotFound(match tag {
+            Some(tag) => format!("{}@{}", project, tag),
+            None => project.to_string(),
+        }));
+    };
+
+    let mut builder = PlatformDownloadOptions::builder().tag(release.tag_name());
+    match pattern {
+        Some(pattern) => builder = builder.regex(pattern),
+        None => {
+            for regex in default_asset_patterns() {
+                builder = builder.compiled_regex(regex);
+            }
+            for keyword in excluded_assets() {
+                builder = builder.exclude_keyword(keyword);
+            }
+        }
+    }
+    let options = builder
+        .build()
+        .map_err(|err| SoarError::InvalidPackageQuery(err.to_string()))?;
+    let assets = handler
+        .filter_releases(std::slice::from_ref(release), &options)
+        .await?;
+
+    Ok(GithubAssets {
+        project: project.to_string(),
+        tag: release.tag_name().to_string(),
+        assets,
+    })
+}
+
+/// Derives a pattern matching `asset_name` in future releases by replacing
+/// the version in it with a wildcard.
+pub fn derive_asset_pattern(asset_name: &str, tag: &str) -> String {
+    let escaped = regex::escape(asset_name);
+    let version = regex::escape(tag.trim_start_matches('v'));
+    if !version.is_empty() && escaped.contains(&version) {
+        format!("^{}$", escaped.replace(&version, ".*"))
+    } else {
+        format!("^{}$", escaped)
+    }
+}
+
+/// Builds an installable package from a GitHub release asset.
+///
+/// Release assets don't come with a blake3 checksum, so the checksum is left
+/// empty and only recorded after download.
+pub fn github_package(project: &str, tag: &str, asset: &GithubAsset) -> Package {
+    let repo = project.rsplit('/').next().unwrap_or(project).to_string();
+    let webpage = format!("https://github.com/{}", project);
+
+    Package {
+        id: 0,
+        repo_name: GITHUB_REPO_NAME.to_string(),
+        disabled: false,
+        disabled_reason: None,
+        pkg: repo.clone(),
+        pkg_id: project.to_string(),
+        pkg_name: repo,
+        pkg_type: "static".to_string(),
+        pkg_webpage: Some(webpage.clone()),
+        app_id: None,
+        description: format!("{} from GitHub releases", asset.name),
+        version: tag.to_string(),
+        download_url: asset.browser_download_url.clone(),
+        size: asset.size,
+        ghcr_pkg: None,
+        ghcr_size: None,
+        checksum: String::new(),
+        homepages: Some(vec![webpage]),
+        notes: None,
+        source_urls: None,
+        tags: None,
+        categories: None,
+        icon: None,
+        desktop: None,
+        build_id: None,
+        build_date: None,
+        build_script: None,
+        build_log: None,
+        collection: None,
+        torrent: None,
+    }
+}
+
+/// Records the upstream project and asset pattern of a GitHub install so
+/// `soar update` can look for newer releases.
+pub fn record_github_source(
+    core_db: Arc<Mutex<Connection>>,
+    repo_name: &str,
+    pkg_id: &str,
+    project: &str,
+    asset_pattern: &str,
+) -> SoarResult<()> {
+    let conn = core_db.lock()?;
+    conn.execute(
+        "UPDATE packages SET upstream = ?1, asset_pattern = ?2
+        WHERE repo_name = ?3 AND pkg_id = ?4",
+        params![project, asset_pattern, repo_name, pkg_id],
+    )?;
+    Ok(())
+}
@@ -1,24 +1,60 @@
 use std::{
+    ffi::OsString,
+    fs,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
-use rusqlite::{prepare_and_bind, Connection};
-use soar_dl::downloader::{DownloadOptions, DownloadState, Downloader};
+use rand::{distributions::Alphanumeric, Rng};
+use rusqlite::{params, prepare_and_bind, Connection};
+use soar_dl::{
+    cancel::is_cancelled,
+    downloader::{DownloadOptions, DownloadState, Downloader},
+};
 
 use crate::{
-    config::get_config,
+    config::{get_config, path_from_root, path_in_root},
     database::models::{InstalledPackage, Package},
-    utils::validate_checksum,
+    error::{ErrorKind, SoarError},
+    events::{EventSink, InstallEvent},
+    http::with_failover,
+    package::{
+        channel::Channel,
+        formats::{
+            appimage::{extract_appimage, is_appimage, APPIMAGE_EXTRACT_DIR},
+            common::integrate_package,
+        },
+        optimize::{optimize_binary, Optimized},
+    },
+    package_cache,
+    platform::{exe_name, link_binary, processes_using},
+    store::dedup,
+    trust::ensure_trusted,
+    utils::{
+        affix_file_name, calculate_checksum, file_name_starts_with, is_valid_file_name,
+        read_link_absolute, replace_atomic, sanitize_file_name, symlink_target, validate_checksum,
+    },
     SoarResult,
 };
 
+/// Packages smaller than this are always downloaded in full, the round trip
+/// for the zsync control file isn't worth it.
+const DELTA_MIN_SIZE: u64 = 4 * 1024 * 1024;
+
 pub struct PackageInstaller {
     package: Package,
     install_dir: PathBuf,
+    /// Directory the package is downloaded to before it's swapped into
+    /// `install_dir`.
+    staging_dir: PathBuf,
     progress_callback: Option<Arc<dyn Fn(DownloadState) + Send + Sync>>,
     db: Arc<Mutex<Connection>>,
     installed_with_family: bool,
+    /// Row of the package in the core database.
+    record_id: i64,
+    /// Whether the installed binary was stripped or compressed, making it
+    /// useless as a seed for delta updates.
+    optimized: bool,
 }
 
 #[derive(Clone)]
@@ -27,6 +63,48 @@ pub struct InstallTarget {
     pub existing_install: Option<InstalledPackage>,
 }
 
+/// What to do when the bin name a package links to is already taken by a
+/// file that doesn't belong to it.
+#[derive(Clone, Debug, Default)]
+pub enum BinConflictPolicy {
+    /// Abort the installation with a conflict error.
+    #[default]
+    Fail,
+    /// Replace the existing file, disowning it from its previous package.
+    Overwrite,
+    /// Install the package without linking its binary.
+    Skip,
+    /// Link under another name. Supports `{pkg_name}`, `{pkg_id}`,
+    /// `{repo_name}` and `{version}` placeholders.
+    Rename(String),
+}
+
+/// An existing file at the bin path a package wants to link to.
+#[derive(Clone, Debug)]
+pub struct BinConflict {
+    pub bin_path: PathBuf,
+    /// `pkg_name#pkg_id` of the installed package owning the file, if any.
+    pub owner: Option<String>,
+    /// Whether the file links another installed version of the same
+    /// package, which the package is then installed next to.
+    pub other_version: bool,
+}
+
+/// Options applied when integrating a package.
+#[derive(Clone, Default)]
+pub struct InstallOptions {
+    pub portable: Option<String>,
+    pub portable_home: Option<String>,
+    pub portable_config: Option<String>,
+    pub bin_conflict: BinConflictPolicy,
+    /// Channel to follow from now on; existing installs keep theirs if unset.
+    pub channel: Option<Channel>,
+    /// Unpack AppImages and link the binary their AppRun points to, so they
+    /// run without FUSE. Packages installed this way stay unpacked on
+    /// updates.
+    pub extract_appimage: bool,
+}
+
 impl PackageInstaller {
     pub async fn new<P: AsRef<Path>>(
         target: &InstallTarget,
@@ -38,8 +116,16 @@ impl PackageInstaller {
         let install_dir = install_dir.as_ref().to_path_buf();
         let package = &target.package;
         let profile = get_config().default_profile.clone();
+        // paths are recorded as text, refuse those that aren't valid UTF-8
+        // before downloading anything rather than after installing it
+        let installed_path = path_in_root(&install_dir)?;
+        for dir in [get_config().get_bin_path()?, get_config().get_share_path()?] {
+            path_in_root(&dir)?;
+        }
 
-        if target.existing_install.is_none() {
+        let record_id = if let Some(ref existing) = target.existing_install {
+            existing.id as i64
+        } else {
             let conn = db.lock()?;
             let Package {
                 ref repo_name,
@@ -51,7 +137,6 @@ impl PackageInstaller {
                 ref checksum,
                 ..
             } = package;
-            let installed_path = install_dir.to_string_lossy();
             let mut stmt = prepare_and_bind!(
                 conn,
                 "INSERT INTO packages (
@@ -65,70 +150,225 @@ impl PackageInstaller {
             )"
             );
             stmt.raw_execute()?;
-        }
+            conn.last_insert_rowid()
+        };
 
         Ok(Self {
             package: package.clone(),
+            staging_dir: sibling_path(&install_dir, "staging"),
             install_dir,
             progress_callback,
             db: db.clone(),
             installed_with_family,
+            record_id,
+            optimized: target
+                .existing_install
+                .as_ref()
+                .is_some_and(|existing| existing.original_checksum.is_some()),
         })
     }
 
+    /// Downloads the package into the staging directory and, once verified,
+    /// swaps it in place of the install directory.
+    ///
+    /// The previous install, if any, stays intact until the swap, so its
+    /// binary keeps working while the update downloads. Once cancelled, the
+    /// install stops before the swap.
     pub async fn install(&self) -> SoarResult<()> {
+        if self.staging_dir.exists() {
+            fs::remove_dir_all(&self.staging_dir)?;
+        }
+
+        let result = self.stage().await.and_then(|_| {
+            if is_cancelled() {
+                return Err(SoarError::Cancelled);
+            }
+            Ok(())
+        });
+        if result.is_err() {
+            let _ = fs::remove_dir_all(&self.staging_dir);
+        }
+        result?;
+
+        swap_dirs(&self.staging_dir, &self.install_dir)
+    }
+
+    async fn stage(&self) -> SoarResult<()> {
         let package = &self.package;
-        let output_path = self.install_dir.join(&package.pkg_name);
+        let output_path = self.staging_dir.join(&package.pkg_name);
+
+        if self.restore_cached(&output_path)? {
+            return Ok(());
+        }
 
         self.download_package(&output_path).await?;
 
-        validate_checksum(&package.checksum, &output_path)?;
+        // packages from GitHub releases have no known checksum upfront
+        if !package.checksum.is_empty() {
+            validate_checksum(&package.checksum, &output_path)?;
+            // kept for the other users of a shared cache regardless
+            let keep = get_config().keep_downloads.unwrap_or(false);
+            if keep || package_cache::is_shared()? {
+                package_cache::insert(package, &self.staging_dir)?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Stages the package from the files it was installed from before if
+    /// they're cached, returning whether they were.
+    fn restore_cached(&self, output_path: &Path) -> SoarResult<bool> {
+        let checksum = &self.package.checksum;
+        if checksum.is_empty() || !package_cache::restore(checksum, &self.staging_dir)? {
+            return Ok(false);
+        }
+        if validate_checksum(checksum, output_path).is_err() {
+            package_cache::remove(checksum)?;
+            fs::remove_dir_all(&self.staging_dir)?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
     async fn download_package<P: AsRef<Path>>(&self, output_path: P) -> SoarResult<()> {
-        let downloader = Downloader::default();
+        let headers = get_config()
+            .get_repository(&self.package.repo_name)
+            .map(|repo| repo.header_map())
+            .unwrap_or_default();
+        let downloader = Downloader::default().with_headers(headers);
         let output_path = output_path.as_ref();
 
+        if self.download_delta(&downloader, output_path).await {
+            return Ok(());
+        }
+
         // fallback to download_url for repositories without ghcr
         let (url, output_path) = if let Some(ref ghcr_pkg) = self.package.ghcr_pkg {
-            (ghcr_pkg, &self.install_dir)
+            (ghcr_pkg, &self.staging_dir)
         } else {
             (&self.package.download_url, &output_path.to_path_buf())
         };
 
-        let options = DownloadOptions {
-            url: url.to_string(),
-            output_path: Some(output_path.to_string_lossy().to_string()),
-            progress_callback: self.progress_callback.clone(),
+        let downloader = &downloader;
+        let result = with_failover(url, |url| async move {
+            let options = DownloadOptions::builder(url)
+                .output_path(output_path)
+                .progress_callback(self.progress_callback.clone())
+                .build()?;
+
+            if self.package.ghcr_pkg.is_some() {
+                downloader.download_oci(options).await?;
+            } else {
+                downloader.download(options).await?;
+            }
+            Ok(())
+        })
+        .await;
+
+        match (result, &self.package.torrent) {
+            (Err(err), Some(torrent)) if err.kind() != ErrorKind::Cancelled => {
+                self.download_torrent(downloader, torrent).await
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Downloads the package from its torrent, the alternative source for
+    /// when the regular download fails.
+    async fn download_torrent(&self, downloader: &Downloader, torrent: &str) -> SoarResult<()> {
+        let output_path = self.staging_dir.join(&self.package.pkg_name);
+        let options = DownloadOptions::builder(torrent)
+            .output_path(&output_path)
+            .progress_callback(self.progress_callback.clone())
+            .build()?;
+        downloader.download(options).await?;
+        Ok(())
+    }
+
+    /// Updates the package from its previous install with zsync if the
+    /// repository publishes a control file for it, returning whether that
+    /// worked. Otherwise the package has to be downloaded in full.
+    async fn download_delta(&self, downloader: &Downloader, output_path: &Path) -> bool {
+        let seed = self.install_dir.join(&self.package.pkg_name);
+        if self.package.ghcr_pkg.is_some()
+            || self.optimized
+            || self.package.download_url.starts_with("ipfs://")
+            || self.package.size < DELTA_MIN_SIZE
+            || !get_config().update.delta.unwrap_or(true)
+            || !seed.is_file()
+        {
+            return false;
+        }
+
+        let Ok(options) = DownloadOptions::builder(&self.package.download_url)
+            .output_path(output_path)
+            .progress_callback(self.progress_callback.clone())
+            .build()
+        else {
+            return false;
         };
+        let zsync_url = format!("{}.zsync", self.package.download_url);
+        if downloader
+            .download_zsync(options, &zsync_url, &seed)
+            .await
+            .is_err()
+        {
+            return false;
+        }
 
-        if self.package.ghcr_pkg.is_some() {
-            downloader.download_oci(options).await?;
-        } else {
-            downloader.download(options).await?;
+        // the control file may be stale or lack a SHA-1 to verify against
+        if !self.package.checksum.is_empty()
+            && validate_checksum(&self.package.checksum, output_path).is_err()
+        {
+            let _ = fs::remove_file(output_path);
+            return false;
         }
+        true
+    }
+
+    /// Keeps bulk updates away from the install.
+    pub fn pin(&self) -> SoarResult<()> {
+        let conn = self.db.lock()?;
+        conn.execute(
+            "UPDATE packages SET pinned = true WHERE id = ?1",
+            [self.record_id],
+        )?;
+        Ok(())
+    }
 
+    /// Removes the record of a new install that didn't complete.
+    pub fn discard(&self) -> SoarResult<()> {
+        let conn = self.db.lock()?;
+        conn.execute(
+            "DELETE FROM packages WHERE id = ?1 AND is_installed = false",
+            [self.record_id],
+        )?;
         Ok(())
     }
 
     pub async fn record<P: AsRef<Path>>(
         &self,
         final_checksum: &str,
-        bin_path: P,
+        optimized: Option<&Optimized>,
+        bin_path: Option<P>,
         icon_path: Option<PathBuf>,
         desktop_path: Option<PathBuf>,
+        channel: Option<Channel>,
     ) -> SoarResult<()> {
         let conn = self.db.lock()?;
+        let channel = channel.map(|channel| channel.as_str());
         let package = &self.package;
-        let bin_path = bin_path.as_ref().to_string_lossy();
-        let icon_path = icon_path.map(|path| path.to_string_lossy().into_owned());
-        let desktop_path = desktop_path.map(|path| path.to_string_lossy().into_owned());
-        let Package {
-            pkg_name, checksum, ..
-        } = package;
+        let bin_path = bin_path
+            .map(|path| path_in_root(path.as_ref()))
+            .transpose()?;
+        let icon_path = icon_path.map(|path| path_in_root(&path)).transpose()?;
+        let desktop_path = desktop_path.map(|path| path_in_root(&path)).transpose()?;
+        let Package { version, size, .. } = package;
+        let original_checksum = optimized.map(|optimized| &optimized.original_checksum);
+        let original_size = optimized.map(|optimized| optimized.original_size);
 
+        let record_id = self.record_id;
         let installed_with_family = self.installed_with_family;
         let mut stmt = prepare_and_bind!(
             conn,
@@ -138,13 +378,16 @@ impl PackageInstaller {
                 icon_path = $icon_path,
                 desktop_path = $desktop_path,
                 checksum = $final_checksum,
+                original_checksum = $original_checksum,
+                original_size = $original_size,
+                version = $version,
+                size = $size,
+                channel = COALESCE($channel, channel),
                 installed_date = datetime(),
                 is_installed = true,
                 installed_with_family = $installed_with_family
             WHERE
-                pkg_name = $pkg_name
-                AND
-                checksum = $checksum
+                id = $record_id
             "
         );
         stmt.raw_execute()?;
@@ -152,3 +395,563 @@ impl PackageInstaller {
         Ok(())
     }
 }
+
+/// Installs a single resolved package: downloads it into its install
+/// directory, links the binary into the bin path, integrates desktop files
+/// and records the installation in the core database.
+///
+/// Progress is reported through `events`; a failure is reported as
+/// [`InstallEvent::Failed`] before the error is returned.
+pub async fn install_package(
+    target: &InstallTarget,
+    options: &InstallOptions,
+    events: Option<EventSink>,
+    core_db: Arc<Mutex<Connection>>,
+) -> SoarResult<()> {
+    let result = run_install(target, options, events.as_ref(), core_db).await;
+
+    if let (Err(err), Some(events)) = (&result, &events) {
+        events(InstallEvent::Failed {
+            pkg_name: target.package.pkg_name.clone(),
+            pkg_id: target.package.pkg_id.clone(),
+            error: err.to_string(),
+        });
+    }
+
+    result
+}
+
+async fn run_install(
+    target: &InstallTarget,
+    options: &InstallOptions,
+    events: Option<&EventSink>,
+    core_db: Arc<Mutex<Connection>>,
+) -> SoarResult<()> {
+    let pkg_name = &target.package.pkg_name;
+    let pkg_id = &target.package.pkg_id;
+
+    if let Some(events) = events {
+        events(InstallEvent::Resolved {
+            pkg_name: pkg_name.clone(),
+            pkg_id: pkg_id.clone(),
+            version: target.package.version.clone(),
+            repo_name: target.package.repo_name.clone(),
+        });
+    }
+
+    if is_cancelled() {
+        return Err(SoarError::Cancelled);
+    }
+    ensure_trusted(&target.package.repo_name)?;
+    if !is_valid_file_name(pkg_name) {
+        return Err(SoarError::InvalidPackageName(pkg_name.clone()));
+    }
+
+    // a version installed next to others leaves them their links and desktop
+    // entry, and isn't updated to the version they're at
+    let side_by_side =
+        target.existing_install.is_none() && has_other_versions(target, core_db.clone())?;
+    let conflict = find_bin_conflict(target, core_db.clone())?;
+    let bin_name = resolve_bin_path(target, conflict.clone(), &options.bin_conflict)?;
+    if let (Some(conflict), BinConflictPolicy::Overwrite) = (conflict, &options.bin_conflict) {
+        if !conflict.other_version {
+            disown_bin(&conflict.bin_path, core_db.clone())?;
+        }
+    }
+
+    let (install_dir, real_bin) = if let Some(ref existing) = target.existing_install {
+        let install_dir = PathBuf::from(&existing.installed_path);
+        let real_bin = install_dir.join(&target.package.pkg_name);
+
+        (install_dir, real_bin)
+    } else {
+        let rand_str: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(12)
+            .map(char::from)
+            .collect();
+
+        // versions installed next to others stay at their version
+        let name = if side_by_side {
+            format!(
+                "{}-{}@{}-{}",
+                sanitize_file_name(&target.package.pkg),
+                sanitize_file_name(&target.package.pkg_id),
+                sanitize_file_name(&target.package.version),
+                rand_str
+            )
+        } else {
+            format!(
+                "{}-{}-{}",
+                sanitize_file_name(&target.package.pkg),
+                sanitize_file_name(&target.package.pkg_id),
+                rand_str
+            )
+        };
+        let install_dir = get_config().get_packages_path()?.join(name);
+        let real_bin = install_dir.join(&target.package.pkg_name);
+
+        (install_dir, real_bin)
+    };
+
+    let progress_callback = events.cloned().map(|events| {
+        let pkg_name = pkg_name.clone();
+        let pkg_id = pkg_id.clone();
+        Arc::new(move |state| {
+            if let Some(event) = InstallEvent::from_download_state(&pkg_name, &pkg_id, state) {
+                events(event);
+            }
+        }) as Arc<dyn Fn(DownloadState) + Send + Sync>
+    });
+
+    let installer =
+        PackageInstaller::new(target, &install_dir, progress_callback, core_db, false).await?;
+
+    let extract = options.extract_appimage || install_dir.join(APPIMAGE_EXTRACT_DIR).exists();
+    if let Err(err) = installer.install().await {
+        // leave no trace of a new install that was cancelled
+        if err.kind() == ErrorKind::Cancelled && target.existing_install.is_none() {
+            installer.discard()?;
+        }
+        return Err(err);
+    }
+    let link_target = if extract && is_appimage(&real_bin)? {
+        extract_appimage(&install_dir, &real_bin)?
+    } else {
+        real_bin.clone()
+    };
+
+    let mut final_checksum = calculate_checksum(&real_bin)?;
+    if let Some(events) = events {
+        events(InstallEvent::Verified {
+            pkg_name: pkg_name.clone(),
+            pkg_id: pkg_id.clone(),
+            checksum: final_checksum.clone(),
+        });
+    }
+
+    let (strip, upx) = get_config().optimize.for_package(pkg_name, pkg_id);
+    let optimized = optimize_binary(&real_bin, &final_checksum, strip, upx)?;
+    if let Some(ref optimized) = optimized {
+        final_checksum = optimized.checksum.clone();
+    }
+    dedup(&real_bin, &final_checksum)?;
+
+    if let Some(ref bin_name) = bin_name {
+        let link_target = symlink_target(&link_target, bin_name);
+        replace_atomic(bin_name, |temp| link_binary(&link_target, temp))?;
+        // a link named after the previous version gives way to the new one
+        if let Some(old) = target
+            .existing_install
+            .as_ref()
+            .and_then(|existing| existing.bin_path.as_ref())
+            .map(Path::new)
+            .filter(|old| *old != bin_name)
+        {
+            if read_link_absolute(old).is_ok_and(|link| link.starts_with(&install_dir)) {
+                fs::remove_file(old)?;
+            }
+        }
+        if let Some(events) = events {
+            events(InstallEvent::Linked {
+                pkg_name: pkg_name.clone(),
+                pkg_id: pkg_id.clone(),
+                bin_path: bin_name.clone(),
+            });
+        }
+    }
+
+    let (icon_path, desktop_path) = if side_by_side {
+        (None, None)
+    } else {
+        integrate_package(
+            &install_dir,
+            &target.package,
+            options.portable.clone(),
+            options.portable_home.clone(),
+            options.portable_config.clone(),
+        )
+        .await?
+    };
+
+    installer
+        .record(
+            &final_checksum,
+            optimized.as_ref(),
+            bin_name.as_ref(),
+            icon_path,
+            desktop_path,
+            options.channel,
+        )
+        .await?;
+    if side_by_side {
+        installer.pin()?;
+    }
+
+    Ok(())
+}
+
+/// Path next to `install_dir` with `suffix` appended to its name, e.g.
+/// `ffmpeg-x.staging`.
+fn sibling_path(install_dir: &Path, suffix: &str) -> PathBuf {
+    affix_file_name(install_dir, "", &format!(".{}", suffix))
+}
+
+/// Prefix of the names of the installs swapped out of `install_dir`.
+fn replaced_prefix(install_dir: &Path) -> OsString {
+    let mut prefix = install_dir.file_name().unwrap_or_default().to_os_string();
+    prefix.push(".old-");
+    prefix
+}
+
+/// Moves the `staged` directory into place at `target`.
+///
+/// On Linux both directories are exchanged in a single atomic rename.
+/// Elsewhere the previous install is renamed aside first, leaving `target`
+/// briefly missing. The previous install is then removed, unless a running
+/// process still uses it.
+fn swap_dirs(staged: &Path, target: &Path) -> SoarResult<()> {
+    if !target.exists() {
+        fs::rename(staged, target)?;
+        return Ok(());
+    }
+
+    let rand_str: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect();
+    let replaced = sibling_path(target, &format!("old-{}", rand_str));
+
+    #[cfg(target_os = "linux")]
+    {
+        use nix::fcntl::{renameat2, RenameFlags};
+
+        if renameat2(None, staged, None, target, RenameFlags::RENAME_EXCHANGE).is_ok() {
+            fs::rename(staged, &replaced)?;
+            remove_replaced_installs(target);
+            return Ok(());
+        }
+    }
+
+    // fall back for filesystems without RENAME_EXCHANGE
+    fs::rename(target, &replaced)?;
+    if let Err(err) = fs::rename(staged, target) {
+        let _ = fs::rename(&replaced, target);
+        return Err(err.into());
+    }
+    remove_replaced_installs(target);
+
+    Ok(())
+}
+
+/// Undoes what an interrupted install of a package left behind. A new
+/// install is removed along with its record, while a previous install is
+/// kept as it was, put back in place if it was swapped out.
+pub fn roll_back_install(
+    db: Arc<Mutex<Connection>>,
+    repo_name: &str,
+    pkg_id: &str,
+    pkg_name: &str,
+) -> SoarResult<()> {
+    let conn = db.lock()?;
+    let records = conn
+        .prepare(
+            "SELECT id, installed_path, is_installed FROM packages
+            WHERE repo_name = ?1 AND pkg_id = ?2 AND pkg_name = ?3",
+        )?
+        .query_map(params![repo_name, pkg_id, pkg_name], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, bool>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, installed_path, is_installed) in records {
+        let installed_path = path_from_root(&installed_path);
+        let install_dir = Path::new(&installed_path);
+        let staging = sibling_path(install_dir, "staging");
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+
+        if !is_installed {
+            if install_dir.exists() {
+                fs::remove_dir_all(install_dir)?;
+            }
+            conn.execute("DELETE FROM packages WHERE id = ?1", [id])?;
+        } else if !install_dir.exists() {
+            // interrupted between the renames of a swap without RENAME_EXCHANGE
+            let prefix = replaced_prefix(install_dir);
+            let replaced = install_dir.parent().and_then(|parent| {
+                fs::read_dir(parent)
+                    .ok()?
+                    .filter_map(Result::ok)
+                    .find(|entry| file_name_starts_with(entry, &prefix))
+            });
+            if let Some(replaced) = replaced {
+                fs::rename(replaced.path(), install_dir)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Removes the installs previously swapped out of `install_dir` that no
+/// running process uses anymore.
+pub fn remove_replaced_installs(install_dir: &Path) {
+    let Some(parent) = install_dir.parent() else {
+        return;
+    };
+    let prefix = replaced_prefix(install_dir);
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if file_name_starts_with(&entry, &prefix) && processes_using(&path).is_empty() {
+            let _ = fs::remove_dir_all(path);
+        }
+    }
+}
+
+/// Bin path the package links to when there is no conflict. An existing
+/// install keeps its link, renamed if it's named after its version.
+fn target_bin_path(target: &InstallTarget) -> SoarResult<PathBuf> {
+    let naming = get_config().bin_naming.unwrap_or_default();
+    let Some(existing) = &target.existing_install else {
+        return bin_path_for(naming.template(), &target.package);
+    };
+    let Some(ref bin_path) = existing.bin_path else {
+        return bin_path_for(naming.template(), &target.package);
+    };
+
+    let bin_dir = get_config().get_bin_path()?;
+    for template in [naming.template(), naming.versioned_template()] {
+        let name = exe_name(&render_installed_bin_name(template, existing));
+        if Path::new(bin_path) == bin_dir.join(name) {
+            return bin_path_for(template, &target.package);
+        }
+    }
+    Ok(PathBuf::from(bin_path))
+}
+
+/// Bin path `target` links to when installed next to another version.
+fn versioned_bin_path(target: &InstallTarget) -> SoarResult<PathBuf> {
+    let naming = get_config().bin_naming.unwrap_or_default();
+    bin_path_for(naming.versioned_template(), &target.package)
+}
+
+fn bin_path_for(template: &str, package: &Package) -> SoarResult<PathBuf> {
+    let name = render_bin_name(template, package);
+    if !is_valid_file_name(&name) {
+        return Err(SoarError::InvalidPackageName(name));
+    }
+    Ok(get_config().get_bin_path()?.join(exe_name(&name)))
+}
+
+/// Whether other versions of the package of `target` are installed.
+fn has_other_versions(target: &InstallTarget, core_db: Arc<Mutex<Connection>>) -> SoarResult<bool> {
+    let conn = core_db.lock()?;
+    let package = &target.package;
+    let count: u32 = conn.query_row(
+        "SELECT COUNT(*) FROM packages
+        WHERE repo_name = ?1 AND pkg_id = ?2 AND pkg_name = ?3 AND is_installed = true",
+        params![package.repo_name, package.pkg_id, package.pkg_name],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Bin path `target` would be linked to under `policy`, or `None` if linking
+/// is skipped. Nothing is changed on disk or in the database.
+pub fn planned_bin_path(
+    target: &InstallTarget,
+    policy: &BinConflictPolicy,
+    core_db: Arc<Mutex<Connection>>,
+) -> SoarResult<Option<PathBuf>> {
+    resolve_bin_path(target, find_bin_conflict(target, core_db)?, policy)
+}
+
+fn resolve_bin_path(
+    target: &InstallTarget,
+    conflict: Option<BinConflict>,
+    policy: &BinConflictPolicy,
+) -> SoarResult<Option<PathBuf>> {
+    let Some(conflict) = conflict else {
+        return Ok(Some(target_bin_path(target)?));
+    };
+    if conflict.other_version {
+        let bin_name = versioned_bin_path(target)?;
+        if bin_name.symlink_metadata().is_ok() {
+            return Err(SoarError::Conflict(format!(
+                "{} already exists",
+                bin_name.display()
+            )));
+        }
+        return Ok(Some(bin_name));
+    }
+    match policy {
+        BinConflictPolicy::Fail => Err(conflict_error(&conflict)),
+        BinConflictPolicy::Overwrite => Ok(Some(conflict.bin_path)),
+        BinConflictPolicy::Skip => Ok(None),
+        BinConflictPolicy::Rename(template) => {
+            let bin_name = bin_path_for(template, &target.package)?;
+            if bin_name.symlink_metadata().is_ok() {
+                return Err(SoarError::Conflict(format!(
+                    "{} already exists",
+                    bin_name.display()
+                )));
+            }
+            Ok(Some(bin_name))
+        }
+    }
+}
+
+/// Checks whether the bin path of `target` is taken by a file that doesn't
+/// belong to a previous installation of the same package.
+pub fn find_bin_conflict(
+    target: &InstallTarget,
+    core_db: Arc<Mutex<Connection>>,
+) -> SoarResult<Option<BinConflict>> {
+    let bin_path = target_bin_path(target)?;
+    if bin_path.symlink_metadata().is_err() {
+        return Ok(None);
+    }
+
+    if let Some(ref existing) = target.existing_install {
+        let owned = existing.bin_path.as_deref() == Some(&*bin_path.to_string_lossy())
+            || read_link_absolute(&bin_path)
+                .is_ok_and(|link| link.starts_with(&existing.installed_path));
+        if owned {
+            return Ok(None);
+        }
+    }
+
+    let conn = core_db.lock()?;
+    let owner = conn
+        .query_row(
+            "SELECT pkg_name, pkg_id, repo_name FROM packages
+            WHERE bin_path = ?1 AND is_installed = true",
+            [path_in_root(&bin_path)?],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            },
+        )
+        .ok();
+    let package = &target.package;
+    let other_version = owner.as_ref().is_some_and(|(pkg_name, pkg_id, repo_name)| {
+        *pkg_name == package.pkg_name
+            && *pkg_id == package.pkg_id
+            && *repo_name == package.repo_name
+    });
+    let owner = owner.map(|(pkg_name, pkg_id, _)| format!("{}#{}", pkg_name, pkg_id));
+
+    Ok(Some(BinConflict {
+        bin_path,
+        owner,
+        other_version,
+    }))
+}
+
+fn conflict_error(conflict: &BinConflict) -> SoarError {
+    let owner = conflict.owner.as_ref().map_or_else(
+        || "not managed by soar".to_string(),
+        |o| format!("owned by {}", o),
+    );
+    SoarError::Conflict(format!(
+        "{} already exists ({})",
+        conflict.bin_path.display(),
+        owner
+    ))
+}
+
+/// Clears ownership of `bin_path` from any package that currently links it.
+fn disown_bin(bin_path: &Path, core_db: Arc<Mutex<Connection>>) -> SoarResult<()> {
+    let conn = core_db.lock()?;
+    conn.execute(
+        "UPDATE packages SET bin_path = NULL WHERE bin_path = ?1",
+        [path_in_root(bin_path)?],
+    )?;
+    Ok(())
+}
+
+/// Pins or unpins the installs of `pkg_name#pkg_id` from `repo_name`.
+/// Pinned packages are left alone by bulk updates.
+pub fn set_pinned(
+    core_db: Arc<Mutex<Connection>>,
+    repo_name: &str,
+    pkg_id: &str,
+    pkg_name: &str,
+    pinned: bool,
+) -> SoarResult<()> {
+    let conn = core_db.lock()?;
+    conn.execute(
+        "UPDATE packages SET pinned = ?1
+        WHERE repo_name = ?2 AND pkg_id = ?3 AND pkg_name = ?4",
+        params![pinned, repo_name, pkg_id, pkg_name],
+    )?;
+    Ok(())
+}
+
+/// Pins the install of `package` at its version, leaving the other installed
+/// versions of it as they are.
+pub fn pin_version(core_db: Arc<Mutex<Connection>>, package: &Package) -> SoarResult<()> {
+    let conn = core_db.lock()?;
+    conn.execute(
+        "UPDATE packages SET pinned = true
+        WHERE repo_name = ?1 AND pkg_id = ?2 AND pkg_name = ?3 AND version = ?4
+        AND is_installed = true",
+        params![
+            package.repo_name,
+            package.pkg_id,
+            package.pkg_name,
+            package.version
+        ],
+    )?;
+    Ok(())
+}
+
+/// Renders a bin name from `template` for `package`. Slashes in the values
+/// of the placeholders, such as in the `owner/repo` pkg_id of packages from
+/// GitHub, are replaced.
+pub fn render_bin_name(template: &str, package: &Package) -> String {
+    render_name(
+        template,
+        &package.pkg_name,
+        &package.pkg_id,
+        &package.repo_name,
+        &package.version,
+    )
+}
+
+/// Like [`render_bin_name`], for an installed package.
+pub fn render_installed_bin_name(template: &str, package: &InstalledPackage) -> String {
+    render_name(
+        template,
+        &package.pkg_name,
+        &package.pkg_id,
+        &package.repo_name,
+        &package.version,
+    )
+}
+
+fn render_name(
+    template: &str,
+    pkg_name: &str,
+    pkg_id: &str,
+    repo_name: &str,
+    version: &str,
+) -> String {
+    template
+        .replace("{pkg_name}", &sanitize_file_name(pkg_name))
+        .replace("{pkg_id}", &sanitize_file_name(pkg_id))
+        .replace("{repo_name}", &sanitize_file_name(repo_name))
+        .replace("{version}", &sanitize_file_name(version))
+}
@@ -1,4 +1,9 @@
+pub mod adopt;
+pub mod channel;
 pub mod formats;
+pub mod github;
 pub mod install;
+pub mod optimize;
 pub mod query;
 pub mod remove;
+pub mod switch;
@@ -0,0 +1,89 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    package::formats::{get_file_type, PackageFormat},
+    utils::calculate_checksum,
+    SoarResult,
+};
+
+/// A binary that was stripped or compressed after installation.
+pub struct Optimized {
+    /// Checksum of the binary as it was downloaded.
+    pub original_checksum: String,
+    /// Size of the binary as it was downloaded.
+    pub original_size: u64,
+    /// Checksum of the processed binary, recorded for `soar verify`.
+    pub checksum: String,
+}
+
+/// Strips and/or compresses the ELF binary at `path`, whose checksum is
+/// `checksum`, in place.
+///
+/// Each step is best effort: it's skipped if the tool isn't installed,
+/// fails, or doesn't make the binary smaller. Returns `None` if the binary
+/// is left as it was, e.g. because it isn't an ELF file.
+pub fn optimize_binary(
+    path: &Path,
+    checksum: &str,
+    strip: bool,
+    upx: bool,
+) -> SoarResult<Option<Optimized>> {
+    if !strip && !upx {
+        return Ok(None);
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    if get_file_type(&mut reader) != PackageFormat::ELF {
+        return Ok(None);
+    }
+
+    let original_size = fs::metadata(path)?.len();
+    let mut size = original_size;
+    let work = path.with_file_name(format!(
+        ".{}.optimize",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+
+    let mut steps: Vec<Vec<&str>> = Vec::new();
+    if strip {
+        steps.push(vec!["strip", "--strip-all"]);
+    }
+    if upx {
+        steps.push(vec!["upx", "-q", "-q", "--best"]);
+    }
+
+    let mut changed = false;
+    for step in steps {
+        fs::copy(path, &work)?;
+        let status = Command::new(step[0])
+            .args(&step[1..])
+            .arg(&work)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        let processed = fs::metadata(&work).map(|meta| meta.len()).unwrap_or(size);
+        if !status.is_ok_and(|status| status.success()) || processed >= size {
+            continue;
+        }
+
+        fs::rename(&work, path)?;
+        changed = true;
+        size = processed;
+    }
+    let _ = fs::remove_file(&work);
+
+    if !changed {
+        return Ok(None);
+    }
+
+    Ok(Some(Optimized {
+        original_checksum: checksum.to_string(),
+        original_size,
+        checksum: calculate_checksum(path)?,
+    }))
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::OnceLock};
+use std::{cmp::Ordering, collections::HashMap, sync::OnceLock};
 
 use regex::Regex;
 
@@ -9,6 +9,7 @@ use crate::{
 
 #[derive(Debug)]
 pub struct PackageQuery {
+    pub collection: Option<String>,
     pub name: Option<String>,
     pub repo_name: Option<String>,
     pub pkg_id: Option<String>,
@@ -45,6 +46,82 @@ impl PackageQuery {
 
         filter
     }
+
+    /// Like [`PackageQuery::create_filter`], but for the repository metadata,
+    /// which unlike the installed packages also knows their collection.
+    pub fn create_repo_filter(&self) -> HashMap<String, Filter> {
+        let mut filter = self.create_filter();
+        if let Some(repo_name) = filter.remove("repo_name") {
+            filter.insert("r.name".to_string(), repo_name);
+        }
+        if let Some(ref collection) = self.collection {
+            filter.insert(
+                "collection".to_string(),
+                (FilterOp::Eq, collection.clone().into()).into(),
+            );
+        }
+
+        filter
+    }
+}
+
+/// Whether `version` is the `wanted` version, or one of its releases if
+/// `wanted` is a prefix of it, e.g. `18` or `18.19` for `18.19.0`.
+pub fn version_matches(wanted: &str, version: &str) -> bool {
+    version
+        .strip_prefix(wanted)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '-', '+']))
+}
+
+/// Orders two versions by their numeric and alphabetic parts, so `1.10` is
+/// newer than `1.9` and a prerelease like `1.0-rc1` is older than `1.0`.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = version_parts(a);
+    let mut b = version_parts(b);
+    loop {
+        return match (a.next(), b.next()) {
+            (None, None) => Ordering::Equal,
+            // a release is newer than its prereleases, older than its patches
+            (Some(VersionPart::Text(_)), None) => Ordering::Less,
+            (None, Some(VersionPart::Text(_))) => Ordering::Greater,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(a), Some(b)) => match a.cmp(&b) {
+                Ordering::Equal => continue,
+                ordering => ordering,
+            },
+        };
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum VersionPart<'a> {
+    // text sorts before numbers, so `1.0.rc1` is older than `1.0.1`
+    Text(&'a str),
+    Number(u64),
+}
+
+fn version_parts(version: &str) -> impl Iterator<Item = VersionPart<'_>> {
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    let mut rest = version;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+        let first = rest.chars().next()?;
+        let len = if first.is_ascii_digit() {
+            rest.find(|c: char| !c.is_ascii_digit())
+        } else {
+            rest.find(|c: char| !c.is_ascii_alphabetic())
+        }
+        .unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(len);
+        rest = tail;
+        Some(if first.is_ascii_digit() {
+            // too many digits for a u64 is still a big number
+            VersionPart::Number(part.parse().unwrap_or(u64::MAX))
+        } else {
+            VersionPart::Text(part)
+        })
+    })
 }
 
 impl TryFrom<&str> for PackageQuery {
@@ -55,6 +132,7 @@ impl TryFrom<&str> for PackageQuery {
         let re = PACKAGE_RE.get_or_init(|| {
             Regex::new(
                 r"(?x)
+            ^(?:(?P<collection>[^\/\#\@:]+)/)?  # optional collection before /
             (?P<name>[^\/\#\@:]+)?              # optional package name
             (?:\#(?P<pkg_id>[^@:]+))?           # optional pkg_id after #
             (?:@(?P<version>[^:]+))?            # optional version after @
@@ -64,14 +142,14 @@ impl TryFrom<&str> for PackageQuery {
             .unwrap()
         });
 
-        let query = value.trim().to_lowercase();
+        let query = value.trim();
         if query.is_empty() {
             return Err(SoarError::InvalidPackageQuery(
                 "Package query can't be empty".into(),
             ));
         }
 
-        let caps = re.captures(&query).ok_or(SoarError::InvalidPackageQuery(
+        let caps = re.captures(query).ok_or(SoarError::InvalidPackageQuery(
             "Invalid package query format".into(),
         ))?;
 
@@ -84,6 +162,7 @@ impl TryFrom<&str> for PackageQuery {
         }
 
         Ok(PackageQuery {
+            collection: caps.name("collection").map(|m| m.as_str().to_string()),
             repo_name: caps.name("repo").map(|m| m.as_str().to_string()),
             pkg_id,
             name,
@@ -91,3 +170,36 @@ impl TryFrom<&str> for PackageQuery {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_versions_by_whole_parts() {
+        assert!(version_matches("18", "18"));
+        assert!(version_matches("18", "18.19.0"));
+        assert!(version_matches("18.19", "18.19.0"));
+        assert!(version_matches("1.0", "1.0-rc1"));
+        assert!(version_matches("1.0", "1.0+build5"));
+        assert!(!version_matches("18", "180.0.0"));
+        assert!(!version_matches("18.1", "18.19.0"));
+        assert!(!version_matches("18.19.0", "18.19"));
+    }
+
+    #[test]
+    fn compares_numeric_parts_as_numbers() {
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("18.19.0", "18.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("v2.0.0", "2.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0", "1.0.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn orders_prereleases_before_their_release() {
+        assert_eq!(compare_versions("1.0-rc1", "1.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-beta", "1.0-rc1"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-rc2", "1.0-rc10"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.rc1", "1.0.1"), Ordering::Less);
+    }
+}
@@ -1,11 +1,16 @@
 use std::{
     fs,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
 use rusqlite::{params, Connection};
 
-use crate::{database::models::InstalledPackage, SoarResult};
+use crate::{
+    config::get_config, database::models::InstalledPackage,
+    package::install::remove_replaced_installs, package_cache, utils::read_link_absolute,
+    SoarResult,
+};
 
 pub struct PackageRemover {
     package: InstalledPackage,
@@ -18,15 +23,15 @@ impl PackageRemover {
     }
 
     pub async fn remove(&self) -> SoarResult<()> {
-        let conn = self.db.lock()?;
-        let mut stmt = conn.prepare(
-            r#"
-            DELETE FROM packages WHERE id = ? AND is_installed = true
-        "#,
-        )?;
-
-        // if the package is installed, it does have bin_path
-        fs::remove_file(&self.package.bin_path.clone().unwrap())?;
+        // bin_path is unset when linking was skipped or the bin was taken
+        // over by another package
+        if let Some(ref bin_path) = self.package.bin_path {
+            if read_link_absolute(bin_path)
+                .is_ok_and(|link| link.starts_with(&self.package.installed_path))
+            {
+                fs::remove_file(bin_path)?;
+            }
+        }
 
         if let Some(ref icon_path) = self.package.icon_path {
             let _ = fs::remove_file(icon_path);
@@ -41,9 +46,56 @@ impl PackageRemover {
         }
 
         fs::remove_dir_all(&self.package.installed_path)?;
+        remove_replaced_installs(Path::new(&self.package.installed_path));
 
-        stmt.execute(params![self.package.id])?;
+        // only locked for the update, so that packages can be removed
+        // concurrently
+        let conn = self.db.lock()?;
+        conn.execute(
+            "DELETE FROM packages WHERE id = ? AND is_installed = true",
+            params![self.package.id],
+        )?;
 
         Ok(())
     }
 }
+
+/// Data `package` keeps outside its install directory, which removing it
+/// leaves behind: the portable home and config directories it was set up
+/// with, its copy in the `soar run` cache and the files it was installed
+/// from if they were kept, unless the cache is shared.
+pub fn leftover_data(package: &InstalledPackage) -> SoarResult<Vec<PathBuf>> {
+    let bin_path = Path::new(&package.installed_path).join(&package.pkg_name);
+    // portable directories inside the install directory go along with it
+    let mut paths: Vec<PathBuf> = ["home", "config"]
+        .into_iter()
+        .filter_map(|ext| read_link_absolute(bin_path.with_extension(ext)).ok())
+        .collect();
+
+    let cache_path = get_config().get_cache_path()?;
+    paths.push(cache_path.join("bin").join(&package.pkg_name));
+    // kept under the checksum of the download, before any optimization,
+    // unless other users may install the package from the cache too
+    let checksum = package
+        .original_checksum
+        .as_ref()
+        .unwrap_or(&package.checksum);
+    if !package_cache::is_shared()? {
+        paths.push(package_cache::entry_path(checksum)?);
+    }
+
+    paths.retain(|path| path.symlink_metadata().is_ok());
+    Ok(paths)
+}
+
+/// Deletes the leftover data at `paths`.
+pub fn purge(paths: &[PathBuf]) -> SoarResult<()> {
+    for path in paths {
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Choosing which of the installs sharing a package name gets the plain bin
+//! name, such as one of several versions installed side by side.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::{params, Connection};
+
+use crate::{
+    config::{get_config, path_in_root, BinNaming},
+    database::{models::InstalledPackage, packages::map_installed_package},
+    error::SoarError,
+    package::install::render_installed_bin_name,
+    platform::{exe_name, link_binary},
+    utils::{is_valid_file_name, read_link_absolute, replace_atomic, symlink_target},
+    SoarResult,
+};
+
+/// What [`use_package`] changed.
+pub struct Switched {
+    /// The bin path the package is linked to now.
+    pub bin_path: PathBuf,
+    /// `pkg_name#pkg_id@version` of the install that had the bin path
+    /// before, and where it's linked now.
+    pub displaced: Option<(String, PathBuf)>,
+}
+
+/// Links `package` under the name the configured naming scheme gives it,
+/// moving the install that had the name to its versioned name, or to its
+/// variant name if it's another package.
+pub fn use_package(
+    core_db: Arc<Mutex<Connection>>,
+    package: &InstalledPackage,
+) -> SoarResult<Switched> {
+    let naming = get_config().bin_naming.unwrap_or_default();
+    let bin_path = default_bin_path(package)?;
+    let current = package.bin_path.as_ref().map(PathBuf::from);
+    if current.as_ref() == Some(&bin_path) {
+        return Ok(Switched {
+            bin_path,
+            displaced: None,
+        });
+    }
+
+    let target = current
+        .as_ref()
+        .and_then(|current| read_link_absolute(current).ok())
+        .unwrap_or_else(|| Path::new(&package.installed_path).join(&package.pkg_name));
+
+    let mut conn = core_db.lock()?;
+    let owner = conn
+        .query_row(
+            "SELECT * FROM packages WHERE bin_path = ?1 AND is_installed = true",
+            [path_in_root(&bin_path)?],
+            map_installed_package,
+        )
+        .ok();
+    if owner.is_none() && bin_path.symlink_metadata().is_ok() {
+        return Err(SoarError::Conflict(format!(
+            "{} already exists (not managed by soar)",
+            bin_path.display()
+        )));
+    }
+
+    let displaced = match owner {
+        Some(owner) => {
+            let template = if owner.repo_name == package.repo_name
+                && owner.pkg_id == package.pkg_id
+                && owner.pkg_name == package.pkg_name
+            {
+                naming.versioned_template()
+            } else {
+                BinNaming::NameVariant.template()
+            };
+            let new_path = bin_path_for(template, &owner)?;
+            if new_path.symlink_metadata().is_ok() && current.as_ref() != Some(&new_path) {
+                return Err(SoarError::Conflict(format!(
+                    "{} already exists",
+                    new_path.display()
+                )));
+            }
+            let owner_target = read_link_absolute(&bin_path)
+                .unwrap_or_else(|_| Path::new(&owner.installed_path).join(&owner.pkg_name));
+            Some((owner, new_path, owner_target))
+        }
+        None => None,
+    };
+
+    relink(&target, &bin_path)?;
+    if let Some((_, ref new_path, ref owner_target)) = displaced {
+        relink(owner_target, new_path)?;
+    }
+    // the previous link of the package, unless the displaced install took it
+    if let Some(ref current) = current {
+        let taken = displaced
+            .as_ref()
+            .is_some_and(|(_, new_path, _)| new_path == current);
+        if !taken && current.symlink_metadata().is_ok() {
+            fs::remove_file(current)?;
+        }
+    }
+
+    let tx = conn.transaction()?;
+    if let Some((ref owner, ref new_path, _)) = displaced {
+        tx.execute(
+            "UPDATE packages SET bin_path = ?1 WHERE id = ?2",
+            params![path_in_root(new_path)?, owner.id],
+        )?;
+    }
+    tx.execute(
+        "UPDATE packages SET bin_path = ?1 WHERE id = ?2",
+        params![path_in_root(&bin_path)?, package.id],
+    )?;
+    tx.commit()?;
+
+    Ok(Switched {
+        bin_path,
+        displaced: displaced.map(|(owner, new_path, _)| {
+            (
+                format!("{}#{}@{}", owner.pkg_name, owner.pkg_id, owner.version),
+                new_path,
+            )
+        }),
+    })
+}
+
+/// Bin path the naming scheme gives `package`, which [`use_package`] links
+/// it to.
+pub fn default_bin_path(package: &InstalledPackage) -> SoarResult<PathBuf> {
+    let naming = get_config().bin_naming.unwrap_or_default();
+    bin_path_for(naming.template(), package)
+}
+
+fn bin_path_for(template: &str, package: &InstalledPackage) -> SoarResult<PathBuf> {
+    let name = render_installed_bin_name(template, package);
+    if !is_valid_file_name(&name) {
+        return Err(SoarError::InvalidPackageName(name));
+    }
+    Ok(get_config().get_bin_path()?.join(exe_name(&name)))
+}
+
+fn relink(target: &Path, link: &Path) -> SoarResult<()> {
+    let target = symlink_target(target, link);
+    replace_atomic(link, |temp| link_binary(&target, temp))?;
+    Ok(())
+}
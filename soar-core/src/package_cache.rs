@@ -0,0 +1,353 @@
+//! Cache of the files packages were installed from.
+//!
+//! With `keep_downloads` enabled, the files downloaded to install a package
+//! are kept in `packages/` under the checksum of the package, along with an
+//! `entry.json` describing it. Installing a package with the same checksum
+//! again, e.g. to reinstall it or after removing it, copies them from there
+//! instead of downloading them, so it works offline.
+//!
+//! With `shared_cache` set, they're kept in that directory instead, which
+//! the users of the host share. Entries are readable by all of them but
+//! only changed by the user who added them, and the directory is locked
+//! while entries are added or removed, as several users may install the
+//! same package at once. Packages are only restored from the entries of
+//! the current user or root, as another user could put any files under
+//! the checksum of a package.
+
+use std::{
+    fs::{self, File, OpenOptions, Permissions},
+    io::ErrorKind,
+    os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use nix::{
+    fcntl::{Flock, FlockArg},
+    unistd::geteuid,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::get_config,
+    database::models::Package,
+    error::SoarError,
+    utils::{dir_size, replace_atomic, write_atomic},
+    SoarResult,
+};
+
+/// Name of the file the cache is locked through.
+const LOCK_FILE: &str = ".lock";
+
+/// A package whose files are cached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPackage {
+    pub checksum: String,
+    pub pkg_name: String,
+    pub pkg_id: String,
+    pub version: String,
+    pub repo_name: String,
+    /// Size of the cached files in bytes.
+    pub size: u64,
+    /// When the entry was stored or last used, in seconds since the epoch.
+    pub last_used: u64,
+}
+
+struct Cache {
+    dir: PathBuf,
+    /// Whether the users of the host share the cache.
+    shared: bool,
+}
+
+fn cache() -> SoarResult<Cache> {
+    let config = get_config();
+    Ok(match config.get_shared_cache_path()? {
+        Some(dir) => Cache { dir, shared: true },
+        None => Cache {
+            dir: config.get_cache_path()?.join("packages"),
+            shared: false,
+        },
+    })
+}
+
+impl Cache {
+    /// Creates the cache directory if it's missing, for all users to add
+    /// to if it's shared, and checks they can't tamper with each other's
+    /// entries otherwise.
+    fn create(&self) -> SoarResult<()> {
+        if !self.dir.is_dir() {
+            fs::create_dir_all(&self.dir)?;
+            if self.shared {
+                // sticky, so that users can only remove their own entries
+                fs::set_permissions(&self.dir, Permissions::from_mode(0o1777))?;
+            }
+        }
+        if self.shared {
+            let mode = fs::metadata(&self.dir)?.mode();
+            if mode & 0o002 != 0 && mode & 0o1000 == 0 {
+                return Err(SoarError::Custom(format!(
+                    "Shared cache {} is writable by all users without the sticky bit, \
+                     set it with `chmod +t`",
+                    self.dir.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Locks the cache, shared to read entries and exclusive to change
+    /// them, until the lock is dropped.
+    fn lock(&self, arg: FlockArg) -> SoarResult<Flock<File>> {
+        let path = self.dir.join(LOCK_FILE);
+        // locking doesn't need write access, which other users don't have
+        // to the lock file of the shared cache
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .mode(0o644)
+                .open(&path)?,
+            Err(err) => return Err(err.into()),
+        };
+        Flock::lock(file, arg).map_err(|(_, errno)| errno.into())
+    }
+
+    /// Whether the files of the entry at `path` can be restored: always
+    /// for the own cache, and in the shared cache if the entry and all its
+    /// files belong to the current user or root and only they can change
+    /// them.
+    fn trusts(&self, path: &Path) -> SoarResult<bool> {
+        if !self.shared {
+            return Ok(true);
+        }
+        let uid = geteuid().as_raw();
+        let files = path.join("files");
+        let mut paths = vec![path.to_path_buf(), path.join("entry.json"), files.clone()];
+        for file in fs::read_dir(&files)? {
+            paths.push(file?.path());
+        }
+        for path in paths {
+            let metadata = fs::symlink_metadata(&path)?;
+            // links could point to files of other users
+            if metadata.is_symlink() || !owned_by(metadata.uid(), metadata.mode(), uid) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Removes the entry at `path`, skipping it if it belongs to another
+    /// user of the shared cache, returning whether it was removed.
+    fn remove_entry(&self, path: &Path) -> SoarResult<bool> {
+        match fs::remove_dir_all(path) {
+            Ok(()) => Ok(true),
+            Err(err) if self.shared && err.kind() == ErrorKind::PermissionDenied => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Whether a file with `owner` and `mode` can only have been written by
+/// the user `uid` or root.
+fn owned_by(owner: u32, mode: u32, uid: u32) -> bool {
+    (owner == uid || owner == 0) && mode & 0o022 == 0
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn read_entry(dir: &Path) -> Option<CachedPackage> {
+    serde_json::from_slice(&fs::read(dir.join("entry.json")).ok()?).ok()
+}
+
+fn write_entry(dir: &Path, entry: &CachedPackage) -> SoarResult<()> {
+    let content = serde_json::to_vec(entry)
+        .map_err(|err| SoarError::Custom(format!("Failed to serialize cache entry: {}", err)))?;
+    write_atomic(dir.join("entry.json"), content)
+}
+
+/// Whether the cache is shared between the users of the host.
+pub fn is_shared() -> SoarResult<bool> {
+    Ok(get_config().get_shared_cache_path()?.is_some())
+}
+
+/// Directory the files of the package with `checksum` are cached in.
+pub fn entry_path(checksum: &str) -> SoarResult<PathBuf> {
+    Ok(cache()?.dir.join(checksum))
+}
+
+/// Copies the cached files of the package with `checksum` to `output_dir`,
+/// returning whether there were any.
+pub fn restore(checksum: &str, output_dir: &Path) -> SoarResult<bool> {
+    let cache = cache()?;
+    let dir = cache.dir.join(checksum);
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+    let _lock = cache.lock(FlockArg::LockShared)?;
+    let Some(mut entry) = read_entry(&dir) else {
+        return Ok(false);
+    };
+    if !cache.trusts(&dir)? {
+        return Ok(false);
+    }
+
+    fs::create_dir_all(output_dir)?;
+    for file in fs::read_dir(dir.join("files"))? {
+        let file = file?;
+        let target = output_dir.join(file.file_name());
+        replace_atomic(&target, |temp| fs::copy(file.path(), temp).map(|_| ()))?;
+    }
+
+    entry.last_used = now();
+    // entries other users added to the shared cache can't be changed
+    if let Err(err) = write_entry(&dir, &entry) {
+        if !cache.shared {
+            return Err(err);
+        }
+    }
+    Ok(true)
+}
+
+/// Caches the files in `dir` that `package` was installed from.
+pub fn insert(package: &Package, dir: &Path) -> SoarResult<()> {
+    let cache = cache()?;
+    cache.create()?;
+    let _lock = cache.lock(FlockArg::LockExclusive)?;
+    let entry_dir = cache.dir.join(&package.checksum);
+    if read_entry(&entry_dir).is_some() {
+        return Ok(());
+    }
+
+    // assembled aside, so that an entry is either complete or missing
+    let staging = cache.dir.join(format!(".{}.tmp", package.checksum));
+    let _ = fs::remove_dir_all(&staging);
+    let files = staging.join("files");
+    fs::create_dir_all(&files)?;
+    for file in fs::read_dir(dir)? {
+        let file = file?;
+        if !file.file_type()?.is_file() {
+            continue;
+        }
+        let target = files.join(file.file_name());
+        if cache.shared {
+            // copied, as a hard link would let the user change the cached
+            // file through the installed one
+            fs::copy(file.path(), &target)?;
+            let executable = file.metadata()?.mode() & 0o111 != 0;
+            let mode = if executable { 0o755 } else { 0o644 };
+            fs::set_permissions(&target, Permissions::from_mode(mode))?;
+        } else {
+            // copied where hard links aren't possible
+            fs::hard_link(file.path(), &target)
+                .or_else(|_| fs::copy(file.path(), &target).map(|_| ()))?;
+        }
+    }
+
+    let entry = CachedPackage {
+        checksum: package.checksum.clone(),
+        pkg_name: package.pkg_name.clone(),
+        pkg_id: package.pkg_id.clone(),
+        version: package.version.clone(),
+        repo_name: package.repo_name.clone(),
+        size: dir_size(&files)?,
+        last_used: now(),
+    };
+    write_entry(&staging, &entry)?;
+    if cache.shared {
+        // readable by all users, whatever their umask
+        fs::set_permissions(staging.join("entry.json"), Permissions::from_mode(0o644))?;
+        for path in [&files, &staging] {
+            fs::set_permissions(path, Permissions::from_mode(0o755))?;
+        }
+    }
+
+    let _ = fs::remove_dir_all(&entry_dir);
+    fs::rename(&staging, &entry_dir)?;
+    Ok(())
+}
+
+/// Drops the cached files of the package with `checksum`, e.g. if they no
+/// longer match it. Entries other users added to the shared cache are left
+/// alone.
+pub fn remove(checksum: &str) -> SoarResult<()> {
+    let cache = cache()?;
+    let dir = cache.dir.join(checksum);
+    if dir.exists() {
+        let _lock = cache.lock(FlockArg::LockExclusive)?;
+        cache.remove_entry(&dir)?;
+    }
+    Ok(())
+}
+
+/// Cached packages, most recently used first.
+pub fn entries() -> SoarResult<Vec<CachedPackage>> {
+    let cache = cache()?;
+    let Ok(dir) = fs::read_dir(&cache.dir) else {
+        return Ok(Vec::new());
+    };
+    let _lock = cache.lock(FlockArg::LockShared)?;
+    let mut entries: Vec<CachedPackage> = dir
+        .filter_map(|entry| read_entry(&entry.ok()?.path()))
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+    Ok(entries)
+}
+
+/// Removes the packages not used within `max_age`, or all of them without
+/// one, returning how many were removed and the bytes freed. Only the
+/// entries of the current user are removed from the shared cache.
+pub fn prune(max_age: Option<Duration>) -> SoarResult<(usize, u64)> {
+    let cache = cache()?;
+    let Ok(dir) = fs::read_dir(&cache.dir) else {
+        return Ok((0, 0));
+    };
+    let _lock = cache.lock(FlockArg::LockExclusive)?;
+    let cutoff = max_age.map(|age| now().saturating_sub(age.as_secs()));
+
+    let mut removed = 0;
+    let mut freed = 0;
+    for path in dir.filter_map(|entry| Some(entry.ok()?.path())) {
+        if path.file_name().is_some_and(|name| name == LOCK_FILE) {
+            continue;
+        }
+        // incomplete entries are always removed
+        let entry = read_entry(&path);
+        if entry
+            .as_ref()
+            .is_some_and(|entry| cutoff.is_some_and(|cutoff| entry.last_used >= cutoff))
+        {
+            continue;
+        }
+        let size = dir_size(&path).unwrap_or(0);
+        if !cache.remove_entry(&path)? {
+            continue;
+        }
+        freed += size;
+        if entry.is_some() {
+            removed += 1;
+        }
+    }
+    Ok((removed, freed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trusts_files_only_the_user_or_root_can_write() {
+        assert!(owned_by(1000, 0o100644, 1000));
+        assert!(owned_by(0, 0o100755, 1000));
+        assert!(owned_by(0, 0o40755, 0));
+        assert!(!owned_by(1001, 0o100644, 1000));
+        assert!(!owned_by(1000, 0o100664, 1000));
+        assert!(!owned_by(0, 0o40777, 1000));
+    }
+}
@@ -0,0 +1,361 @@
+//! Platform specific behavior: where files live, how binaries are linked into
+//! the bin directory and how release artifacts are named.
+//!
+//! Linux follows the XDG base directory spec. macOS uses the `~/Library`
+//! locations unless the XDG variables are set, and Windows uses the known
+//! folders from `APPDATA`/`LOCALAPPDATA`, linking binaries through `.exe`
+//! shims since symlinks need elevated rights there.
+
+use std::{
+    env::{
+        self,
+        consts::{ARCH, EXE_SUFFIX, OS},
+    },
+    fs, io,
+    path::Path,
+};
+
+/// Operating system name as used in repository metadata, e.g. `Linux`.
+pub fn os_name() -> String {
+    match OS {
+        "linux" => "Linux".to_string(),
+        "macos" => "Darwin".to_string(),
+        "windows" => "Windows".to_string(),
+        os => format!("{}{}", &os[..1].to_uppercase(), &os[1..]),
+    }
+}
+
+/// Retrieves the platform string in the format `ARCH-Os`.
+///
+/// This combines the architecture (e.g., `x86_64`) and the operating system
+/// name (e.g., `Linux`, `Darwin`) to identify the platform, and selects the
+/// metadata of the default repositories.
+pub fn get_platform() -> String {
+    format!("{}-{}", ARCH, os_name())
+}
+
+/// Regex fragment matching the names release artifacts use for the current
+/// architecture.
+pub fn arch_pattern() -> &'static str {
+    match ARCH {
+        "x86_64" => "(x86_64|x86-64|amd64|x64)",
+        "aarch64" => "(aarch64|arm64)",
+        "x86" => "(i386|i686|x86)",
+        "arm" => "(armv7|armhf|arm)",
+        arch => arch,
+    }
+}
+
+/// Regex fragment matching the names release artifacts use for the current
+/// operating system.
+pub fn os_pattern() -> &'static str {
+    match OS {
+        "macos" => "(darwin|macos|apple|osx)",
+        "windows" => "(windows|win64|win32)",
+        os => os,
+    }
+}
+
+/// Name fragments of release artifacts built for other operating systems.
+pub fn foreign_os_keywords() -> &'static [&'static str] {
+    match OS {
+        "macos" => &["linux", "windows", "freebsd"],
+        "windows" => &["linux", "darwin", "macos", "freebsd"],
+        "linux" => &["darwin", "macos", "windows", "freebsd"],
+        _ => &["linux", "darwin", "macos", "windows"],
+    }
+}
+
+/// Architecture name fragments of release artifacts, checked for ones built
+/// for other architectures.
+const ARCH_KEYWORDS: &[&str] = &[
+    "x86_64",
+    "x86-64",
+    "amd64",
+    "x64",
+    "aarch64",
+    "arm64",
+    "i386",
+    "i686",
+    "armv7",
+    "armhf",
+    "riscv64",
+    "s390x",
+    "ppc64le",
+    "loongarch64",
+];
+
+/// Extensions of distribution packages, which can't be installed as is.
+const PACKAGE_EXTENSIONS: &[&str] = &[".deb", ".rpm", ".apk", ".msi", ".dmg", ".pkg"];
+
+/// Extensions of archives.
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.zst", ".tar.bz2", ".zip", ".7z",
+];
+
+/// Extensions of checksums and signatures published next to artifacts.
+const SIDECAR_EXTENSIONS: &[&str] = &[
+    ".sha256",
+    ".sha512",
+    ".sha256sum",
+    ".sig",
+    ".asc",
+    ".pem",
+    ".sbom",
+    ".json",
+    ".txt",
+];
+
+/// C library of the host, `musl` or `gnu`.
+pub fn host_libc() -> &'static str {
+    let musl = fs::read_dir("/lib").is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+    });
+    if musl {
+        "musl"
+    } else {
+        "gnu"
+    }
+}
+
+/// Scores how well a release artifact named `name` suits the host, higher
+/// is better.
+///
+/// Building for the architecture and operating system counts most, then
+/// the C library, where static musl builds run on glibc hosts too. Archives
+/// are preferred over distribution packages, and checksums or signatures
+/// rank last.
+pub fn asset_score(name: &str) -> i32 {
+    let name = name.to_lowercase();
+    let fragment_matches = |pattern: &str| {
+        pattern
+            .trim_matches(|c| c == '(' || c == ')')
+            .split('|')
+            .any(|fragment| name.contains(fragment))
+    };
+
+    let mut score = 0;
+    if fragment_matches(arch_pattern()) {
+        score += 8;
+    } else if ARCH_KEYWORDS.iter().any(|arch| name.contains(arch)) {
+        score -= 8;
+    }
+    if fragment_matches(os_pattern()) {
+        score += 4;
+    }
+    if foreign_os_keywords().iter().any(|os| name.contains(os)) {
+        score -= 8;
+    }
+
+    let (native, foreign) = match host_libc() {
+        "musl" => ("musl", "gnu"),
+        _ => ("gnu", "musl"),
+    };
+    if name.contains(native) {
+        score += 2;
+    } else if name.contains(foreign) {
+        score += if foreign == "musl" { 1 } else { -4 };
+    }
+
+    if ARCHIVE_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+        score += 1;
+    }
+    if PACKAGE_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+        score -= 2;
+    }
+    if SIDECAR_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+        score -= 16;
+    }
+    score
+}
+
+#[cfg(unix)]
+fn get_username() -> Option<String> {
+    use nix::unistd::{geteuid, User};
+
+    User::from_uid(geteuid()).ok()?.map(|user| user.name)
+}
+
+#[cfg(not(unix))]
+fn get_username() -> Option<String> {
+    None
+}
+
+pub fn home_path() -> String {
+    let home_var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var(home_var).unwrap_or_else(|_| {
+        let username = env::var("USER")
+            .or_else(|_| env::var("LOGNAME"))
+            .or_else(|_| env::var("USERNAME"))
+            .ok()
+            .or_else(get_username)
+            .unwrap_or_else(|| panic!("Couldn't determine username. Please fix the system."));
+        match OS {
+            "macos" => format!("/Users/{}", username),
+            "windows" => format!("C:\\Users\\{}", username),
+            _ => format!("/home/{}", username),
+        }
+    })
+}
+
+/// Resolves a base directory from the XDG variable `xdg_var`, falling back to
+/// the platform's conventional location.
+fn base_dir(xdg_var: &str, linux: &str, macos: &str, windows_var: &str) -> String {
+    if let Ok(dir) = env::var(xdg_var) {
+        return dir;
+    }
+    match OS {
+        "macos" => format!("{}/{}", home_path(), macos),
+        "windows" => {
+            env::var(windows_var).unwrap_or_else(|_| format!("{}\\AppData\\Local", home_path()))
+        }
+        _ => format!("{}/{}", home_path(), linux),
+    }
+}
+
+pub fn home_config_path() -> String {
+    base_dir(
+        "XDG_CONFIG_HOME",
+        ".config",
+        "Library/Application Support",
+        "APPDATA",
+    )
+}
+
+pub fn home_cache_path() -> String {
+    base_dir("XDG_CACHE_HOME", ".cache", "Library/Caches", "LOCALAPPDATA")
+}
+
+pub fn home_data_path() -> String {
+    base_dir(
+        "XDG_DATA_HOME",
+        ".local/share",
+        "Library/Application Support",
+        "LOCALAPPDATA",
+    )
+}
+
+pub fn home_state_path() -> String {
+    base_dir(
+        "XDG_STATE_HOME",
+        ".local/state",
+        "Library/Logs",
+        "LOCALAPPDATA",
+    )
+}
+
+/// Name of the file a binary called `name` is linked as in the bin directory,
+/// i.e. with `.exe` appended on Windows.
+pub fn exe_name(name: &str) -> String {
+    if EXE_SUFFIX.is_empty() || name.ends_with(EXE_SUFFIX) {
+        name.to_string()
+    } else {
+        format!("{}{}", name, EXE_SUFFIX)
+    }
+}
+
+/// Creates a symlink at `to` pointing to `from`.
+pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(from, to)
+    }
+    #[cfg(windows)]
+    {
+        if from.as_ref().is_dir() {
+            std::os::windows::fs::symlink_dir(from, to)
+        } else {
+            std::os::windows::fs::symlink_file(from, to)
+        }
+    }
+}
+
+/// Makes the binary `target` available as `link` in the bin directory.
+///
+/// This is a symlink on Unix. On Windows it's a hard link to the binary, or a
+/// copy if the bin directory is on another volume, acting as an `.exe` shim.
+pub fn link_binary<P: AsRef<Path>, Q: AsRef<Path>>(target: P, link: Q) -> io::Result<()> {
+    if cfg!(windows) {
+        let (target, link) = (target.as_ref(), link.as_ref());
+        fs::hard_link(target, link).or_else(|_| fs::copy(target, link).map(|_| ()))
+    } else {
+        symlink(target, link)
+    }
+}
+
+/// Marks `path` as executable by everyone. A no-op where files carry no
+/// executable bit.
+pub fn make_executable<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut permissions = fs::metadata(&path)?.permissions();
+        permissions.set_mode(permissions.mode() | 0o755);
+        fs::set_permissions(path, permissions)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
+/// Whether `metadata` describes an executable file.
+pub fn is_executable(path: &Path, metadata: &fs::Metadata) -> bool {
+    if !metadata.is_file() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _ = path;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        path.extension().is_some_and(|ext| ext == "exe")
+    }
+}
+
+/// IDs of the running processes whose executable lives under `dir`.
+///
+/// Only implemented on Linux, where `/proc` exposes the executable of each
+/// process; elsewhere no processes are reported.
+pub fn processes_using(dir: &Path) -> Vec<u32> {
+    if OS != "linux" {
+        return Vec::new();
+    }
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let exe = fs::read_link(entry.path().join("exe")).ok()?;
+            exe.starts_with(dir).then_some(pid)
+        })
+        .collect()
+}
+
+/// Whether the current user can create files at `path`, checking the closest
+/// existing ancestor if `path` doesn't exist yet.
+pub fn is_writable(path: &Path) -> bool {
+    let Some(existing) = path.ancestors().find(|dir| dir.exists()) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use nix::unistd::{access, AccessFlags};
+
+        access(existing, AccessFlags::W_OK).is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        fs::metadata(existing).is_ok_and(|meta| !meta.permissions().readonly())
+    }
+}
@@ -0,0 +1,107 @@
+//! Install counts published by repositories.
+//!
+//! A repository with a `stats_url` serves the install counts of its
+//! packages there as a JSON array of [`PackageStats`]. With `telemetry`
+//! enabled, installs are reported by POSTing an [`InstallReport`] to the
+//! same URL. Reports only name the packages installed, nothing about the
+//! machine or the user beyond what any HTTP request carries.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Repository,
+    database::models::Package,
+    error::SoarError,
+    http::{client, send_with_retry},
+    SoarResult,
+};
+
+/// Installs are reported in the background of an install, so they must
+/// never hold it up for long.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Install count of a package.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageStats {
+    pub pkg_id: String,
+    pub pkg_name: String,
+    pub installs: u64,
+}
+
+#[derive(Serialize)]
+struct InstalledPackage<'a> {
+    pkg_id: &'a str,
+    pkg_name: &'a str,
+    version: &'a str,
+}
+
+/// Packages installed from a repository in one go.
+#[derive(Serialize)]
+struct InstallReport<'a> {
+    installs: Vec<InstalledPackage<'a>>,
+}
+
+/// Fetches the install counts of the packages of `repo`, most installed
+/// first.
+pub async fn fetch_stats(repo: &Repository) -> SoarResult<Vec<PackageStats>> {
+    let Some(url) = &repo.stats_url else {
+        return Ok(Vec::new());
+    };
+    let client = client();
+    let resp = send_with_retry(|| client.get(url).headers(repo.header_map())).await?;
+    if !resp.status().is_success() {
+        return Err(SoarError::Custom(format!(
+            "Failed to fetch the stats of {} [{}]",
+            repo.name,
+            resp.status()
+        )));
+    }
+    let mut stats: Vec<PackageStats> = resp
+        .json()
+        .await
+        .map_err(|err| SoarError::Custom(format!("Invalid stats from {}: {}", repo.name, err)))?;
+    stats.sort_by(|a, b| {
+        b.installs
+            .cmp(&a.installs)
+            .then(a.pkg_name.cmp(&b.pkg_name))
+    });
+    Ok(stats)
+}
+
+/// Reports the `packages` installed from `repo` to its stats endpoint.
+pub async fn report_installs(repo: &Repository, packages: &[Package]) -> SoarResult<()> {
+    let Some(url) = &repo.stats_url else {
+        return Ok(());
+    };
+    let installs: Vec<InstalledPackage> = packages
+        .iter()
+        .filter(|package| package.repo_name == repo.name)
+        .map(|package| InstalledPackage {
+            pkg_id: &package.pkg_id,
+            pkg_name: &package.pkg_name,
+            version: &package.version,
+        })
+        .collect();
+    if installs.is_empty() {
+        return Ok(());
+    }
+
+    // sent once, a lost report isn't worth retrying for
+    let resp = client()
+        .post(url)
+        .headers(repo.header_map())
+        .timeout(REPORT_TIMEOUT)
+        .json(&InstallReport { installs })
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        return Err(SoarError::Custom(format!(
+            "Failed to report installs to {} [{}]",
+            repo.name,
+            resp.status()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Software bill of materials documents for installed packages.
+
+use chrono::{SecondsFormat, Utc};
+use rand::RngCore;
+use serde_json::{json, Value};
+
+/// A package entry in the bill of materials.
+#[derive(Debug, Clone)]
+pub struct SbomComponent {
+    pub pkg_name: String,
+    pub pkg_id: String,
+    pub version: String,
+    pub repo_name: String,
+    pub download_url: Option<String>,
+    /// blake3 checksum of the installed file.
+    pub checksum: String,
+}
+
+fn random_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    // version 4, RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Builds an SPDX 2.3 JSON document.
+pub fn spdx_document(components: &[SbomComponent]) -> Value {
+    let packages: Vec<Value> = components
+        .iter()
+        .enumerate()
+        .map(|(idx, c)| {
+            json!({
+                "SPDXID": format!("SPDXRef-Package-{}", idx + 1),
+                "name": c.pkg_name,
+                "versionInfo": c.version,
+                "supplier": format!("Organization: {}", c.repo_name),
+                "downloadLocation": c.download_url.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+                "checksums": [{ "algorithm": "BLAKE3", "checksumValue": c.checksum }],
+                "comment": format!("soar package {}#{}:{}", c.pkg_name, c.pkg_id, c.repo_name),
+            })
+        })
+        .collect();
+
+    let relationships: Vec<Value> = (1..=components.len())
+        .map(|idx| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": format!("SPDXRef-Package-{idx}"),
+            })
+        })
+        .collect();
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": "soar-installed-packages",
+        "documentNamespace": format!("https://spdx.org/spdxdocs/soar-{}", random_uuid()),
+        "creationInfo": {
+            "created": timestamp(),
+            "creators": [format!("Tool: soar-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// Builds a CycloneDX 1.5 JSON document.
+pub fn cyclonedx_document(components: &[SbomComponent]) -> Value {
+    let components: Vec<Value> = components
+        .iter()
+        .map(|c| {
+            let mut component = json!({
+                "type": "application",
+                "bom-ref": format!("{}#{}:{}", c.pkg_name, c.pkg_id, c.repo_name),
+                "name": c.pkg_name,
+                "version": c.version,
+                "hashes": [{ "alg": "BLAKE3", "content": c.checksum }],
+                "properties": [
+                    { "name": "soar:pkg_id", "value": c.pkg_id },
+                    { "name": "soar:repo_name", "value": c.repo_name },
+                ],
+            });
+            if let Some(ref url) = c.download_url {
+                component["externalReferences"] = json!([{ "type": "distribution", "url": url }]);
+            }
+            component
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", random_uuid()),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp(),
+            "tools": [{ "name": "soar", "version": env!("CARGO_PKG_VERSION") }],
+        },
+        "components": components,
+    })
+}
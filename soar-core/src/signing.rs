@@ -0,0 +1,128 @@
+//! Verification of detached ed25519 signatures on repository metadata.
+//!
+//! A signed repository publishes `<url>.sig`, the base64 signature of the
+//! metadata, and `<url>.pub`, the base64 public key. Unless keys are pinned in
+//! the repository config, the first key seen is trusted and stored next to
+//! the metadata. A repository can rotate its key by publishing
+//! `<url>.pub.sig`, the new key signed by the previous one; any other key
+//! change has to be accepted explicitly with [`trust_key`].
+
+use std::fs;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::StatusCode;
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+use crate::{
+    config::Repository,
+    error::SoarError,
+    http::{client, send_with_retry},
+    SoarResult,
+};
+
+const TRUSTED_KEY_FILE: &str = "trusted.pub";
+
+async fn fetch_optional(repo: &Repository, url: &str) -> SoarResult<Option<String>> {
+    let client = client();
+    let headers = repo.header_map();
+    let resp = send_with_retry(|| client.get(url).headers(headers.clone())).await?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        return Err(SoarError::FailedToFetchRemote(url.to_string()));
+    }
+    Ok(Some(resp.text().await?.trim().to_string()))
+}
+
+fn verify(pubkey: &str, data: &[u8], signature: &str) -> bool {
+    let (Ok(pubkey), Ok(signature)) = (STANDARD.decode(pubkey), STANDARD.decode(signature)) else {
+        return false;
+    };
+    UnparsedPublicKey::new(&ED25519, pubkey)
+        .verify(data, &signature)
+        .is_ok()
+}
+
+/// Key currently trusted for `repo` on first use, if any.
+pub fn trusted_key(repo: &Repository) -> SoarResult<Option<String>> {
+    let path = repo.get_path()?.join(TRUSTED_KEY_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(fs::read_to_string(path)?.trim().to_string()))
+}
+
+/// Trusts `key` for future metadata of `repo`, replacing any previous key.
+pub fn trust_key(repo: &Repository, key: &str) -> SoarResult<()> {
+    let repo_path = repo.get_path()?;
+    fs::create_dir_all(&repo_path)?;
+    fs::write(repo_path.join(TRUSTED_KEY_FILE), key)?;
+    Ok(())
+}
+
+/// Verifies the signature of the metadata `data` fetched for `repo`.
+///
+/// Unsigned repositories are accepted until they are first seen signed or a
+/// key is pinned for them, after which a missing signature is an error.
+pub async fn verify_metadata(repo: &Repository, data: &[u8]) -> SoarResult<()> {
+    let signature = fetch_optional(repo, &format!("{}.sig", repo.url)).await?;
+    let trusted = trusted_key(repo)?;
+
+    let Some(signature) = signature else {
+        if repo.pubkeys.is_empty() && trusted.is_none() {
+            return Ok(());
+        }
+        return Err(SoarError::SignatureVerificationFailed(format!(
+            "{}: metadata is not signed",
+            repo.name
+        )));
+    };
+
+    if !repo.pubkeys.is_empty() {
+        if repo.pubkeys.iter().any(|key| verify(key, data, &signature)) {
+            return Ok(());
+        }
+        return Err(SoarError::SignatureVerificationFailed(format!(
+            "{}: metadata is not signed by a configured key",
+            repo.name
+        )));
+    }
+
+    let key = fetch_optional(repo, &format!("{}.pub", repo.url))
+        .await?
+        .ok_or_else(|| {
+            SoarError::SignatureVerificationFailed(format!(
+                "{}: public key is not published",
+                repo.name
+            ))
+        })?;
+
+    match trusted {
+        None => trust_key(repo, &key)?,
+        Some(ref trusted) if *trusted == key => {}
+        Some(ref trusted) => {
+            let rotation = fetch_optional(repo, &format!("{}.pub.sig", repo.url)).await?;
+            match rotation {
+                Some(rotation) if verify(trusted, key.as_bytes(), &rotation) => {
+                    trust_key(repo, &key)?
+                }
+                _ => {
+                    return Err(SoarError::UntrustedKey {
+                        repo_name: repo.name.clone(),
+                        key,
+                    })
+                }
+            }
+        }
+    }
+
+    if !verify(&key, data, &signature) {
+        return Err(SoarError::SignatureVerificationFailed(format!(
+            "{}: invalid metadata signature",
+            repo.name
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Content-addressed store of installed package files.
+//!
+//! Each file is kept once under its checksum and hard-linked into the
+//! install directories of the packages that contain it, so installing the
+//! same artifact for several variants or profiles takes its space once.
+//! Entries outlive the packages they were linked into until `soar gc`
+//! removes them.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::Connection;
+
+use crate::{
+    config::get_config,
+    utils::{calculate_checksum, replace_atomic},
+    SoarResult,
+};
+
+/// Stores the file at `path` with the given `checksum`, replacing it with a
+/// hard link to the stored copy if the store already has one.
+///
+/// Nothing is deduplicated where hard links aren't possible, e.g. if the
+/// store is on another filesystem than `path`.
+pub fn dedup(path: &Path, checksum: &str) -> SoarResult<()> {
+    let store_path = get_config().get_store_path()?;
+    fs::create_dir_all(&store_path)?;
+    let entry = store_path.join(checksum);
+
+    // an installed copy may have been modified in place, taking the stored
+    // one along with it
+    if entry.is_file() && calculate_checksum(&entry).is_ok_and(|stored| stored == checksum) {
+        let _ = replace_atomic(path, |temp| fs::hard_link(&entry, temp));
+        return Ok(());
+    }
+
+    let _ = fs::remove_file(&entry);
+    let _ = fs::hard_link(path, &entry);
+    Ok(())
+}
+
+/// Removes the store entries no installed package refers to anymore,
+/// returning how many were removed and the bytes they took up.
+pub fn gc(core_db: Arc<Mutex<Connection>>) -> SoarResult<(usize, u64)> {
+    let store_path = get_config().get_store_path()?;
+    let Ok(entries) = fs::read_dir(&store_path) else {
+        return Ok((0, 0));
+    };
+
+    let referenced: HashSet<String> = {
+        let conn = core_db.lock()?;
+        let mut stmt = conn.prepare("SELECT checksum FROM packages WHERE is_installed = true")?;
+        let checksums = stmt
+            .query_map([], |row| row.get(0))?
+            .filter_map(Result::ok)
+            .collect();
+        checksums
+    };
+
+    let mut removed = 0;
+    let mut freed = 0;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if referenced.contains(&name) {
+            continue;
+        }
+        let size = entry.metadata()?.len();
+        fs::remove_file(entry.path())?;
+        removed += 1;
+        freed += size;
+    }
+
+    Ok((removed, freed))
+}
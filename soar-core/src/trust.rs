@@ -0,0 +1,96 @@
+//! Trust in repositories, decided by their `trust_policy`.
+//!
+//! Under the default `prompt` policy, a repository added with `soar repo add`
+//! is untrusted until the user accepts it, which they are asked to on the
+//! first install from it. Under `never` nobody is asked and only repositories
+//! with signed metadata are trusted, under `always` every repository is.
+
+use std::fs;
+
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine,
+};
+use ring::digest::{digest, SHA256};
+
+use crate::{
+    config::{get_config, Repository, TrustPolicy},
+    error::SoarError,
+    signing::trusted_key,
+    SoarResult,
+};
+
+/// Marks a repository that hasn't been accepted since it was added.
+const UNTRUSTED_FILE: &str = "untrusted";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trust {
+    Trusted,
+    /// The user has to accept the repository first.
+    Unconfirmed,
+    /// The trust policy rules the repository out.
+    Refused,
+}
+
+/// Fingerprints of the keys the metadata of `repo` is verified with, empty
+/// for unsigned repositories.
+pub fn key_fingerprints(repo: &Repository) -> SoarResult<Vec<String>> {
+    let keys = if repo.pubkeys.is_empty() {
+        trusted_key(repo)?.into_iter().collect()
+    } else {
+        repo.pubkeys.clone()
+    };
+
+    Ok(keys
+        .iter()
+        .map(|key| {
+            let key = STANDARD
+                .decode(key)
+                .unwrap_or_else(|_| key.as_bytes().to_vec());
+            format!("SHA256:{}", STANDARD_NO_PAD.encode(digest(&SHA256, &key)))
+        })
+        .collect())
+}
+
+pub fn repository_trust(repo: &Repository) -> SoarResult<Trust> {
+    Ok(match repo.trust_policy.unwrap_or_default() {
+        TrustPolicy::Always => Trust::Trusted,
+        TrustPolicy::Prompt if repo.get_path()?.join(UNTRUSTED_FILE).exists() => Trust::Unconfirmed,
+        TrustPolicy::Prompt => Trust::Trusted,
+        TrustPolicy::Never if key_fingerprints(repo)?.is_empty() => Trust::Refused,
+        TrustPolicy::Never => Trust::Trusted,
+    })
+}
+
+/// Marks the newly added `repo` as untrusted until it's accepted with
+/// [`trust_repository`].
+pub fn mark_untrusted(repo: &Repository) -> SoarResult<()> {
+    let repo_path = repo.get_path()?;
+    fs::create_dir_all(&repo_path)?;
+    fs::write(repo_path.join(UNTRUSTED_FILE), "")?;
+    Ok(())
+}
+
+pub fn trust_repository(repo: &Repository) -> SoarResult<()> {
+    let path = repo.get_path()?.join(UNTRUSTED_FILE);
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Fails unless packages from the repository `repo_name` may be installed.
+/// Packages not from a configured repository, e.g. GitHub releases, are
+/// left to the user.
+pub fn ensure_trusted(repo_name: &str) -> SoarResult<()> {
+    let config = get_config();
+    let Ok(repo) = config.get_repository(repo_name) else {
+        return Ok(());
+    };
+    match repository_trust(repo)? {
+        Trust::Trusted => Ok(()),
+        Trust::Unconfirmed | Trust::Refused => {
+            Err(SoarError::UntrustedRepository(repo_name.to_string()))
+        }
+    }
+}
@@ -1,49 +1,23 @@
 use std::{
-    env::{
-        self,
-        consts::{ARCH, OS},
-    },
+    env,
+    ffi::{OsStr, OsString},
     fs::{self, File},
-    io::{BufReader, Read, Seek},
-    os,
-    path::{Path, PathBuf},
+    io::{self, BufReader, Read, Seek},
+    os::unix::fs::MetadataExt,
+    path::{Component, Path, PathBuf},
 };
 
-use nix::unistd::{geteuid, User};
+use rand::{distributions::Alphanumeric, Rng};
 
-use crate::{config::get_config, error::SoarError, SoarResult};
+use crate::{
+    config::{get_config, get_install_root, is_system_mode},
+    error::SoarError,
+    platform::{self, home_path},
+    SoarResult,
+};
 
 type Result<T> = std::result::Result<T, SoarError>;
 
-fn get_username() -> Result<String> {
-    let uid = geteuid();
-    User::from_uid(uid)?
-        .ok_or_else(|| panic!("Failed to get user"))
-        .map(|user| user.name)
-}
-
-pub fn home_path() -> String {
-    env::var("HOME").unwrap_or_else(|_| {
-        let username = env::var("USER")
-            .or_else(|_| env::var("LOGNAME"))
-            .or_else(|_| get_username().map_err(|_| ()))
-            .unwrap_or_else(|_| panic!("Couldn't determine username. Please fix the system."));
-        format!("/home/{}", username)
-    })
-}
-
-pub fn home_config_path() -> String {
-    env::var("XDG_CONFIG_HOME").unwrap_or(format!("{}/.config", home_path()))
-}
-
-pub fn home_cache_path() -> String {
-    env::var("XDG_CACHE_HOME").unwrap_or(format!("{}/.cache", home_path()))
-}
-
-pub fn home_data_path() -> String {
-    env::var("XDG_DATA_HOME").unwrap_or(format!("{}/.local/share", home_path()))
-}
-
 /// Expands the environment variables and user home directory in a given path.
 pub fn build_path(path: &str) -> Result<PathBuf> {
     let mut result = String::new();
@@ -143,10 +117,14 @@ pub fn setup_required_paths() -> Result<()> {
         fs::create_dir_all(db_path)?
     }
 
-    for (_, profile) in &config.profile {
-        let packages_path = profile.get_packages_path();
-        if !packages_path.exists() {
-            fs::create_dir_all(packages_path)?;
+    if is_system_mode() {
+        fs::create_dir_all(config.get_packages_path()?)?;
+    } else {
+        for profile in config.profile.values() {
+            let packages_path = profile.get_packages_path();
+            if !packages_path.exists() {
+                fs::create_dir_all(packages_path)?;
+            }
         }
     }
 
@@ -163,14 +141,161 @@ pub fn calc_magic_bytes<P: AsRef<Path>>(file_path: P, size: usize) -> Result<Vec
 }
 
 pub fn create_symlink<P: AsRef<Path>>(from: P, to: P) -> SoarResult<()> {
-    let to = to.as_ref();
-    if to.is_symlink() {
-        fs::remove_file(to)?;
+    let from = symlink_target(from.as_ref(), to.as_ref());
+    replace_atomic(to, |temp| platform::symlink(&from, temp))
+}
+
+/// What a symlink at `link` to `target` should point to: `target` relative
+/// to the directory of the link if both are in the install root, so that it
+/// resolves the same wherever the root ends up mounted, and `target` as is
+/// otherwise.
+pub fn symlink_target(target: &Path, link: &Path) -> PathBuf {
+    symlink_target_in(get_install_root().as_deref(), target, link)
+}
+
+fn symlink_target_in(root: Option<&Path>, target: &Path, link: &Path) -> PathBuf {
+    let (Some(root), Some(dir)) = (root, link.parent()) else {
+        return target.to_path_buf();
+    };
+    if cfg!(windows) || !target.starts_with(root) || !dir.starts_with(root) {
+        return target.to_path_buf();
+    }
+
+    let target: Vec<Component> = target.components().collect();
+    let dir: Vec<Component> = dir.components().collect();
+    let common = target.iter().zip(&dir).take_while(|(a, b)| a == b).count();
+    let mut relative: PathBuf = dir[common..].iter().map(|_| Component::ParentDir).collect();
+    relative.extend(&target[common..]);
+    relative
+}
+
+/// Where the symlink at `path` points to, resolving a relative target
+/// against the directory of the link without following further links.
+pub fn read_link_absolute<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    let path = path.as_ref();
+    let target = fs::read_link(path)?;
+    if target.is_absolute() {
+        return Ok(target);
+    }
+
+    let mut resolved = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    for component in target.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            component => resolved.push(component),
+        }
+    }
+    Ok(resolved)
+}
+
+/// Replaces `path` with the file `create` makes at the temporary path it is
+/// given, renaming it into place so readers see either the old or the new
+/// file but never a missing or partially written one.
+pub fn replace_atomic<P, F>(path: P, create: F) -> SoarResult<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&Path) -> io::Result<()>,
+{
+    let path = path.as_ref();
+    let rand_str: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect();
+    let temp = affix_file_name(path, ".", &format!(".{}.tmp", rand_str));
+
+    let result = create(&temp).and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    Ok(result?)
+}
+
+/// Whether `name` can be used as a file name as is: spaces and any other
+/// characters are fine, but it can't name another directory.
+pub fn is_valid_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\0'])
+        && !(cfg!(windows) && name.contains('\\'))
+}
+
+/// `name` with what would make it name another directory replaced, e.g.
+/// the slash of a GitHub `owner/repo`.
+pub fn sanitize_file_name(name: &str) -> String {
+    if name == "." || name == ".." {
+        return name.replace('.', "_");
+    }
+    name.replace(['/', '\\', '\0'], "_")
+}
+
+/// `path` with `prefix` and `suffix` added to its file name, keeping names
+/// that aren't valid UTF-8 intact.
+pub fn affix_file_name(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Whether the file name of `entry` starts with `prefix`, comparing bytes so
+/// that names which aren't valid UTF-8 match too.
+pub fn file_name_starts_with(entry: &fs::DirEntry, prefix: &OsStr) -> bool {
+    entry
+        .file_name()
+        .as_encoded_bytes()
+        .starts_with(prefix.as_encoded_bytes())
+}
+
+/// Writes `contents` to `path` through [`replace_atomic`].
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> SoarResult<()> {
+    replace_atomic(path, |temp| fs::write(temp, contents))
+}
+
+/// Bytes available to unprivileged users on the filesystem of `path`, or of
+/// its closest existing ancestor if it doesn't exist yet.
+pub fn free_space<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let path = existing_ancestor(path.as_ref());
+    let stat = nix::sys::statvfs::statvfs(path)?;
+    Ok(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+}
+
+/// Fails with [`SoarError::InsufficientSpace`] if any filesystem lacks room
+/// for the bytes `required` on it. Sizes of paths on the same filesystem
+/// add up.
+pub fn check_free_space(required: &[(PathBuf, u64)]) -> Result<()> {
+    let mut filesystems: Vec<(u64, &Path, u64)> = Vec::new();
+    for (path, size) in required {
+        let dev = fs::metadata(existing_ancestor(path))?.dev();
+        match filesystems.iter_mut().find(|(known, ..)| *known == dev) {
+            Some((_, _, total)) => *total += size,
+            None => filesystems.push((dev, path, *size)),
+        }
+    }
+
+    for (_, path, required) in filesystems {
+        let available = free_space(path)?;
+        if required > available {
+            return Err(SoarError::InsufficientSpace {
+                path: path.display().to_string(),
+                required: format_bytes(required),
+                available: format_bytes(available),
+            });
+        }
     }
-    os::unix::fs::symlink(from, to)?;
     Ok(())
 }
 
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|path| path.exists())
+        .unwrap_or(Path::new("/"))
+}
+
 pub fn cleanup_cache() -> Result<()> {
     let cache_path = get_config().get_cache_path()?;
     Ok(fs::remove_dir_all(cache_path)?)
@@ -188,10 +313,164 @@ pub fn remove_broken_symlinks() -> Result<()> {
     Ok(())
 }
 
-/// Retrieves the platform string in the format `ARCH-Os`.
-///
-/// This function combines the architecture (e.g., `x86_64`) and the operating
-/// system (e.g., `Linux`) into a single string to identify the platform.
-pub fn get_platform() -> String {
-    format!("{}-{}{}", ARCH, &OS[..1].to_uppercase(), &OS[1..])
+/// Matches `text` against a shell-style glob supporting `*` and `?`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // let the last `*` swallow one more character
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Total size of the regular files under `path`, not following symlinks.
+pub fn dir_size<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_file_names() {
+        assert!(is_valid_file_name("foo"));
+        assert!(is_valid_file_name(".foo"));
+        assert!(is_valid_file_name("foo..bar"));
+        assert!(!is_valid_file_name(""));
+        assert!(!is_valid_file_name("."));
+        assert!(!is_valid_file_name(".."));
+        assert!(!is_valid_file_name("owner/repo"));
+        assert!(!is_valid_file_name("foo\0"));
+    }
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(sanitize_file_name("owner/repo"), "owner_repo");
+        assert_eq!(sanitize_file_name("a\\b\0c"), "a_b_c");
+        assert_eq!(sanitize_file_name("."), "_");
+        assert_eq!(sanitize_file_name(".."), "__");
+        assert_eq!(sanitize_file_name("foo.bar"), "foo.bar");
+    }
+
+    #[test]
+    fn affixes_non_utf8_file_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = OsStr::from_bytes(b"caf\xe9");
+        let path = Path::new("/tmp").join(name);
+        let affixed = affix_file_name(&path, ".", ".part");
+        assert_eq!(affixed.parent(), Some(Path::new("/tmp")));
+        assert_eq!(affixed.file_name().unwrap().as_bytes(), b".caf\xe9.part");
+    }
+
+    #[test]
+    fn matches_file_name_prefixes_by_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(OsStr::from_bytes(b"foo-\xff")), "").unwrap();
+        let entry = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap();
+        assert!(file_name_starts_with(&entry, OsStr::new("foo-")));
+        assert!(file_name_starts_with(
+            &entry,
+            OsStr::from_bytes(b"foo-\xff")
+        ));
+        assert!(!file_name_starts_with(&entry, OsStr::new("bar")));
+    }
+
+    #[test]
+    fn links_relative_to_the_install_root() {
+        let root = Path::new("/mnt/image");
+        assert_eq!(
+            symlink_target_in(
+                Some(root),
+                Path::new("/mnt/image/opt/soar/packages/foo/foo"),
+                Path::new("/mnt/image/opt/soar/bin/foo")
+            ),
+            Path::new("../packages/foo/foo")
+        );
+        assert_eq!(
+            symlink_target_in(
+                Some(root),
+                Path::new("/mnt/image/usr/lib/foo"),
+                Path::new("/mnt/image/usr/lib/nested/dir/foo")
+            ),
+            Path::new("../../foo")
+        );
+    }
+
+    #[test]
+    fn links_absolutely_outside_the_install_root() {
+        let target = Path::new("/home/user/.local/share/soar/packages/foo/foo");
+        let link = Path::new("/home/user/.local/share/soar/bin/foo");
+        assert_eq!(symlink_target_in(None, target, link), target);
+        assert_eq!(
+            symlink_target_in(Some(Path::new("/mnt/image")), target, link),
+            target
+        );
+        assert_eq!(
+            symlink_target_in(
+                Some(Path::new("/mnt/image")),
+                target,
+                Path::new("/mnt/image/opt/soar/bin/foo")
+            ),
+            target
+        );
+    }
+
+    #[test]
+    fn reads_links_as_absolute_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        fs::create_dir(&bin).unwrap();
+
+        let relative = bin.join("relative");
+        platform::symlink("../packages/./foo/foo", &relative).unwrap();
+        assert_eq!(
+            read_link_absolute(&relative).unwrap(),
+            dir.path().join("packages/foo/foo")
+        );
+
+        let absolute = bin.join("absolute");
+        platform::symlink("/opt/foo", &absolute).unwrap();
+        assert_eq!(
+            read_link_absolute(&absolute).unwrap(),
+            Path::new("/opt/foo")
+        );
+
+        assert!(read_link_absolute(dir.path()).is_err());
+    }
 }
@@ -0,0 +1,71 @@
+//! On-disk cache of release API responses.
+//!
+//! Responses are stored with their ETag so later requests can be made
+//! conditional: an unchanged listing comes back as `304 Not Modified`, which
+//! doesn't count against the GitHub rate limit.
+
+use std::{fs, path::PathBuf, sync::OnceLock};
+
+use crate::hash::sha1;
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets the directory API responses are cached in. Without one, nothing is
+/// cached.
+///
+/// Must be called before the first request is made; once set the directory
+/// is returned back as an error.
+pub fn configure_api_cache(dir: PathBuf) -> Result<(), PathBuf> {
+    CACHE_DIR.set(dir)
+}
+
+/// A cached page of an API listing.
+pub(crate) struct CachedPage {
+    pub etag: String,
+    /// URL of the next page, if the listing continues.
+    pub next: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Cache file of `url`, requested with `token`, since responses may differ
+/// between tokens.
+fn cache_file(url: &str, token: Option<&str>) -> Option<PathBuf> {
+    let dir = CACHE_DIR.get()?;
+    let key = format!("{}\n{}", url, token.unwrap_or_default());
+    let name: String = sha1(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Some(dir.join(name))
+}
+
+pub(crate) fn load(url: &str, token: Option<&str>) -> Option<CachedPage> {
+    let content = fs::read(cache_file(url, token)?).ok()?;
+    let mut parts = content.splitn(3, |&byte| byte == b'\n');
+    let etag = String::from_utf8(parts.next()?.to_vec()).ok()?;
+    let next = String::from_utf8(parts.next()?.to_vec()).ok()?;
+    Some(CachedPage {
+        etag,
+        next: (!next.is_empty()).then_some(next),
+        body: parts.next()?.to_vec(),
+    })
+}
+
+pub(crate) fn store(url: &str, token: Option<&str>, page: &CachedPage) {
+    let Some(path) = cache_file(url, token) else {
+        return;
+    };
+    let mut content = format!(
+        "{}\n{}\n",
+        page.etag,
+        page.next.as_deref().unwrap_or_default()
+    )
+    .into_bytes();
+    content.extend_from_slice(&page.body);
+
+    // best effort, the listing is fetched in full again otherwise
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, content);
+}
@@ -0,0 +1,72 @@
+//! Process-wide cancellation of running downloads, e.g. on Ctrl-C.
+//!
+//! Cancelled downloads stop at their next chunk and fail with
+//! [`DownloadError::Cancelled`]. Partial files of direct and OCI downloads
+//! are kept so the download can be resumed later, those of delta and torrent
+//! downloads are removed.
+
+use std::{
+    future::Future,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock,
+    },
+};
+
+use tokio::sync::Notify;
+
+use crate::error::DownloadError;
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static NOTIFY: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Cancels all running downloads and fails the ones started afterwards,
+/// until [`reset`] is called.
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+    NOTIFY.notify_waiters();
+}
+
+/// Lets downloads run again after [`cancel`].
+pub fn reset() {
+    CANCELLED.store(false, Ordering::SeqCst);
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Completes once downloads are cancelled.
+pub async fn cancelled() {
+    loop {
+        let notified = NOTIFY.notified();
+        tokio::pin!(notified);
+        // register before checking, so a cancel in between isn't missed
+        notified.as_mut().enable();
+        if is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Runs `future` unless downloads are cancelled first, removing `partial`
+/// if they are.
+pub(crate) async fn cancellable<T, F>(future: F, partial: Option<&Path>) -> Result<T, DownloadError>
+where
+    F: Future<Output = Result<T, DownloadError>>,
+{
+    if is_cancelled() {
+        return Err(DownloadError::Cancelled);
+    }
+    tokio::select! {
+        result = future => result,
+        _ = cancelled() => {
+            if let Some(partial) = partial {
+                let _ = tokio::fs::remove_file(partial).await;
+            }
+            Err(DownloadError::Cancelled)
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    collections::HashMap,
+    fs::Permissions,
+    io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures::{future::join_all, StreamExt};
+use reqwest::{
+    header::{HeaderMap, RANGE},
+    StatusCode,
+};
+use tokio::{
+    fs::{self, OpenOptions},
+    io::AsyncWriteExt,
+    task,
+};
+use url::Url;
+
+use crate::{
+    cancel::{cancellable, is_cancelled},
+    error::DownloadError,
+    external::find_external,
+    http_client::shared_client,
+    oci::{OciClient, OciLayer, Reference},
+    utils::{extract_filename, is_dir_path, is_elf, is_torrent_source, part_path},
+};
+
+#[derive(Debug, Clone)]
+pub enum DownloadState {
+    Preparing(u64),
+    Progress(u64),
+    /// Progress of a single layer of an OCI download, reported alongside
+    /// the overall [`DownloadState::Progress`].
+    LayerProgress {
+        layer: String,
+        downloaded: u64,
+        total: u64,
+    },
+    Complete,
+}
+
+/// Callback receiving the progress of a download.
+pub type ProgressCallback = Arc<dyn Fn(DownloadState) + Send + Sync + 'static>;
+
+/// Decides from their names which files of an OCI artifact to download.
+pub type FileFilter = Arc<dyn Fn(&str) -> bool + Send + Sync + 'static>;
+
+/// What to download and where, built with [`DownloadOptions::builder`].
+#[derive(Clone)]
+pub struct DownloadOptions {
+    pub url: String,
+    pub output_path: Option<PathBuf>,
+    pub progress_callback: Option<ProgressCallback>,
+    pub file_filter: Option<FileFilter>,
+}
+
+impl DownloadOptions {
+    /// Starts building the options to download `url`, or for
+    /// [`Downloader::download_oci`], an OCI reference such as
+    /// `ghcr.io/pkgforge/bincache/soar:latest`.
+    pub fn builder(url: impl Into<String>) -> DownloadOptionsBuilder {
+        DownloadOptionsBuilder {
+            url: url.into(),
+            output_path: None,
+            progress_callback: None,
+            file_filter: None,
+        }
+    }
+}
+
+/// Builder of [`DownloadOptions`].
+#[must_use]
+pub struct DownloadOptionsBuilder {
+    url: String,
+    output_path: Option<PathBuf>,
+    progress_callback: Option<ProgressCallback>,
+    file_filter: Option<FileFilter>,
+}
+
+impl DownloadOptionsBuilder {
+    /// File to save the download to, or the directory to save it in if the
+    /// path ends with a slash. By default it's saved in the current
+    /// directory, named after the URL or the server's suggestion.
+    pub fn output_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.output_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Reports the progress of the download to `callback`.
+    pub fn progress_callback(mut self, callback: impl Into<Option<ProgressCallback>>) -> Self {
+        self.progress_callback = callback.into();
+        self
+    }
+
+    /// Downloads only the files of a multi-file OCI artifact `filter`
+    /// accepts, rather than all of them. Ignored for other downloads.
+    pub fn file_filter(mut self, filter: impl Into<Option<FileFilter>>) -> Self {
+        self.file_filter = filter.into();
+        self
+    }
+
+    /// Builds the options, failing on an empty URL or output path, or an
+    /// HTTP URL that doesn't parse.
+    pub fn build(self) -> Result<DownloadOptions, DownloadError> {
+        let url = self.url.trim();
+        if url.is_empty() {
+            return Err(DownloadError::InvalidOptions("URL is empty".to_string()));
+        }
+        if url.starts_with("http://") || url.starts_with("https://") {
+            Url::parse(url).map_err(|source| DownloadError::InvalidUrl {
+                url: url.to_string(),
+                source,
+            })?;
+        }
+        if self
+            .output_path
+            .as_ref()
+            .is_some_and(|path| path.as_os_str().is_empty())
+        {
+            return Err(DownloadError::InvalidOptions(
+                "output path is empty".to_string(),
+            ));
+        }
+
+        Ok(DownloadOptions {
+            url: url.to_string(),
+            output_path: self.output_path,
+            progress_callback: self.progress_callback,
+            file_filter: self.file_filter,
+        })
+    }
+}
+
+pub struct Downloader {
+    pub(crate) client: reqwest::Client,
+    pub(crate) headers: HeaderMap,
+}
+
+impl Default for Downloader {
+    fn default() -> Self {
+        Self {
+            client: shared_client(),
+            headers: HeaderMap::new(),
+        }
+    }
+}
+
+impl Downloader {
+    /// Sends `headers` with the HTTP requests of direct and zsync downloads,
+    /// e.g. a token the server requires.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub async fn download(&self, options: DownloadOptions) -> Result<PathBuf, DownloadError> {
+        if is_torrent_source(&options.url) {
+            #[cfg(feature = "torrent")]
+            return self.download_torrent(options).await;
+            #[cfg(not(feature = "torrent"))]
+            return Err(DownloadError::TorrentError(
+                "built without torrent support".to_string(),
+            ));
+        }
+
+        // the partial file is kept to resume from
+        cancellable(self.download_url(options), None).await
+    }
+
+    async fn download_url(&self, options: DownloadOptions) -> Result<PathBuf, DownloadError> {
+        let url = Url::parse(&options.url).map_err(|err| DownloadError::InvalidUrl {
+            url: options.url.clone(),
+            source: err,
+        })?;
+
+        let output_path = options
+            .output_path
+            .unwrap_or_else(|| PathBuf::from(extract_filename(&options.url)));
+        let output_path = if is_dir_path(&output_path) {
+            output_path.join(extract_filename(&options.url))
+        } else {
+            output_path
+        };
+
+        if let Some(output_dir) = output_path.parent() {
+            if !output_dir.as_os_str().is_empty() && !output_dir.exists() {
+                fs::create_dir_all(output_dir).await?;
+            }
+        }
+
+        let temp_path = part_path(&output_path);
+        let progress_callback = options.progress_callback;
+        match find_external(&options.url) {
+            Some(external) => {
+                external
+                    .download(
+                        &options.url,
+                        &temp_path,
+                        &self.headers,
+                        progress_callback.as_ref(),
+                    )
+                    .await?
+            }
+            None => {
+                self.fetch(url, &temp_path, progress_callback.as_ref())
+                    .await?
+            }
+        }
+
+        fs::rename(&temp_path, &output_path).await?;
+
+        if is_elf(&output_path).await {
+            fs::set_permissions(&output_path, Permissions::from_mode(0o755)).await?;
+        }
+
+        if let Some(ref callback) = progress_callback {
+            callback(DownloadState::Complete);
+        }
+
+        Ok(output_path)
+    }
+
+    /// Fetches `url` into `temp_path` with the built-in client, continuing
+    /// where a previous attempt stopped.
+    async fn fetch(
+        &self,
+        url: Url,
+        temp_path: &Path,
+        progress_callback: Option<&Arc<dyn Fn(DownloadState) + Send + Sync>>,
+    ) -> Result<(), DownloadError> {
+        let url_str = url.to_string();
+        // continue a download interrupted earlier where it stopped
+        let resume_from = fs::metadata(&temp_path)
+            .await
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+        let mut request = self.client.get(url).headers(self.headers.clone());
+        if resume_from > 0 {
+            request = request.header(RANGE, format!("bytes={}-", resume_from));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // the partial file is stale, start over on the next attempt
+            let _ = fs::remove_file(&temp_path).await;
+        }
+        if !response.status().is_success() {
+            return Err(DownloadError::ResourceError {
+                status: response.status(),
+                url: url_str,
+            });
+        }
+
+        // servers ignoring the range send the whole file again
+        let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+        let offset = if resumed { resume_from } else { 0 };
+        let content_length = response
+            .content_length()
+            .map_or(0, |length| length + offset);
+
+        let mut stream = response.bytes_stream();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(&temp_path)
+            .await?;
+
+        let mut downloaded_bytes = offset;
+
+        if let Some(callback) = progress_callback {
+            callback(DownloadState::Preparing(content_length));
+        }
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|err| DownloadError::NetworkError { source: err })?;
+            file.write_all(&chunk).await?;
+            downloaded_bytes = downloaded_bytes.saturating_add(chunk.len() as u64);
+
+            if let Some(callback) = progress_callback {
+                callback(DownloadState::Progress(downloaded_bytes));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn download_blob(
+        &self,
+        client: OciClient,
+        options: DownloadOptions,
+    ) -> Result<(), DownloadError> {
+        let reference = client.reference.clone();
+        let digest = reference.tag;
+        let downloaded_bytes = Arc::new(Mutex::new(0u64));
+        let output_path = options.output_path;
+        let ref_name = reference
+            .package
+            .rsplit_once('/')
+            .map_or(digest.clone(), |(_, name)| name.to_string());
+        let file_path = output_path.unwrap_or_else(|| PathBuf::from(&ref_name));
+        let file_path = if is_dir_path(&file_path) {
+            fs::create_dir_all(&file_path).await?;
+            file_path.join(&ref_name)
+        } else {
+            file_path
+        };
+
+        let fake_layer = OciLayer {
+            media_type: String::from("application/octet-stream"),
+            digest: digest.clone(),
+            size: 0,
+            annotations: HashMap::new(),
+        };
+
+        let cb_clone = options.progress_callback.clone();
+        client
+            .pull_layer(&fake_layer, &file_path, move |bytes, total_bytes| {
+                if let Some(ref callback) = cb_clone {
+                    if total_bytes > 0 {
+                        callback(DownloadState::Preparing(total_bytes));
+                    }
+                    let mut current = downloaded_bytes.lock().unwrap();
+                    *current += bytes;
+                    callback(DownloadState::Progress(*current));
+                }
+            })
+            .await?;
+
+        if let Some(ref callback) = options.progress_callback {
+            callback(DownloadState::Complete);
+        }
+
+        Ok(())
+    }
+
+    pub async fn download_oci(&self, options: DownloadOptions) -> Result<(), DownloadError> {
+        let url = options.url.clone();
+        let reference: Reference = url.into();
+        let oci_client = OciClient::new(&reference);
+
+        if reference.tag.starts_with("sha256:") {
+            return self.download_blob(oci_client, options).await;
+        }
+
+        let manifest = oci_client.manifest().await?;
+        let layers: Vec<(String, OciLayer)> = manifest
+            .layers
+            .into_iter()
+            .filter_map(|layer| Some((layer.get_title()?, layer)))
+            .collect();
+        let (layers, skipped): (Vec<_>, Vec<_>) = layers.into_iter().partition(|(filename, _)| {
+            options
+                .file_filter
+                .as_ref()
+                .is_none_or(|filter| filter(filename))
+        });
+        if layers.is_empty() && !skipped.is_empty() {
+            return Err(DownloadError::NoMatchingFiles {
+                available_files: skipped.into_iter().map(|(filename, _)| filename).collect(),
+            });
+        }
+
+        let mut tasks = Vec::new();
+        let total_bytes: u64 = layers.iter().map(|(_, layer)| layer.size).sum();
+
+        if let Some(ref callback) = options.progress_callback {
+            callback(DownloadState::Preparing(total_bytes));
+        }
+
+        let downloaded_bytes = Arc::new(Mutex::new(0u64));
+        let outdir = options.output_path;
+        let base_path = if let Some(dir) = outdir {
+            fs::create_dir_all(&dir).await?;
+            dir
+        } else {
+            PathBuf::new()
+        };
+
+        for (filename, layer) in layers {
+            let client_clone = oci_client.clone();
+            let cb_clone = options.progress_callback.clone();
+            let downloaded_bytes = downloaded_bytes.clone();
+            let file_path = base_path.join(&filename);
+            let layer_total = layer.size;
+            let layer_downloaded = AtomicU64::new(0);
+
+            let task = task::spawn(async move {
+                client_clone
+                    .pull_layer(&layer, &file_path, move |bytes, _| {
+                        if let Some(ref callback) = cb_clone {
+                            let current = {
+                                let mut current = downloaded_bytes.lock().unwrap();
+                                *current += bytes;
+                                *current
+                            };
+                            callback(DownloadState::LayerProgress {
+                                layer: filename.clone(),
+                                downloaded: layer_downloaded.fetch_add(bytes, Ordering::Relaxed)
+                                    + bytes,
+                                total: layer_total,
+                            });
+                            callback(DownloadState::Progress(current));
+                        }
+                    })
+                    .await?;
+
+                Ok::<(), DownloadError>(())
+            });
+            tasks.push(task);
+        }
+
+        // every layer is let finish, the first failure is reported
+        let mut result = Ok(());
+        for joined in join_all(tasks).await {
+            let layer_result = joined.unwrap_or_else(|err| Err(io::Error::other(err).into()));
+            if result.is_ok() {
+                result = layer_result;
+            }
+        }
+        if is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+        result?;
+
+        if let Some(ref callback) = options.progress_callback {
+            callback(DownloadState::Complete);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use std::{error::Error, fmt::Display, io};
+
+#[derive(Debug)]
+pub enum DownloadError {
+    InvalidUrl {
+        url: String,
+        source: url::ParseError,
+    },
+    IoError(io::Error),
+    NetworkError {
+        source: reqwest::Error,
+    },
+    ResourceError {
+        url: String,
+        status: reqwest::StatusCode,
+    },
+    InvalidResponse,
+    InvalidZsync(String),
+    TorrentError(String),
+    ExternalError(String),
+    InvalidOptions(String),
+    DigestMismatch {
+        expected: String,
+        actual: String,
+    },
+    NoMatchingFiles {
+        available_files: Vec<String>,
+    },
+    Cancelled,
+}
+
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadError::IoError(err) => write!(f, "IO error: {}", err),
+            DownloadError::InvalidUrl { url, .. } => write!(f, "Invalid URL: {}", url),
+            DownloadError::NetworkError { .. } => write!(f, "Network Request failed"),
+            DownloadError::ResourceError { url, status } => {
+                write!(f, "Failed to fetch resource from {} [{}]", url, status)
+            }
+            DownloadError::InvalidResponse => write!(f, "Failed to parse response"),
+            DownloadError::InvalidZsync(msg) => write!(f, "Delta download failed: {}", msg),
+            DownloadError::TorrentError(msg) => write!(f, "Torrent download failed: {}", msg),
+            DownloadError::ExternalError(msg) => write!(f, "External downloader failed: {}", msg),
+            DownloadError::InvalidOptions(msg) => write!(f, "Invalid download options: {}", msg),
+            DownloadError::DigestMismatch { expected, actual } => {
+                write!(f, "Digest mismatch: expected {}, got {}", expected, actual)
+            }
+            DownloadError::NoMatchingFiles { available_files } => write!(
+                f,
+                "No matching files found, available: {}",
+                available_files.join(", ")
+            ),
+            DownloadError::Cancelled => write!(f, "Download cancelled"),
+        }
+    }
+}
+
+impl Error for DownloadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DownloadError::IoError(err) => Some(err),
+            DownloadError::InvalidUrl { source, .. } => Some(source),
+            DownloadError::NetworkError { source } => Some(source),
+            DownloadError::ResourceError { .. } => None,
+            DownloadError::InvalidResponse => None,
+            DownloadError::InvalidZsync(_) => None,
+            DownloadError::TorrentError(_) => None,
+            DownloadError::ExternalError(_) => None,
+            DownloadError::InvalidOptions(_) => None,
+            DownloadError::DigestMismatch { .. } => None,
+            DownloadError::NoMatchingFiles { .. } => None,
+            DownloadError::Cancelled => None,
+        }
+    }
+}
+
+impl From<io::Error> for DownloadError {
+    fn from(value: io::Error) -> Self {
+        Self::IoError(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum PlatformError {
+    ApiError {
+        status: reqwest::StatusCode,
+    },
+    DownloadError(DownloadError),
+    InvalidInput(String),
+    InvalidOptions(String),
+    InvalidResponse,
+    NoMatchingAssets {
+        available_assets: Vec<String>,
+    },
+    NoRelease {
+        tag: Option<String>,
+    },
+    RepositoryNotFound {
+        owner: String,
+        repo: String,
+    },
+    RateLimited {
+        reset_in: std::time::Duration,
+        /// Variable a token raising the limit is read from, `None` if it
+        /// comes from elsewhere.
+        token_var: Option<&'static str>,
+    },
+}
+
+impl Display for PlatformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlatformError::ApiError { status } => write!(f, "API error [{}]", status),
+            PlatformError::DownloadError(err) => write!(f, "Download error: {}", err),
+            PlatformError::InvalidInput(msg) => {
+                write!(f, "{} is invalid. Should be in format (owner/repo)", msg)
+            }
+            PlatformError::InvalidOptions(msg) => write!(f, "Invalid download options: {}", msg),
+            PlatformError::InvalidResponse => write!(f, "Failed to parse response"),
+            PlatformError::NoRelease { tag } => write!(
+                f,
+                "No {} found.",
+                tag.clone()
+                    .map(|t| format!("tag {}", t))
+                    .unwrap_or("release".to_string())
+            ),
+            PlatformError::NoMatchingAssets { .. } => write!(f, "No matching assets found"),
+            PlatformError::RepositoryNotFound { owner, repo } => {
+                write!(f, "Repository not found: {}/{}", owner, repo)
+            }
+            PlatformError::RateLimited {
+                reset_in,
+                token_var,
+            } => {
+                let secs = reset_in.as_secs();
+                if secs < 60 {
+                    write!(f, "API rate limit exceeded, try again in {}s", secs)?;
+                } else {
+                    write!(
+                        f,
+                        "API rate limit exceeded, try again in {}m",
+                        secs.div_ceil(60)
+                    )?;
+                }
+                if let Some(var) = token_var {
+                    write!(f, " or set {} to raise it", var)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<DownloadError> for PlatformError {
+    fn from(value: DownloadError) -> Self {
+        Self::DownloadError(value)
+    }
+}
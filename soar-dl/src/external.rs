@@ -0,0 +1,176 @@
+//! Downloads handed to external programs such as aria2c or curl.
+//!
+//! Direct downloads whose URL matches the pattern of a configured
+//! [`ExternalDownloader`] run its command instead of the built-in client.
+//! Progress is reported by polling the size of the file the program writes.
+
+use std::{
+    ffi::OsString,
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use tokio::{fs, time::sleep};
+
+use crate::{downloader::DownloadState, error::DownloadError, http_client::shared_client};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// `arg` with `{url}` and `{output}` replaced, keeping an output path that
+/// isn't valid UTF-8 intact.
+fn expand_arg(arg: &str, url: &str, output: &Path) -> OsString {
+    let mut expanded = OsString::new();
+    for (idx, part) in arg.replace("{url}", url).split("{output}").enumerate() {
+        if idx > 0 {
+            expanded.push(output);
+        }
+        expanded.push(part);
+    }
+    expanded
+}
+
+/// External program used to download the URLs matching a pattern.
+#[derive(Debug, Clone)]
+pub struct ExternalDownloader {
+    pub pattern: Regex,
+    /// Program followed by its arguments, in which `{url}` and `{output}`
+    /// are replaced by the URL and the file to write to.
+    pub command: Vec<String>,
+}
+
+static EXTERNAL_DOWNLOADERS: OnceLock<Vec<ExternalDownloader>> = OnceLock::new();
+
+/// Sets the external downloaders, the first one matching a URL is used.
+///
+/// Must be called before the first download is made; afterwards the
+/// downloaders are returned back as an error.
+pub fn configure_external(
+    downloaders: Vec<ExternalDownloader>,
+) -> Result<(), Vec<ExternalDownloader>> {
+    EXTERNAL_DOWNLOADERS.set(downloaders)
+}
+
+/// External downloader configured for `url`, if any.
+pub(crate) fn find_external(url: &str) -> Option<&'static ExternalDownloader> {
+    EXTERNAL_DOWNLOADERS
+        .get_or_init(Vec::new)
+        .iter()
+        .find(|downloader| downloader.pattern.is_match(url))
+}
+
+/// Kills the program if the download is dropped, e.g. when cancelled.
+struct ChildGuard(Child);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        if self.0.try_wait().is_ok_and(|status| status.is_none()) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+}
+
+impl ExternalDownloader {
+    /// Downloads `url` to `output` with the program.
+    pub(crate) async fn download(
+        &self,
+        url: &str,
+        output: &Path,
+        headers: &HeaderMap,
+        progress_callback: Option<&Arc<dyn Fn(DownloadState) + Send + Sync>>,
+    ) -> Result<(), DownloadError> {
+        let Some((program, args)) = self.command.split_first() else {
+            return Err(DownloadError::ExternalError(
+                "no command configured".to_string(),
+            ));
+        };
+
+        if let Some(callback) = progress_callback {
+            // the program doesn't tell, so ask the server
+            let total = shared_client()
+                .head(url)
+                .headers(headers.clone())
+                .send()
+                .await
+                .ok()
+                .filter(|resp| resp.status().is_success())
+                .and_then(|resp| resp.content_length())
+                .unwrap_or(0);
+            callback(DownloadState::Preparing(total));
+        }
+
+        let child = Command::new(program)
+            .args(args.iter().map(|arg| expand_arg(arg, url, output)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| {
+                DownloadError::ExternalError(format!("failed to run {}: {}", program, err))
+            })?;
+        let mut child = ChildGuard(child);
+
+        let status = loop {
+            if let Some(status) = child.0.try_wait()? {
+                break status;
+            }
+            if let Some(callback) = progress_callback {
+                if let Ok(meta) = fs::metadata(output).await {
+                    callback(DownloadState::Progress(meta.len()));
+                }
+            }
+            sleep(POLL_INTERVAL).await;
+        };
+
+        if !status.success() {
+            return Err(DownloadError::ExternalError(format!(
+                "{} exited with {}",
+                program, status
+            )));
+        }
+        if !output.is_file() {
+            return Err(DownloadError::ExternalError(format!(
+                "{} didn't write {}",
+                program,
+                output.display()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    use super::*;
+
+    #[test]
+    fn expands_url_and_output() {
+        let output = Path::new("/tmp/foo.part");
+        assert_eq!(
+            expand_arg("{url}", "https://example.com/foo", output),
+            "https://example.com/foo"
+        );
+        assert_eq!(expand_arg("-o{output}", "", output), "-o/tmp/foo.part");
+        assert_eq!(
+            expand_arg("{output}:{output}", "", output),
+            "/tmp/foo.part:/tmp/foo.part"
+        );
+        assert_eq!(expand_arg("--quiet", "", output), "--quiet");
+    }
+
+    #[test]
+    fn keeps_non_utf8_output_paths() {
+        let output = Path::new(OsStr::from_bytes(b"/tmp/caf\xe9"));
+        assert_eq!(
+            expand_arg("--out={output}", "", output).as_bytes(),
+            b"--out=/tmp/caf\xe9"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Hashes needed by the download protocols: MD4 for zsync block checksums,
+//! SHA-1 for zsync and BitTorrent pieces and SHA-256 for OCI blob digests.
+
+use md4::Md4;
+pub(crate) use sha1::{Digest, Sha1};
+pub(crate) use sha2::Sha256;
+
+/// Decodes a hex string of exactly `N` bytes.
+pub(crate) fn parse_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != N * 2 {
+        return None;
+    }
+    let mut out = [0u8; N];
+    for (idx, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(idx * 2..idx * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+pub(crate) fn md4(data: &[u8]) -> [u8; 16] {
+    Md4::digest(data).into()
+}
+
+pub(crate) fn sha1(data: &[u8]) -> [u8; 20] {
+    Sha1::digest(data).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn parses_hex_of_the_exact_length() {
+        assert_eq!(parse_hex::<2>("00ff"), Some([0x00, 0xff]));
+        assert_eq!(parse_hex::<2>("0AfF"), Some([0x0a, 0xff]));
+        assert_eq!(parse_hex::<2>("00f"), None);
+        assert_eq!(parse_hex::<2>("00ff00"), None);
+        assert_eq!(parse_hex::<2>("zz00"), None);
+    }
+
+    #[test]
+    fn md4_known_answers() {
+        assert_eq!(hex(&md4(b"")), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(hex(&md4(b"abc")), "a448017aaf21d8525fc10ae87aa6729d");
+    }
+
+    #[test]
+    fn sha1_known_answers() {
+        assert_eq!(
+            hex(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        let mut hasher = Sha1::default();
+        hasher.update(b"abcdbcdecdefdefgefghfghighij");
+        hasher.update(b"hijkijkljklmklmnlmnomnopnopq");
+        assert_eq!(
+            hex(&hasher.finalize()),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn sha256_known_answers() {
+        assert_eq!(
+            hex(&Sha256::digest(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    sync::{LazyLock, OnceLock},
+    time::Duration,
+};
+
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Client,
+};
+
+/// Settings used to build the shared HTTP client.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub user_agent: String,
+    /// Headers sent with every request.
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub connect_timeout: Option<Duration>,
+    /// Longest wait for the server to send more data.
+    pub read_timeout: Option<Duration>,
+    /// Deadline for a whole request, from connecting until the body has been
+    /// read, so it bounds each download as well.
+    pub timeout: Option<Duration>,
+    pub pool_idle_timeout: Option<Duration>,
+    pub pool_max_idle_per_host: usize,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: format!("soar-dl/{}", env!("CARGO_PKG_VERSION")),
+            headers: Vec::new(),
+            connect_timeout: Some(Duration::from_secs(30)),
+            read_timeout: Some(Duration::from_secs(60)),
+            timeout: None,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: 8,
+        }
+    }
+}
+
+static CLIENT_CONFIG: OnceLock<ClientConfig> = OnceLock::new();
+
+static SHARED_CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    let config = CLIENT_CONFIG.get_or_init(ClientConfig::default);
+
+    let mut builder = Client::builder()
+        .user_agent(&config.user_agent)
+        .default_headers(config.headers.iter().cloned().collect())
+        .pool_idle_timeout(config.pool_idle_timeout)
+        .pool_max_idle_per_host(config.pool_max_idle_per_host);
+    if let Some(timeout) = config.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = config.read_timeout {
+        builder = builder.read_timeout(timeout);
+    }
+    if let Some(timeout) = config.timeout {
+        builder = builder.timeout(timeout);
+    }
+
+    builder.build().expect("Failed to build HTTP client")
+});
+
+/// Sets the configuration of the shared client.
+///
+/// Must be called before the first request is made; once the client is built
+/// the configuration is returned back as an error.
+pub fn configure_client(config: ClientConfig) -> Result<(), ClientConfig> {
+    CLIENT_CONFIG.set(config)
+}
+
+/// Returns the process-wide HTTP client.
+///
+/// Clones share the same connection pool.
+pub fn shared_client() -> Client {
+    SHARED_CLIENT.clone()
+}
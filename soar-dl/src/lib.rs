@@ -0,0 +1,73 @@
+//! Download engine of [soar](https://github.com/pkgforge/soar).
+//!
+//! soar-dl downloads direct links, with resuming and delta updates through
+//! zsync, release assets of GitHub and GitLab projects, and OCI artifacts
+//! such as the ones soar publishes on ghcr.io.
+//!
+//! # Direct downloads
+//!
+//! ```no_run
+//! use soar_dl::downloader::{DownloadOptions, Downloader};
+//!
+//! # async fn run() -> Result<(), soar_dl::error::DownloadError> {
+//! let options = DownloadOptions::builder("https://example.com/tool.tar.gz")
+//!     .output_path("downloads/")
+//!     .build()?;
+//! let path = Downloader::default().download(options).await?;
+//! println!("saved to {}", path.display());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! OCI references are downloaded the same way with
+//! [`Downloader::download_oci`](downloader::Downloader::download_oci).
+//!
+//! # Release assets
+//!
+//! [`ReleaseHandler`](platform::ReleaseHandler) fetches the releases of a
+//! project and picks assets out of them with
+//! [`PlatformDownloadOptions`](platform::PlatformDownloadOptions):
+//!
+//! ```no_run
+//! use soar_dl::{
+//!     github::{Github, GithubRelease},
+//!     platform::{PlatformDownloadOptions, ReleaseHandler},
+//! };
+//!
+//! # async fn run() -> Result<(), soar_dl::error::PlatformError> {
+//! let handler = ReleaseHandler::<Github>::new();
+//! let releases = handler.fetch_releases::<GithubRelease>("pkgforge/soar").await?;
+//! let options = PlatformDownloadOptions::builder()
+//!     .regex("x86_64-linux$")
+//!     .build()?;
+//! let assets = handler.filter_releases(&releases, &options).await?;
+//! handler.download(&assets[0], options).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Configuration
+//!
+//! Downloads share a single connection-pooled HTTP client, configured with
+//! [`http_client::configure_client`] before the first request. Likewise
+//! [`external::configure_external`] hands URLs to programs like aria2c and
+//! [`api_cache::configure_api_cache`] caches release listings on disk.
+//! Running downloads can be stopped with [`cancel::cancel`].
+//!
+//! The `torrent` feature adds downloads of magnet links and `.torrent` files.
+
+pub mod api_cache;
+pub mod cancel;
+pub mod downloader;
+pub mod error;
+pub mod external;
+pub mod github;
+pub mod gitlab;
+mod hash;
+pub mod http_client;
+pub mod oci;
+pub mod platform;
+#[cfg(feature = "torrent")]
+pub mod torrent;
+pub mod utils;
+pub mod zsync;
@@ -0,0 +1,367 @@
+use std::{
+    collections::HashMap,
+    fs::Permissions,
+    io::{self, Write},
+    mem,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+
+use flate2::write::GzDecoder;
+use futures::StreamExt;
+use reqwest::{
+    header::{self, HeaderMap},
+    StatusCode,
+};
+use serde::Deserialize;
+use tokio::{
+    fs::{self, OpenOptions},
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+use zstd::stream::write::Decoder as ZstdDecoder;
+
+use crate::{
+    cancel::cancellable,
+    error::DownloadError,
+    hash::{Digest, Sha256},
+    http_client::shared_client,
+    utils::{is_elf, part_path},
+};
+
+#[derive(Deserialize)]
+pub struct OciLayer {
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    pub annotations: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+pub struct OciConfig {
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+}
+
+#[derive(Deserialize)]
+pub struct OciManifest {
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub config: OciConfig,
+    pub layers: Vec<OciLayer>,
+}
+
+#[derive(Clone)]
+pub struct OciClient {
+    client: reqwest::Client,
+    pub reference: Reference,
+}
+
+#[derive(Clone)]
+pub struct Reference {
+    pub package: String,
+    pub tag: String,
+}
+
+impl From<&str> for Reference {
+    fn from(value: &str) -> Self {
+        let paths = value.trim_start_matches("ghcr.io/");
+
+        // <package>@sha256:<digest>
+        if let Some((package, digest)) = paths.split_once("@") {
+            return Self {
+                package: package.to_string(),
+                tag: digest.to_string(),
+            };
+        }
+
+        // <package>:<tag>
+        if let Some((package, tag)) = paths.split_once(':') {
+            return Self {
+                package: package.to_string(),
+                tag: tag.to_string(),
+            };
+        }
+
+        Self {
+            package: paths.to_string(),
+            tag: "latest".to_string(),
+        }
+    }
+}
+
+impl From<String> for Reference {
+    fn from(value: String) -> Self {
+        value.as_str().into()
+    }
+}
+
+impl OciClient {
+    pub fn new(reference: &Reference) -> Self {
+        Self {
+            client: shared_client(),
+            reference: reference.clone(),
+        }
+    }
+
+    pub fn headers(&self) -> HeaderMap {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(header::ACCEPT, "application/vnd.docker.distribution.manifest.v2+json, application/vnd.docker.distribution.manifest.list.v2+json, application/vnd.oci.image.manifest.v1+json, application/vnd.oci.image.index.v1+json, application/vnd.oci.artifact.manifest.v1+json".parse().unwrap());
+        header_map.insert(header::AUTHORIZATION, "Bearer QQ==".parse().unwrap());
+        header_map
+    }
+
+    pub async fn manifest(&self) -> Result<OciManifest, DownloadError> {
+        let manifest_url = format!(
+            "https://ghcr.io/v2/{}/manifests/{}",
+            self.reference.package, self.reference.tag
+        );
+        let resp = self
+            .client
+            .get(&manifest_url)
+            .headers(self.headers())
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+
+        if !resp.status().is_success() {
+            return Err(DownloadError::ResourceError {
+                status: resp.status(),
+                url: manifest_url,
+            });
+        }
+
+        let manifest: OciManifest = resp
+            .json()
+            .await
+            .map_err(|_| DownloadError::InvalidResponse)?;
+        Ok(manifest)
+    }
+
+    /// Downloads `layer` to `output_path`, decompressing it on the fly if
+    /// its media type says it's compressed and checking its digest as it
+    /// comes in. `progress_callback` is given the bytes received and, once,
+    /// the size of the blob.
+    ///
+    /// Uncompressed layers resume from a partial download, compressed ones
+    /// start over since the decompressor can't pick up midway.
+    pub async fn pull_layer<F, P>(
+        &self,
+        layer: &OciLayer,
+        output_path: P,
+        progress_callback: F,
+    ) -> Result<u64, DownloadError>
+    where
+        P: AsRef<Path>,
+        F: Fn(u64, u64) + Send + 'static,
+    {
+        let blob_url = format!(
+            "https://ghcr.io/v2/{}/blobs/{}",
+            self.reference.package, layer.digest
+        );
+        let output_path = output_path.as_ref();
+        let temp_path = part_path(output_path);
+        let compression = layer.compression();
+        let resume_from = if compression == LayerCompression::None {
+            fs::metadata(&temp_path)
+                .await
+                .map(|meta| meta.len())
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        let mut headers = self.headers();
+        if resume_from > 0 {
+            headers.insert(
+                header::RANGE,
+                format!("bytes={}-", resume_from).parse().unwrap(),
+            );
+        }
+        let resp = self
+            .client
+            .get(&blob_url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+
+        if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // the partial file is stale, start over on the next attempt
+            let _ = fs::remove_file(&temp_path).await;
+        }
+        if !resp.status().is_success() {
+            return Err(DownloadError::ResourceError {
+                status: resp.status(),
+                url: blob_url,
+            });
+        }
+
+        // servers ignoring the range send the whole blob again
+        let resumed = resp.status() == StatusCode::PARTIAL_CONTENT;
+        let offset = if resumed { resume_from } else { 0 };
+        let content_length = resp.content_length().map_or(0, |length| length + offset);
+        progress_callback(0, content_length);
+        if resumed {
+            progress_callback(offset, 0);
+        }
+
+        // only SHA-256 digests are checked, the only ones in use
+        let mut hasher = layer.digest.starts_with("sha256:").then(Sha256::default);
+        if let (Some(hasher), true) = (&mut hasher, resumed) {
+            let mut partial = fs::File::open(&temp_path).await?;
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let n = partial.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(&temp_path)
+            .await?;
+        let mut decoder = LayerDecoder::new(compression)?;
+
+        let mut stream = resp.bytes_stream();
+        let mut total_bytes_downloaded = 0;
+
+        // the partial file is kept to resume from
+        cancellable(
+            async {
+                while let Some(chunk) = stream.next().await {
+                    let chunk = chunk.map_err(|err| DownloadError::NetworkError { source: err })?;
+                    let chunk_size = chunk.len() as u64;
+                    if let Some(hasher) = &mut hasher {
+                        hasher.update(&chunk);
+                    }
+                    match &mut decoder {
+                        Some(decoder) => file.write_all(&decoder.decode(&chunk)?).await?,
+                        None => file.write_all(&chunk).await?,
+                    }
+
+                    progress_callback(chunk_size, 0);
+                    total_bytes_downloaded += chunk_size;
+                }
+                if let Some(decoder) = decoder {
+                    file.write_all(&decoder.finish()?).await?;
+                }
+                file.flush().await?;
+                Ok(())
+            },
+            None,
+        )
+        .await?;
+
+        if let Some(hasher) = hasher {
+            let digest: String = hasher
+                .finalize()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            if layer.digest != format!("sha256:{}", digest) {
+                // a corrupt partial file would never complete
+                let _ = fs::remove_file(&temp_path).await;
+                return Err(DownloadError::DigestMismatch {
+                    expected: layer.digest.clone(),
+                    actual: format!("sha256:{}", digest),
+                });
+            }
+        }
+
+        fs::rename(&temp_path, &output_path).await?;
+
+        if is_elf(&output_path).await {
+            fs::set_permissions(&output_path, Permissions::from_mode(0o755)).await?;
+        }
+
+        Ok(total_bytes_downloaded)
+    }
+}
+
+/// Compression of a layer, as told by its media type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// Decompresses a layer chunk by chunk as it downloads.
+enum LayerDecoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    Zstd(ZstdDecoder<'static, Vec<u8>>),
+}
+
+impl LayerDecoder {
+    fn new(compression: LayerCompression) -> io::Result<Option<Self>> {
+        Ok(match compression {
+            LayerCompression::None => None,
+            LayerCompression::Gzip => Some(Self::Gzip(GzDecoder::new(Vec::new()))),
+            LayerCompression::Zstd => Some(Self::Zstd(ZstdDecoder::new(Vec::new())?)),
+        })
+    }
+
+    /// Feeds `chunk` in, returning what it decompressed to.
+    fn decode(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(match self {
+            Self::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                mem::take(decoder.get_mut())
+            }
+            Self::Zstd(decoder) => {
+                decoder.write_all(chunk)?;
+                mem::take(decoder.get_mut())
+            }
+        })
+    }
+
+    /// Returns the rest of the output. A cut off stream is caught by the
+    /// digest check rather than here, zstd doesn't tell.
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => decoder.finish(),
+            Self::Zstd(mut decoder) => {
+                decoder.flush()?;
+                Ok(decoder.into_inner())
+            }
+        }
+    }
+}
+
+impl OciLayer {
+    /// Compression to undo when downloading the layer. Layers titled as a
+    /// compressed file, e.g. `foo.tar.gz`, are kept as they are.
+    pub fn compression(&self) -> LayerCompression {
+        let title = self.get_title().unwrap_or_default();
+        let media_type = self.media_type.as_str();
+        if media_type.ends_with("gzip") && !title.ends_with(".gz") && !title.ends_with(".tgz") {
+            LayerCompression::Gzip
+        } else if media_type.ends_with("zstd") && !title.ends_with(".zst") {
+            LayerCompression::Zstd
+        } else {
+            LayerCompression::None
+        }
+    }
+
+    pub fn get_title(&self) -> Option<String> {
+        self.annotations
+            .get("org.opencontainers.image.title")
+            .cloned()
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.annotations.insert(
+            "org.opencontainers.image.title".to_string(),
+            title.to_string(),
+        );
+    }
+}
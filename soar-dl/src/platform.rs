@@ -0,0 +1,614 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, ETAG, IF_NONE_MATCH, LINK},
+    Response, StatusCode,
+};
+use serde::Deserialize;
+use tokio::time::sleep;
+
+use crate::{
+    api_cache::{self, CachedPage},
+    downloader::{DownloadOptions, Downloader, ProgressCallback},
+    error::{DownloadError, PlatformError},
+    http_client::shared_client,
+    utils::should_fallback,
+};
+
+/// Most pages of a release listing fetched, 100 releases each.
+const MAX_PAGES: usize = 10;
+
+/// Longest wait for an API rate limit to reset before giving up.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Most times a rate limit is waited out for a single request.
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// A page of an API listing.
+struct Page {
+    status: StatusCode,
+    url: String,
+    /// URL of the next page, if the listing continues.
+    next: Option<String>,
+    body: Vec<u8>,
+}
+
+/// URL of the next page from the `Link` header, which GitHub and GitLab
+/// both paginate with.
+fn next_page(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(LINK)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .find_map(|link| {
+            let (url, params) = link.split_once(';')?;
+            params
+                .split(';')
+                .any(|param| param.trim() == "rel=\"next\"")
+                .then(|| {
+                    url.trim()
+                        .trim_start_matches('<')
+                        .trim_end_matches('>')
+                        .to_string()
+                })
+        })
+}
+
+/// Time until the rate limit that rejected `response` resets, `None` if it
+/// wasn't rejected for the rate limit.
+fn rate_limit_reset(response: &Response) -> Option<Duration> {
+    let status = response.status();
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+
+    if let Some(seconds) = header("retry-after") {
+        return Some(Duration::from_secs(seconds));
+    }
+    // GitHub sends the x- prefixed headers, GitLab the plain ones
+    let remaining = header("x-ratelimit-remaining").or_else(|| header("ratelimit-remaining"));
+    if remaining != Some(0) {
+        return None;
+    }
+    let reset = header("x-ratelimit-reset").or_else(|| header("ratelimit-reset"))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    // a second of slack for clock skew
+    Some(Duration::from_secs(reset.saturating_sub(now) + 1))
+}
+
+pub enum ApiType {
+    PkgForge,
+    Primary,
+}
+
+#[derive(Debug)]
+pub enum PlatformUrl {
+    Github(String),
+    Gitlab(String),
+    Oci(String),
+    DirectUrl(String),
+}
+
+static GITHUB_RELEASE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i)(?:https?://)?(?:github(?:\.com)?[:/])([^/@]+/[^/@]+)(?:@([^/\s]*)?)?$")
+        .unwrap()
+});
+static GITLAB_RELEASE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i)(?:https?://)?(?:gitlab(?:\.com)?[:/])([^/@]+/[^/@]+)(?:@([^/\s]*)?)?$")
+        .unwrap()
+});
+
+impl PlatformUrl {
+    pub fn parse(url: impl Into<String>) -> Result<Self, PlatformError> {
+        let url = url.into();
+        if url.starts_with("ghcr.io") {
+            return Ok(PlatformUrl::Oci(url));
+        }
+        if GITHUB_RELEASE_RE.is_match(&url) {
+            if let Some(caps) = GITHUB_RELEASE_RE.captures(&url) {
+                let project = caps.get(1).unwrap().as_str();
+                let tag = caps
+                    .get(2)
+                    .map(|tag| tag.as_str().trim())
+                    .filter(|&tag| !tag.is_empty());
+                if let Some(tag) = tag {
+                    return Ok(PlatformUrl::Github(format!("{}@{}", project, tag)));
+                } else {
+                    return Ok(PlatformUrl::Github(project.to_string()));
+                }
+            }
+            return Err(PlatformError::InvalidInput(url));
+        }
+        if GITLAB_RELEASE_RE.is_match(&url) {
+            if let Some(caps) = GITLAB_RELEASE_RE.captures(&url) {
+                let project = caps.get(1).unwrap().as_str();
+                let tag = caps
+                    .get(2)
+                    .map(|tag| tag.as_str().trim())
+                    .filter(|&tag| !tag.is_empty());
+                if let Some(tag) = tag {
+                    return Ok(PlatformUrl::Gitlab(format!("{}@{}", project, tag)));
+                } else {
+                    return Ok(PlatformUrl::Gitlab(project.to_string()));
+                }
+            }
+            return Err(PlatformError::InvalidInput(url));
+        }
+        Ok(PlatformUrl::DirectUrl(url))
+    }
+}
+
+pub trait DownloadableAsset {
+    fn name(&self) -> &str;
+    fn size(&self) -> u64;
+    fn download_url(&self) -> &str;
+}
+
+pub trait ReleasePlatform {
+    const API_BASE_PRIMARY: &'static str;
+    const API_BASE_PKGFORGE: &'static str;
+    const TOKEN_ENV_VAR: &'static str;
+    /// Whether release assets can have GitHub artifact attestations.
+    const ATTESTATIONS: bool = false;
+
+    fn format_project_path(project: &str) -> Result<(String, String), PlatformError>;
+    fn format_api_path(project: &str) -> Result<String, PlatformError>;
+}
+
+pub trait ReleaseAsset {
+    fn name(&self) -> &str;
+    fn size(&self) -> Option<u64>;
+    fn download_url(&self) -> &str;
+}
+
+pub trait Release<A: ReleaseAsset> {
+    fn name(&self) -> &str;
+    fn tag_name(&self) -> &str;
+    fn is_prerelease(&self) -> bool;
+    fn published_at(&self) -> &str;
+    fn assets(&self) -> Vec<A>;
+}
+
+/// Which release asset to pick and where to download it, built with
+/// [`PlatformDownloadOptions::builder`].
+#[derive(Clone, Default)]
+pub struct PlatformDownloadOptions {
+    pub output_path: Option<PathBuf>,
+    pub progress_callback: Option<ProgressCallback>,
+    pub tag: Option<String>,
+    pub regex_patterns: Vec<Regex>,
+    pub match_keywords: Vec<String>,
+    pub exclude_keywords: Vec<String>,
+    pub exact_case: bool,
+    /// Pick the newest release even if it is a prerelease. Ignored when `tag`
+    /// is set.
+    pub prerelease: bool,
+}
+
+impl PlatformDownloadOptions {
+    /// Starts building options that pick the newest stable release and
+    /// accept any of its assets.
+    pub fn builder() -> PlatformDownloadOptionsBuilder {
+        PlatformDownloadOptionsBuilder::default()
+    }
+}
+
+/// Builder of [`PlatformDownloadOptions`].
+#[derive(Default)]
+#[must_use]
+pub struct PlatformDownloadOptionsBuilder {
+    options: PlatformDownloadOptions,
+    patterns: Vec<String>,
+}
+
+impl PlatformDownloadOptionsBuilder {
+    /// File to save the asset to, or the directory to save it in if the path
+    /// ends with a slash.
+    pub fn output_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.options.output_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Reports the progress of the download to `callback`.
+    pub fn progress_callback(mut self, callback: impl Into<Option<ProgressCallback>>) -> Self {
+        self.options.progress_callback = callback.into();
+        self
+    }
+
+    /// Picks the release tagged `tag` instead of the newest one.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.options.tag = Some(tag.into());
+        self
+    }
+
+    /// Only accepts assets whose name matches the regex `pattern`, on top of
+    /// any other patterns added.
+    pub fn regex(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    /// Only accepts assets whose name matches `regex`, like [`Self::regex`]
+    /// for an already compiled one.
+    pub fn compiled_regex(mut self, regex: Regex) -> Self {
+        self.options.regex_patterns.push(regex);
+        self
+    }
+
+    /// Only accepts assets whose name contains `keyword`.
+    pub fn match_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.options.match_keywords.push(keyword.into());
+        self
+    }
+
+    /// Rejects assets whose name contains `keyword`.
+    pub fn exclude_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.options.exclude_keywords.push(keyword.into());
+        self
+    }
+
+    /// Matches keywords case-sensitively, they're case-insensitive by
+    /// default.
+    pub fn exact_case(mut self, exact_case: bool) -> Self {
+        self.options.exact_case = exact_case;
+        self
+    }
+
+    /// Picks the newest release even if it's a prerelease.
+    pub fn prerelease(mut self, prerelease: bool) -> Self {
+        self.options.prerelease = prerelease;
+        self
+    }
+
+    /// Builds the options, failing on an invalid regex or an empty tag,
+    /// output path or keyword.
+    pub fn build(mut self) -> Result<PlatformDownloadOptions, PlatformError> {
+        let invalid = |msg: String| Err(PlatformError::InvalidOptions(msg));
+        for pattern in &self.patterns {
+            match Regex::new(pattern) {
+                Ok(regex) => self.options.regex_patterns.push(regex),
+                Err(err) => return invalid(format!("invalid asset pattern {}: {}", pattern, err)),
+            }
+        }
+        if self
+            .options
+            .tag
+            .as_deref()
+            .is_some_and(|tag| tag.trim().is_empty())
+        {
+            return invalid("release tag is empty".to_string());
+        }
+        if self
+            .options
+            .output_path
+            .as_ref()
+            .is_some_and(|path| path.as_os_str().is_empty())
+        {
+            return invalid("output path is empty".to_string());
+        }
+        if self
+            .options
+            .match_keywords
+            .iter()
+            .chain(&self.options.exclude_keywords)
+            .any(|keyword| keyword.is_empty())
+        {
+            return invalid("asset keyword is empty".to_string());
+        }
+
+        Ok(self.options)
+    }
+}
+
+/// Self-hosted instance of a platform, e.g. a GitLab installation.
+#[derive(Clone, Debug)]
+pub struct Instance {
+    /// Base URL, e.g. `https://gitlab.example.com`.
+    pub url: String,
+    /// Access token sent with the API requests.
+    pub token: Option<String>,
+}
+
+pub struct ReleaseHandler<P: ReleasePlatform> {
+    downloader: Downloader,
+    client: reqwest::Client,
+    instance: Option<Instance>,
+    _platform: std::marker::PhantomData<P>,
+}
+
+impl<P: ReleasePlatform> Default for ReleaseHandler<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: ReleasePlatform> ReleaseHandler<P> {
+    pub fn new() -> Self {
+        Self {
+            downloader: Downloader::default(),
+            client: shared_client(),
+            instance: None,
+            _platform: std::marker::PhantomData,
+        }
+    }
+
+    /// Handler fetching releases from a self-hosted `instance` instead of
+    /// the public one and its mirror.
+    pub fn with_instance(instance: Instance) -> Self {
+        Self {
+            instance: Some(instance),
+            ..Self::new()
+        }
+    }
+
+    fn api_base(&self, api_type: &ApiType) -> (String, Option<String>) {
+        match (&self.instance, api_type) {
+            (Some(instance), _) => (
+                instance.url.trim_end_matches('/').to_string(),
+                instance.token.clone(),
+            ),
+            (None, ApiType::PkgForge) => (P::API_BASE_PKGFORGE.to_string(), None),
+            (None, ApiType::Primary) => (
+                P::API_BASE_PRIMARY.to_string(),
+                env::var(P::TOKEN_ENV_VAR).ok(),
+            ),
+        }
+    }
+
+    /// Fetches a page of an API listing, conditionally if it's cached.
+    ///
+    /// With `wait_on_rate_limit`, a rate limit that resets soon is waited
+    /// out and one that doesn't fails the request. Otherwise the response
+    /// is returned as is so the caller can try elsewhere.
+    async fn get_page(
+        &self,
+        url: &str,
+        token: Option<&str>,
+        wait_on_rate_limit: bool,
+    ) -> Result<Page, PlatformError> {
+        let cached = api_cache::load(url, token);
+
+        let mut waits = 0;
+        loop {
+            let mut request = self.client.get(url);
+            if let Some(Ok(value)) =
+                token.map(|token| format!("Bearer {}", token).parse::<HeaderValue>())
+            {
+                request = request.header(AUTHORIZATION, value);
+            }
+            if let Some(ref cached) = cached {
+                request = request.header(IF_NONE_MATCH, &cached.etag);
+            }
+
+            let response = request
+                .send()
+                .await
+                .map_err(|err| DownloadError::NetworkError { source: err })?;
+            let status = response.status();
+            if status == StatusCode::NOT_MODIFIED {
+                if let Some(cached) = cached {
+                    return Ok(Page {
+                        status: StatusCode::OK,
+                        url: url.to_string(),
+                        next: cached.next,
+                        body: cached.body,
+                    });
+                }
+            }
+
+            if wait_on_rate_limit {
+                if let Some(reset) = rate_limit_reset(&response) {
+                    if reset > MAX_RATE_LIMIT_WAIT || waits == MAX_RATE_LIMIT_RETRIES {
+                        return Err(PlatformError::RateLimited {
+                            reset_in: reset,
+                            token_var: self.instance.is_none().then_some(P::TOKEN_ENV_VAR),
+                        });
+                    }
+                    waits += 1;
+                    sleep(reset).await;
+                    continue;
+                }
+            }
+
+            let headers = response.headers();
+            let next = next_page(headers);
+            let etag = headers
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(String::from);
+            let body = response
+                .bytes()
+                .await
+                .map_err(|err| DownloadError::NetworkError { source: err })?
+                .to_vec();
+
+            let page = Page {
+                status,
+                url: url.to_string(),
+                next,
+                body,
+            };
+            if let Some(etag) = etag.filter(|_| status.is_success()) {
+                api_cache::store(
+                    url,
+                    token,
+                    &CachedPage {
+                        etag,
+                        next: page.next.clone(),
+                        body: page.body.clone(),
+                    },
+                );
+            }
+            return Ok(page);
+        }
+    }
+
+    /// Fetches the releases of `project`, newest first, following the
+    /// pagination of the API for up to 1000 releases.
+    pub async fn fetch_releases<R>(&self, project: &str) -> Result<Vec<R>, PlatformError>
+    where
+        R: for<'de> Deserialize<'de>,
+    {
+        let api_path = P::format_api_path(project)?;
+
+        // self-hosted instances have no mirror
+        let mirrored = if self.instance.is_none() {
+            let (base_url, token) = self.api_base(&ApiType::PkgForge);
+            let page = self
+                .get_page(
+                    &format!("{}{}", base_url, api_path),
+                    token.as_deref(),
+                    false,
+                )
+                .await?;
+            (!should_fallback(page.status)).then_some(page)
+        } else {
+            None
+        };
+        let (base_url, token) = self.api_base(&ApiType::Primary);
+        let mut page = match mirrored {
+            Some(page) => page,
+            None => {
+                self.get_page(&format!("{}{}", base_url, api_path), token.as_deref(), true)
+                    .await?
+            }
+        };
+
+        let mut releases = Vec::new();
+        for _ in 0..MAX_PAGES {
+            if !page.status.is_success() {
+                return Err(DownloadError::ResourceError {
+                    url: page.url,
+                    status: page.status,
+                }
+                .into());
+            }
+            let items: Vec<R> =
+                serde_json::from_slice(&page.body).map_err(|_| PlatformError::InvalidResponse)?;
+            releases.extend(items);
+
+            let Some(next) = page.next else {
+                break;
+            };
+            // only send the token to the host it's for
+            let token = token.as_deref().filter(|_| next.starts_with(&base_url));
+            page = self.get_page(&next, token, true).await?;
+        }
+
+        Ok(releases)
+    }
+
+    pub async fn filter_releases<R, A>(
+        &self,
+        releases: &[R],
+        options: &PlatformDownloadOptions,
+    ) -> Result<Vec<A>, PlatformError>
+    where
+        R: Release<A>,
+        A: ReleaseAsset + Clone,
+    {
+        let release = if let Some(ref tag_name) = options.tag {
+            releases
+                .iter()
+                .find(|release| release.tag_name() == tag_name)
+        } else if options.prerelease {
+            releases.first()
+        } else {
+            releases
+                .iter()
+                .find(|release| !release.is_prerelease())
+                .map_or_else(|| releases.first(), Some)
+        };
+
+        let Some(release) = release else {
+            return Err(PlatformError::NoRelease {
+                tag: options.tag.clone(),
+            });
+        };
+
+        let assets: Vec<A> = release
+            .assets()
+            .into_iter()
+            .filter(|asset| {
+                let name = asset.name();
+                options
+                    .regex_patterns
+                    .iter()
+                    .all(|regex| regex.is_match(name))
+                    && options.match_keywords.iter().all(|keyword| {
+                        keyword
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|s| !s.is_empty())
+                            .all(|part| {
+                                let (asset_name, part) = if options.exact_case {
+                                    (name.to_string(), part.to_string())
+                                } else {
+                                    (name.to_lowercase(), part.to_lowercase())
+                                };
+                                asset_name.contains(&part)
+                            })
+                    })
+                    && options.exclude_keywords.iter().all(|keyword| {
+                        keyword
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|s| !s.is_empty())
+                            .all(|part| {
+                                let (asset_name, part) = if options.exact_case {
+                                    (name.to_string(), part.to_string())
+                                } else {
+                                    (name.to_lowercase(), part.to_lowercase())
+                                };
+                                !asset_name.contains(&part)
+                            })
+                    })
+            })
+            .collect();
+
+        if assets.is_empty() {
+            return Err(PlatformError::NoMatchingAssets {
+                available_assets: release
+                    .assets()
+                    .into_iter()
+                    .map(|a| a.name().to_string())
+                    .collect(),
+            });
+        }
+
+        Ok(assets)
+    }
+
+    pub async fn download<A: ReleaseAsset>(
+        &self,
+        asset: &A,
+        options: PlatformDownloadOptions,
+    ) -> Result<PathBuf, PlatformError> {
+        Ok(self
+            .downloader
+            .download(DownloadOptions {
+                url: asset.download_url().to_string(),
+                output_path: options.output_path,
+                progress_callback: options.progress_callback,
+                file_filter: None,
+            })
+            .await?)
+    }
+}
@@ -0,0 +1,1156 @@
+//! Leech-only BitTorrent downloads of single-file torrents, given as the URL
+//! of a `.torrent` file or as a magnet link.
+//!
+//! Peers are found through the HTTP and UDP trackers of the torrent. There is
+//! no DHT, so magnet links need at least one `tr` tracker; their metadata is
+//! fetched from the peers with the `ut_metadata` extension.
+
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    fs::{File, Permissions},
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    os::unix::fs::{FileExt, PermissionsExt},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures::{future::join_all, stream, FutureExt, StreamExt};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{lookup_host, TcpStream, UdpSocket},
+    time::{sleep, timeout},
+};
+use url::Url;
+
+use crate::{
+    cancel::cancellable,
+    downloader::{DownloadOptions, DownloadState, Downloader},
+    error::DownloadError,
+    hash::{parse_hex, sha1},
+    utils::{is_dir_path, is_elf, part_path},
+};
+
+const BLOCK_SIZE: usize = 16 * 1024;
+/// Number of peers downloaded from at once.
+const MAX_PEERS: usize = 30;
+/// Number of block requests kept in flight per peer.
+const PIPELINE: usize = 8;
+const PEER_TIMEOUT: Duration = Duration::from_secs(15);
+const TRACKER_TIMEOUT: Duration = Duration::from_secs(10);
+/// Largest message accepted from a peer, a bitfield of ~128 million pieces.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// Largest piece length accepted, as each piece is held in memory until
+/// it's verified.
+const MAX_PIECE_LENGTH: usize = 16 * 1024 * 1024;
+/// Port announced to trackers. Nothing listens on it since only leeching
+/// is supported.
+const ANNOUNCE_PORT: u16 = 6881;
+/// Extension message ID we assign to `ut_metadata`.
+const UT_METADATA: u8 = 1;
+
+const CHOKE: u8 = 0;
+const UNCHOKE: u8 = 1;
+const INTERESTED: u8 = 2;
+const HAVE: u8 = 4;
+const BITFIELD: u8 = 5;
+const REQUEST: u8 = 6;
+const PIECE: u8 = 7;
+const EXTENDED: u8 = 20;
+
+fn torrent_error(msg: impl Into<String>) -> DownloadError {
+    DownloadError::TorrentError(msg.into())
+}
+
+#[derive(Debug, Clone)]
+enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<Vec<u8>, Bencode>),
+}
+
+impl Bencode {
+    /// Decodes the value at the start of `data`, returning it with the
+    /// number of bytes it took.
+    fn decode(data: &[u8]) -> Result<(Self, usize), DownloadError> {
+        Self::decode_nested(data, 0)
+    }
+
+    fn decode_nested(data: &[u8], depth: usize) -> Result<(Self, usize), DownloadError> {
+        let invalid = || torrent_error("invalid bencoded data");
+        if depth > 32 {
+            return Err(invalid());
+        }
+
+        match data.first().ok_or_else(invalid)? {
+            b'i' => {
+                let end = data.iter().position(|&b| b == b'e').ok_or_else(invalid)?;
+                let value = std::str::from_utf8(&data[1..end])
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(invalid)?;
+                Ok((Bencode::Int(value), end + 1))
+            }
+            b'l' => {
+                let mut pos = 1;
+                let mut items = Vec::new();
+                while data.get(pos) != Some(&b'e') {
+                    let (item, len) =
+                        Self::decode_nested(data.get(pos..).ok_or_else(invalid)?, depth + 1)?;
+                    items.push(item);
+                    pos += len;
+                }
+                Ok((Bencode::List(items), pos + 1))
+            }
+            b'd' => {
+                let mut pos = 1;
+                let mut entries = BTreeMap::new();
+                while data.get(pos) != Some(&b'e') {
+                    let rest = data.get(pos..).ok_or_else(invalid)?;
+                    let (Bencode::Bytes(key), key_len) = Self::decode_nested(rest, depth + 1)?
+                    else {
+                        return Err(invalid());
+                    };
+                    pos += key_len;
+                    let (value, len) =
+                        Self::decode_nested(data.get(pos..).ok_or_else(invalid)?, depth + 1)?;
+                    entries.insert(key, value);
+                    pos += len;
+                }
+                Ok((Bencode::Dict(entries), pos + 1))
+            }
+            b'0'..=b'9' => {
+                let colon = data.iter().position(|&b| b == b':').ok_or_else(invalid)?;
+                let len: usize = std::str::from_utf8(&data[..colon])
+                    .ok()
+                    .and_then(|len| len.parse().ok())
+                    .ok_or_else(invalid)?;
+                let start = colon + 1;
+                let end = start.checked_add(len).ok_or_else(invalid)?;
+                let bytes = data.get(start..end).ok_or_else(invalid)?;
+                Ok((Bencode::Bytes(bytes.to_vec()), start + len))
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Int(value) => out.extend_from_slice(format!("i{}e", value).as_bytes()),
+            Bencode::Bytes(bytes) => {
+                out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+            }
+            Bencode::List(items) => {
+                out.push(b'l');
+                for item in items {
+                    item.encode(out);
+                }
+                out.push(b'e');
+            }
+            Bencode::Dict(entries) => {
+                out.push(b'd');
+                for (key, value) in entries {
+                    Bencode::Bytes(key.clone()).encode(out);
+                    value.encode(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+
+    fn dict<const N: usize>(entries: [(&str, Bencode); N]) -> Self {
+        Bencode::Dict(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.as_bytes().to_vec(), value))
+                .collect(),
+        )
+    }
+
+    fn get(&self, key: &str) -> Option<&Bencode> {
+        match self {
+            Bencode::Dict(entries) => entries.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    fn as_int(&self) -> Option<i64> {
+        match self {
+            Bencode::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Bencode::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        self.as_bytes()
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    fn as_list(&self) -> Option<&[Bencode]> {
+        match self {
+            Bencode::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// The `info` dictionary of a torrent.
+struct Metainfo {
+    name: String,
+    piece_length: usize,
+    pieces: Vec<[u8; 20]>,
+    length: u64,
+}
+
+impl Metainfo {
+    fn parse(info: &Bencode) -> Result<Self, DownloadError> {
+        if info.get("files").is_some() {
+            return Err(torrent_error("multi-file torrents aren't supported"));
+        }
+
+        let name = info
+            .get("name")
+            .and_then(Bencode::as_bytes)
+            .map(String::from_utf8_lossy)
+            .ok_or_else(|| torrent_error("torrent has no name"))?;
+        // the name comes from strangers, don't let it leave the output directory
+        let name = Path::new(name.as_ref())
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| torrent_error("torrent has an invalid name"))?;
+
+        let piece_length =
+            info.get("piece length")
+                .and_then(Bencode::as_int)
+                .filter(|len| *len > 0)
+                .ok_or_else(|| torrent_error("torrent has no piece length"))? as u64;
+        if piece_length > MAX_PIECE_LENGTH as u64 {
+            return Err(torrent_error(format!(
+                "torrent pieces of {} bytes are larger than the supported {}",
+                piece_length, MAX_PIECE_LENGTH
+            )));
+        }
+        let piece_length = piece_length as usize;
+        let length = info
+            .get("length")
+            .and_then(Bencode::as_int)
+            .filter(|len| *len >= 0)
+            .ok_or_else(|| torrent_error("torrent has no length"))? as u64;
+        let hashes = info
+            .get("pieces")
+            .and_then(Bencode::as_bytes)
+            .filter(|hashes| hashes.len().is_multiple_of(20))
+            .ok_or_else(|| torrent_error("torrent has invalid piece hashes"))?;
+        let pieces: Vec<[u8; 20]> = hashes
+            .chunks_exact(20)
+            .map(|hash| hash.try_into().unwrap())
+            .collect();
+        if pieces.len() as u64 != length.div_ceil(piece_length as u64) {
+            return Err(torrent_error("torrent has the wrong number of pieces"));
+        }
+
+        Ok(Self {
+            name,
+            piece_length,
+            pieces,
+            length,
+        })
+    }
+
+    fn piece_len(&self, piece: usize) -> usize {
+        let start = piece as u64 * self.piece_length as u64;
+        (self.length - start).min(self.piece_length as u64) as usize
+    }
+}
+
+/// What's known about a torrent before talking to peers.
+struct Source {
+    info_hash: [u8; 20],
+    trackers: Vec<String>,
+    metainfo: Option<Metainfo>,
+}
+
+fn parse_magnet(link: &str) -> Result<Source, DownloadError> {
+    let url = Url::parse(link).map_err(|err| DownloadError::InvalidUrl {
+        url: link.to_string(),
+        source: err,
+    })?;
+
+    let mut info_hash = None;
+    let mut trackers = Vec::new();
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "xt" => {
+                if let Some(hash) = value.strip_prefix("urn:btih:") {
+                    info_hash = match hash.len() {
+                        40 => parse_hex(&hash.to_lowercase()),
+                        32 => base32_decode(hash),
+                        _ => None,
+                    };
+                }
+            }
+            "tr" => trackers.push(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    let info_hash =
+        info_hash.ok_or_else(|| torrent_error("magnet link has no BitTorrent info hash"))?;
+    if trackers.is_empty() {
+        return Err(torrent_error("magnet link has no trackers"));
+    }
+
+    Ok(Source {
+        info_hash,
+        trackers,
+        metainfo: None,
+    })
+}
+
+fn parse_torrent_file(data: &[u8]) -> Result<Source, DownloadError> {
+    let (torrent, _) = Bencode::decode(data)?;
+    let info = torrent
+        .get("info")
+        .ok_or_else(|| torrent_error("torrent file has no info"))?;
+
+    let mut trackers = Vec::new();
+    if let Some(announce) = torrent.get("announce").and_then(Bencode::as_str) {
+        trackers.push(announce.to_string());
+    }
+    for tier in torrent
+        .get("announce-list")
+        .and_then(Bencode::as_list)
+        .unwrap_or_default()
+    {
+        for tracker in tier.as_list().unwrap_or_default() {
+            if let Some(tracker) = tracker.as_str() {
+                if !trackers.iter().any(|known| known == tracker) {
+                    trackers.push(tracker.to_string());
+                }
+            }
+        }
+    }
+    if trackers.is_empty() {
+        return Err(torrent_error("torrent file has no trackers"));
+    }
+
+    Ok(Source {
+        info_hash: sha1(&info.to_bytes()),
+        trackers,
+        metainfo: Some(Metainfo::parse(info)?),
+    })
+}
+
+fn base32_decode(input: &str) -> Option<[u8; 20]> {
+    let mut out = [0u8; 20];
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    let mut idx = 0;
+    for c in input.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = buffer << 5 | value as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            *out.get_mut(idx)? = (buffer >> bits) as u8;
+            idx += 1;
+        }
+    }
+    (idx == 20).then_some(out)
+}
+
+fn percent_encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+fn random_u32() -> u32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    nanos ^ process::id().rotate_left(16)
+}
+
+fn make_peer_id() -> [u8; 20] {
+    let mut peer_id = [0u8; 20];
+    peer_id[..8].copy_from_slice(b"-SD0300-");
+    let suffix = format!(
+        "{:012}",
+        (random_u32() as u64 * 2654435761) % 1_000_000_000_000
+    );
+    peer_id[8..].copy_from_slice(suffix.as_bytes());
+    peer_id
+}
+
+fn parse_compact_peers(data: &[u8]) -> Vec<SocketAddr> {
+    data.chunks_exact(6)
+        .map(|peer| {
+            let ip = Ipv4Addr::new(peer[0], peer[1], peer[2], peer[3]);
+            let port = u16::from_be_bytes([peer[4], peer[5]]);
+            SocketAddr::V4(SocketAddrV4::new(ip, port))
+        })
+        .collect()
+}
+
+impl Downloader {
+    /// Downloads the single file of the torrent at `options.url`, a magnet
+    /// link or the URL of a `.torrent` file.
+    pub async fn download_torrent(
+        &self,
+        options: DownloadOptions,
+    ) -> Result<PathBuf, DownloadError> {
+        let source = if options.url.starts_with("magnet:") {
+            parse_magnet(&options.url)?
+        } else {
+            let response = self
+                .client
+                .get(&options.url)
+                .send()
+                .await
+                .map_err(|err| DownloadError::NetworkError { source: err })?;
+            if !response.status().is_success() {
+                return Err(DownloadError::ResourceError {
+                    status: response.status(),
+                    url: options.url,
+                });
+            }
+            let data = response
+                .bytes()
+                .await
+                .map_err(|err| DownloadError::NetworkError { source: err })?;
+            parse_torrent_file(&data)?
+        };
+
+        let peer_id = make_peer_id();
+        let left = source.metainfo.as_ref().map_or(0, |meta| meta.length);
+        let peers = cancellable(
+            async { Ok(self.find_peers(&source, &peer_id, left).await) },
+            None,
+        )
+        .await?;
+        if peers.is_empty() {
+            return Err(torrent_error("no peers found"));
+        }
+
+        let metainfo = match source.metainfo {
+            Some(metainfo) => metainfo,
+            None => cancellable(fetch_metadata(&peers, &source.info_hash, &peer_id), None).await?,
+        };
+
+        let output_path = options
+            .output_path
+            .unwrap_or_else(|| PathBuf::from(&metainfo.name));
+        let output_path = if is_dir_path(&output_path) {
+            output_path.join(&metainfo.name)
+        } else {
+            output_path
+        };
+        if let Some(output_dir) = output_path.parent() {
+            fs::create_dir_all(output_dir).await?;
+        }
+
+        let temp_path = part_path(&output_path);
+        let file = File::create(&temp_path)?;
+        file.set_len(metainfo.length)?;
+
+        if let Some(ref callback) = options.progress_callback {
+            callback(DownloadState::Preparing(metainfo.length));
+        }
+
+        let swarm = Arc::new(Swarm {
+            info_hash: source.info_hash,
+            peer_id,
+            pending: Mutex::new((0..metainfo.pieces.len()).collect()),
+            completed: AtomicUsize::new(0),
+            downloaded: AtomicU64::new(0),
+            metainfo,
+            file,
+            progress_callback: options.progress_callback.clone(),
+        });
+        let leech = stream::iter(peers).for_each_concurrent(MAX_PEERS, |addr| {
+            let swarm = swarm.clone();
+            async move {
+                let _ = swarm.leech(addr).await;
+            }
+        });
+        cancellable(leech.map(Ok), Some(&temp_path)).await?;
+
+        let missing = swarm.metainfo.pieces.len() - swarm.completed.load(Ordering::SeqCst);
+        if missing > 0 {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(torrent_error(format!(
+                "ran out of peers with {} pieces missing",
+                missing
+            )));
+        }
+
+        fs::rename(&temp_path, &output_path).await?;
+        if is_elf(&output_path).await {
+            fs::set_permissions(&output_path, Permissions::from_mode(0o755)).await?;
+        }
+
+        if let Some(ref callback) = options.progress_callback {
+            callback(DownloadState::Complete);
+        }
+
+        Ok(output_path)
+    }
+
+    /// Asks all trackers of `source` for peers at once.
+    async fn find_peers(&self, source: &Source, peer_id: &[u8; 20], left: u64) -> Vec<SocketAddr> {
+        let announces = source.trackers.iter().map(|tracker| async move {
+            let result = if tracker.starts_with("udp://") {
+                announce_udp(tracker, &source.info_hash, peer_id, left).await
+            } else {
+                self.announce_http(tracker, &source.info_hash, peer_id, left)
+                    .await
+            };
+            result.unwrap_or_default()
+        });
+
+        let mut seen = HashSet::new();
+        join_all(announces)
+            .await
+            .into_iter()
+            .flatten()
+            .filter(|peer| seen.insert(*peer))
+            .collect()
+    }
+
+    async fn announce_http(
+        &self,
+        tracker: &str,
+        info_hash: &[u8; 20],
+        peer_id: &[u8; 20],
+        left: u64,
+    ) -> Result<Vec<SocketAddr>, DownloadError> {
+        let separator = if tracker.contains('?') { '&' } else { '?' };
+        let url = format!(
+            "{}{}info_hash={}&peer_id={}&port={}&uploaded=0&downloaded=0&left={}&compact=1&event=started",
+            tracker,
+            separator,
+            percent_encode(info_hash),
+            percent_encode(peer_id),
+            ANNOUNCE_PORT,
+            left
+        );
+        let response = self
+            .client
+            .get(&url)
+            .timeout(TRACKER_TIMEOUT)
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+
+        let (reply, _) = Bencode::decode(&body)?;
+        if let Some(reason) = reply.get("failure reason").and_then(Bencode::as_str) {
+            return Err(torrent_error(format!("tracker refused: {}", reason)));
+        }
+        Ok(match reply.get("peers") {
+            Some(Bencode::Bytes(peers)) => parse_compact_peers(peers),
+            Some(Bencode::List(peers)) => peers
+                .iter()
+                .filter_map(|peer| {
+                    let ip = peer.get("ip")?.as_str()?.parse().ok()?;
+                    let port = u16::try_from(peer.get("port")?.as_int()?).ok()?;
+                    Some(SocketAddr::new(ip, port))
+                })
+                .collect(),
+            _ => Vec::new(),
+        })
+    }
+}
+
+/// Announces to a UDP tracker as described in BEP 15.
+async fn announce_udp(
+    tracker: &str,
+    info_hash: &[u8; 20],
+    peer_id: &[u8; 20],
+    left: u64,
+) -> Result<Vec<SocketAddr>, DownloadError> {
+    let url = Url::parse(tracker).map_err(|err| DownloadError::InvalidUrl {
+        url: tracker.to_string(),
+        source: err,
+    })?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| torrent_error("tracker has no host"))?;
+    let port = url
+        .port()
+        .ok_or_else(|| torrent_error("tracker has no port"))?;
+    let addr = lookup_host((host, port))
+        .await?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| torrent_error("tracker has no IPv4 address"))?;
+
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(addr).await?;
+    let mut buf = [0u8; 2048];
+
+    let transaction_id = random_u32();
+    let mut request = Vec::with_capacity(16);
+    request.extend_from_slice(&0x41727101980u64.to_be_bytes());
+    request.extend_from_slice(&0u32.to_be_bytes());
+    request.extend_from_slice(&transaction_id.to_be_bytes());
+    socket.send(&request).await?;
+    let len = timeout(TRACKER_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| torrent_error("tracker timed out"))??;
+    if len < 16 || buf[..4] != 0u32.to_be_bytes() || buf[4..8] != transaction_id.to_be_bytes() {
+        return Err(torrent_error("invalid tracker response"));
+    }
+    let connection_id = &buf[8..16];
+
+    let transaction_id = random_u32().wrapping_add(1);
+    let mut request = Vec::with_capacity(98);
+    request.extend_from_slice(connection_id);
+    request.extend_from_slice(&1u32.to_be_bytes());
+    request.extend_from_slice(&transaction_id.to_be_bytes());
+    request.extend_from_slice(info_hash);
+    request.extend_from_slice(peer_id);
+    request.extend_from_slice(&0u64.to_be_bytes());
+    request.extend_from_slice(&left.to_be_bytes());
+    request.extend_from_slice(&0u64.to_be_bytes());
+    request.extend_from_slice(&2u32.to_be_bytes());
+    request.extend_from_slice(&0u32.to_be_bytes());
+    request.extend_from_slice(&random_u32().to_be_bytes());
+    request.extend_from_slice(&(-1i32).to_be_bytes());
+    request.extend_from_slice(&ANNOUNCE_PORT.to_be_bytes());
+    socket.send(&request).await?;
+    let len = timeout(TRACKER_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| torrent_error("tracker timed out"))??;
+    if len < 20 || buf[..4] != 1u32.to_be_bytes() || buf[4..8] != transaction_id.to_be_bytes() {
+        return Err(torrent_error("invalid tracker response"));
+    }
+
+    Ok(parse_compact_peers(&buf[20..len]))
+}
+
+/// Connection to a peer after the handshake.
+struct PeerConnection {
+    stream: TcpStream,
+    have: Vec<bool>,
+    choked: bool,
+}
+
+impl PeerConnection {
+    async fn connect(
+        addr: SocketAddr,
+        info_hash: &[u8; 20],
+        peer_id: &[u8; 20],
+        pieces: usize,
+    ) -> Result<Self, DownloadError> {
+        let mut stream = timeout(PEER_TIMEOUT, TcpStream::connect(addr))
+            .await
+            .map_err(|_| torrent_error("peer timed out"))??;
+
+        let mut handshake = Vec::with_capacity(68);
+        handshake.push(19);
+        handshake.extend_from_slice(b"BitTorrent protocol");
+        // advertise the extension protocol, for ut_metadata
+        handshake.extend_from_slice(&[0, 0, 0, 0, 0, 0x10, 0, 0]);
+        handshake.extend_from_slice(info_hash);
+        handshake.extend_from_slice(peer_id);
+        stream.write_all(&handshake).await?;
+
+        let mut reply = [0u8; 68];
+        timeout(PEER_TIMEOUT, stream.read_exact(&mut reply))
+            .await
+            .map_err(|_| torrent_error("peer timed out"))??;
+        if reply[0] != 19
+            || &reply[1..20] != b"BitTorrent protocol"
+            || reply[28..48] != info_hash[..]
+        {
+            return Err(torrent_error("invalid handshake"));
+        }
+
+        Ok(Self {
+            stream,
+            have: vec![false; pieces],
+            choked: true,
+        })
+    }
+
+    async fn send(&mut self, id: u8, payload: &[u8]) -> Result<(), DownloadError> {
+        let mut message = Vec::with_capacity(5 + payload.len());
+        message.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+        message.push(id);
+        message.extend_from_slice(payload);
+        self.stream.write_all(&message).await?;
+        Ok(())
+    }
+
+    /// Reads the next message, keeping track of the choke state and the
+    /// pieces the peer has. Returns `None` for keep-alives.
+    async fn recv(&mut self) -> Result<Option<(u8, Vec<u8>)>, DownloadError> {
+        let mut len = [0u8; 4];
+        timeout(PEER_TIMEOUT, self.stream.read_exact(&mut len))
+            .await
+            .map_err(|_| torrent_error("peer timed out"))??;
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 {
+            return Ok(None);
+        }
+        if len > MAX_MESSAGE_SIZE {
+            return Err(torrent_error("peer sent an oversized message"));
+        }
+
+        let mut message = vec![0u8; len];
+        timeout(PEER_TIMEOUT, self.stream.read_exact(&mut message))
+            .await
+            .map_err(|_| torrent_error("peer timed out"))??;
+        let id = message.remove(0);
+
+        match id {
+            CHOKE => self.choked = true,
+            UNCHOKE => self.choked = false,
+            HAVE if message.len() == 4 => {
+                let piece = u32::from_be_bytes(message[..4].try_into().unwrap()) as usize;
+                if let Some(have) = self.have.get_mut(piece) {
+                    *have = true;
+                }
+            }
+            BITFIELD => {
+                for (piece, have) in self.have.iter_mut().enumerate() {
+                    *have = message
+                        .get(piece / 8)
+                        .is_some_and(|byte| byte & (0x80 >> (piece % 8)) != 0);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(Some((id, message)))
+    }
+
+    async fn download_piece(
+        &mut self,
+        piece: usize,
+        piece_len: usize,
+    ) -> Result<Vec<u8>, DownloadError> {
+        while self.choked {
+            self.recv().await?;
+        }
+
+        let blocks = piece_len.div_ceil(BLOCK_SIZE);
+        let mut data = vec![0u8; piece_len];
+        let mut received = vec![false; blocks];
+        let mut remaining = blocks;
+        let mut requested = 0;
+        let mut in_flight = 0;
+
+        while remaining > 0 {
+            while in_flight < PIPELINE && requested < blocks {
+                let begin = requested * BLOCK_SIZE;
+                let len = BLOCK_SIZE.min(piece_len - begin);
+                let mut payload = Vec::with_capacity(12);
+                payload.extend_from_slice(&(piece as u32).to_be_bytes());
+                payload.extend_from_slice(&(begin as u32).to_be_bytes());
+                payload.extend_from_slice(&(len as u32).to_be_bytes());
+                self.send(REQUEST, &payload).await?;
+                requested += 1;
+                in_flight += 1;
+            }
+
+            match self.recv().await? {
+                Some((PIECE, payload)) if payload.len() >= 8 => {
+                    let index = u32::from_be_bytes(payload[..4].try_into().unwrap()) as usize;
+                    let begin = u32::from_be_bytes(payload[4..8].try_into().unwrap()) as usize;
+                    let block = &payload[8..];
+                    let block_idx = begin / BLOCK_SIZE;
+                    if index != piece
+                        || !begin.is_multiple_of(BLOCK_SIZE)
+                        || block_idx >= blocks
+                        || received[block_idx]
+                        || begin + block.len() > piece_len
+                    {
+                        continue;
+                    }
+                    data[begin..begin + block.len()].copy_from_slice(block);
+                    received[block_idx] = true;
+                    remaining -= 1;
+                    in_flight -= 1;
+                }
+                // pending requests are dropped when the peer chokes us
+                _ if self.choked => return Err(torrent_error("peer choked")),
+                _ => {}
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// Fetches the info dictionary of a magnet link from the first peer that
+/// has it.
+async fn fetch_metadata(
+    peers: &[SocketAddr],
+    info_hash: &[u8; 20],
+    peer_id: &[u8; 20],
+) -> Result<Metainfo, DownloadError> {
+    let (info_hash, peer_id) = (*info_hash, *peer_id);
+    let mut attempts = stream::iter(peers.to_vec())
+        .map(|addr| async move { fetch_metadata_from(addr, &info_hash, &peer_id).await })
+        .buffer_unordered(MAX_PEERS);
+    while let Some(result) = attempts.next().await {
+        if let Ok(metainfo) = result {
+            return Ok(metainfo);
+        }
+    }
+    Err(torrent_error("no peer sent the torrent metadata"))
+}
+
+async fn fetch_metadata_from(
+    addr: SocketAddr,
+    info_hash: &[u8; 20],
+    peer_id: &[u8; 20],
+) -> Result<Metainfo, DownloadError> {
+    let mut peer = PeerConnection::connect(addr, info_hash, peer_id, 0).await?;
+
+    let handshake = Bencode::dict([(
+        "m",
+        Bencode::dict([("ut_metadata", Bencode::Int(UT_METADATA as i64))]),
+    )]);
+    let mut payload = vec![0];
+    payload.extend_from_slice(&handshake.to_bytes());
+    peer.send(EXTENDED, &payload).await?;
+
+    let (their_id, size) = loop {
+        if let Some((EXTENDED, payload)) = peer.recv().await? {
+            if payload.first() != Some(&0) {
+                continue;
+            }
+            let (handshake, _) = Bencode::decode(&payload[1..])?;
+            let their_id = handshake
+                .get("m")
+                .and_then(|m| m.get("ut_metadata"))
+                .and_then(Bencode::as_int)
+                .and_then(|id| u8::try_from(id).ok())
+                .filter(|id| *id != 0)
+                .ok_or_else(|| torrent_error("peer doesn't support ut_metadata"))?;
+            let size = handshake
+                .get("metadata_size")
+                .and_then(Bencode::as_int)
+                .and_then(|size| usize::try_from(size).ok())
+                .filter(|size| (1..=MAX_MESSAGE_SIZE).contains(size))
+                .ok_or_else(|| torrent_error("peer sent an invalid metadata size"))?;
+            break (their_id, size);
+        }
+    };
+
+    let mut metadata = Vec::with_capacity(size);
+    for piece in 0..size.div_ceil(BLOCK_SIZE) {
+        let request = Bencode::dict([
+            ("msg_type", Bencode::Int(0)),
+            ("piece", Bencode::Int(piece as i64)),
+        ]);
+        let mut payload = vec![their_id];
+        payload.extend_from_slice(&request.to_bytes());
+        peer.send(EXTENDED, &payload).await?;
+
+        loop {
+            let Some((EXTENDED, payload)) = peer.recv().await? else {
+                continue;
+            };
+            if payload.first() != Some(&UT_METADATA) {
+                continue;
+            }
+            let (reply, len) = Bencode::decode(&payload[1..])?;
+            if reply.get("msg_type").and_then(Bencode::as_int) != Some(1)
+                || reply.get("piece").and_then(Bencode::as_int) != Some(piece as i64)
+            {
+                return Err(torrent_error("peer rejected the metadata request"));
+            }
+            metadata.extend_from_slice(&payload[1 + len..]);
+            break;
+        }
+    }
+
+    if metadata.len() != size || sha1(&metadata) != *info_hash {
+        return Err(torrent_error("peer sent invalid metadata"));
+    }
+    let (info, _) = Bencode::decode(&metadata)?;
+    Metainfo::parse(&info)
+}
+
+/// Download state shared by the connections to all peers.
+struct Swarm {
+    info_hash: [u8; 20],
+    peer_id: [u8; 20],
+    metainfo: Metainfo,
+    file: File,
+    /// Pieces nobody is downloading yet.
+    pending: Mutex<VecDeque<usize>>,
+    completed: AtomicUsize,
+    downloaded: AtomicU64,
+    progress_callback: Option<Arc<dyn Fn(DownloadState) + Send + Sync + 'static>>,
+}
+
+impl Swarm {
+    fn is_complete(&self) -> bool {
+        self.completed.load(Ordering::SeqCst) == self.metainfo.pieces.len()
+    }
+
+    /// Takes a pending piece the peer has. Returns `Err` with whether any
+    /// pieces are pending at all if there's none.
+    fn take_piece(&self, have: &[bool]) -> Result<usize, bool> {
+        let mut pending = self.pending.lock().unwrap();
+        match pending.iter().position(|piece| have[*piece]) {
+            Some(idx) => Ok(pending.remove(idx).unwrap()),
+            None => Err(!pending.is_empty()),
+        }
+    }
+
+    fn return_piece(&self, piece: usize) {
+        self.pending.lock().unwrap().push_back(piece);
+    }
+
+    /// Downloads pieces from the peer at `addr` until all pieces are done or
+    /// the peer fails.
+    async fn leech(&self, addr: SocketAddr) -> Result<(), DownloadError> {
+        if self.is_complete() {
+            return Ok(());
+        }
+
+        let mut peer = PeerConnection::connect(
+            addr,
+            &self.info_hash,
+            &self.peer_id,
+            self.metainfo.pieces.len(),
+        )
+        .await?;
+        peer.send(INTERESTED, &[]).await?;
+
+        while !self.is_complete() {
+            let piece = match self.take_piece(&peer.have) {
+                Ok(piece) => piece,
+                // wait for the peer to announce pieces we still need
+                Err(true) => {
+                    peer.recv().await?;
+                    continue;
+                }
+                // other peers are finishing the last pieces, and may give
+                // them back if they fail
+                Err(false) => {
+                    sleep(Duration::from_millis(500)).await;
+                    continue;
+                }
+            };
+
+            let piece_len = self.metainfo.piece_len(piece);
+            let data = match peer.download_piece(piece, piece_len).await {
+                Ok(data) if sha1(&data) == self.metainfo.pieces[piece] => data,
+                Ok(_) => {
+                    self.return_piece(piece);
+                    return Err(torrent_error("peer sent a corrupt piece"));
+                }
+                Err(err) => {
+                    self.return_piece(piece);
+                    return Err(err);
+                }
+            };
+
+            let offset = piece as u64 * self.metainfo.piece_length as u64;
+            if let Err(err) = self.file.write_all_at(&data, offset) {
+                self.return_piece(piece);
+                return Err(err.into());
+            }
+            self.completed.fetch_add(1, Ordering::SeqCst);
+            let downloaded = self
+                .downloaded
+                .fetch_add(data.len() as u64, Ordering::SeqCst)
+                + data.len() as u64;
+            if let Some(ref callback) = self.progress_callback {
+                callback(DownloadState::Progress(downloaded));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(value: &[u8]) -> Bencode {
+        Bencode::Bytes(value.to_vec())
+    }
+
+    fn info(piece_length: i64, length: i64, pieces: usize) -> Bencode {
+        Bencode::dict([
+            ("name", bytes(b"tool")),
+            ("piece length", Bencode::Int(piece_length)),
+            ("length", Bencode::Int(length)),
+            ("pieces", Bencode::Bytes(vec![0xab; pieces * 20])),
+        ])
+    }
+
+    #[test]
+    fn decodes_and_encodes_bencode() {
+        let data = b"d4:listli-3e3:abce6:nestedd0:0:e3:numi42ee";
+        let (value, len) = Bencode::decode(data).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(value.get("num").and_then(Bencode::as_int), Some(42));
+        let list = value.get("list").and_then(Bencode::as_list).unwrap();
+        assert_eq!(list[0].as_int(), Some(-3));
+        assert_eq!(list[1].as_str(), Some("abc"));
+        assert_eq!(value.to_bytes(), data);
+    }
+
+    #[test]
+    fn decodes_only_the_first_value() {
+        let (value, len) = Bencode::decode(b"3:abctrailing").unwrap();
+        assert_eq!(value.as_str(), Some("abc"));
+        assert_eq!(len, 5);
+    }
+
+    #[test]
+    fn rejects_invalid_bencode() {
+        for data in [
+            &b""[..],
+            b"i12",
+            b"ixe",
+            b"5:abc",
+            b"18446744073709551615:a",
+            b"l3:abc",
+            b"di1e3:abce",
+            b"x",
+        ] {
+            assert!(Bencode::decode(data).is_err(), "{:?}", data);
+        }
+
+        let nested = [vec![b'l'; 64], vec![b'e'; 64]].concat();
+        assert!(Bencode::decode(&nested).is_err());
+    }
+
+    #[test]
+    fn parses_single_file_metainfo() {
+        let metainfo = Metainfo::parse(&info(16384, 40000, 3)).unwrap();
+        assert_eq!(metainfo.name, "tool");
+        assert_eq!(metainfo.pieces.len(), 3);
+        assert_eq!(metainfo.piece_len(0), 16384);
+        assert_eq!(metainfo.piece_len(2), 40000 - 2 * 16384);
+    }
+
+    #[test]
+    fn rejects_invalid_metainfo() {
+        assert!(Metainfo::parse(&info(16384, 40000, 2)).is_err());
+        assert!(Metainfo::parse(&info(0, 0, 0)).is_err());
+        assert!(Metainfo::parse(&info(16384, -1, 0)).is_err());
+        let huge = MAX_PIECE_LENGTH as i64 * 2;
+        assert!(Metainfo::parse(&info(huge, huge, 1)).is_err());
+
+        let Bencode::Dict(mut entries) = info(16384, 16384, 1) else {
+            unreachable!()
+        };
+        entries.insert(b"files".to_vec(), Bencode::List(Vec::new()));
+        assert!(Metainfo::parse(&Bencode::Dict(entries)).is_err());
+    }
+
+    #[test]
+    fn keeps_the_name_inside_the_output_directory() {
+        let Bencode::Dict(mut entries) = info(16384, 16384, 1) else {
+            unreachable!()
+        };
+        entries.insert(b"name".to_vec(), bytes(b"../../.bashrc"));
+        let metainfo = Metainfo::parse(&Bencode::Dict(entries.clone())).unwrap();
+        assert_eq!(metainfo.name, ".bashrc");
+
+        entries.insert(b"name".to_vec(), bytes(b".."));
+        assert!(Metainfo::parse(&Bencode::Dict(entries)).is_err());
+    }
+
+    #[test]
+    fn parses_torrent_files() {
+        let info = info(16384, 16384, 1);
+        let torrent = Bencode::dict([
+            ("announce", bytes(b"http://tracker.example/announce")),
+            (
+                "announce-list",
+                Bencode::List(vec![Bencode::List(vec![
+                    bytes(b"http://tracker.example/announce"),
+                    bytes(b"udp://tracker.example:6969"),
+                ])]),
+            ),
+            ("info", info.clone()),
+        ]);
+
+        let source = parse_torrent_file(&torrent.to_bytes()).unwrap();
+        assert_eq!(source.info_hash, sha1(&info.to_bytes()));
+        assert_eq!(
+            source.trackers,
+            [
+                "http://tracker.example/announce",
+                "udp://tracker.example:6969"
+            ]
+        );
+        assert!(source.metainfo.is_some());
+
+        let untracked = Bencode::dict([("info", info)]);
+        assert!(parse_torrent_file(&untracked.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn parses_magnet_links() {
+        let hash = [0x3b; 20];
+        let hex: String = hash.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let source = parse_magnet(&format!(
+            "magnet:?xt=urn:btih:{}&tr=udp%3A%2F%2Ftracker.example%3A6969",
+            hex
+        ))
+        .unwrap();
+        assert_eq!(source.info_hash, hash);
+        assert_eq!(source.trackers, ["udp://tracker.example:6969"]);
+
+        let base32 = "HM5TWO134567ABCDEFGHIJKLMNOPQRST";
+        assert!(base32_decode(base32).is_none());
+        let source = parse_magnet(&format!(
+            "magnet:?xt=urn:btih:{}&tr=http://t",
+            "HM5TWOZ3HM5TWOZ3HM5TWOZ3HM5TWOZ3"
+        ))
+        .unwrap();
+        assert_eq!(source.info_hash, hash);
+
+        assert!(parse_magnet(&format!("magnet:?xt=urn:btih:{}", hex)).is_err());
+        assert!(parse_magnet("magnet:?tr=http://t").is_err());
+    }
+}
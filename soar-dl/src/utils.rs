@@ -0,0 +1,66 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::StatusCode;
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, BufReader},
+};
+
+pub const ELF_MAGIC_BYTES: [u8; 4] = [0x7f, 0x45, 0x4c, 0x46];
+
+pub fn extract_filename(url: &str) -> String {
+    Path::new(url)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| {
+            let dt = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis();
+            dt.to_string()
+        })
+}
+
+/// Whether `path` names a directory to save downloads in, i.e. ends with a
+/// slash.
+pub fn is_dir_path(path: &Path) -> bool {
+    path.as_os_str().as_encoded_bytes().ends_with(b"/")
+}
+
+/// Where `path` is written to while it downloads, `path` with `.part`
+/// appended, keeping names that aren't valid UTF-8 intact.
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+pub async fn is_elf<P: AsRef<Path>>(file_path: P) -> bool {
+    let Ok(file) = File::open(file_path).await else {
+        return false;
+    };
+    let mut file = BufReader::new(file);
+
+    let mut magic_bytes = [0_u8; 4];
+    if file.read_exact(&mut magic_bytes).await.is_ok() {
+        return magic_bytes == ELF_MAGIC_BYTES;
+    }
+    false
+}
+
+/// Whether `url` is a magnet link or points to a `.torrent` file.
+pub fn is_torrent_source(url: &str) -> bool {
+    url.starts_with("magnet:")
+        || url::Url::parse(url).is_ok_and(|url| url.path().ends_with(".torrent"))
+}
+
+pub fn should_fallback(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::UNAUTHORIZED
+        || status == StatusCode::FORBIDDEN
+        || status.is_server_error()
+}
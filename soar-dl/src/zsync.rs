@@ -0,0 +1,453 @@
+//! Delta downloads using zsync control files.
+//!
+//! A `.zsync` control file lists a weak rolling checksum and a truncated MD4
+//! checksum for every block of the target file. Blocks found anywhere in a
+//! local seed file, usually the previous version, are copied from it and only
+//! the remaining ones are fetched with HTTP range requests.
+
+use std::{
+    collections::HashMap,
+    fs::Permissions,
+    io::{Read, SeekFrom},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use futures::StreamExt;
+use reqwest::{header::RANGE, StatusCode};
+use tokio::{
+    fs::{self, OpenOptions},
+    io::{AsyncSeekExt, AsyncWriteExt},
+};
+use url::Url;
+
+use crate::{
+    cancel::cancellable,
+    downloader::{DownloadOptions, DownloadState, Downloader},
+    error::DownloadError,
+    hash::{md4, parse_hex, Digest, Sha1},
+    utils::{extract_filename, is_elf, part_path},
+};
+
+/// Parsed zsync control file.
+pub struct ControlFile {
+    pub blocksize: usize,
+    pub length: u64,
+    /// URL of the target file, relative to the control file.
+    pub url: Option<String>,
+    sha1: Option<[u8; 20]>,
+    seq_matches: usize,
+    rsum_mask: u16,
+    checksum_bytes: usize,
+    blocks: Vec<BlockSum>,
+}
+
+struct BlockSum {
+    rsum: u32,
+    checksum: Vec<u8>,
+}
+
+impl ControlFile {
+    pub fn parse(data: &[u8]) -> Result<Self, DownloadError> {
+        let invalid = |msg: &str| DownloadError::InvalidZsync(msg.to_string());
+
+        let mut headers = HashMap::new();
+        let mut pos = 0;
+        loop {
+            let end = data[pos..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .ok_or_else(|| invalid("missing end of header"))?;
+            let line = std::str::from_utf8(&data[pos..pos + end])
+                .map_err(|_| invalid("header isn't valid UTF-8"))?
+                .trim_end_matches('\r');
+            pos += end + 1;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                headers
+                    .entry(key.trim().to_lowercase())
+                    .or_insert_with(|| value.trim().to_string());
+            }
+        }
+
+        let number = |key: &str| -> Result<u64, DownloadError> {
+            headers
+                .get(key)
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| invalid(&format!("missing or invalid {}", key)))
+        };
+        let blocksize = number("blocksize")? as usize;
+        let length = number("length")?;
+        if blocksize == 0 {
+            return Err(invalid("block size is 0"));
+        }
+
+        let hash_lengths: Vec<usize> = headers
+            .get("hash-lengths")
+            .map(|value| {
+                value
+                    .split(',')
+                    .filter_map(|n| n.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_else(|| vec![1, 4, 16]);
+        let [seq_matches, rsum_bytes, checksum_bytes] = hash_lengths[..] else {
+            return Err(invalid("invalid Hash-Lengths"));
+        };
+        if !(1..=2).contains(&seq_matches)
+            || !(1..=4).contains(&rsum_bytes)
+            || !(3..=16).contains(&checksum_bytes)
+        {
+            return Err(invalid("invalid Hash-Lengths"));
+        }
+
+        let sha1 = match headers.get("sha-1") {
+            Some(hex) => Some(parse_hex(hex).ok_or_else(|| invalid("invalid SHA-1"))?),
+            None => None,
+        };
+
+        let block_count = length.div_ceil(blocksize as u64) as usize;
+        let entry_len = rsum_bytes + checksum_bytes;
+        let sums = &data[pos..];
+        if sums.len() < block_count * entry_len {
+            return Err(invalid("truncated block checksums"));
+        }
+        let blocks = sums
+            .chunks_exact(entry_len)
+            .take(block_count)
+            .map(|entry| {
+                let mut rsum = [0u8; 4];
+                rsum[4 - rsum_bytes..].copy_from_slice(&entry[..rsum_bytes]);
+                BlockSum {
+                    rsum: u32::from_be_bytes(rsum),
+                    checksum: entry[rsum_bytes..].to_vec(),
+                }
+            })
+            .collect();
+
+        // zsync drops the high bytes of the first half of the rolling
+        // checksum when fewer than 4 bytes are stored
+        let rsum_mask = match rsum_bytes {
+            4 => 0xffff,
+            3 => 0xff,
+            _ => 0,
+        };
+
+        Ok(Self {
+            blocksize,
+            length,
+            url: headers.get("url").cloned(),
+            sha1,
+            seq_matches,
+            rsum_mask,
+            checksum_bytes,
+            blocks,
+        })
+    }
+
+    fn key(&self, a: u16, b: u16) -> u32 {
+        ((a & self.rsum_mask) as u32) << 16 | b as u32
+    }
+
+    fn block_matches(&self, idx: usize, data: &[u8]) -> bool {
+        md4(data)[..self.checksum_bytes] == self.blocks[idx].checksum[..]
+    }
+
+    fn matches_at(&self, idx: usize, data: &[u8], pos: usize) -> bool {
+        let Some(window) = data.get(pos..pos + self.blocksize) else {
+            return false;
+        };
+        let (a, b) = rsum(window);
+        self.key(a, b) == self.blocks[idx].rsum && self.block_matches(idx, window)
+    }
+
+    /// Finds the blocks of the target that exist in `seed`, returning the
+    /// offset in `seed` of each block found.
+    fn find_blocks(&self, seed: &[u8]) -> Vec<Option<usize>> {
+        let bs = self.blocksize;
+        let mut found = vec![None; self.blocks.len()];
+        if seed.is_empty() {
+            return found;
+        }
+
+        let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (idx, block) in self.blocks.iter().enumerate() {
+            index.entry(block.rsum).or_default().push(idx);
+        }
+
+        // the last block is zero padded, so pad the seed the same way
+        let mut data = seed.to_vec();
+        data.resize(seed.len() + bs, 0);
+
+        let (mut a, mut b) = rsum(&data[..bs]);
+        let mut pos = 0;
+        // block matched by the previous window, whose successor only needs a
+        // single match even if more are required otherwise
+        let mut previous: Option<usize> = None;
+        while pos + bs <= data.len() {
+            let window = &data[pos..pos + bs];
+            let mut matched = None;
+
+            if let Some(next) = previous.map(|idx| idx + 1) {
+                if next < self.blocks.len()
+                    && self.key(a, b) == self.blocks[next].rsum
+                    && self.block_matches(next, window)
+                {
+                    matched = Some(next);
+                }
+            }
+
+            if let Some(candidates) = index.get(&self.key(a, b)).filter(|_| matched.is_none()) {
+                let checksum = md4(window);
+                for &idx in candidates {
+                    if checksum[..self.checksum_bytes] != self.blocks[idx].checksum[..] {
+                        continue;
+                    }
+                    if self.seq_matches > 1
+                        && idx + 1 < self.blocks.len()
+                        && !self.matches_at(idx + 1, &data, pos + bs)
+                    {
+                        continue;
+                    }
+                    // identical blocks all come from the same window
+                    found[idx].get_or_insert(pos);
+                    matched.get_or_insert(idx);
+                }
+            }
+
+            if let Some(idx) = matched {
+                found[idx].get_or_insert(pos);
+                previous = Some(idx);
+                pos += bs;
+                if pos + bs <= data.len() {
+                    (a, b) = rsum(&data[pos..pos + bs]);
+                }
+            } else {
+                previous = None;
+                if pos + bs < data.len() {
+                    let old = data[pos] as u16;
+                    let new = data[pos + bs] as u16;
+                    a = a.wrapping_sub(old).wrapping_add(new);
+                    b = b
+                        .wrapping_sub((bs as u16).wrapping_mul(old))
+                        .wrapping_add(a);
+                }
+                pos += 1;
+            }
+        }
+
+        found
+    }
+}
+
+impl Downloader {
+    /// Downloads the file described by the zsync control file at
+    /// `zsync_url`, reusing the blocks found in `seed` and fetching only the
+    /// others.
+    ///
+    /// Fails if the control file can't be fetched, the server doesn't
+    /// support range requests or the result doesn't match the SHA-1 of the
+    /// control file, in which case callers should fall back to a full
+    /// download.
+    pub async fn download_zsync<P: AsRef<Path>>(
+        &self,
+        options: DownloadOptions,
+        zsync_url: &str,
+        seed: P,
+    ) -> Result<(), DownloadError> {
+        let control_url = Url::parse(zsync_url).map_err(|err| DownloadError::InvalidUrl {
+            url: zsync_url.to_string(),
+            source: err,
+        })?;
+        let response = self
+            .client
+            .get(control_url.clone())
+            .headers(self.headers.clone())
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+        if !response.status().is_success() {
+            return Err(DownloadError::ResourceError {
+                status: response.status(),
+                url: zsync_url.to_string(),
+            });
+        }
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+        let control = ControlFile::parse(&body)?;
+
+        let target_url = match control.url {
+            Some(ref url) => control_url
+                .join(url)
+                .map_err(|err| DownloadError::InvalidUrl {
+                    url: url.clone(),
+                    source: err,
+                })?,
+            None => Url::parse(&options.url).map_err(|err| DownloadError::InvalidUrl {
+                url: options.url.clone(),
+                source: err,
+            })?,
+        };
+
+        let seed = fs::read(seed).await?;
+        let found = control.find_blocks(&seed);
+
+        let output_path = options
+            .output_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(extract_filename(&options.url)));
+        if let Some(output_dir) = output_path.parent() {
+            fs::create_dir_all(output_dir).await?;
+        }
+        let temp_path = part_path(&output_path);
+
+        let result = cancellable(
+            self.assemble(&control, &found, &seed, &target_url, &temp_path, &options),
+            None,
+        )
+        .await;
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path).await;
+            return result;
+        }
+
+        fs::rename(&temp_path, &output_path).await?;
+        if is_elf(&output_path).await {
+            fs::set_permissions(&output_path, Permissions::from_mode(0o755)).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn assemble(
+        &self,
+        control: &ControlFile,
+        found: &[Option<usize>],
+        seed: &[u8],
+        target_url: &Url,
+        temp_path: &Path,
+        options: &DownloadOptions,
+    ) -> Result<(), DownloadError> {
+        let bs = control.blocksize as u64;
+        let block_len = |idx: usize| bs.min(control.length - idx as u64 * bs) as usize;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(temp_path)
+            .await?;
+        file.set_len(control.length).await?;
+
+        // ranges of consecutive blocks missing from the seed
+        let mut missing: Vec<(usize, usize)> = Vec::new();
+        for (idx, offset) in found.iter().enumerate() {
+            match offset {
+                Some(offset) => {
+                    file.seek(SeekFrom::Start(idx as u64 * bs)).await?;
+                    file.write_all(&seed[*offset..*offset + block_len(idx)])
+                        .await?;
+                }
+                None => match missing.last_mut() {
+                    Some((_, end)) if *end == idx => *end = idx + 1,
+                    _ => missing.push((idx, idx + 1)),
+                },
+            }
+        }
+
+        let total: u64 = missing
+            .iter()
+            .flat_map(|&(start, end)| start..end)
+            .map(|idx| block_len(idx) as u64)
+            .sum();
+        if let Some(ref callback) = options.progress_callback {
+            callback(DownloadState::Preparing(total));
+        }
+
+        let mut downloaded = 0u64;
+        for (start, end) in missing {
+            let from = start as u64 * bs;
+            let to = (end as u64 * bs).min(control.length) - 1;
+            let response = self
+                .client
+                .get(target_url.clone())
+                .headers(self.headers.clone())
+                .header(RANGE, format!("bytes={}-{}", from, to))
+                .send()
+                .await
+                .map_err(|err| DownloadError::NetworkError { source: err })?;
+            if response.status() != StatusCode::PARTIAL_CONTENT {
+                return Err(DownloadError::InvalidZsync(format!(
+                    "{} doesn't support range requests",
+                    target_url
+                )));
+            }
+
+            file.seek(SeekFrom::Start(from)).await?;
+            let mut received = 0u64;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|err| DownloadError::NetworkError { source: err })?;
+                file.write_all(&chunk).await?;
+                received += chunk.len() as u64;
+                downloaded += chunk.len() as u64;
+                if let Some(ref callback) = options.progress_callback {
+                    callback(DownloadState::Progress(downloaded));
+                }
+            }
+            if received != to - from + 1 {
+                return Err(DownloadError::InvalidResponse);
+            }
+        }
+        file.flush().await?;
+        drop(file);
+
+        if let Some(expected) = control.sha1 {
+            let temp_path = temp_path.to_path_buf();
+            let actual = tokio::task::spawn_blocking(move || sha1_file(&temp_path))
+                .await
+                .map_err(|err| DownloadError::IoError(std::io::Error::other(err)))??;
+            if actual != expected {
+                return Err(DownloadError::InvalidZsync(
+                    "SHA-1 of the assembled file doesn't match".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref callback) = options.progress_callback {
+            callback(DownloadState::Complete);
+        }
+
+        Ok(())
+    }
+}
+
+/// zsync's rolling checksum of a block.
+fn rsum(data: &[u8]) -> (u16, u16) {
+    let mut a = 0u16;
+    let mut b = 0u16;
+    let len = data.len();
+    for (i, &c) in data.iter().enumerate() {
+        a = a.wrapping_add(c as u16);
+        b = b.wrapping_add(((len - i) as u16).wrapping_mul(c as u16));
+    }
+    (a, b)
+}
+
+fn sha1_file(path: &Path) -> Result<[u8; 20], DownloadError> {
+    let mut file = std::fs::File::open(path)?;
+    let mut sha1 = Sha1::default();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        sha1.update(&buf[..n]);
+    }
+    Ok(sha1.finalize().into())
+}
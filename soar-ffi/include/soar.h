@@ -0,0 +1,129 @@
+/*
+ * libsoar - C interface to the soar package manager.
+ *
+ * Generated by cbindgen from soar-ffi/src/lib.rs, don't edit by hand.
+ *
+ * All functions returning `int32_t` return 0 on success and -1 on failure.
+ * On failure, soar_last_error() returns a description of the error for the
+ * calling thread. Strings returned by the library are owned by it and stay
+ * valid until the owning object is freed.
+ */
+
+#ifndef LIBSOAR_H
+#define LIBSOAR_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum SoarProgressState {
+  SOAR_PROGRESS_STATE_PREPARING = 0,
+  SOAR_PROGRESS_STATE_DOWNLOADING = 1,
+  SOAR_PROGRESS_STATE_COMPLETE = 2,
+} SoarProgressState;
+
+// Handle to the soar databases, created by [`soar_init`].
+typedef struct SoarContext SoarContext;
+
+// Packages returned by [`soar_query`] and [`soar_list_installed`].
+typedef struct SoarPackageList SoarPackageList;
+
+// Progress callback invoked from a worker thread during downloads.
+// `current` and `total` are byte counts; `total` may be 0 when unknown.
+typedef void (*SoarProgressCallback)(const char *pkg_name,
+                                     enum SoarProgressState state,
+                                     uint64_t current,
+                                     uint64_t total,
+                                     void *user_data);
+
+typedef struct SoarPackageInfo {
+  const char *pkg_name;
+  const char *pkg_id;
+  const char *version;
+  const char *repo_name;
+  const char *description;
+  uint64_t size;
+  bool installed;
+} SoarPackageInfo;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the last error message for the calling thread, or NULL.
+const char *soar_last_error(void);
+
+// Opens the soar databases. When `sync` is true, repository metadata is
+// fetched from the configured remotes first. Returns NULL on failure,
+// including when syncing while another soar process is running.
+struct SoarContext *soar_init(bool sync);
+
+// # Safety
+//
+// `ctx` must be NULL or a pointer returned by [`soar_init`] that wasn't freed.
+void soar_free(struct SoarContext *ctx);
+
+// Installs the packages matching the `count` queries in `packages`,
+// reinstalling installed ones only when `force` is true. Returns 0 on
+// success and -1 on failure, also when another soar process is running.
+//
+// # Safety
+//
+// `ctx` must be a valid context and `packages` must point to `count` valid
+// NUL-terminated strings.
+int32_t soar_install(struct SoarContext *ctx,
+                     const char *const *packages,
+                     size_t count,
+                     bool force,
+                     SoarProgressCallback callback,
+                     void *user_data);
+
+// Removes the installed packages matching the `count` queries in
+// `packages`. Returns 0 on success and -1 on failure, also when another
+// soar process is running.
+//
+// # Safety
+//
+// `ctx` must be a valid context and `packages` must point to `count` valid
+// NUL-terminated strings.
+int32_t soar_remove(struct SoarContext *ctx,
+                    const char *const *packages,
+                    size_t count);
+
+// Searches available packages by name. Returns NULL on failure.
+//
+// # Safety
+//
+// `ctx` must be a valid context and `query` a valid NUL-terminated string.
+struct SoarPackageList *soar_query(struct SoarContext *ctx,
+                                   const char *query,
+                                   uint32_t limit);
+
+// Lists installed packages. Returns NULL on failure.
+//
+// # Safety
+//
+// `ctx` must be a valid context.
+struct SoarPackageList *soar_list_installed(struct SoarContext *ctx);
+
+// # Safety
+//
+// `list` must be NULL or a list returned by this library.
+size_t soar_package_list_len(const struct SoarPackageList *list);
+
+// # Safety
+//
+// `list` must be NULL or a list returned by this library.
+const struct SoarPackageInfo *soar_package_list_get(const struct SoarPackageList *list,
+                                                    size_t index);
+
+// # Safety
+//
+// `list` must be NULL or a list returned by this library that wasn't freed.
+void soar_package_list_free(struct SoarPackageList *list);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LIBSOAR_H */
@@ -0,0 +1,564 @@
+//! C ABI for soar.
+//!
+//! `include/soar.h` is generated from the exported items here by cbindgen
+//! when the crate is built.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ffi::{c_char, c_void, CStr, CString},
+    fs::{self, File},
+    path::PathBuf,
+    ptr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use rusqlite::Connection;
+use soar_core::{
+    config::get_config,
+    constants::CORE_MIGRATIONS,
+    database::{
+        connection::Database,
+        migration::MigrationManager,
+        packages::{get_installed_packages, get_packages, FilterOp, QueryOptions},
+    },
+    error::SoarError,
+    events::{EventSink, InstallEvent},
+    http::{client_config, external_downloaders},
+    lock::InstanceLock,
+    metadata::fetch_metadata,
+    package::{
+        install::{install_package, InstallOptions, InstallTarget},
+        query::PackageQuery,
+        remove::PackageRemover,
+    },
+    utils::setup_required_paths,
+    SoarResult,
+};
+use soar_dl::{
+    api_cache::configure_api_cache, external::configure_external, http_client::configure_client,
+};
+use tokio::runtime::Runtime;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(err: impl ToString) {
+    let msg = CString::new(err.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(msg));
+}
+
+fn to_cstring(value: &str) -> CString {
+    CString::new(value.replace('\0', " ")).unwrap_or_default()
+}
+
+/// Handle to the soar databases, created by [`soar_init`].
+pub struct SoarContext {
+    runtime: Runtime,
+    repo_db: Database,
+    core_db: Database,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum SoarProgressState {
+    Preparing = 0,
+    Downloading = 1,
+    Complete = 2,
+}
+
+#[repr(C)]
+pub struct SoarPackageInfo {
+    pub pkg_name: *const c_char,
+    pub pkg_id: *const c_char,
+    pub version: *const c_char,
+    pub repo_name: *const c_char,
+    pub description: *const c_char,
+    pub size: u64,
+    pub installed: bool,
+}
+
+/// Owned strings backing the pointers in a [`SoarPackageInfo`].
+struct PackageStrings {
+    pkg_name: CString,
+    pkg_id: CString,
+    version: CString,
+    repo_name: CString,
+    description: CString,
+}
+
+/// Packages returned by [`soar_query`] and [`soar_list_installed`].
+pub struct SoarPackageList {
+    _strings: Vec<PackageStrings>,
+    items: Vec<SoarPackageInfo>,
+}
+
+impl SoarPackageList {
+    fn new(entries: Vec<(PackageStrings, u64, bool)>) -> Self {
+        let mut strings = Vec::with_capacity(entries.len());
+        let mut items = Vec::with_capacity(entries.len());
+        for (s, size, installed) in entries {
+            items.push(SoarPackageInfo {
+                pkg_name: s.pkg_name.as_ptr(),
+                pkg_id: s.pkg_id.as_ptr(),
+                version: s.version.as_ptr(),
+                repo_name: s.repo_name.as_ptr(),
+                description: s.description.as_ptr(),
+                size,
+                installed,
+            });
+            // moving the CStrings doesn't move their heap buffers
+            strings.push(s);
+        }
+        Self {
+            _strings: strings,
+            items,
+        }
+    }
+}
+
+/// Progress callback invoked from a worker thread during downloads.
+/// `current` and `total` are byte counts; `total` may be 0 when unknown.
+pub type SoarProgressCallback = Option<
+    unsafe extern "C" fn(
+        pkg_name: *const c_char,
+        state: SoarProgressState,
+        current: u64,
+        total: u64,
+        user_data: *mut c_void,
+    ),
+>;
+
+/// Wrapper that lets the caller's user data pointer cross threads; the
+/// caller is responsible for its thread safety.
+#[derive(Clone, Copy)]
+struct UserData(*mut c_void);
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+impl SoarContext {
+    fn open(sync: bool) -> SoarResult<Self> {
+        let _ = configure_client(client_config(format!(
+            "libsoar/{}",
+            env!("CARGO_PKG_VERSION")
+        )));
+        let _ = configure_external(external_downloaders());
+        let _ = configure_api_cache(get_config().get_cache_path()?.join("api"));
+        let runtime = Runtime::new()?;
+        setup_required_paths()?;
+
+        let core_db_file: PathBuf = get_config().get_db_path()?.join("soar.db");
+        if !core_db_file.exists() {
+            File::create(&core_db_file)?;
+        }
+        let conn = Connection::open(&core_db_file)?;
+        let mut manager = MigrationManager::new(conn)?;
+        manager.migrate_from_dir(CORE_MIGRATIONS)?;
+        let core_db = Database::new(&core_db_file)?;
+
+        let repositories: Vec<_> = get_config().enabled_repositories().cloned().collect();
+        let _lock = if sync {
+            Some(InstanceLock::acquire_or_fail()?)
+        } else {
+            None
+        };
+        let mut repo_paths = Vec::new();
+        for repo in &repositories {
+            let db_file = repo.get_path()?.join("metadata.db");
+            if !db_file.exists() {
+                fs::create_dir_all(repo.get_path()?)?;
+                File::create(&db_file)?;
+            }
+            if sync {
+                runtime.block_on(fetch_metadata(repo.clone(), core_db.conn.clone(), false))?;
+            }
+            repo_paths.push(db_file);
+        }
+        if repo_paths.is_empty() {
+            return Err(SoarError::Custom("No repositories enabled".into()));
+        }
+        let repo_db = Database::new_multi(&repo_paths)?;
+
+        Ok(Self {
+            runtime,
+            repo_db,
+            core_db,
+        })
+    }
+
+    fn install(
+        &self,
+        packages: Vec<String>,
+        force: bool,
+        callback: SoarProgressCallback,
+        user_data: UserData,
+    ) -> SoarResult<()> {
+        let _lock = InstanceLock::acquire_or_fail()?;
+        let mut targets = Vec::new();
+        for package in &packages {
+            let query = PackageQuery::try_from(package.as_str())?;
+            let options = QueryOptions {
+                limit: 1,
+                filters: query.create_filter(),
+                ..Default::default()
+            };
+
+            let existing_install =
+                get_installed_packages(self.core_db.conn.clone(), options.clone())?
+                    .items
+                    .into_iter()
+                    .next();
+            if existing_install.as_ref().is_some_and(|p| p.is_installed) && !force {
+                continue;
+            }
+
+            let options = QueryOptions {
+                filters: query.create_repo_filter(),
+                ..options
+            };
+            let package = get_packages(self.repo_db.conn.clone(), options)?
+                .items
+                .into_iter()
+                .next()
+                .ok_or_else(|| SoarError::PackageNotFound(package.clone()))?;
+            targets.push(InstallTarget {
+                package,
+                existing_install,
+            });
+        }
+
+        let options = InstallOptions::default();
+        for target in targets {
+            let events = callback.map(|cb| {
+                let total = Arc::new(AtomicU64::new(0));
+                Arc::new(move |event: InstallEvent| {
+                    let (state, current) = match &event {
+                        InstallEvent::DownloadStarted { total: len, .. } => {
+                            total.store(*len, Ordering::Relaxed);
+                            (SoarProgressState::Preparing, 0)
+                        }
+                        InstallEvent::DownloadProgress { downloaded, .. } => {
+                            (SoarProgressState::Downloading, *downloaded)
+                        }
+                        InstallEvent::DownloadComplete { .. } => {
+                            (SoarProgressState::Complete, total.load(Ordering::Relaxed))
+                        }
+                        _ => return,
+                    };
+                    let pkg_name = to_cstring(event.pkg_name());
+                    let user_data = user_data;
+                    unsafe {
+                        cb(
+                            pkg_name.as_ptr(),
+                            state,
+                            current,
+                            total.load(Ordering::Relaxed),
+                            user_data.0,
+                        )
+                    };
+                }) as EventSink
+            });
+
+            self.runtime.block_on(install_package(
+                &target,
+                &options,
+                events,
+                self.core_db.conn.clone(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, packages: Vec<String>) -> SoarResult<()> {
+        let _lock = InstanceLock::acquire_or_fail()?;
+        for package in &packages {
+            let query = PackageQuery::try_from(package.as_str())?;
+            let options = QueryOptions {
+                filters: query.create_filter(),
+                ..Default::default()
+            };
+            let installed = get_installed_packages(self.core_db.conn.clone(), options)?
+                .items
+                .into_iter()
+                .find(|p| p.is_installed)
+                .ok_or_else(|| SoarError::PackageNotFound(package.clone()))?;
+
+            self.runtime.block_on(async {
+                PackageRemover::new(installed, self.core_db.conn.clone())
+                    .await
+                    .remove()
+                    .await
+            })?;
+        }
+        Ok(())
+    }
+
+    fn query(&self, query: String, limit: u32) -> SoarResult<SoarPackageList> {
+        let mut filters = HashMap::new();
+        filters.insert(
+            "pkg_name".to_string(),
+            (FilterOp::ILike, query.into()).into(),
+        );
+        let packages = get_packages(
+            self.repo_db.conn.clone(),
+            QueryOptions {
+                limit: if limit == 0 { u32::MAX } else { limit },
+                filters,
+                ..Default::default()
+            },
+        )?;
+        let installed: HashSet<(String, String, String)> =
+            get_installed_packages(self.core_db.conn.clone(), QueryOptions::default())?
+                .items
+                .into_iter()
+                .filter(|p| p.is_installed)
+                .map(|p| (p.repo_name, p.pkg_id, p.pkg_name))
+                .collect();
+
+        Ok(SoarPackageList::new(
+            packages
+                .items
+                .into_iter()
+                .map(|p| {
+                    let is_installed = installed.contains(&(
+                        p.repo_name.clone(),
+                        p.pkg_id.clone(),
+                        p.pkg_name.clone(),
+                    ));
+                    (
+                        PackageStrings {
+                            pkg_name: to_cstring(&p.pkg_name),
+                            pkg_id: to_cstring(&p.pkg_id),
+                            version: to_cstring(&p.version),
+                            repo_name: to_cstring(&p.repo_name),
+                            description: to_cstring(&p.description),
+                        },
+                        p.size,
+                        is_installed,
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    fn list_installed(&self) -> SoarResult<SoarPackageList> {
+        let packages = get_installed_packages(self.core_db.conn.clone(), QueryOptions::default())?;
+
+        Ok(SoarPackageList::new(
+            packages
+                .items
+                .into_iter()
+                .map(|p| {
+                    (
+                        PackageStrings {
+                            pkg_name: to_cstring(&p.pkg_name),
+                            pkg_id: to_cstring(&p.pkg_id),
+                            version: to_cstring(&p.version),
+                            repo_name: to_cstring(&p.repo_name),
+                            description: CString::default(),
+                        },
+                        p.size,
+                        p.is_installed,
+                    )
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Collects a C array of C strings into owned Rust strings.
+///
+/// # Safety
+///
+/// `packages` must point to `count` valid NUL-terminated strings.
+unsafe fn collect_strings(packages: *const *const c_char, count: usize) -> SoarResult<Vec<String>> {
+    if packages.is_null() && count > 0 {
+        return Err(SoarError::Custom("packages must not be NULL".into()));
+    }
+    (0..count)
+        .map(|idx| {
+            let ptr = *packages.add(idx);
+            if ptr.is_null() {
+                return Err(SoarError::Custom("package name must not be NULL".into()));
+            }
+            Ok(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+fn status(result: SoarResult<()>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            set_last_error(err);
+            -1
+        }
+    }
+}
+
+/// Returns the last error message for the calling thread, or NULL.
+#[no_mangle]
+pub extern "C" fn soar_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |err| err.as_ptr())
+    })
+}
+
+/// Opens the soar databases. When `sync` is true, repository metadata is
+/// fetched from the configured remotes first. Returns NULL on failure,
+/// including when syncing while another soar process is running.
+#[no_mangle]
+pub extern "C" fn soar_init(sync: bool) -> *mut SoarContext {
+    match SoarContext::open(sync) {
+        Ok(ctx) => Box::into_raw(Box::new(ctx)),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `ctx` must be NULL or a pointer returned by [`soar_init`] that wasn't freed.
+#[no_mangle]
+pub unsafe extern "C" fn soar_free(ctx: *mut SoarContext) {
+    if !ctx.is_null() {
+        drop(Box::from_raw(ctx));
+    }
+}
+
+/// Installs the packages matching the `count` queries in `packages`,
+/// reinstalling installed ones only when `force` is true. Returns 0 on
+/// success and -1 on failure, also when another soar process is running.
+///
+/// # Safety
+///
+/// `ctx` must be a valid context and `packages` must point to `count` valid
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn soar_install(
+    ctx: *mut SoarContext,
+    packages: *const *const c_char,
+    count: usize,
+    force: bool,
+    callback: SoarProgressCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    let Some(ctx) = ctx.as_ref() else {
+        set_last_error("context must not be NULL");
+        return -1;
+    };
+    status(
+        collect_strings(packages, count)
+            .and_then(|packages| ctx.install(packages, force, callback, UserData(user_data))),
+    )
+}
+
+/// Removes the installed packages matching the `count` queries in
+/// `packages`. Returns 0 on success and -1 on failure, also when another
+/// soar process is running.
+///
+/// # Safety
+///
+/// `ctx` must be a valid context and `packages` must point to `count` valid
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn soar_remove(
+    ctx: *mut SoarContext,
+    packages: *const *const c_char,
+    count: usize,
+) -> i32 {
+    let Some(ctx) = ctx.as_ref() else {
+        set_last_error("context must not be NULL");
+        return -1;
+    };
+    status(collect_strings(packages, count).and_then(|packages| ctx.remove(packages)))
+}
+
+/// Searches available packages by name. Returns NULL on failure.
+///
+/// # Safety
+///
+/// `ctx` must be a valid context and `query` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn soar_query(
+    ctx: *mut SoarContext,
+    query: *const c_char,
+    limit: u32,
+) -> *mut SoarPackageList {
+    let Some(ctx) = ctx.as_ref() else {
+        set_last_error("context must not be NULL");
+        return ptr::null_mut();
+    };
+    if query.is_null() {
+        set_last_error("query must not be NULL");
+        return ptr::null_mut();
+    }
+    let query = CStr::from_ptr(query).to_string_lossy().into_owned();
+    match ctx.query(query, limit) {
+        Ok(list) => Box::into_raw(Box::new(list)),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Lists installed packages. Returns NULL on failure.
+///
+/// # Safety
+///
+/// `ctx` must be a valid context.
+#[no_mangle]
+pub unsafe extern "C" fn soar_list_installed(ctx: *mut SoarContext) -> *mut SoarPackageList {
+    let Some(ctx) = ctx.as_ref() else {
+        set_last_error("context must not be NULL");
+        return ptr::null_mut();
+    };
+    match ctx.list_installed() {
+        Ok(list) => Box::into_raw(Box::new(list)),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `list` must be NULL or a list returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn soar_package_list_len(list: *const SoarPackageList) -> usize {
+    list.as_ref().map_or(0, |list| list.items.len())
+}
+
+/// # Safety
+///
+/// `list` must be NULL or a list returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn soar_package_list_get(
+    list: *const SoarPackageList,
+    index: usize,
+) -> *const SoarPackageInfo {
+    list.as_ref()
+        .and_then(|list| list.items.get(index))
+        .map_or(ptr::null(), |item| item as *const SoarPackageInfo)
+}
+
+/// # Safety
+///
+/// `list` must be NULL or a list returned by this library that wasn't freed.
+#[no_mangle]
+pub unsafe extern "C" fn soar_package_list_free(list: *mut SoarPackageList) {
+    if !list.is_null() {
+        drop(Box::from_raw(list));
+    }
+}